@@ -29,10 +29,22 @@ impl AIClient {
     }
 
     pub async fn chat(&self, messages: &[Message]) -> Result<String, AIError> {
-        match self {
-            AIClient::Gemini(c) => Ok(c.chat(messages).await?),
-            AIClient::OpenAI(c) => Ok(c.chat(messages).await?),
+        let started = std::time::Instant::now();
+        tracing::debug!(
+            model = self.model(),
+            messages = messages.len(),
+            "AI request"
+        );
+        let result = match self {
+            AIClient::Gemini(c) => c.chat(messages).await.map_err(AIError::from),
+            AIClient::OpenAI(c) => c.chat(messages).await.map_err(AIError::from),
+        };
+        let ms = started.elapsed().as_millis() as u64;
+        match &result {
+            Ok(text) => tracing::debug!(ms, chars = text.len(), "AI reply"),
+            Err(e) => tracing::warn!(ms, "AI request failed: {}", e),
         }
+        result
     }
 
     pub fn set_model(&mut self, model: String) {
@@ -2,32 +2,98 @@
 //!
 //! Contains the App struct that holds all application state.
 
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::layout::{Position, Rect};
 use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use tokio::task::JoinHandle;
-use tui_textarea::TextArea;
+use tui_textarea::{CursorMove, TextArea};
 
+use crate::artifacts;
+use crate::checkpoint;
+use crate::compaction;
 use crate::config::Config;
+use crate::container::{self, ContainerTarget};
+use crate::executor::{CommandExecutor, DangerAssessment, resolve_dir, split_cd};
+use crate::export::{self, Format};
+use crate::images::ImageProtocol;
+use crate::keychain;
+use crate::loop_guard::LoopGuard;
 use crate::mcp::McpClient;
 use crate::message::{Message, MessageRole};
+use crate::models;
+use crate::patterns;
+use crate::process_group::ProcessGroups;
+use crate::project::Project;
+use crate::py_session::PythonSession;
+use crate::pyenv::{self, PythonEnv};
+use crate::session_picker::{PickerAction, SessionPicker};
+use crate::session_sync;
+use crate::settings::{self, Source};
+use crate::setup::{Choice, SetupAction, SetupWizard};
+use crate::snippets;
 use crate::state::{AppState, StateEvent, TransitionResult, transition};
+use crate::summary::{self, Summary};
+use crate::theme::Palette;
+use crate::toast::Toasts;
 use crate::tool_call::ToolCall;
+use crate::ui::ChatCache;
+use crate::undo::UndoStack;
+use crate::workspace;
+use crate::write_guard::WriteGuard;
 
 /// Available slash commands
 pub const SLASH_COMMANDS: &[(&str, &str)] = &[
     ("/clear", "Clear chat history"),
-    ("/new", "Start new session"),
-    ("/sessions", "List all sessions"),
-    ("/switch", "Switch to session: /switch <id>"),
-    ("/delete", "Delete session: /delete <id>"),
+    ("/new", "Start new session: /new [template]"),
+    ("/sessions", "Browse, switch, rename or delete sessions"),
+    ("/resume", "Resume a recent session: /resume [id|name]"),
+    ("/workspace", "Workspaces: /workspace [switch <name>|off]"),
+    ("/rename", "Name this session: /rename <name>"),
+    ("/switch", "Switch to session: /switch <id|name>"),
+    ("/delete", "Delete session: /delete <id|name>"),
     ("/image", "Attach image: /image <path> [prompt]"),
+    ("/edit", "Open file in $EDITOR: /edit <path>"),
     ("/model", "List/switch model: /model [name]"),
+    ("/config", "Show settings: /config [set <key> <value>]"),
+    ("/setup", "Change the provider, API key and model"),
+    ("/patterns", "Dangerous patterns: list|add|remove|test"),
     ("/usage", "Show session token usage stats"),
-    ("/export", "Export chat: /export [filename.md]"),
+    ("/export", "Export chat: /export [md|html] [path]"),
+    ("/find", "Search the chat: /find <text>"),
+    ("/multiline", "Toggle Enter for newlines (send with Ctrl+S)"),
+    ("/compact", "Toggle the compact layout"),
+    ("/output", "View full output of the last command"),
+    ("/artifact", "View a stored output: /artifact [id]"),
+    ("/rerun", "Review the last tool call again"),
+    ("/pyreset", "Restart the persistent Python session"),
+    ("/snippet", "Run a command snippet: /snippet [name]"),
+    ("/target", "Run commands in: /target [local|container:<n>]"),
+    ("/undo", "Revert agent file changes: /undo [n]"),
+    ("/checkpoints", "List git checkpoints"),
+    ("/rollback", "Restore git checkpoint: /rollback <id>"),
     ("/help", "Show available commands"),
     ("/quit", "Exit application"),
 ];
 
+/// Chat lines scrolled per mouse wheel step
+const MOUSE_SCROLL: u16 = 3;
+
+/// Chat lines scrolled by PageUp / PageDown while the chat has focus
+const CHAT_PAGE: u16 = 10;
+
+/// Turns finished within this long of each other are saved once
+const AUTOSAVE_DELAY: Duration = Duration::from_secs(2);
+
+/// Without input for this long, and nothing animating, ticks slow down
+const IDLE_AFTER: Duration = Duration::from_secs(2);
+
+/// Prefix of the marker files naming the session of a running sabi
+const RUNNING_MARKER: &str = ".running-";
+
 /// Session data for persistence
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Session {
@@ -36,22 +102,39 @@ pub struct Session {
     pub timestamp: String,
     pub cwd: String,
     pub messages: Vec<Message>,
+    /// Summary of the messages that fell out of the context window
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<Summary>,
+    /// Template the session was started from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// Workspace the session belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<String>,
 }
 
 impl Session {
     pub fn new() -> Self {
         let id = chrono::Local::now().format("%Y%m%d_%H%M%S").to_string();
         Self {
-            id: id.clone(),
-            name: format!("Session {}", &id[9..]), // Use time part as name
+            name: Self::default_name(&id),
+            id,
             timestamp: chrono::Local::now().to_rfc3339(),
             cwd: std::env::current_dir()
                 .map(|p| p.to_string_lossy().into_owned())
                 .unwrap_or_default(),
             messages: Vec::new(),
+            summary: None,
+            template: None,
+            workspace: None,
         }
     }
 
+    /// "Session 143022" from the time part of an id
+    pub fn default_name(id: &str) -> String {
+        format!("Session {}", id.get(9..).unwrap_or(id))
+    }
+
     pub fn from_messages(messages: &[Message]) -> Self {
         let mut session = Self::new();
         session.messages = messages
@@ -99,6 +182,9 @@ pub struct App<'a> {
     /// Current tool call being executed
     pub current_tool: Option<ToolCall>,
 
+    /// Independent read-only tool calls awaiting review, run concurrently
+    pub tool_batch: Vec<ToolCall>,
+
     /// Output from command execution
     pub execution_output: String,
 
@@ -117,6 +203,27 @@ pub struct App<'a> {
     /// Flag indicating dangerous command detected
     pub dangerous_command_detected: bool,
 
+    /// Explanations of the dangerous patterns the pending command matched
+    pub danger_reasons: Vec<String>,
+
+    /// The AI's prose around the tool call under review
+    pub reasoning: Option<String>,
+
+    /// Snapshots of files the agent modified, for /undo
+    pub undo: UndoStack,
+
+    /// Stamps of files read or written, to catch overwriting outside changes
+    pub write_guard: WriteGuard,
+
+    /// Conflict the user confirmed overwriting, reported with the result
+    pub write_conflict: Option<String>,
+
+    /// Catches the AI repeating the same tool call
+    pub loop_guard: LoopGuard,
+
+    /// Last tool call that was executed, for /rerun
+    pub last_tool: Option<ToolCall>,
+
     /// Confirmation step for dangerous commands (0 = not started, 1 = first confirm, 2 = ready)
     pub danger_confirm_step: u8,
 
@@ -126,9 +233,18 @@ pub struct App<'a> {
     /// Python availability (checked at startup)
     pub python_available: bool,
 
+    /// Project virtualenv run_python uses, if one was found
+    pub python_env: Option<PythonEnv>,
+
+    /// Persistent interpreter for run_python (used with `python_session`)
+    pub python_session: PythonSession,
+
     /// Currently running async task (for cancellation)
     pub running_task: Option<JoinHandle<()>>,
 
+    /// Process groups of running commands, killed on cancel and exit
+    pub processes: ProcessGroups,
+
     /// Current session ID
     pub current_session_id: String,
 
@@ -137,6 +253,332 @@ pub struct App<'a> {
 
     /// MCP client for external tools
     pub mcp_client: Option<McpClient>,
+
+    /// Working directory for tool execution (tracks `cd`, saved per session)
+    pub cwd: PathBuf,
+
+    /// The directory was given with `--cwd`; resumed sessions keep it
+    pub cwd_pinned: bool,
+
+    /// Directory before the last `cd`, for `cd -`
+    pub previous_cwd: Option<PathBuf>,
+
+    /// Masked password prompt for a sudo command awaiting execution
+    pub sudo_prompt: Option<SudoPrompt>,
+
+    /// An explanation of the pending action has been requested from the AI
+    pub explaining: bool,
+
+    /// Summary of the messages that fell out of the context window
+    pub summary: Option<Summary>,
+
+    /// A summary has been requested from the AI
+    pub summarizing: bool,
+
+    /// History length when a summary was last requested, so a failed
+    /// request isn't retried until the conversation moves on
+    summary_attempt: usize,
+
+    /// Spool file with the complete output of the last command
+    pub last_spool: Option<PathBuf>,
+
+    /// Full-screen output pager, when open
+    pub pager: Option<Pager>,
+
+    /// Snippet whose placeholders are being entered in the input box
+    pub snippet_fill: Option<snippets::Fill>,
+
+    /// Container run_cmd and run_python execute in (None = the host)
+    pub target: Option<ContainerTarget>,
+
+    /// Pane that receives scroll keys
+    pub focus: Pane,
+
+    /// Chat text being selected with the mouse
+    pub chat_selection: Option<ChatSelection>,
+
+    /// `/find` query and the match it is on
+    pub search: Option<ChatSearch>,
+
+    /// AI reply being typed out in the chat
+    pub reveal: Option<Reveal>,
+
+    /// Rendered chat messages reused between frames
+    pub chat_cache: RefCell<ChatCache>,
+
+    /// vi normal mode saw a `g` and waits for the second one
+    vi_pending_g: bool,
+
+    /// `/multiline`: Enter inserts a newline and Ctrl+S sends
+    pub multiline: bool,
+
+    /// Compact layout without boxes (`compact` config, `/compact`)
+    pub compact: bool,
+
+    /// Wrap long lines of command output (`w` in the output pane and pager)
+    pub output_wrap: bool,
+
+    /// Number the lines of command output (`#` in the output pane and pager)
+    pub output_line_numbers: bool,
+
+    /// Lines the live output pane is scrolled up from the newest one
+    pub output_scroll: usize,
+
+    /// `/sessions` overlay
+    pub session_picker: Option<SessionPicker>,
+
+    /// Path typed into the export prompt (Ctrl+E, `/export`) while it is open
+    pub export_path: Option<String>,
+
+    /// Session offered by the "Resume last session?" prompt at startup
+    pub resume_offer: Option<Session>,
+
+    /// Messages typed while the agent was busy, sent once it's back at the input
+    pub queued: VecDeque<String>,
+
+    /// Scroll of the help overlay while it is open (F1 / `?`)
+    pub help_scroll: Option<u16>,
+
+    /// Show the file preview pane while a file tool is reviewed (F2)
+    pub show_preview: bool,
+
+    /// Name of the current session once it was loaded or renamed
+    pub current_session_name: Option<String>,
+
+    /// Template the current session was started from
+    pub template: Option<String>,
+
+    /// Workspace new sessions belong to and `/sessions` lists
+    pub workspace: Option<String>,
+
+    /// `/patterns` changed the dangerous patterns; the detector is rebuilt
+    pub patterns_changed: bool,
+
+    /// `/config` changed the writable roots or protected paths; the path
+    /// guard is rebuilt
+    pub paths_changed: bool,
+
+    /// `/setup` overlay, while open
+    pub setup: Option<SetupWizard>,
+
+    /// `/setup` changed the provider settings; the AI client is rebuilt
+    pub client_changed: bool,
+
+    /// Project settings merged into `config`, from `.sabi.toml`
+    pub project: Option<Project>,
+
+    /// Colors the UI is drawn with
+    pub palette: Palette,
+
+    /// Draw the spinner and status bar with ASCII only
+    pub ascii: bool,
+
+    /// Graphics protocol for inline images (resolved from the config)
+    pub image_protocol: ImageProtocol,
+
+    /// Message picked with Ctrl+↑/↓ or a click, by index into `messages`
+    pub selected_message: Option<usize>,
+
+    /// Long messages the user expanded
+    pub expanded_messages: HashSet<usize>,
+
+    /// Terminal size, for layout outside of drawing
+    pub screen: Rect,
+
+    /// Status notifications shown in a corner, outside of the chat
+    pub toasts: Toasts,
+
+    /// When the current state was entered, for "thought for" / "ran in"
+    pub phase_started: Instant,
+
+    /// When the session is saved after a finished turn
+    pub autosave_due: Option<Instant>,
+}
+
+/// Panes that can be focused
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Pane {
+    /// Chat history: arrows and PageUp/PageDown scroll it
+    Chat,
+    /// Input / command box
+    #[default]
+    Input,
+    /// Live command output: arrows and PageUp/PageDown scroll it
+    Output,
+}
+
+/// Chat text selected by dragging, as (rendered line, column) positions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChatSelection {
+    /// Where the drag started
+    pub anchor: (usize, usize),
+    /// Where the mouse is now
+    pub cursor: (usize, usize),
+}
+
+impl ChatSelection {
+    /// Start and end in reading order
+    fn ordered(&self) -> ((usize, usize), (usize, usize)) {
+        if self.anchor <= self.cursor {
+            (self.anchor, self.cursor)
+        } else {
+            (self.cursor, self.anchor)
+        }
+    }
+
+    /// Selected columns of rendered line `line` (`len` chars long), end exclusive
+    pub fn columns(&self, line: usize, len: usize) -> Option<(usize, usize)> {
+        let (start, end) = self.ordered();
+        if line < start.0 || line > end.0 {
+            return None;
+        }
+        let from = if line == start.0 { start.1 } else { 0 };
+        let to = if line == end.0 { end.1 + 1 } else { len };
+        Some((from.min(len), to.min(len)))
+    }
+
+    /// The selected text out of the rendered chat lines
+    pub fn text(&self, lines: &[String]) -> String {
+        lines
+            .iter()
+            .enumerate()
+            .filter_map(|(index, line)| {
+                let (from, to) = self.columns(index, line.chars().count())?;
+                let part: String = line.chars().skip(from).take(to - from).collect();
+                Some(part.trim_end().to_string())
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Active `/find` search in the chat history
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatSearch {
+    pub query: String,
+    /// Index of the focused match, counted from the top of the chat
+    pub current: usize,
+}
+
+/// AI reply shown a little more on every tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reveal {
+    /// Index into `messages`
+    pub message: usize,
+    /// Characters shown so far
+    pub shown: usize,
+}
+
+/// Full-screen, scrollable view of a command's complete output, or of a
+/// zoomed pane
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Pager {
+    /// Shown in the pager border
+    pub title: String,
+    /// Output lines
+    pub lines: Vec<String>,
+    /// Index of the first visible line
+    pub scroll: usize,
+    /// Columns scrolled right while lines are not wrapped
+    pub hscroll: usize,
+    /// Query typed after `/`, until Enter or Esc
+    pub query: Option<String>,
+    /// Search moved through with n / N
+    pub search: Option<ChatSearch>,
+    /// Follows the running command's output as it streams in
+    pub live: bool,
+}
+
+impl Pager {
+    /// Lines scrolled by PageUp / PageDown
+    const PAGE: usize = 20;
+
+    /// Columns scrolled by ← / →
+    const SIDE_STEP: usize = 8;
+
+    /// Scroll by `delta` lines, clamped to the content
+    fn scroll_by(&mut self, delta: isize) {
+        let max = self.lines.len().saturating_sub(1);
+        self.scroll = self.scroll.saturating_add_signed(delta).min(max);
+    }
+
+    /// Scroll so the last page is in view
+    fn scroll_to_end(&mut self) {
+        self.scroll = self.lines.len().saturating_sub(Self::PAGE);
+    }
+
+    /// Indexes of the lines containing `query`, ignoring case
+    pub fn matches(&self, query: &str) -> Vec<usize> {
+        let query = query.to_lowercase();
+        if query.is_empty() {
+            return Vec::new();
+        }
+        (0..self.lines.len())
+            .filter(|&i| self.lines[i].to_lowercase().contains(&query))
+            .collect()
+    }
+
+    /// Go to the next (1) or previous (-1) match; 0 goes to the first one
+    /// from the top of the screen on
+    fn search_step(&mut self, step: isize) {
+        let Some(ref search) = self.search else {
+            return;
+        };
+        let matches = self.matches(&search.query);
+        if matches.is_empty() {
+            return;
+        }
+        let current = if step == 0 {
+            matches
+                .iter()
+                .position(|&line| line >= self.scroll)
+                .unwrap_or(0)
+        } else {
+            (search.current.min(matches.len() - 1) as isize + step)
+                .rem_euclid(matches.len() as isize) as usize
+        };
+        self.scroll = matches[current];
+        if let Some(ref mut search) = self.search {
+            search.current = current;
+        }
+    }
+}
+
+/// A sudo command waiting for the user's password
+///
+/// The password is only handed out by `App::take_sudo_submission` and is
+/// never stored in messages, sessions, or logs.
+pub struct SudoPrompt {
+    /// The run_cmd tool to execute once the password is entered
+    pub tool: ToolCall,
+    password: String,
+}
+
+impl SudoPrompt {
+    /// One mask character per typed character
+    pub fn masked(&self) -> String {
+        "•".repeat(self.password.chars().count())
+    }
+}
+
+impl std::fmt::Debug for SudoPrompt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SudoPrompt")
+            .field("tool", &self.tool)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
+/// Outcome of checking a shell command for a leading `cd`
+#[derive(Debug, Clone, PartialEq)]
+pub enum CdIntercept {
+    /// Not a directory change; run the command as-is
+    NotCd,
+    /// Directory changed; run the chained remainder if any
+    Changed(Option<String>),
+    /// Target directory is invalid
+    Failed(String),
 }
 
 impl<'a> App<'a> {
@@ -146,13 +588,18 @@ impl<'a> App<'a> {
         input_textarea.set_placeholder_text("Type your query here...");
 
         let action_textarea = TextArea::default();
+        let compact = config.compact;
 
         // Check Python availability at startup
-        let python_available = std::process::Command::new("python3")
-            .arg("--version")
-            .output()
-            .map(|o| o.status.success())
-            .unwrap_or(false);
+        let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let python_env = pyenv::detect(&cwd);
+        let workspace = workspace::detect(&config.workspaces, &cwd);
+        let python_available = python_env.is_some()
+            || std::process::Command::new("python3")
+                .arg("--version")
+                .output()
+                .map(|o| o.status.success())
+                .unwrap_or(false);
 
         // Load MCP client if configured
         let mcp_client = McpClient::load().ok();
@@ -164,27 +611,422 @@ impl<'a> App<'a> {
             messages: Vec::new(),
             current_command: None,
             current_tool: None,
+            tool_batch: Vec::new(),
             execution_output: String::new(),
             error_message: None,
             spinner_frame: 0,
             should_quit: false,
             scroll_offset: 0,
             dangerous_command_detected: false,
+            danger_reasons: Vec::new(),
+            reasoning: None,
+            undo: UndoStack::default(),
+            write_guard: WriteGuard::default(),
+            write_conflict: None,
+            loop_guard: LoopGuard::default(),
+            last_tool: None,
             danger_confirm_step: 0,
             config,
             python_available,
+            python_env,
+            python_session: PythonSession::default(),
             running_task: None,
+            processes: ProcessGroups::default(),
             current_session_id: chrono::Local::now().format("%Y%m%d_%H%M%S").to_string(),
             pending_image: None,
             mcp_client,
+            cwd,
+            sudo_prompt: None,
+            explaining: false,
+            summary: None,
+            summarizing: false,
+            summary_attempt: 0,
+            last_spool: None,
+            pager: None,
+            snippet_fill: None,
+            target: None,
+            focus: Pane::default(),
+            chat_selection: None,
+            search: None,
+            reveal: None,
+            chat_cache: RefCell::default(),
+            vi_pending_g: false,
+            multiline: false,
+            compact,
+            output_wrap: true,
+            output_line_numbers: false,
+            output_scroll: 0,
+            session_picker: None,
+            export_path: None,
+            resume_offer: None,
+            cwd_pinned: false,
+            previous_cwd: None,
+            queued: VecDeque::new(),
+            help_scroll: None,
+            show_preview: false,
+            current_session_name: None,
+            template: None,
+            workspace,
+            patterns_changed: false,
+            paths_changed: false,
+            setup: None,
+            client_changed: false,
+            project: None,
+            palette: Palette::default(),
+            ascii: false,
+            image_protocol: ImageProtocol::Off,
+            selected_message: None,
+            expanded_messages: HashSet::new(),
+            screen: Rect::new(0, 0, 80, 24),
+            toasts: Toasts::default(),
+            phase_started: Instant::now(),
+            autosave_due: None,
+        }
+    }
+
+    /// Open the pager on the complete output of the last command
+    pub fn open_output_pager(&mut self) -> Result<(), String> {
+        let path = self
+            .last_spool
+            .as_ref()
+            .ok_or("No command output to show yet")?;
+        let bytes =
+            std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        self.pager = Some(Pager {
+            title: path.display().to_string(),
+            lines: String::from_utf8_lossy(&bytes)
+                .lines()
+                .map(String::from)
+                .collect(),
+            ..Default::default()
+        });
+        Ok(())
+    }
+
+    /// Open the pager on artifact `id` (or a prefix of it)
+    pub fn open_artifact(&mut self, id: &str) -> Result<(), String> {
+        let (id, text) = artifacts::load(id)?;
+        self.pager = Some(Pager {
+            title: format!("artifact {}", id),
+            lines: text.lines().map(String::from).collect(),
+            ..Default::default()
+        });
+        Ok(())
+    }
+
+    /// Artifacts the chat refers to, with the message each belongs to
+    pub fn list_artifacts(&self) -> String {
+        let rows: Vec<String> = self
+            .messages
+            .iter()
+            .flat_map(|m| {
+                let label = m.content.lines().next().unwrap_or("").to_string();
+                m.artifacts
+                    .iter()
+                    .map(move |id| format!("  {}  {}", id, label))
+            })
+            .collect();
+        if rows.is_empty() {
+            return "No artifacts in this session. Truncated outputs and written files are \
+                    stored as artifacts."
+                .to_string();
+        }
+        format!(
+            "📦 Artifacts (/artifact <id> to view):\n{}",
+            rows.join("\n")
+        )
+    }
+
+    /// Show the running command's output full screen, following it
+    pub fn zoom_output(&mut self) {
+        let mut pager = Pager {
+            title: "Output".to_string(),
+            lines: self.execution_output.lines().map(String::from).collect(),
+            live: self.state == AppState::Executing,
+            ..Default::default()
+        };
+        pager.scroll_to_end();
+        self.pager = Some(pager);
+    }
+
+    /// Show the whole conversation full screen
+    pub fn zoom_chat(&mut self) {
+        let mut lines = Vec::new();
+        for msg in self.messages.iter().filter(|m| !m.is_system_prompt()) {
+            let role = if msg.is_tool_result() {
+                &MessageRole::Tool
+            } else {
+                &msg.role
+            };
+            let (prefix, _) = crate::ui::message_style(role, &self.palette);
+            lines.push(prefix.to_string());
+            lines.extend(msg.content.lines().map(|line| format!("  {}", line)));
+            lines.push(String::new());
+        }
+        let mut pager = Pager {
+            title: "Chat".to_string(),
+            lines,
+            ..Default::default()
+        };
+        pager.scroll_to_end();
+        self.pager = Some(pager);
+    }
+
+    /// Handle keyboard events while the pager is open
+    fn handle_pager_key(&mut self, key: KeyEvent) -> InputResult {
+        let Some(ref mut pager) = self.pager else {
+            return InputResult::Ignored;
+        };
+        // Typing a search query
+        if let Some(ref mut query) = pager.query {
+            match key.code {
+                KeyCode::Enter => {
+                    let query = std::mem::take(query);
+                    pager.query = None;
+                    pager.search = (!query.is_empty()).then_some(ChatSearch { query, current: 0 });
+                    pager.search_step(0);
+                }
+                KeyCode::Esc => pager.query = None,
+                KeyCode::Backspace => {
+                    query.pop();
+                }
+                KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    query.push(c);
+                }
+                _ => return InputResult::Ignored,
+            }
+            return InputResult::Handled;
+        }
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q' | 'z') => self.pager = None,
+            KeyCode::Char('/') => pager.query = Some(String::new()),
+            KeyCode::Char('n') => pager.search_step(1),
+            KeyCode::Char('N') => pager.search_step(-1),
+            KeyCode::Up | KeyCode::Char('k') => pager.scroll_by(-1),
+            KeyCode::Down | KeyCode::Char('j') => pager.scroll_by(1),
+            KeyCode::PageUp => pager.scroll_by(-(Pager::PAGE as isize)),
+            KeyCode::PageDown | KeyCode::Char(' ') => pager.scroll_by(Pager::PAGE as isize),
+            KeyCode::Home | KeyCode::Char('g') => pager.scroll = 0,
+            KeyCode::End | KeyCode::Char('G') => pager.scroll_by(isize::MAX),
+            KeyCode::Left if !self.output_wrap => {
+                pager.hscroll = pager.hscroll.saturating_sub(Pager::SIDE_STEP);
+            }
+            KeyCode::Right if !self.output_wrap => pager.hscroll += Pager::SIDE_STEP,
+            KeyCode::Char('w' | '#') => self.toggle_output_view(key.code),
+            _ => return InputResult::Ignored,
+        }
+        InputResult::Handled
+    }
+
+    /// Open, scroll and close the help overlay
+    ///
+    /// F1 toggles it anywhere; `?` opens it from the chat or an empty input.
+    fn handle_help_key(&mut self, key: KeyEvent) -> Option<InputResult> {
+        let Some(scroll) = self.help_scroll else {
+            let question = key.code == KeyCode::Char('?')
+                && self.pager.is_none()
+                && self.session_picker.is_none()
+                && (self.focus == Pane::Chat
+                    || (self.state == AppState::Input && self.get_input_text().is_empty()));
+            if key.code == KeyCode::F(1) || question {
+                self.help_scroll = Some(0);
+                return Some(InputResult::Handled);
+            }
+            return None;
+        };
+        self.help_scroll = match key.code {
+            KeyCode::Esc | KeyCode::Char('q' | '?') | KeyCode::F(1) => None,
+            KeyCode::Up | KeyCode::Char('k') => Some(scroll.saturating_sub(1)),
+            KeyCode::Down | KeyCode::Char('j') => Some(scroll.saturating_add(1)),
+            KeyCode::PageUp => Some(scroll.saturating_sub(CHAT_PAGE)),
+            KeyCode::PageDown | KeyCode::Char(' ') => Some(scroll.saturating_add(CHAT_PAGE)),
+            _ => Some(scroll),
+        };
+        Some(InputResult::Handled)
+    }
+
+    /// Ask where to export the conversation, suggesting a file for `format`
+    fn open_export_prompt(&mut self, format: Format) {
+        self.export_path = Some(format.default_file().to_string());
+    }
+
+    /// Export the conversation to `filename` and report it in a toast
+    fn export_chat(&mut self, filename: &str, format: Format) {
+        match self.export_to(filename, format) {
+            Ok(path) => self.toast(format!("✓ Exported to {}", path.display())),
+            Err(e) => self.toast(format!("✗ Export failed: {}", e)),
+        }
+    }
+
+    /// Handle keyboard events while the export prompt is open
+    fn handle_export_prompt_key(&mut self, key: KeyEvent) -> InputResult {
+        let Some(ref mut path) = self.export_path else {
+            return InputResult::Ignored;
+        };
+        match key.code {
+            KeyCode::Enter => {
+                let filename = path.trim().to_string();
+                self.export_path = None;
+                if !filename.is_empty() {
+                    self.export_chat(&filename, Format::from_path(&filename));
+                }
+            }
+            KeyCode::Esc => self.export_path = None,
+            KeyCode::Backspace => {
+                path.pop();
+            }
+            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => path.clear(),
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => path.push(c),
+            _ => {}
+        }
+        InputResult::Handled
+    }
+
+    /// Handle keyboard events while the session picker is open
+    fn handle_session_picker_key(&mut self, key: KeyEvent) -> InputResult {
+        let Some(ref mut picker) = self.session_picker else {
+            return InputResult::Ignored;
+        };
+        let status = match picker.handle_key(key) {
+            PickerAction::None => return InputResult::Handled,
+            PickerAction::Close => {
+                self.session_picker = None;
+                return InputResult::Handled;
+            }
+            PickerAction::Switch(id) => {
+                self.session_picker = None;
+                if id != self.current_session_id {
+                    match self.switch_session(&id) {
+                        Ok(_) => {
+                            self.toast(format!("✓ Switched to session: {}", self.session_name()))
+                        }
+                        Err(e) => self.toast(format!("✗ Failed to switch: {}", e)),
+                    }
+                }
+                return InputResult::Handled;
+            }
+            PickerAction::Delete(id) if id == self.current_session_id => {
+                "Cannot delete the current session.".to_string()
+            }
+            PickerAction::Delete(id) => match Self::delete_session(&id) {
+                Ok(_) => format!("Deleted session {}", id),
+                Err(e) => format!("Failed to delete: {}", e),
+            },
+            PickerAction::Rename { id, name } => match self.rename_session(&id, &name) {
+                Ok(_) => format!("Renamed to '{}'", name),
+                Err(e) => format!("Failed to rename: {}", e),
+            },
+        };
+        let sessions = self.workspace_sessions();
+        if sessions.is_empty() {
+            self.session_picker = None;
+            self.toast(status);
+        } else if let Some(ref mut picker) = self.session_picker {
+            picker.refresh(sessions);
+            picker.status = Some(status);
+        }
+        InputResult::Handled
+    }
+
+    /// Ask for a sudo password before running `tool`
+    pub fn request_sudo_password(&mut self, tool: ToolCall) {
+        self.sudo_prompt = Some(SudoPrompt {
+            tool,
+            password: String::new(),
+        });
+    }
+
+    /// Take the pending sudo tool and the entered password
+    pub fn take_sudo_submission(&mut self) -> Option<(ToolCall, String)> {
+        self.sudo_prompt.take().map(|p| (p.tool, p.password))
+    }
+
+    /// Flag a pending write_file whose target changed since it was read
+    ///
+    /// The conflict counts as danger, so it needs the two-step confirmation
+    /// and is never auto-approved.
+    pub fn check_write_conflict(&mut self) {
+        self.write_conflict = self
+            .current_tool
+            .as_ref()
+            .filter(|tool| tool.is_write_file())
+            .and_then(|tool| self.write_guard.check(&self.cwd.join(&tool.path)));
+        if let Some(ref conflict) = self.write_conflict {
+            self.dangerous_command_detected = true;
+            self.danger_reasons
+                .push(format!("Write conflict: {}", conflict));
+        }
+    }
+
+    /// Create an executor for tool calls, running in the session's cwd
+    pub fn executor(&self) -> CommandExecutor {
+        let mut executor = CommandExecutor::new(&self.config)
+            .with_cwd(&self.cwd)
+            .with_process_groups(self.processes.clone());
+        if self.config.python_session {
+            executor = executor.with_python_session(self.python_session.clone());
+        }
+        if let Some(ref target) = self.target {
+            executor = executor.with_container(target.clone());
+        }
+        match self.python_env {
+            Some(ref env) => executor.with_python(&env.interpreter),
+            None => executor,
+        }
+    }
+
+    /// Apply a leading `cd`/`pushd` in a shell command to the session cwd
+    pub fn intercept_cd(&mut self, command: &str) -> CdIntercept {
+        let Some((target, rest)) = split_cd(command) else {
+            return CdIntercept::NotCd;
+        };
+        let dir = match (target.as_str(), &self.previous_cwd) {
+            ("-", Some(previous)) => Ok(previous.clone()),
+            ("-", None) => Err("cd: OLDPWD not set".to_string()),
+            _ => resolve_dir(&self.cwd, &target),
+        };
+        match dir {
+            Ok(dir) => {
+                self.previous_cwd = Some(std::mem::replace(&mut self.cwd, dir));
+                self.python_env = pyenv::detect(&self.cwd);
+                CdIntercept::Changed(rest)
+            }
+            Err(e) => CdIntercept::Failed(e),
+        }
+    }
+
+    /// Working directory for display, with the home directory shown as `~`
+    pub fn display_cwd(&self) -> String {
+        let cwd = self.cwd.to_string_lossy().into_owned();
+        match dirs::home_dir() {
+            Some(home) if self.cwd.starts_with(&home) => {
+                cwd.replacen(home.to_string_lossy().as_ref(), "~", 1)
+            }
+            _ => cwd,
         }
     }
 
-    /// Cancel any running task
+    /// Cancel any running task and kill the process trees it started
     pub fn cancel_task(&mut self) {
+        let (killed, _) = self.kill_processes();
+        tracing::info!(state = ?self.state, killed = killed.len(), "task cancelled");
+        if !killed.is_empty() {
+            self.add_message(Message::system(format!(
+                "🛑 Killed:\n{}",
+                killed.join("\n")
+            )));
+        }
+    }
+
+    /// Abort the running task and kill its process groups, reporting each
+    ///
+    /// The returned task escalates to SIGKILL; only an exiting caller needs
+    /// to await it.
+    pub fn kill_processes(&mut self) -> (Vec<String>, Option<tokio::task::JoinHandle<()>>) {
         if let Some(handle) = self.running_task.take() {
             handle.abort();
         }
+        self.processes.kill_all()
     }
 
     /// Start all configured MCP servers
@@ -215,7 +1057,7 @@ impl<'a> App<'a> {
             return String::new();
         }
 
-        let mut prompt = String::from("\n\n6. Call MCP external tools:\n   {\"tool\": \"mcp\", \"server\": \"<server>\", \"name\": \"<tool_name>\", \"arguments\": {<args>}}\n\nAvailable MCP tools:\n");
+        let mut prompt = String::from("\n\n10. Call MCP external tools:\n   {\"tool\": \"mcp\", \"server\": \"<server>\", \"name\": \"<tool_name>\", \"arguments\": {<args>}}\n\nAvailable MCP tools:\n");
         for (server, tools) in &all_tools {
             for tool in tools {
                 let desc = tool.description.as_deref().unwrap_or("").lines().next().unwrap_or("");
@@ -244,6 +1086,36 @@ impl<'a> App<'a> {
         self.action_textarea.lines().join("\n").trim().to_string()
     }
 
+    /// The action under review, even while the confirmation phrase is typed
+    pub fn pending_action_text(&self) -> String {
+        match (self.danger_confirm_step, &self.current_command) {
+            (2, Some(cmd)) => cmd.clone(),
+            _ => self.get_action_text(),
+        }
+    }
+
+    /// Conversation to send when asking the AI to explain the pending action
+    ///
+    /// The question is not added to `messages`, so it never becomes part of
+    /// the ReAct history.
+    pub fn explain_request(&self) -> Vec<Message> {
+        let flagged = if self.dangerous_command_detected {
+            "It was flagged as DANGEROUS by the safety checks.\n"
+        } else {
+            ""
+        };
+        let mut messages = self.context_messages();
+        messages.push(Message::user(format!(
+            "Before I approve it, explain exactly what this pending action does, \
+             step by step, and list its risks (data loss, privilege use, network \
+             access, whether it can be undone). {}Reply in plain text only and do \
+             not call any tools.\n\n```\n{}\n```",
+            flagged,
+            self.pending_action_text()
+        )));
+        messages
+    }
+
     /// Check if the input is empty (whitespace-only counts as empty)
     pub fn is_input_empty(&self) -> bool {
         self.get_input_text().is_empty()
@@ -273,6 +1145,9 @@ impl<'a> App<'a> {
     pub fn clear_action(&mut self) {
         self.action_textarea = TextArea::default();
         self.dangerous_command_detected = false;
+        self.danger_reasons.clear();
+        self.reasoning = None;
+        self.tool_batch.clear();
     }
 
     /// Set the action textarea content (for command review)
@@ -290,11 +1165,22 @@ impl<'a> App<'a> {
 
     /// Add a message to the conversation history
     pub fn add_message(&mut self, message: Message) {
+        crate::stats::record(&self.config, &message);
         self.messages.push(message);
         // Reset scroll to show latest message
         self.scroll_offset = 0;
     }
 
+    /// Time spent in the current state (thinking, running a command)
+    pub fn phase_elapsed(&self) -> Duration {
+        self.phase_started.elapsed()
+    }
+
+    /// Show a status report as a toast instead of a chat message
+    pub fn toast(&mut self, text: impl Into<String>) {
+        self.toasts.push(text, Instant::now());
+    }
+
     /// Get usage statistics for current session
     pub fn get_usage_stats(&self) -> String {
         let total_messages = self.messages.len();
@@ -313,72 +1199,149 @@ impl<'a> App<'a> {
             .iter()
             .filter(|m| m.role == MessageRole::System)
             .count();
+        let tool_messages = self
+            .messages
+            .iter()
+            .filter(|m| m.role == MessageRole::Tool)
+            .count();
 
         // Estimate tokens (rough: ~4 chars per token)
-        let total_chars: usize = self.messages.iter().map(|m| m.content.len()).sum();
-        let estimated_tokens = total_chars / 4;
+        let estimated_tokens: usize = self
+            .messages
+            .iter()
+            .map(|m| models::estimate_tokens(&m.content))
+            .sum();
 
         // Count images
         let images = self.messages.iter().filter(|m| m.image.is_some()).count();
 
-        // Gemini 2.5 Flash context window
-        let context_limit = 1_000_000;
-        let usage_percent = (estimated_tokens as f64 / context_limit as f64) * 100.0;
+        let (sent, context_limit) = self.context_usage();
+        let usage_percent = (sent as f64 / context_limit as f64) * 100.0;
 
         format!(
             "📊 Session Usage Stats\n\
              ─────────────────────\n\
-             Session ID: {}\n\
+             Session: {} ({})\n\
              Messages: {} total\n\
              • User: {}\n\
              • AI: {}\n\
              • System: {}\n\
+             • Tool results: {}\n\
              Images: {}\n\
              ─────────────────────\n\
              Est. tokens: ~{}\n\
-             Context: {:.2}% of 1M",
+             Context: {:.2}% of {}",
+            self.session_name(),
             self.current_session_id,
             total_messages,
             user_messages,
             model_messages,
             system_messages,
+            tool_messages,
             images,
             estimated_tokens,
-            usage_percent
+            usage_percent,
+            models::short_count(context_limit)
         )
     }
 
-    /// Export chat history to markdown file
-    pub fn export_to_markdown(&self, filename: &str) -> std::io::Result<()> {
-        use std::io::Write;
+    /// Messages to send to the AI: the conversation, with messages covered
+    /// by the summary replaced by it
+    pub fn context_messages(&self) -> Vec<Message> {
+        let max_history = self.config.max_history_messages;
+        let mut messages = summary::context(&self.messages, self.summary.as_ref(), max_history);
+        let template = self.template.as_deref().and_then(|name| {
+            let prompt = self.config.templates.get(name)?.prompt(name);
+            Some(prompt).filter(|p| !p.is_empty())
+        });
+        if let Some(template) = template {
+            for msg in messages.iter_mut().filter(|m| m.is_system_prompt()) {
+                msg.content.push_str(&template);
+            }
+        }
+        messages
+    }
 
-        let mut file = std::fs::File::create(filename)?;
+    /// Request for a new summary and the messages it will cover, if the
+    /// history has outgrown the context window since the last one
+    pub fn start_summary(&mut self) -> Option<(Vec<Message>, usize)> {
+        if !self.config.summarize_history || self.summarizing {
+            return None;
+        }
+        let history: Vec<Message> = self
+            .messages
+            .iter()
+            .filter(|m| m.role != MessageRole::System)
+            .cloned()
+            .collect();
+        if history.len() <= self.summary_attempt {
+            return None;
+        }
+        let covered = self.summary.as_ref().map_or(0, |s| s.covers);
+        let max_history = self.config.max_history_messages;
+        let covers = summary::due(history.len(), covered, max_history)?;
+        self.summarizing = true;
+        self.summary_attempt = history.len();
+        let previous = self.summary.as_ref().filter(|s| s.covers <= covers);
+        let start = previous.map_or(0, |s| s.covers);
+        Some((summary::request(previous, &history[start..covers]), covers))
+    }
 
-        writeln!(file, "# Sabi Chat Export")?;
-        writeln!(
-            file,
-            "\nSession: {} | Exported: {}\n",
-            self.current_session_id,
-            chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
-        )?;
-        writeln!(file, "---\n")?;
-
-        for msg in &self.messages {
-            let (prefix, role) = match msg.role {
-                MessageRole::User => ("👤", "User"),
-                MessageRole::Model => ("🤖", "Assistant"),
-                MessageRole::System => ("⚙️", "System"),
-            };
+    /// Use the summary of the first `covers` messages of session `id`
+    ///
+    /// A summary arriving after switching sessions is dropped.
+    pub fn finish_summary(&mut self, id: &str, text: String, covers: usize) {
+        self.summarizing = false;
+        if id != self.current_session_id || text.trim().is_empty() {
+            return;
+        }
+        self.summary = Some(Summary {
+            text: text.trim().to_string(),
+            covers,
+        });
+        self.autosave_due
+            .get_or_insert_with(|| Instant::now() + AUTOSAVE_DELAY);
+    }
 
-            writeln!(file, "## {} {}\n", prefix, role)?;
-            writeln!(file, "{}\n", msg.content)?;
+    /// Estimated tokens sent with the next request, and the model's window
+    ///
+    /// Providers send the system prompt and the last `max_history_messages`
+    /// other messages.
+    pub fn context_usage(&self) -> (usize, usize) {
+        let context = self.context_messages();
+        let prompt: usize = context
+            .iter()
+            .filter(|m| m.is_system_prompt())
+            .map(|m| models::estimate_tokens(&m.content))
+            .sum();
+        let history: usize = context
+            .iter()
+            .filter(|m| m.role != MessageRole::System)
+            .rev()
+            .take(self.config.max_history_messages)
+            .map(|m| models::estimate_tokens(&m.content))
+            .sum();
+        let window = self
+            .config
+            .context_window
+            .unwrap_or_else(|| models::context_window(&self.config.model));
+        (prompt + history, window.max(1))
+    }
 
-            if msg.image.is_some() {
-                writeln!(file, "*[Image attached]*\n")?;
-            }
-        }
+    /// Export the conversation to `filename` in `format`
+    ///
+    /// Relative paths are resolved against the working directory; returns
+    /// the path written.
+    pub fn export_to(&self, filename: &str, format: Format) -> std::io::Result<PathBuf> {
+        let path = export::resolve_path(&self.cwd, filename);
+        let document = export::render(format, &self.session_name(), &self.messages);
+        std::fs::write(&path, document)?;
+        Ok(path)
+    }
 
-        Ok(())
+    /// The conversation as shown in the chat, as Markdown
+    pub fn conversation_markdown(&self) -> String {
+        export::markdown(&self.session_name(), &self.messages)
     }
 
     /// Clear the error message
@@ -395,13 +1358,35 @@ impl<'a> App<'a> {
     ///
     /// Returns true if the transition was successful, false otherwise.
     pub fn transition(&mut self, event: StateEvent) -> bool {
-        match transition(self.state, event) {
+        match transition(self.state, event.clone()) {
             TransitionResult::Success(new_state) => {
-                self.state = new_state;
+                // Start each execution with an empty output pane
+                if new_state == AppState::Executing {
+                    self.execution_output.clear();
+                    self.output_scroll = 0;
+                }
+                if new_state != self.state {
+                    tracing::debug!(from = ?self.state, to = ?new_state, ?event, "state transition");
+                    self.phase_started = Instant::now();
+                    // A reply or tool result came in: save it soon
+                    if self.is_busy() {
+                        self.autosave_due
+                            .get_or_insert_with(|| Instant::now() + AUTOSAVE_DELAY);
+                    }
+                }
+                self.state = new_state;
+                // The input and the output share the middle pane, unless a
+                // message is being typed ahead
+                let typing_ahead =
+                    self.focus == Pane::Input && self.is_busy() && !self.is_input_empty();
+                if self.focus != Pane::Chat && !typing_ahead {
+                    self.focus = self.middle_pane();
+                }
                 true
             }
             TransitionResult::Ignored => false,
             TransitionResult::Error(msg) => {
+                tracing::warn!(state = ?self.state, ?event, "{}", msg);
                 self.set_error(msg);
                 false
             }
@@ -412,6 +1397,13 @@ impl<'a> App<'a> {
     ///
     /// Returns SubmitResult indicating what action to take
     pub fn submit_input(&mut self) -> SubmitResult {
+        // Input answers the snippet's current placeholder (empty = default)
+        if self.snippet_fill.is_some() {
+            let value = self.get_input_text();
+            self.clear_input();
+            return self.answer_snippet(&value);
+        }
+
         let is_empty = self.is_input_empty();
 
         if is_empty && self.pending_image.is_none() {
@@ -462,8 +1454,6 @@ impl<'a> App<'a> {
 
     /// Execute shell escape command (!) directly without AI
     fn execute_shell_escape(&mut self, cmd: &str) -> SubmitResult {
-        use crate::executor::CommandExecutor;
-        
         // Block commands that break TUI
         let base_cmd = cmd.split_whitespace().next().unwrap_or("");
         if matches!(base_cmd, "clear" | "reset" | "tput") {
@@ -472,8 +1462,19 @@ impl<'a> App<'a> {
         }
         
         self.add_message(Message::system(format!("$ {}", cmd)));
-        let executor = CommandExecutor::new(&self.config);
-        let result = executor.execute(cmd);
+        let cmd = match self.intercept_cd(cmd) {
+            CdIntercept::NotCd => cmd.to_string(),
+            CdIntercept::Changed(Some(rest)) => rest,
+            CdIntercept::Changed(None) => {
+                self.add_message(Message::system(format!("✓ {}", self.display_cwd())));
+                return SubmitResult::Handled;
+            }
+            CdIntercept::Failed(e) => {
+                self.add_message(Message::system(format!("✗ {}", e)));
+                return SubmitResult::Handled;
+            }
+        };
+        let result = self.executor().execute(&cmd);
         let output = if !result.stdout.is_empty() {
             result.stdout
         } else if !result.stderr.is_empty() {
@@ -491,31 +1492,55 @@ impl<'a> App<'a> {
         let parts: Vec<&str> = input.trim().splitn(2, ' ').collect();
         let cmd = parts[0].to_lowercase();
         let arg = parts.get(1).map(|s| s.trim());
+        tracing::info!(command = %cmd, "slash command");
 
         match cmd.as_str() {
             "/clear" => {
                 // Keep only system prompt
                 self.messages
                     .retain(|m| m.role == crate::message::MessageRole::System);
-                self.add_message(Message::system("Chat cleared."));
+                self.reset_chat_view();
+                self.summary = None;
+                self.summary_attempt = 0;
+                self.toast("Chat cleared.");
                 SubmitResult::Handled
             }
             "/help" => {
                 self.add_message(Message::system(
                     "Available commands:\n\
-                     /new - Start new session\n\
-                     /sessions - List all sessions\n\
-                     /switch <id> - Switch to session\n\
-                     /delete <id> - Delete session\n\
+                     /new [template] - Start new session, optionally from a template\n\
+                     /sessions - Browse sessions (Enter switch, d delete, r rename)\n\
+                     /resume [id|name] - Resume a recent session (1-9, 0 pick)\n\
+                     /workspace [switch <name>|off] - List or switch workspaces\n\
+                     /rename <name> - Name this session\n\
+                     /switch <id|name> - Switch to session\n\
+                     /delete <id|name> - Delete session\n\
                      /image <path> [prompt] - Analyze image\n\
+                     /edit <path> - Open file in $EDITOR\n\
                      /model [name] - List or switch model\n\
+                     /config [set <key> <value>] - Show settings, or change and save one\n\
+                     /setup - Change the provider, API key and model\n\
+                     /patterns [add|remove|test] - List, add, remove or test dangerous patterns\n\
                      /usage - Show session stats\n\
-                     /export [file.md] - Export chat to markdown\n\
+                     /export [md|html] [path] - Export chat to Markdown or HTML (Ctrl+E asks for the file)\n\
+                     /find <text> - Search the chat (n/N next/previous match)\n\
+                     /multiline - Toggle Enter inserting newlines (Ctrl+S sends)\n\
+                     /compact - Toggle the compact layout for small panes\n\
+                     /output - View full output of the last command (Ctrl+G)\n\
+                     /artifact [id] - List stored outputs and files, or view one\n\
+                     /rerun - Review the last tool call again (Ctrl+R)\n\
+                     /pyreset - Restart the persistent Python session\n\
+                     /snippet [name] - List snippets or run one (no AI)\n\
+                     /target [local|container:<name>] - Run commands on the host or in a container\n\
+                     /undo [n] - List or revert the last n file changes\n\
+                     /checkpoints - List git checkpoints\n\
+                     /rollback <id> - Restore files from a git checkpoint\n\
                      /clear - Clear chat history\n\
                      /help - Show this help\n\
                      /quit - Exit application\n\n\
                      Shell escape:\n\
-                     !<command> - Run shell command directly (no AI)",
+                     !<command> - Run shell command directly (no AI)\n\n\
+                     Press F1 for the keybindings.",
                 ));
                 SubmitResult::Handled
             }
@@ -525,12 +1550,9 @@ impl<'a> App<'a> {
                 SubmitResult::Handled
             }
             "/export" => {
-                let filename = arg.unwrap_or("chat_export.md");
-                match self.export_to_markdown(filename) {
-                    Ok(_) => {
-                        self.add_message(Message::system(format!("✓ Exported to {}", filename)))
-                    }
-                    Err(e) => self.add_message(Message::system(format!("✗ Export failed: {}", e))),
+                match export::parse_args(arg.unwrap_or_default()) {
+                    (format, Some(filename)) => self.export_chat(filename, format),
+                    (format, None) => self.open_export_prompt(format),
                 }
                 SubmitResult::Handled
             }
@@ -558,74 +1580,224 @@ impl<'a> App<'a> {
                 }
                 SubmitResult::Handled
             }
-            "/new" => {
-                self.new_session();
-                self.add_message(Message::system(format!(
-                    "New session started: {}",
-                    self.current_session_id
-                )));
+            "/new" => match arg.map(str::trim).filter(|name| !name.is_empty()) {
+                None => {
+                    self.new_session();
+                    self.toast(format!("New session started: {}", self.session_name()));
+                    SubmitResult::Handled
+                }
+                Some(name) => match self.new_session_from(name) {
+                    Ok(model) => {
+                        self.toast(format!("New session from template '{}'", name));
+                        match model {
+                            Some(model) => SubmitResult::FetchModels(Some(model)),
+                            None => SubmitResult::Handled,
+                        }
+                    }
+                    Err(e) => {
+                        self.toast(format!("✗ {}", e));
+                        SubmitResult::Handled
+                    }
+                },
+            },
+            "/rename" => {
+                match arg.map(str::trim).filter(|name| !name.is_empty()) {
+                    Some(name) => {
+                        let id = self.current_session_id.clone();
+                        match self.rename_session(&id, name) {
+                            Ok(_) => self.toast(format!("✓ Renamed session to '{}'", name)),
+                            Err(e) => self.toast(format!("✗ Failed to rename: {}", e)),
+                        }
+                    }
+                    None => self.add_message(Message::system("Usage: /rename <name>")),
+                }
                 SubmitResult::Handled
             }
             "/sessions" => {
-                let sessions = Self::list_sessions();
+                let sessions = self.workspace_sessions();
                 if sessions.is_empty() {
                     self.add_message(Message::system("No saved sessions."));
                 } else {
-                    let list: Vec<String> = sessions
-                        .iter()
-                        .map(|s| {
-                            let marker = if s.id == self.current_session_id {
-                                "→ "
-                            } else {
-                                "  "
-                            };
-                            format!(
-                                "{}{} | {} | {}",
-                                marker,
-                                s.id,
-                                s.timestamp.split('T').next().unwrap_or(""),
-                                s.preview()
-                            )
-                        })
-                        .collect();
-                    self.add_message(Message::system(format!("Sessions:\n{}", list.join("\n"))));
+                    self.session_picker =
+                        Some(SessionPicker::new(sessions, &self.current_session_id));
                 }
                 SubmitResult::Handled
             }
-            "/switch" => {
-                if let Some(id) = arg {
-                    match self.switch_session(id) {
-                        Ok(_) => self
-                            .add_message(Message::system(format!("Switched to session: {}", id))),
-                        Err(e) => {
-                            self.add_message(Message::system(format!("Failed to switch: {}", e)))
+            "/workspace" => {
+                let report = self.workspace_command(arg);
+                self.add_message(Message::system(report));
+                SubmitResult::Handled
+            }
+            "/resume" if arg.is_none_or(str::is_empty) => {
+                if !self.open_recent() {
+                    self.toast("No other saved sessions.");
+                }
+                SubmitResult::Handled
+            }
+            "/switch" | "/resume" => {
+                if let Some(arg) = arg {
+                    let id = Self::find_session(arg);
+                    match self.switch_session(&id) {
+                        Ok(_) => {
+                            self.toast(format!("✓ Switched to session: {}", self.session_name()))
                         }
+                        Err(e) => self.toast(format!("✗ Failed to switch: {}", e)),
                     }
                 } else {
-                    self.add_message(Message::system("Usage: /switch <session_id>"));
+                    self.add_message(Message::system("Usage: /switch <id|name>"));
                 }
                 SubmitResult::Handled
             }
             "/delete" => {
-                if let Some(id) = arg {
+                if let Some(arg) = arg {
+                    let id = Self::find_session(arg);
                     if id == self.current_session_id {
                         self.add_message(Message::system(
                             "Cannot delete current session. Switch first.",
                         ));
                     } else {
-                        match Self::delete_session(id) {
-                            Ok(_) => self
-                                .add_message(Message::system(format!("Deleted session: {}", id))),
-                            Err(e) => self
-                                .add_message(Message::system(format!("Failed to delete: {}", e))),
+                        match Self::delete_session(&id) {
+                            Ok(_) => self.toast(format!("✓ Deleted session: {}", arg)),
+                            Err(e) => self.toast(format!("✗ Failed to delete: {}", e)),
                         }
                     }
                 } else {
-                    self.add_message(Message::system("Usage: /delete <session_id>"));
+                    self.add_message(Message::system("Usage: /delete <id|name>"));
                 }
                 SubmitResult::Handled
             }
+            "/edit" => {
+                if let Some(path) = arg.filter(|p| !p.is_empty()) {
+                    SubmitResult::OpenEditor(path.to_string())
+                } else {
+                    self.add_message(Message::system("Usage: /edit <path>"));
+                    SubmitResult::Handled
+                }
+            }
             "/model" => SubmitResult::FetchModels(arg.map(String::from)),
+            "/config" => self.config_command(arg),
+            "/setup" => {
+                self.setup = Some(SetupWizard::new(&self.config));
+                SubmitResult::Handled
+            }
+            "/patterns" => {
+                let report = self.patterns_command(arg);
+                self.add_message(Message::system(report));
+                SubmitResult::Handled
+            }
+            "/rerun" => SubmitResult::Rerun,
+            "/pyreset" => {
+                let message = if !self.config.python_session {
+                    "The Python session is off. Set python_session = true in config to enable it."
+                } else if self.python_session.reset() {
+                    "Python session restarted; variables were cleared."
+                } else {
+                    "No Python session running."
+                };
+                self.toast(message);
+                SubmitResult::Handled
+            }
+            "/snippet" => self.start_snippet(arg),
+            "/target" => match arg
+                .and_then(|a| a.strip_prefix("container:"))
+                .filter(|name| !name.is_empty())
+            {
+                // Starting a container can pull an image; main connects off-thread
+                Some(name) => {
+                    self.toast(format!("Connecting to container {}...", name));
+                    SubmitResult::ConnectTarget(name.to_string())
+                }
+                None => {
+                    let report = self.set_target(arg);
+                    self.add_message(Message::system(report));
+                    SubmitResult::Handled
+                }
+            },
+            "/multiline" => {
+                self.multiline = !self.multiline;
+                self.toast(if self.multiline {
+                    "Multi-line input on: Enter inserts a newline, Ctrl+S sends."
+                } else {
+                    "Multi-line input off: Enter sends (Alt+Enter for a newline)."
+                });
+                SubmitResult::Handled
+            }
+            "/compact" => {
+                self.compact = !self.compact;
+                self.toast(if self.compact {
+                    "Compact layout on."
+                } else {
+                    "Compact layout off."
+                });
+                SubmitResult::Handled
+            }
+            "/find" => {
+                match arg {
+                    Some(query) if !query.is_empty() => self.find_in_chat(query),
+                    _ => self.add_message(Message::system("Usage: /find <text>")),
+                }
+                SubmitResult::Handled
+            }
+            "/output" => {
+                if let Err(e) = self.open_output_pager() {
+                    self.add_message(Message::system(e));
+                }
+                SubmitResult::Handled
+            }
+            "/artifact" => {
+                match arg.filter(|a| !a.is_empty()) {
+                    Some(id) => {
+                        if let Err(e) = self.open_artifact(id) {
+                            self.add_message(Message::system(e));
+                        }
+                    }
+                    None => {
+                        let list = self.list_artifacts();
+                        self.add_message(Message::system(list));
+                    }
+                }
+                SubmitResult::Handled
+            }
+            "/undo" => {
+                let report = self.undo_files(arg);
+                self.add_message(Message::system(report));
+                SubmitResult::Handled
+            }
+            "/checkpoints" => {
+                let report = match checkpoint::list(&self.cwd, 20) {
+                    Ok(list) if list.is_empty() => {
+                        "No checkpoints yet. Enable git_checkpoints in config to create them."
+                            .to_string()
+                    }
+                    Ok(list) => {
+                        let lines: Vec<String> = list
+                            .iter()
+                            .map(|c| format!("  {}  {:<16} {}", c.id, c.age, c.label))
+                            .collect();
+                        format!(
+                            "Checkpoints (most recent first):\n{}\n\nUse /rollback <id> to restore.",
+                            lines.join("\n")
+                        )
+                    }
+                    Err(e) => format!("✗ {}", e),
+                };
+                self.add_message(Message::system(report));
+                SubmitResult::Handled
+            }
+            "/rollback" => {
+                let report = match arg {
+                    Some(id) => match checkpoint::rollback(&self.cwd, id.trim()) {
+                        Ok(()) => format!(
+                            "✓ Restored files from checkpoint {} (previous state saved as a new checkpoint)",
+                            id.trim()
+                        ),
+                        Err(e) => format!("✗ Rollback failed: {}", e),
+                    },
+                    None => "Usage: /rollback <id> (see /checkpoints)".to_string(),
+                };
+                self.add_message(Message::system(report));
+                SubmitResult::Handled
+            }
             "/quit" | "/exit" | "/q" => {
                 self.should_quit = true;
                 SubmitResult::Quit
@@ -640,28 +1812,196 @@ impl<'a> App<'a> {
         }
     }
 
+    /// Show where run_cmd and run_python execute, or switch back to the host
+    fn set_target(&mut self, arg: Option<&str>) -> String {
+        let usage = "Usage: /target local | /target container:<name>";
+        match arg.filter(|a| !a.is_empty()) {
+            None => match self.target {
+                Some(ref target) => {
+                    format!("Commands run in container {}.\n{}", target.label(), usage)
+                }
+                None => format!("Commands run on the host.\n{}", usage),
+            },
+            Some("local" | "host") => match self.target.take() {
+                Some(old) => {
+                    container::stop(&old);
+                    self.add_message(Message::user(
+                        "[Execution target: run_cmd and run_python run on the host again.]",
+                    ));
+                    "✓ Commands run on the host again.".to_string()
+                }
+                None => "Commands already run on the host.".to_string(),
+            },
+            Some(_) => usage.to_string(),
+        }
+    }
+
+    /// Switch to the container `/target container:<name>` connected to
+    pub fn finish_target(&mut self, result: Result<ContainerTarget, String>) {
+        let report = match result {
+            Ok(target) => {
+                if let Some(old) = self.target.take()
+                    && old.container != target.container
+                {
+                    container::stop(&old);
+                }
+                self.add_message(Message::user(target.context_note()));
+                let report = format!("✓ Commands now run in container {}.", target.label());
+                self.target = Some(target);
+                report
+            }
+            Err(e) => format!("✗ {}", e),
+        };
+        self.add_message(Message::system(report));
+    }
+
+    /// List snippets, or start filling in the one called `name`
+    fn start_snippet(&mut self, name: Option<&str>) -> SubmitResult {
+        let snippets = match snippets::load() {
+            Ok(snippets) => snippets,
+            Err(e) => {
+                self.add_message(Message::system(format!("✗ {}", e)));
+                return SubmitResult::Handled;
+            }
+        };
+        let Some(name) = name.filter(|n| !n.is_empty()) else {
+            let report = if snippets.is_empty() {
+                "No snippets yet. Add them to ~/.sabi/snippets.toml, e.g.\n\n\
+                 [snippets.logs]\n\
+                 description = \"Recent service logs\"\n\
+                 command = \"journalctl -u {{service}} -n {{lines:100}}\""
+                    .to_string()
+            } else {
+                let list: Vec<String> = snippets
+                    .iter()
+                    .map(|(name, s)| {
+                        format!("  {} - {}\n      $ {}", name, s.description, s.command)
+                    })
+                    .collect();
+                format!(
+                    "Snippets:\n{}\n\nRun one with /snippet <name>.",
+                    list.join("\n")
+                )
+            };
+            self.add_message(Message::system(report));
+            return SubmitResult::Handled;
+        };
+        match snippets.get(name) {
+            Some(snippet) => {
+                self.snippet_fill = Some(snippets::Fill::new(name, snippet.clone()));
+                self.advance_snippet()
+            }
+            None => {
+                self.add_message(Message::system(format!(
+                    "Unknown snippet: {}. /snippet lists them.",
+                    name
+                )));
+                SubmitResult::Handled
+            }
+        }
+    }
+
+    /// Use `value` for the current snippet placeholder
+    fn answer_snippet(&mut self, value: &str) -> SubmitResult {
+        if let Some(ref mut fill) = self.snippet_fill
+            && let Err(e) = fill.answer(value)
+        {
+            self.add_message(Message::system(format!("✗ {}", e)));
+            return SubmitResult::Handled;
+        }
+        self.advance_snippet()
+    }
+
+    /// Ask for the next snippet value, or hand the finished command to review
+    fn advance_snippet(&mut self) -> SubmitResult {
+        let Some(ref fill) = self.snippet_fill else {
+            return SubmitResult::Handled;
+        };
+        let name = fill.name.clone();
+        if let Some(prompt) = fill.prompt() {
+            self.input_textarea
+                .set_placeholder_text(format!("{}: {}", name, prompt));
+            self.add_message(Message::system(format!(
+                "Snippet {}: enter {} (Esc cancels)",
+                name, prompt
+            )));
+            return SubmitResult::Handled;
+        }
+        let command = fill.command().unwrap_or_default();
+        self.snippet_fill = None;
+        self.add_message(Message::system(format!("Snippet {}: $ {}", name, command)));
+        SubmitResult::ReviewTool(Box::new(ToolCall::run_cmd(command)))
+    }
+
+    /// List undoable file changes, or revert the last `n` of them
+    fn undo_files(&mut self, arg: Option<&str>) -> String {
+        if self.undo.is_empty() {
+            return "Nothing to undo.".to_string();
+        }
+        let Some(arg) = arg else {
+            let list: Vec<String> = self
+                .undo
+                .list()
+                .enumerate()
+                .map(|(i, s)| format!("  {}. {}", i + 1, s.describe()))
+                .collect();
+            return format!(
+                "File changes (most recent first):\n{}\n\nUse /undo <n> to revert the last n.",
+                list.join("\n")
+            );
+        };
+        match arg.trim().parse::<usize>() {
+            Ok(n) if n > 0 => format!("Reverted:\n{}", self.undo.undo(n).join("\n")),
+            _ => "Usage: /undo [n]".to_string(),
+        }
+    }
+
     /// Save session to file
     fn save_session(&self, filename: &str) -> std::io::Result<()> {
         let mut session = Session::from_messages(&self.messages);
         session.id = self.current_session_id.clone();
+        if let Some(ref name) = self.current_session_name {
+            session.name = name.clone();
+        }
+        session.cwd = self.cwd.to_string_lossy().into_owned();
+        session.summary = self.summary.clone();
+        session.template = self.template.clone();
+        session.workspace = self.workspace.clone();
+        let limit = self.config.session_output_limit;
+        compaction::compact(&mut session.messages, limit, artifacts::store);
         let json = serde_json::to_string_pretty(&session).map_err(std::io::Error::other)?;
-        std::fs::write(filename, json)
+        write_atomic(std::path::Path::new(filename), &json)
     }
 
     /// Load session from file
     fn load_session(&mut self, filename: &str) -> std::io::Result<()> {
         let json = std::fs::read_to_string(filename)?;
-        let session: Session = serde_json::from_str(&json).map_err(std::io::Error::other)?;
+        let mut session: Session = serde_json::from_str(&json).map_err(std::io::Error::other)?;
+        // Sessions saved before compaction existed may still be bulky
+        let limit = self.config.session_output_limit;
+        compaction::compact(&mut session.messages, limit, artifacts::store);
         self.messages
             .retain(|m| m.role == crate::message::MessageRole::System);
+        self.reset_chat_view();
         self.messages.extend(session.messages);
         self.current_session_id = session.id;
+        self.current_session_name = Some(session.name);
+        self.summary = session.summary;
+        self.summary_attempt = 0;
+        self.template = session.template;
+        self.workspace = session.workspace;
+        // Restore the session's directory if it still exists
+        let cwd = PathBuf::from(&session.cwd);
+        if !self.cwd_pinned && !session.cwd.is_empty() && cwd.is_dir() {
+            self.cwd = cwd;
+            self.python_env = pyenv::detect(&self.cwd);
+        }
         Ok(())
     }
 
-    /// Get sessions directory (~/.sabi/sessions/)
+    /// Get sessions directory (`sessions/` in the data directory)
     pub fn sessions_dir() -> Option<std::path::PathBuf> {
-        dirs::home_dir().map(|d| d.join(".sabi").join("sessions"))
+        crate::config::data_dir().map(|d| d.join("sessions"))
     }
 
     /// Get path for a specific session
@@ -692,12 +2032,299 @@ impl<'a> App<'a> {
         sessions
     }
 
+    /// Saved sessions of the current workspace, or all outside one
+    pub fn workspace_sessions(&self) -> Vec<Session> {
+        let mut sessions = Self::list_sessions();
+        if self.workspace.is_some() {
+            sessions.retain(|s| s.workspace == self.workspace);
+        }
+        sessions
+    }
+
+    /// Why `tool` needs confirmation for reaching outside the workspace
+    pub fn check_workspace_scope(&self, tool: &ToolCall) -> Option<DangerAssessment> {
+        let name = self.workspace.as_deref()?;
+        let workspace = self.config.workspaces.get(name)?;
+        if !workspace.scope_tools {
+            return None;
+        }
+        workspace::check_scope(name, &workspace.root_path(), &self.cwd, tool)
+    }
+
+    /// `/workspace [switch <name>|off]`
+    fn workspace_command(&mut self, arg: Option<&str>) -> String {
+        let arg = arg.unwrap_or("").trim();
+        let (action, name) = arg.split_once(' ').unwrap_or((arg, ""));
+        match (action, name.trim()) {
+            ("", _) | ("list", _) => {
+                if self.config.workspaces.is_empty() {
+                    return "No workspaces. Define [workspaces.<name>] with a root in config."
+                        .to_string();
+                }
+                let sessions = Self::list_sessions();
+                let lines: Vec<String> = self
+                    .config
+                    .workspaces
+                    .iter()
+                    .map(|(name, workspace)| {
+                        let count = sessions
+                            .iter()
+                            .filter(|s| s.workspace.as_deref() == Some(name))
+                            .count();
+                        let mark = if self.workspace.as_deref() == Some(name) {
+                            "→"
+                        } else {
+                            " "
+                        };
+                        let root = &workspace.root;
+                        format!("{} {}  {}  ({} sessions)", mark, name, root, count)
+                    })
+                    .collect();
+                format!(
+                    "Workspaces:\n{}\n\nUse /workspace switch <name> or /workspace off",
+                    lines.join("\n")
+                )
+            }
+            ("off", _) => {
+                self.workspace = None;
+                "Left the workspace; /sessions lists all sessions.".to_string()
+            }
+            ("switch", "") => "Usage: /workspace switch <name>".to_string(),
+            ("switch", name) => {
+                let Some(workspace) = self.config.workspaces.get(name) else {
+                    return format!("No workspace '{}'", name);
+                };
+                let root = workspace.root_path();
+                if !root.is_dir() {
+                    return format!("Workspace root {} doesn't exist", root.display());
+                }
+                self.new_session();
+                self.workspace = Some(name.to_string());
+                self.cwd = root;
+                self.python_env = pyenv::detect(&self.cwd);
+                let count = self.workspace_sessions().len();
+                format!(
+                    "Switched to workspace '{}' ({}); {} saved sessions, /resume picks one",
+                    name,
+                    self.cwd.display(),
+                    count
+                )
+            }
+            _ => "Usage: /workspace [switch <name>|off]".to_string(),
+        }
+    }
+
+    /// `/config [set <key> <value>]`
+    fn config_command(&mut self, arg: Option<&str>) -> SubmitResult {
+        let path = match Config::config_path() {
+            Ok(path) => path,
+            Err(e) => {
+                self.toast(format!("✗ {}", e));
+                return SubmitResult::Handled;
+            }
+        };
+        let arg = arg.unwrap_or("").trim();
+        let (action, rest) = arg.split_once(' ').unwrap_or((arg, ""));
+        let set = rest.trim().split_once(' ');
+        match (action, set) {
+            ("", _) => {
+                let file = settings::file_keys(&path);
+                let mut lines: Vec<String> = settings::warnings(&self.config, &file)
+                    .into_iter()
+                    .map(|warning| format!("⚠ {}", warning))
+                    .collect();
+                if !lines.is_empty() {
+                    lines.push(String::new());
+                }
+                lines.extend(settings::render(&self.config, &file));
+                self.pager = Some(Pager {
+                    title: format!("config · {}", path.display()),
+                    lines,
+                    ..Default::default()
+                });
+            }
+            ("set", Some(("api_key", value))) => {
+                let key = settings::unquote(value.trim());
+                match self.save_api_key(&path, key) {
+                    Ok(place) => self.toast(format!(
+                        "✓ Saved api_key to {} (takes effect after a restart)",
+                        place
+                    )),
+                    Err(e) => self.toast(format!("✗ {}", e)),
+                }
+            }
+            ("set", Some((key, value))) => match settings::set(&self.config, &path, key, value) {
+                Ok(config) => {
+                    self.config = config;
+                    self.paths_changed |= matches!(
+                        key,
+                        "writable_roots" | "protected_paths" | "protected_path_severity"
+                    );
+                    let mut note = format!("✓ Saved {} = {}", key, value.trim());
+                    if let Source::Env(var) = Source::of(key, &Default::default()) {
+                        note.push_str(&format!("; ${} overrides it at startup", var));
+                    } else if settings::RESTART_KEYS.contains(&key) {
+                        note.push_str(" (takes effect after a restart)");
+                    }
+                    self.toast(note);
+                    if key == "model" {
+                        return SubmitResult::FetchModels(Some(self.config.model.clone()));
+                    }
+                }
+                Err(e) => self.toast(format!("✗ {}", e)),
+            },
+            _ => self.add_message(Message::system("Usage: /config [set <key> <value>]")),
+        }
+        SubmitResult::Handled
+    }
+
+    /// `/patterns [list|add|remove|test]`
+    fn patterns_command(&mut self, arg: Option<&str>) -> String {
+        let arg = arg.unwrap_or("").trim();
+        let (action, rest) = arg.split_once(' ').unwrap_or((arg, ""));
+        let rest = rest.trim();
+        let mut patterns = self.config.dangerous_patterns.clone();
+        let done = match (action, rest) {
+            ("" | "list", _) => return patterns::list(&self.config),
+            ("test", "") => return "Usage: /patterns test <command>".to_string(),
+            ("test", command) => return patterns::test(&self.config, command),
+            ("add", rest) => match patterns::parse(rest) {
+                Ok(pattern) => {
+                    let severity = pattern.severity.name();
+                    let done = format!("✓ Added [{}] {}", severity, pattern.pattern);
+                    patterns.push(pattern);
+                    done
+                }
+                Err(e) => return e,
+            },
+            ("remove", "") => return "Usage: /patterns remove <n|regex>".to_string(),
+            ("remove", rest) => match patterns::find(&patterns, rest) {
+                Some(i) => format!("✓ Removed {}", patterns.remove(i).pattern),
+                None => return format!("No pattern '{}' (see /patterns)", rest),
+            },
+            _ => return "Usage: /patterns [list|add|remove|test]".to_string(),
+        };
+        // Patterns from .sabi.toml stay in the project file
+        let project: &[_] = match self.project {
+            Some(ref project) => &project.config.dangerous_patterns,
+            None => &[],
+        };
+        let user: Vec<_> = patterns
+            .iter()
+            .filter(|p| !project.contains(p))
+            .cloned()
+            .collect();
+        self.config.dangerous_patterns = patterns;
+        self.patterns_changed = true;
+        let saved = Config::config_path()
+            .map_err(|e| e.to_string())
+            .and_then(|path| patterns::save(&path, &user));
+        match saved {
+            Ok(()) => format!("{}; saved to config.toml", done),
+            Err(e) => format!("{} for this session (not saved: {})", done, e),
+        }
+    }
+
+    /// Handle keyboard events while `/setup` is open
+    fn handle_setup_key(&mut self, key: KeyEvent) -> InputResult {
+        let Some(ref mut wizard) = self.setup else {
+            return InputResult::Ignored;
+        };
+        match wizard.handle_key(key) {
+            SetupAction::None => {}
+            SetupAction::Close => self.setup = None,
+            SetupAction::Save(choice) => match self.apply_setup(&choice) {
+                Ok(note) => {
+                    self.setup = None;
+                    self.toast(note);
+                }
+                Err(e) => {
+                    if let Some(ref mut wizard) = self.setup {
+                        wizard.error = Some(e);
+                    }
+                }
+            },
+        }
+        InputResult::Handled
+    }
+
+    /// Save the settings chosen in `/setup` and switch to them
+    ///
+    /// Returns the note to show.
+    fn apply_setup(&mut self, choice: &Choice) -> Result<String, String> {
+        let path = Config::config_path().map_err(|e| e.to_string())?;
+        let provider = choice.provider.name();
+        let mut config = settings::set(&self.config, &path, "provider", provider)?;
+        config = settings::set(&config, &path, "model", &choice.model)?;
+        match choice.base_url {
+            Some(ref url) => config = settings::set(&config, &path, "base_url", url)?,
+            None => {
+                settings::remove(&path, "base_url")?;
+                config.base_url = None;
+            }
+        }
+        self.config = config;
+
+        let mut note = format!("✓ Saved {} · {}", provider, choice.model);
+        match choice.api_key.as_deref() {
+            None => {}
+            Some("") => {
+                settings::remove(&path, "api_key")?;
+                self.config.api_key.clear();
+            }
+            Some(key) => {
+                let place = self.save_api_key(&path, key)?;
+                note.push_str(&format!(", API key in {}", place));
+            }
+        }
+        self.client_changed = true;
+
+        let overrides: Vec<String> = ["provider", "base_url", "api_key", "model"]
+            .iter()
+            .filter_map(|key| match Source::of(key, &Default::default()) {
+                Source::Env(var) => Some(format!("${}", var)),
+                _ => None,
+            })
+            .collect();
+        if !overrides.is_empty() {
+            note.push_str(&format!("; {} still win at startup", overrides.join(", ")));
+        }
+        Ok(note)
+    }
+
+    /// Store the API key in the OS keychain, or in config.toml without one;
+    /// returns where it went
+    fn save_api_key(&mut self, path: &Path, key: &str) -> Result<String, String> {
+        let stored = keychain::store(&self.config.provider, key)
+            .and_then(|_| settings::remove(path, "api_key"));
+        match stored {
+            Ok(()) => {
+                self.config.api_key = key.to_string();
+                Ok("the system keychain".to_string())
+            }
+            Err(e) => {
+                self.config = settings::set(&self.config, path, "api_key", key)?;
+                Ok(format!("config.toml (no keychain: {})", e))
+            }
+        }
+    }
+
     /// Save current session
+    ///
+    /// A session with nothing said in it yet is only saved once renamed.
     pub fn save_current_session(&self) {
+        let said = self.messages.iter().any(|m| m.role != MessageRole::System);
+        if !said && self.current_session_name.is_none() {
+            return;
+        }
         if let Some(dir) = Self::sessions_dir() {
             let _ = std::fs::create_dir_all(&dir);
-            if let Some(path) = Self::session_path(&self.current_session_id) {
-                let _ = self.save_session(path.to_string_lossy().as_ref());
+            if let Some(path) = Self::session_path(&self.current_session_id)
+                && self.save_session(path.to_string_lossy().as_ref()).is_ok()
+            {
+                // Left behind if sabi dies, so the next start can recover
+                let _ = std::fs::write(crash_marker(&dir), &self.current_session_id);
+                session_sync::after_save(&self.config, &dir, &self.current_session_id);
             }
         }
     }
@@ -717,7 +2344,78 @@ impl<'a> App<'a> {
     pub fn new_session(&mut self) {
         self.save_current_session();
         self.messages.retain(|m| m.role == MessageRole::System);
+        self.reset_chat_view();
         self.current_session_id = chrono::Local::now().format("%Y%m%d_%H%M%S").to_string();
+        self.current_session_name = None;
+        self.summary = None;
+        self.summary_attempt = 0;
+        self.template = None;
+    }
+
+    /// Start a new session from template `name`
+    ///
+    /// Returns the template's model, to be switched to by the caller.
+    pub fn new_session_from(&mut self, name: &str) -> Result<Option<String>, String> {
+        let Some(template) = self.config.templates.get(name).cloned() else {
+            let names: Vec<&str> = self.config.templates.keys().map(String::as_str).collect();
+            return Err(if names.is_empty() {
+                format!("No template '{0}'; define [templates.{0}] in config", name)
+            } else {
+                format!("No template '{}'. Templates: {}", name, names.join(", "))
+            });
+        };
+        self.new_session();
+        self.template = Some(name.to_string());
+        if let Some(dir) = template.start_dir(&self.cwd) {
+            self.cwd = dir;
+            self.python_env = pyenv::detect(&self.cwd);
+        }
+        Ok(Some(template.model.trim().to_string()).filter(|m| !m.is_empty()))
+    }
+
+    /// Name of the current session, the default one until it is renamed
+    pub fn session_name(&self) -> String {
+        self.current_session_name
+            .clone()
+            .unwrap_or_else(|| Session::default_name(&self.current_session_id))
+    }
+
+    /// Id of the saved session `arg` names (ignoring case), or `arg` itself
+    /// taken as an id
+    fn find_session(arg: &str) -> String {
+        let arg = arg.trim();
+        let sessions = Self::list_sessions();
+        if sessions.iter().any(|s| s.id == arg) {
+            return arg.to_string();
+        }
+        sessions
+            .into_iter()
+            .find(|s| s.name.eq_ignore_ascii_case(arg))
+            .map_or_else(|| arg.to_string(), |s| s.id)
+    }
+
+    /// Read the saved session `arg` names by id or name
+    pub fn read_session(arg: &str) -> std::io::Result<Session> {
+        let path = Self::session_path(&Self::find_session(arg))
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "Invalid path"))?;
+        let json = std::fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(std::io::Error::other)
+    }
+
+    /// Set the name of a saved session (or the current one)
+    pub fn rename_session(&mut self, id: &str, name: &str) -> std::io::Result<()> {
+        if id == self.current_session_id {
+            self.current_session_name = Some(name.to_string());
+            self.save_current_session();
+            return Ok(());
+        }
+        let path = Self::session_path(id)
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "Invalid path"))?;
+        let json = std::fs::read_to_string(&path)?;
+        let mut session: Session = serde_json::from_str(&json).map_err(std::io::Error::other)?;
+        session.name = name.to_string();
+        let json = serde_json::to_string_pretty(&session).map_err(std::io::Error::other)?;
+        write_atomic(&path, &json)
     }
 
     /// Delete a session
@@ -732,16 +2430,120 @@ impl<'a> App<'a> {
         }
     }
 
-    /// Auto-save session to default location
+    /// Save the session on a clean exit and drop the crash marker
     pub fn auto_save(&self) {
         self.save_current_session();
+        if let Some(dir) = Self::sessions_dir() {
+            let _ = std::fs::remove_file(crash_marker(&dir));
+        }
     }
 
-    /// Auto-load most recent session
-    pub fn auto_load(&mut self) {
-        let sessions = Self::list_sessions();
-        if let Some(latest) = sessions.first() {
-            let _ = self.switch_session(&latest.id);
+    /// Save the session if a finished turn is waiting for it (on ticks)
+    pub fn autosave_if_due(&mut self, now: Instant) {
+        if self.autosave_due.is_some_and(|due| due <= now) {
+            self.autosave_due = None;
+            self.save_current_session();
+        }
+    }
+
+    /// Continue the session a sabi that didn't exit cleanly was in
+    ///
+    /// Returns whether a session was recovered.
+    pub fn recover_crashed_session(&mut self) -> bool {
+        let Some(id) = Self::sessions_dir().and_then(|dir| take_crashed_session(&dir)) else {
+            return false;
+        };
+        if self.switch_session(&id).is_err() {
+            return false;
+        }
+        self.toast(format!(
+            "↺ Recovered {} after an unexpected exit",
+            self.session_name()
+        ));
+        true
+    }
+
+    /// Most recent saved session with messages, other than the current one
+    pub fn last_session(&self) -> Option<Session> {
+        self.workspace_sessions()
+            .into_iter()
+            .find(|s| s.id != self.current_session_id && !s.messages.is_empty())
+    }
+
+    /// Continue the most recent session (`--continue`, `resume = "last"`)
+    pub fn resume_last(&mut self) {
+        if let Some(last) = self.last_session() {
+            let _ = self.switch_session(&last.id);
+        }
+    }
+
+    /// Open the quick list of recent sessions (`/resume`, `sabi --resume`)
+    ///
+    /// Returns whether there was any session to list.
+    pub fn open_recent(&mut self) -> bool {
+        let sessions: Vec<Session> = self
+            .workspace_sessions()
+            .into_iter()
+            .filter(|s| s.id != self.current_session_id && !s.messages.is_empty())
+            .collect();
+        if sessions.is_empty() {
+            return false;
+        }
+        self.session_picker = Some(SessionPicker::recent(sessions));
+        true
+    }
+
+    /// Ask whether to continue the most recent session (`resume = "ask"`)
+    pub fn offer_resume(&mut self) {
+        self.resume_offer = self.last_session();
+    }
+
+    /// Handle keyboard events while the resume prompt is open: y resumes,
+    /// n, Enter or Esc start fresh
+    fn handle_resume_offer_key(&mut self, key: KeyEvent) -> InputResult {
+        match key.code {
+            KeyCode::Char('y' | 'Y') => {
+                let Some(session) = self.resume_offer.take() else {
+                    return InputResult::Ignored;
+                };
+                match self.switch_session(&session.id) {
+                    Ok(()) => self.toast(format!("✓ Resumed {}", session.name)),
+                    Err(e) => self.toast(format!("✗ Failed to resume: {}", e)),
+                }
+            }
+            KeyCode::Char('n' | 'N') | KeyCode::Enter | KeyCode::Esc => self.resume_offer = None,
+            _ => {}
+        }
+        InputResult::Handled
+    }
+
+    /// Append a line of live command output, keeping at most
+    /// `max_output_lines` lines
+    pub fn append_output_chunk(&mut self, line: &str) {
+        self.execution_output.push_str(line);
+        self.execution_output.push('\n');
+
+        let max_lines = self.config.max_output_lines.max(1);
+        let line_count = self.execution_output.lines().count();
+        if line_count > max_lines {
+            let skip = line_count - max_lines;
+            self.execution_output = self
+                .execution_output
+                .lines()
+                .skip(skip)
+                .map(|l| format!("{}\n", l))
+                .collect();
+        }
+
+        // A zoomed output follows along unless it was scrolled up
+        if let Some(ref mut pager) = self.pager
+            && pager.live
+        {
+            let following = pager.scroll + Pager::PAGE >= pager.lines.len();
+            pager.lines = self.execution_output.lines().map(String::from).collect();
+            if following {
+                pager.scroll_to_end();
+            }
         }
     }
 
@@ -751,10 +2553,23 @@ impl<'a> App<'a> {
         self.spinner_frame = (self.spinner_frame + 1) % SPINNER_FRAMES;
     }
 
+    /// Tick interval: `tick_rate_ms` while busy, revealing a reply or
+    /// shortly after input, `idle_tick_rate_ms` otherwise
+    pub fn tick_rate(&self, since_input: Duration) -> Duration {
+        let (tick, idle) = self.config.tick_rates();
+        if self.is_busy() || self.reveal.is_some() || since_input < IDLE_AFTER {
+            tick
+        } else {
+            idle
+        }
+    }
+
     /// Get the current spinner character
     pub fn spinner_char(&self) -> char {
         const SPINNER: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
-        SPINNER[self.spinner_frame % SPINNER.len()]
+        const ASCII_SPINNER: &[char] = &['|', '/', '-', '\\'];
+        let frames = if self.ascii { ASCII_SPINNER } else { SPINNER };
+        frames[self.spinner_frame % frames.len()]
     }
 
     /// Handle a keyboard event based on the current state
@@ -767,9 +2582,98 @@ impl<'a> App<'a> {
             return InputResult::Quit;
         }
 
-        match self.state {
-            AppState::Input => self.handle_input_state(key),
-            AppState::Thinking => self.handle_thinking_state(key),
+        if self.resume_offer.is_some() {
+            return self.handle_resume_offer_key(key);
+        }
+
+        if let Some(result) = self.handle_help_key(key) {
+            return result;
+        }
+
+        // Any key shows the whole reply and does nothing else, so a key
+        // pressed to skip the animation can't approve a tool call
+        if self.reveal.take().is_some() {
+            return InputResult::Handled;
+        }
+
+        if self.pager.is_some() {
+            return self.handle_pager_key(key);
+        }
+
+        if self.session_picker.is_some() {
+            return self.handle_session_picker_key(key);
+        }
+
+        if self.export_path.is_some() {
+            return self.handle_export_prompt_key(key);
+        }
+
+        if self.setup.is_some() {
+            return self.handle_setup_key(key);
+        }
+
+        if key.code == KeyCode::F(2) {
+            self.show_preview = !self.show_preview;
+            return InputResult::Handled;
+        }
+
+        // Ctrl+↑/↓ step through messages (Enter then expands or collapses)
+        if key.modifiers.contains(KeyModifiers::CONTROL)
+            && matches!(key.code, KeyCode::Up | KeyCode::Down)
+        {
+            self.select_message(if key.code == KeyCode::Up { -1 } else { 1 });
+            return InputResult::Handled;
+        }
+
+        // Alt+Y / Alt+C / Alt+M copy the last code block, command, or message
+        if key.modifiers.contains(KeyModifiers::ALT)
+            && let KeyCode::Char(c @ ('y' | 'c' | 'm')) = key.code
+        {
+            let copied = match c {
+                'y' => self.last_code_block().map(|code| ("code block", code)),
+                'c' => self.last_command().map(|command| ("command", command)),
+                _ => self.message_to_copy().map(|text| ("message", text)),
+            };
+            match copied {
+                Ok((what, text)) => self.copy_to_clipboard(what, &text),
+                Err(e) => self.add_message(Message::system(e)),
+            }
+            return InputResult::Handled;
+        }
+
+        if self.focus == Pane::Chat
+            && let Some(result) = self.handle_chat_focus_key(key)
+        {
+            return result;
+        }
+
+        // Ctrl+E exports the conversation, as HTML for a .html path
+        if key.modifiers.contains(KeyModifiers::CONTROL)
+            && key.code == KeyCode::Char('e')
+            && matches!(self.state, AppState::Input | AppState::Done)
+        {
+            self.open_export_prompt(Format::Markdown);
+            return InputResult::Handled;
+        }
+
+        // Ctrl+G opens the full output of the last command
+        if key.modifiers.contains(KeyModifiers::CONTROL)
+            && key.code == KeyCode::Char('g')
+            && matches!(self.state, AppState::Input | AppState::Done)
+        {
+            if let Err(e) = self.open_output_pager() {
+                self.add_message(Message::system(e));
+            }
+            return InputResult::Handled;
+        }
+
+        if let Some(result) = self.handle_queue_key(key) {
+            return result;
+        }
+
+        match self.state {
+            AppState::Input => self.handle_input_state(key),
+            AppState::Thinking => self.handle_thinking_state(key),
             AppState::ReviewAction => self.handle_review_action_state(key),
             AppState::Executing => self.handle_executing_state(key),
             AppState::Finalizing => self.handle_finalizing_state(key),
@@ -777,6 +2681,66 @@ impl<'a> App<'a> {
         }
     }
 
+    /// Typing ahead while the agent is busy; Enter queues the message
+    ///
+    /// Returns None outside Thinking, Executing and Finalizing, or when the
+    /// input doesn't have the focus.
+    fn handle_queue_key(&mut self, key: KeyEvent) -> Option<InputResult> {
+        if !self.is_busy()
+            || self.focus != Pane::Input
+            || self.sudo_prompt.is_some()
+            || key.code == KeyCode::Esc
+        {
+            return None;
+        }
+
+        let shifted = key
+            .modifiers
+            .intersects(KeyModifiers::ALT | KeyModifiers::SHIFT);
+        Some(match key.code {
+            KeyCode::Enter if shifted => {
+                self.input_textarea.insert_newline();
+                InputResult::Handled
+            }
+            KeyCode::Enter if self.is_input_empty() => InputResult::Blocked,
+            KeyCode::Enter => {
+                self.queued.push_back(self.get_input_text());
+                self.clear_input();
+                InputResult::Handled
+            }
+            KeyCode::Tab | KeyCode::BackTab => {
+                self.cycle_focus(key.code == KeyCode::BackTab);
+                InputResult::Handled
+            }
+            _ => {
+                self.input_textarea.input(key);
+                InputResult::Handled
+            }
+        })
+    }
+
+    /// Whether the agent is thinking or a command runs
+    pub fn is_busy(&self) -> bool {
+        matches!(
+            self.state,
+            AppState::Thinking | AppState::Executing | AppState::Finalizing
+        )
+    }
+
+    /// Send the oldest queued message as if it was typed and submitted
+    ///
+    /// Whatever was typed after it stays in the input.
+    pub fn send_queued(&mut self) -> InputResult {
+        let Some(text) = self.queued.pop_front() else {
+            return InputResult::Ignored;
+        };
+        let draft = std::mem::take(&mut self.input_textarea);
+        self.input_textarea.insert_str(&text);
+        let result = self.submit();
+        self.input_textarea = draft;
+        result
+    }
+
     /// Scroll chat history up
     pub fn scroll_up(&mut self) {
         self.scroll_offset = self.scroll_offset.saturating_add(1);
@@ -787,6 +2751,443 @@ impl<'a> App<'a> {
         self.scroll_offset = self.scroll_offset.saturating_sub(1);
     }
 
+    /// The pane below the chat: the live output while a command runs
+    fn middle_pane(&self) -> Pane {
+        if self.state == AppState::Executing {
+            Pane::Output
+        } else {
+            Pane::Input
+        }
+    }
+
+    /// Focus the next pane (Tab) or the previous one (Shift+Tab)
+    pub fn cycle_focus(&mut self, back: bool) {
+        // A running command's output sits above the input for typing ahead
+        let panes: &[Pane] = match self.middle_pane() {
+            Pane::Output => &[Pane::Chat, Pane::Output, Pane::Input],
+            _ => &[Pane::Chat, Pane::Input],
+        };
+        let current = panes.iter().position(|&p| p == self.focus).unwrap_or(0);
+        let step = if back { panes.len() - 1 } else { 1 };
+        self.focus = panes[(current + step) % panes.len()];
+        self.chat_selection = None;
+    }
+
+    /// Scroll keys while the chat pane has focus; other keys hand focus back
+    ///
+    /// Returns None when the key should be handled as usual.
+    fn handle_chat_focus_key(&mut self, key: KeyEvent) -> Option<InputResult> {
+        match key.code {
+            KeyCode::Tab => self.cycle_focus(false),
+            KeyCode::BackTab => self.cycle_focus(true),
+            KeyCode::Char('z') => self.zoom_chat(),
+            _ if self.config.vi_mode => return self.handle_vi_normal_key(key),
+            KeyCode::Up => self.scroll_up(),
+            KeyCode::Down => self.scroll_down(),
+            KeyCode::PageUp => {
+                self.scroll_offset = self.scroll_offset.saturating_add(CHAT_PAGE);
+            }
+            KeyCode::PageDown => {
+                self.scroll_offset = self.scroll_offset.saturating_sub(CHAT_PAGE);
+            }
+            KeyCode::End => self.scroll_offset = 0,
+            KeyCode::Enter | KeyCode::Char(' ') if self.selected_message.is_some() => {
+                self.toggle_selected_message();
+            }
+            KeyCode::Char('y') if self.selected_message.is_some() => match self.message_to_copy() {
+                Ok(text) => self.copy_to_clipboard("message", &text),
+                Err(e) => self.add_message(Message::system(e)),
+            },
+            KeyCode::Char('n') if self.search.is_some() => self.search_step(-1),
+            KeyCode::Char('N') if self.search.is_some() => self.search_step(1),
+            KeyCode::Char('/') => {
+                self.focus = Pane::Input;
+                self.clear_input();
+                self.input_textarea.insert_str("/find ");
+            }
+            KeyCode::Esc => {
+                self.focus = self.middle_pane();
+                self.chat_selection = None;
+                self.selected_message = None;
+                self.search = None;
+            }
+            _ => {
+                self.focus = self.middle_pane();
+                self.chat_selection = None;
+                return None;
+            }
+        }
+        Some(InputResult::Handled)
+    }
+
+    /// Scroll and view keys while the live output pane has focus
+    ///
+    /// Returns None when the key should be handled as usual.
+    fn handle_output_focus_key(&mut self, key: KeyEvent) -> Option<InputResult> {
+        let lines = self.execution_output.lines().count();
+        match key.code {
+            KeyCode::Tab => self.cycle_focus(false),
+            KeyCode::BackTab => self.cycle_focus(true),
+            KeyCode::Up => self.output_scroll = (self.output_scroll + 1).min(lines),
+            KeyCode::Down => self.output_scroll = self.output_scroll.saturating_sub(1),
+            KeyCode::PageUp => {
+                self.output_scroll = (self.output_scroll + CHAT_PAGE as usize).min(lines);
+            }
+            KeyCode::PageDown => {
+                self.output_scroll = self.output_scroll.saturating_sub(CHAT_PAGE as usize);
+            }
+            KeyCode::End => self.output_scroll = 0,
+            KeyCode::Char('w' | '#') => self.toggle_output_view(key.code),
+            KeyCode::Char('z') => self.zoom_output(),
+            _ => return None,
+        }
+        Some(InputResult::Handled)
+    }
+
+    /// Keys in vi normal mode (the chat focused with `vi_mode` on)
+    ///
+    /// j/k scroll, gg/G jump to the top/bottom, / searches, n/N step
+    /// through matches, y yanks a message and i returns to the input.
+    /// Ctrl combinations fall through to the usual handling.
+    fn handle_vi_normal_key(&mut self, key: KeyEvent) -> Option<InputResult> {
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            return None;
+        }
+        let pending_g = std::mem::take(&mut self.vi_pending_g);
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => self.scroll_down(),
+            KeyCode::Char('k') | KeyCode::Up => self.scroll_up(),
+            KeyCode::Char('g') if pending_g => self.scroll_to_line(0),
+            KeyCode::Char('g') => self.vi_pending_g = true,
+            KeyCode::Char('G') | KeyCode::End => self.scroll_offset = 0,
+            KeyCode::PageUp => {
+                self.scroll_offset = self.scroll_offset.saturating_add(CHAT_PAGE);
+            }
+            KeyCode::PageDown => {
+                self.scroll_offset = self.scroll_offset.saturating_sub(CHAT_PAGE);
+            }
+            KeyCode::Char('n') => self.search_step(-1),
+            KeyCode::Char('N') => self.search_step(1),
+            KeyCode::Char('/') => {
+                self.focus = Pane::Input;
+                self.clear_input();
+                self.input_textarea.insert_str("/find ");
+            }
+            KeyCode::Char('y') => match self.message_to_copy() {
+                Ok(text) => self.copy_to_clipboard("message", &text),
+                Err(e) => self.add_message(Message::system(e)),
+            },
+            KeyCode::Enter | KeyCode::Char(' ') => self.toggle_selected_message(),
+            KeyCode::Char('i') | KeyCode::Char('a') => self.focus = self.middle_pane(),
+            KeyCode::Esc => {
+                self.chat_selection = None;
+                self.selected_message = None;
+                self.search = None;
+            }
+            _ => {}
+        }
+        Some(InputResult::Handled)
+    }
+
+    /// Handle mouse events; `panes` is the layout from `ui::create_main_layout`
+    ///
+    /// The wheel scrolls the chat (or the pager), a click focuses the pane
+    /// under the mouse, and dragging in the chat selects text, which is
+    /// copied to the clipboard when the button is released.
+    pub fn handle_mouse_event(&mut self, mouse: MouseEvent, panes: &[Rect]) -> InputResult {
+        let position = Position::new(mouse.column, mouse.row);
+        if self.session_picker.is_some()
+            || self.export_path.is_some()
+            || self.setup.is_some()
+            || self.resume_offer.is_some()
+            || self.help_scroll.is_some()
+        {
+            return InputResult::Ignored;
+        }
+        if let Some(ref mut pager) = self.pager {
+            match mouse.kind {
+                MouseEventKind::ScrollUp => pager.scroll_by(-(MOUSE_SCROLL as isize)),
+                MouseEventKind::ScrollDown => pager.scroll_by(MOUSE_SCROLL as isize),
+                _ => return InputResult::Ignored,
+            }
+            return InputResult::Handled;
+        }
+        let [chat, middle, ..] = panes else {
+            return InputResult::Ignored;
+        };
+        let chat = &crate::ui::split_preview(*chat, self).0;
+
+        match mouse.kind {
+            MouseEventKind::ScrollUp => {
+                self.scroll_offset = self.scroll_offset.saturating_add(MOUSE_SCROLL);
+            }
+            MouseEventKind::ScrollDown => {
+                self.scroll_offset = self.scroll_offset.saturating_sub(MOUSE_SCROLL);
+            }
+            MouseEventKind::Down(MouseButton::Left) if chat.contains(position) => {
+                self.focus = Pane::Chat;
+                let point = self.chat_point(*chat, mouse.column, mouse.row);
+                let view = crate::ui::chat_view(self, *chat);
+                if let Some(index) = view.owner(point.0) {
+                    self.selected_message = Some(index);
+                }
+                self.chat_selection = Some(ChatSelection {
+                    anchor: point,
+                    cursor: point,
+                });
+            }
+            MouseEventKind::Down(MouseButton::Left) if middle.contains(position) => {
+                // The bottom of a busy middle pane is the input for typing ahead
+                let queue_rows = if self.compact { 2 } else { 3 };
+                let queue = self.is_busy() && mouse.row >= middle.bottom() - queue_rows;
+                self.focus = if queue {
+                    Pane::Input
+                } else {
+                    self.middle_pane()
+                };
+                self.chat_selection = None;
+                // Clicking the input line moves the cursor there
+                let left = u16::from(!self.compact);
+                if self.state == AppState::Input && mouse.row == middle.y + 1 {
+                    let col = mouse.column.saturating_sub(middle.x + left);
+                    self.input_textarea.move_cursor(CursorMove::Jump(0, col));
+                }
+            }
+            MouseEventKind::Drag(MouseButton::Left) if self.chat_selection.is_some() => {
+                // Dragging past the edge scrolls
+                if mouse.row <= chat.y {
+                    self.scroll_up();
+                } else if mouse.row >= chat.bottom().saturating_sub(1) {
+                    self.scroll_down();
+                }
+                let point = self.chat_point(*chat, mouse.column, mouse.row);
+                if let Some(ref mut selection) = self.chat_selection {
+                    selection.cursor = point;
+                }
+            }
+            MouseEventKind::Up(MouseButton::Left) => {
+                let Some(selection) = self.chat_selection else {
+                    return InputResult::Ignored;
+                };
+                let view = crate::ui::chat_view(self, *chat);
+                if selection.anchor == selection.cursor {
+                    // A click on a "▸ N more lines" marker expands the message
+                    self.chat_selection = None;
+                    if view.markers.contains(&selection.anchor.0) {
+                        self.toggle_selected_message();
+                    }
+                    return InputResult::Handled;
+                }
+                let lines: Vec<String> = view.lines().map(crate::ui::line_text).collect();
+                self.chat_selection = None;
+                self.copy_to_clipboard("selection", &selection.text(&lines));
+            }
+            _ => return InputResult::Ignored,
+        }
+        InputResult::Handled
+    }
+
+    /// (rendered line, column) of the chat text under a screen position
+    fn chat_point(&self, chat: Rect, column: u16, row: u16) -> (usize, usize) {
+        let scroll = crate::ui::chat_view(self, chat).scroll;
+        let text = crate::ui::chat_text_area(self, chat);
+        let inner_bottom = text.bottom().saturating_sub(1).max(text.y);
+        let row = row.clamp(text.y, inner_bottom) - text.y;
+        let column = column.saturating_sub(text.x);
+        (scroll + row as usize, column as usize)
+    }
+
+    /// Chat pane area on the current screen
+    pub fn chat_area(&self) -> Rect {
+        let chat = crate::ui::create_main_layout(self.screen, self)[0];
+        crate::ui::split_preview(chat, self).0
+    }
+
+    /// Scroll the chat just enough to show rendered line `line`
+    pub fn scroll_to_line(&mut self, line: usize) {
+        let area = self.chat_area();
+        let view = crate::ui::chat_view(self, area);
+        let visible = (crate::ui::chat_text_area(self, area).height as usize).max(1);
+        let max_scroll = view.line_count().saturating_sub(visible);
+        let target = if line < view.scroll {
+            line
+        } else if line >= view.scroll + visible {
+            line + 1 - visible
+        } else {
+            return;
+        };
+        let offset = max_scroll.saturating_sub(target.min(max_scroll));
+        self.scroll_offset = offset.min(u16::MAX as usize) as u16;
+    }
+
+    /// Select the previous (`-1`) or next (`1`) message in the chat
+    ///
+    /// The system prompt is skipped; the chat pane takes focus.
+    pub fn select_message(&mut self, step: isize) {
+        let view = crate::ui::chat_view(self, self.chat_area());
+        let shown: Vec<usize> = view.messages().collect();
+        let Some(&last) = shown.last() else {
+            return;
+        };
+        let current = self.selected_message;
+        let next = match current.and_then(|m| shown.iter().position(|&i| i == m)) {
+            Some(pos) => shown[pos.saturating_add_signed(step).min(shown.len() - 1)],
+            None => last,
+        };
+        self.selected_message = Some(next);
+        self.focus = Pane::Chat;
+        if let Some(start) = view.message_start(next) {
+            self.scroll_to_line(start);
+        }
+    }
+
+    /// Expand or collapse the selected message
+    pub fn toggle_selected_message(&mut self) {
+        if let Some(index) = self.selected_message
+            && !self.expanded_messages.remove(&index)
+        {
+            self.expanded_messages.insert(index);
+        }
+    }
+
+    /// Copy `text` and report it in a toast; `what` names it ("command")
+    pub fn copy_to_clipboard(&mut self, what: &str, text: &str) {
+        let count = text.chars().count();
+        self.toast(match crate::clipboard::copy(text) {
+            Ok(crate::clipboard::Method::System) => {
+                format!("📋 Copied {} ({} characters)", what, count)
+            }
+            Ok(crate::clipboard::Method::Osc52) => {
+                format!("📋 Copied {} ({} characters) via OSC 52", what, count)
+            }
+            Err(e) => format!("⚠ Can't copy to the clipboard: {}", e),
+        });
+    }
+
+    /// Last fenced code block in an AI reply
+    fn last_code_block(&self) -> Result<String, String> {
+        self.messages
+            .iter()
+            .rev()
+            .filter(|m| m.role == MessageRole::Model)
+            .find_map(|m| crate::markdown::code_blocks(&m.content).pop())
+            .map(|code| code.trim_end_matches('\n').to_string())
+            .ok_or_else(|| "No code block in the AI's replies".to_string())
+    }
+
+    /// Command (or Python code) of the last executed tool call
+    fn last_command(&self) -> Result<String, String> {
+        match self.last_tool {
+            Some(ref tool) if !tool.command.is_empty() => Ok(tool.command.clone()),
+            Some(ref tool) if !tool.code.is_empty() => Ok(tool.code.clone()),
+            Some(ref tool) => Err(format!("The last tool call ({}) has no command", tool.tool)),
+            None => Err("No command has been run yet".to_string()),
+        }
+    }
+
+    /// Selected message, or the last AI reply when none is selected
+    fn message_to_copy(&self) -> Result<String, String> {
+        let message = match self.selected_message {
+            Some(index) => self.messages.get(index),
+            None => self
+                .messages
+                .iter()
+                .rev()
+                .find(|m| m.role == MessageRole::Model),
+        };
+        message
+            .map(|m| m.content.clone())
+            .ok_or_else(|| "No message to copy".to_string())
+    }
+
+    /// Forget per-message view state when the history is replaced
+    fn reset_chat_view(&mut self) {
+        self.selected_message = None;
+        self.expanded_messages.clear();
+        self.chat_selection = None;
+        self.search = None;
+        self.reveal = None;
+        self.chat_cache.get_mut().clear();
+    }
+
+    /// Start typing out the reply just added (if `reveal_replies` is on)
+    pub fn start_reveal(&mut self) {
+        let Some(message) = self.messages.len().checked_sub(1) else {
+            return;
+        };
+        if self.config.reveal_replies && self.messages[message].role == MessageRole::Model {
+            self.reveal = Some(Reveal { message, shown: 0 });
+        }
+    }
+
+    /// Show the next part of the reply being revealed
+    ///
+    /// Goes at least `REVEAL_MIN_STEP` characters a tick and finishes long
+    /// replies within `REVEAL_TICKS` ticks.
+    pub fn advance_reveal(&mut self) {
+        const REVEAL_MIN_STEP: usize = 24;
+        const REVEAL_TICKS: usize = 15;
+
+        let Some(ref mut reveal) = self.reveal else {
+            return;
+        };
+        let total = self
+            .messages
+            .get(reveal.message)
+            .map_or(0, |m| m.content.chars().count());
+        reveal.shown += (total / REVEAL_TICKS).max(REVEAL_MIN_STEP);
+        if reveal.shown >= total {
+            self.reveal = None;
+        }
+    }
+
+    /// Start a search of the chat and jump to the newest match
+    ///
+    /// Collapsed messages containing `query` are expanded first so every
+    /// match can be seen.
+    pub fn find_in_chat(&mut self, query: &str) {
+        let needle = query.to_lowercase();
+        for (index, message) in self.messages.iter().enumerate() {
+            if crate::ui::is_collapsible(message)
+                && message.content.to_lowercase().contains(&needle)
+            {
+                self.expanded_messages.insert(index);
+            }
+        }
+        let view = crate::ui::chat_view(self, self.chat_area());
+        let matches = crate::ui::find_matches(view.lines(), query);
+        let Some(&(line, _, _)) = matches.last() else {
+            self.search = None;
+            self.add_message(Message::system(format!("No matches for '{}'", query)));
+            return;
+        };
+        self.search = Some(ChatSearch {
+            query: query.to_string(),
+            current: matches.len() - 1,
+        });
+        self.focus = Pane::Chat;
+        self.scroll_to_line(line);
+    }
+
+    /// Move to an older (`-1`) or newer (`1`) match, wrapping around
+    pub fn search_step(&mut self, step: isize) {
+        let Some(ref search) = self.search else {
+            return;
+        };
+        let view = crate::ui::chat_view(self, self.chat_area());
+        let matches = crate::ui::find_matches(view.lines(), &search.query);
+        if matches.is_empty() {
+            return;
+        }
+        let count = matches.len() as isize;
+        let current = (search.current.min(matches.len() - 1) as isize + step).rem_euclid(count);
+        let line = matches[current as usize].0;
+        if let Some(ref mut search) = self.search {
+            search.current = current as usize;
+        }
+        self.scroll_to_line(line);
+    }
+
     /// Handle keyboard events in Input state
     fn handle_input_state(&mut self, key: KeyEvent) -> InputResult {
         // Ctrl+O to attach image from clipboard (macOS) or prompt for path
@@ -811,13 +3212,37 @@ impl<'a> App<'a> {
             return InputResult::Handled;
         }
 
+        // Ctrl+R re-runs the last tool call without asking the AI again
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('r') {
+            return InputResult::Rerun;
+        }
+
+        // Alt+Enter, Shift+Enter and Ctrl+J insert a newline; Ctrl+S always sends
+        let shifted = key
+            .modifiers
+            .intersects(KeyModifiers::ALT | KeyModifiers::SHIFT);
+        let newline = match key.code {
+            KeyCode::Enter => self.multiline || shifted,
+            KeyCode::Char('j') => key.modifiers.contains(KeyModifiers::CONTROL),
+            _ => false,
+        };
+        if newline {
+            self.input_textarea.insert_newline();
+            return InputResult::Handled;
+        }
+        let send = key.code == KeyCode::Enter
+            || (key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('s'));
+
         match key.code {
-            KeyCode::Enter => match self.submit_input() {
-                SubmitResult::Query => InputResult::SubmitQuery,
-                SubmitResult::Quit => InputResult::Quit,
-                SubmitResult::FetchModels(model) => InputResult::FetchModels(model),
-                _ => InputResult::Handled,
-            },
+            _ if send => self.submit(),
+            KeyCode::BackTab => {
+                self.cycle_focus(true);
+                InputResult::Handled
+            }
+            KeyCode::Tab if !self.get_input_text().starts_with('/') => {
+                self.cycle_focus(false);
+                InputResult::Handled
+            }
             KeyCode::Tab => {
                 // Autocomplete slash commands
                 let input = self.get_input_text();
@@ -840,16 +3265,27 @@ impl<'a> App<'a> {
                 }
                 InputResult::Handled
             }
+            KeyCode::Esc if self.snippet_fill.is_some() => {
+                self.snippet_fill = None;
+                self.clear_input();
+                self.add_message(Message::system("Snippet cancelled."));
+                InputResult::Handled
+            }
+            KeyCode::Esc if self.config.vi_mode => {
+                self.focus = Pane::Chat;
+                InputResult::Handled
+            }
             KeyCode::Esc => {
                 self.should_quit = true;
                 self.transition(StateEvent::Escape);
                 InputResult::Quit
             }
-            KeyCode::Up => {
+            // In a multi-line draft the arrows move the cursor instead
+            KeyCode::Up if self.input_textarea.lines().len() == 1 => {
                 self.scroll_up();
                 InputResult::Handled
             }
-            KeyCode::Down => {
+            KeyCode::Down if self.input_textarea.lines().len() == 1 => {
                 self.scroll_down();
                 InputResult::Handled
             }
@@ -875,6 +3311,20 @@ impl<'a> App<'a> {
         Some(temp_path)
     }
 
+    /// Submit the input and say what the main loop should do next
+    fn submit(&mut self) -> InputResult {
+        match self.submit_input() {
+            SubmitResult::Query => InputResult::SubmitQuery,
+            SubmitResult::Quit => InputResult::Quit,
+            SubmitResult::FetchModels(model) => InputResult::FetchModels(model),
+            SubmitResult::OpenEditor(path) => InputResult::OpenEditor(path),
+            SubmitResult::Rerun => InputResult::Rerun,
+            SubmitResult::ReviewTool(tool) => InputResult::ReviewTool(tool),
+            SubmitResult::ConnectTarget(name) => InputResult::ConnectTarget(name),
+            _ => InputResult::Handled,
+        }
+    }
+
     /// Handle keyboard events in Thinking state (input blocked)
     fn handle_thinking_state(&mut self, key: KeyEvent) -> InputResult {
         // Only allow Escape for emergency quit in async states
@@ -889,6 +3339,15 @@ impl<'a> App<'a> {
 
     /// Handle keyboard events in ReviewAction state
     fn handle_review_action_state(&mut self, key: KeyEvent) -> InputResult {
+        // Ctrl+X asks the AI to explain the action before confirming
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('x') {
+            if self.explaining || self.pending_action_text().is_empty() {
+                return InputResult::Ignored;
+            }
+            self.explaining = true;
+            return InputResult::ExplainCommand;
+        }
+
         match key.code {
             KeyCode::Enter => {
                 // Dangerous commands require 2-step confirmation
@@ -898,11 +3357,17 @@ impl<'a> App<'a> {
                             self.danger_confirm_step = 1;
                             // Save the command before confirmation flow
                             self.current_command = Some(self.get_action_text());
-                            self.add_message(Message::system(
+                            let matched = if self.danger_reasons.is_empty() {
+                                String::new()
+                            } else {
+                                format!("Matched: {}\n", self.danger_reasons.join("; "))
+                            };
+                            self.add_message(Message::system(format!(
                                 "⚠️ DANGEROUS COMMAND DETECTED!\n\n\
-                                 This command could cause irreversible damage.\n\
+                                 {}This command could cause irreversible damage.\n\
                                  Press Enter again to proceed to final confirmation.",
-                            ));
+                                matched
+                            )));
                             return InputResult::Ignored;
                         }
                         1 => {
@@ -948,6 +3413,10 @@ impl<'a> App<'a> {
                     InputResult::Ignored
                 }
             }
+            KeyCode::Tab | KeyCode::BackTab => {
+                self.cycle_focus(key.code == KeyCode::BackTab);
+                InputResult::Handled
+            }
             KeyCode::Esc => {
                 // Cancel command and return to input
                 self.clear_action();
@@ -970,6 +3439,31 @@ impl<'a> App<'a> {
 
     /// Handle keyboard events in Executing state (input blocked)
     fn handle_executing_state(&mut self, key: KeyEvent) -> InputResult {
+        if let Some(ref mut prompt) = self.sudo_prompt {
+            return match key.code {
+                KeyCode::Enter => InputResult::SubmitSudoPassword,
+                KeyCode::Esc => {
+                    self.sudo_prompt = None;
+                    InputResult::CancelCommand
+                }
+                KeyCode::Backspace => {
+                    prompt.password.pop();
+                    InputResult::Handled
+                }
+                KeyCode::Char(c) => {
+                    prompt.password.push(c);
+                    InputResult::Handled
+                }
+                _ => InputResult::Blocked,
+            };
+        }
+
+        if self.focus == Pane::Output
+            && let Some(result) = self.handle_output_focus_key(key)
+        {
+            return result;
+        }
+
         match key.code {
             KeyCode::Esc => {
                 // Cancel and go back to input
@@ -980,6 +3474,20 @@ impl<'a> App<'a> {
         }
     }
 
+    /// `w` toggles wrapping and `#` line numbers of command output
+    fn toggle_output_view(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Char('w') => {
+                self.output_wrap = !self.output_wrap;
+                if let Some(ref mut pager) = self.pager {
+                    pager.hscroll = 0;
+                }
+            }
+            KeyCode::Char('#') => self.output_line_numbers = !self.output_line_numbers,
+            _ => {}
+        }
+    }
+
     /// Handle keyboard events in Finalizing state (input blocked)
     fn handle_finalizing_state(&mut self, key: KeyEvent) -> InputResult {
         match key.code {
@@ -999,6 +3507,10 @@ impl<'a> App<'a> {
                 self.transition(StateEvent::Continue);
                 InputResult::Continue
             }
+            KeyCode::Tab | KeyCode::BackTab => {
+                self.cycle_focus(key.code == KeyCode::BackTab);
+                InputResult::Handled
+            }
             KeyCode::Esc | KeyCode::Char('q') => {
                 self.should_quit = true;
                 InputResult::Quit
@@ -1029,6 +3541,18 @@ pub enum InputResult {
     Quit,
     /// Fetch models from API (with optional model name to switch to)
     FetchModels(Option<String>),
+    /// Open a file in the external editor (suspends the TUI)
+    OpenEditor(String),
+    /// User entered the sudo password for the pending command
+    SubmitSudoPassword,
+    /// Ask the AI to explain the action under review
+    ExplainCommand,
+    /// Put the last executed tool call back up for review
+    Rerun,
+    /// Put a tool call (e.g. a filled-in snippet) up for review without the AI
+    ReviewTool(Box<ToolCall>),
+    /// Connect to the named container for `/target` (may start it)
+    ConnectTarget(String),
 }
 
 /// Result of submitting input
@@ -1044,15 +3568,119 @@ pub enum SubmitResult {
     Quit,
     /// Fetch models from API (with optional model name to switch to)
     FetchModels(Option<String>),
+    /// Open a file in the external editor
+    OpenEditor(String),
+    /// Review the last executed tool call again
+    Rerun,
+    /// Review a tool call built without the AI
+    ReviewTool(Box<ToolCall>),
+    /// Connect to the named container for `/target`
+    ConnectTarget(String),
+}
+
+/// Write `contents` to a temporary file next to `path`, then rename it over
+/// `path`, so a crash mid-write never leaves a truncated file
+///
+/// The data is synced before the rename, so the new name never points at
+/// blocks that didn't reach the disk.
+fn write_atomic(path: &std::path::Path, contents: &str) -> std::io::Result<()> {
+    use std::io::Write;
+    let tmp = temp_path(path, std::process::id());
+    let mut file = std::fs::File::create(&tmp)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    std::fs::rename(&tmp, path)
+}
+
+/// Temporary file process `pid` writes `path` through
+fn temp_path(path: &std::path::Path, pid: u32) -> std::path::PathBuf {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(".{}.tmp", pid));
+    tmp.into()
+}
+
+/// Crash marker of this process in the sessions directory `dir`
+fn crash_marker(dir: &std::path::Path) -> std::path::PathBuf {
+    dir.join(format!("{}{}", RUNNING_MARKER, std::process::id()))
+}
+
+/// Session a sabi that didn't exit cleanly left in the sessions `dir`
+///
+/// Crash markers of processes that are gone are removed, as are the
+/// half-written saves of those processes; saves other running sabis are
+/// writing are left alone.
+#[cfg(unix)]
+fn take_crashed_session(dir: &std::path::Path) -> Option<String> {
+    let entries = std::fs::read_dir(dir).ok()?;
+    let mut crashed = None;
+    let mut gone = Vec::new();
+    for entry in entries.filter_map(|e| e.ok()) {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Some(pid) = name
+            .strip_prefix(RUNNING_MARKER)
+            .and_then(|pid| pid.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        if pid == std::process::id() || process_alive(pid) {
+            continue;
+        }
+        if let Ok(id) = std::fs::read_to_string(entry.path()) {
+            crashed.get_or_insert(id.trim().to_string());
+        }
+        let _ = std::fs::remove_file(entry.path());
+        gone.push(pid);
+    }
+    // Half-written saves of the crashed runs; the last complete one stays
+    if !gone.is_empty()
+        && let Ok(entries) = std::fs::read_dir(dir)
+    {
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let pid = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_suffix(".tmp"))
+                .and_then(|name| name.rsplit_once('.'))
+                .and_then(|(_, pid)| pid.parse::<u32>().ok());
+            if pid.is_some_and(|pid| gone.contains(&pid)) {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+    crashed
+}
+
+/// Without a way to tell whether a marker's process is still running,
+/// other sabis' sessions would look crashed, so nothing is recovered
+#[cfg(not(unix))]
+fn take_crashed_session(_dir: &std::path::Path) -> Option<String> {
+    None
+}
+
+/// Whether process `pid` is still running
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    // SAFETY: signal 0 only checks that the process exists
+    let found = unsafe { libc::kill(pid as libc::pid_t, 0) } == 0;
+    found || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
 }
 
 #[cfg(test)]
+#[allow(clippy::items_after_test_module)]
 mod tests {
     use super::*;
     use proptest::prelude::*;
 
-    /// Create a test App with default config
+    /// Create a test App with default config, saving sessions in a data
+    /// dir private to the test run
     fn test_app() -> App<'static> {
+        static DATA_DIR: std::sync::LazyLock<tempfile::TempDir> =
+            std::sync::LazyLock::new(|| tempfile::tempdir().unwrap());
+        crate::config::set_data_dir(&Config {
+            data_dir: DATA_DIR.path().display().to_string(),
+            ..Config::default()
+        });
         App::new(Config::default())
     }
 
@@ -1577,12 +4205,260 @@ mod tests {
         assert!(!app.dangerous_command_detected);
     }
 
-    // Strategy to generate async (blocking) states
-    fn arb_async_state() -> impl Strategy<Value = AppState> {
-        prop_oneof![
-            Just(AppState::Thinking),
-            Just(AppState::Finalizing),
-            Just(AppState::Executing),
+    #[test]
+    fn test_rerun_requests() {
+        let mut app = test_app();
+        let ctrl_r = KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL);
+        assert_eq!(app.handle_key_event(ctrl_r), InputResult::Rerun);
+
+        app.input_textarea.insert_str("/rerun");
+        assert_eq!(app.submit_input(), SubmitResult::Rerun);
+    }
+
+    // **Feature: Sabi-TUI, Property: Output Pager**
+    #[test]
+    fn test_output_pager() {
+        let mut app = test_app();
+        let ctrl_g = KeyEvent::new(KeyCode::Char('g'), KeyModifiers::CONTROL);
+
+        // Nothing to show before any command ran
+        app.handle_key_event(ctrl_g);
+        assert!(app.pager.is_none());
+
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("output.log");
+        std::fs::write(&path, "a\nb\nc\n").unwrap();
+        app.last_spool = Some(path.clone());
+
+        app.handle_key_event(ctrl_g);
+        assert_eq!(app.pager.as_ref().unwrap().lines, vec!["a", "b", "c"]);
+
+        // Keys scroll the pager instead of editing the input
+        app.handle_key_event(KeyEvent::new(KeyCode::End, KeyModifiers::NONE));
+        assert_eq!(app.pager.as_ref().unwrap().scroll, 2);
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::NONE));
+        assert_eq!(app.pager.as_ref().unwrap().scroll, 1);
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE));
+        assert!(app.pager.is_none());
+        assert!(app.is_input_empty());
+    }
+
+    #[test]
+    fn test_output_wrap_and_line_number_toggles() {
+        let mut app = test_app();
+        let key = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+        // Running a command focuses its output
+        app.state = AppState::ReviewAction;
+        app.transition(StateEvent::ConfirmCommand);
+        assert_eq!(app.focus, Pane::Output);
+        assert_eq!(app.handle_key_event(key('#')), InputResult::Handled);
+        assert!(app.output_line_numbers);
+
+        app.state = AppState::Input;
+        app.pager = Some(Pager {
+            title: "out".to_string(),
+            lines: vec!["x".repeat(200)],
+            ..Default::default()
+        });
+        let right = KeyEvent::new(KeyCode::Right, KeyModifiers::NONE);
+        assert_eq!(app.handle_key_event(right), InputResult::Ignored);
+        app.handle_key_event(key('w'));
+        assert!(!app.output_wrap);
+        app.handle_key_event(right);
+        assert_eq!(app.pager.as_ref().unwrap().hscroll, 8);
+
+        // Wrapping again resets the horizontal scroll
+        app.handle_key_event(key('w'));
+        assert_eq!(app.pager.as_ref().unwrap().hscroll, 0);
+        assert!(app.is_input_empty());
+    }
+
+    // **Feature: Sabi-TUI, Property: Fullscreen Zoom**
+    #[test]
+    fn test_zoom_output_follows_and_searches() {
+        let mut app = test_app();
+        let key = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+        app.state = AppState::ReviewAction;
+        app.transition(StateEvent::ConfirmCommand);
+        for i in 1..=30 {
+            app.append_output_chunk(&format!("step {}", i));
+        }
+
+        app.handle_key_event(key('z'));
+        let pager = app.pager.as_ref().unwrap();
+        assert!(pager.live);
+        assert_eq!(pager.scroll, 10);
+
+        // New output keeps the end in view
+        app.append_output_chunk("error: linker failed");
+        assert_eq!(app.pager.as_ref().unwrap().lines.len(), 31);
+        assert_eq!(app.pager.as_ref().unwrap().scroll, 11);
+
+        for c in "/step 2".chars() {
+            app.handle_key_event(key(c));
+        }
+        app.handle_key_event(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        let pager = app.pager.as_ref().unwrap();
+        assert_eq!(pager.matches("step 2").len(), 11);
+        assert_eq!(pager.scroll, 19);
+        app.handle_key_event(key('n'));
+        assert_eq!(app.pager.as_ref().unwrap().scroll, 20);
+        app.handle_key_event(key('N'));
+        app.handle_key_event(key('N'));
+        assert_eq!(app.pager.as_ref().unwrap().scroll, 1);
+
+        // Esc closes the zoom rather than cancelling the command
+        assert_eq!(
+            app.handle_key_event(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)),
+            InputResult::Handled
+        );
+        assert!(app.pager.is_none());
+        assert_eq!(app.state, AppState::Executing);
+
+        app.focus = Pane::Chat;
+        app.add_message(Message::user("build it"));
+        app.handle_key_event(key('z'));
+        let pager = app.pager.as_ref().unwrap();
+        assert!(!pager.live);
+        assert_eq!(pager.lines[pager.lines.len() - 2..], ["  build it", ""]);
+    }
+
+    // **Feature: Sabi-TUI, Property: Markdown Export**
+    #[test]
+    fn test_export_prompt_writes_markdown() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = test_app();
+        app.cwd = dir.path().to_path_buf();
+        app.add_message(Message::user("list files"));
+        app.add_message(Message::model("Sure."));
+        app.add_message(Message::user(
+            "Tool: run_cmd ls\nExit code: 0\nOutput:\na.txt\n```b```",
+        ));
+        app.add_message(Message::system("$ git status"));
+
+        let ctrl_e = KeyEvent::new(KeyCode::Char('e'), KeyModifiers::CONTROL);
+        app.handle_key_event(ctrl_e);
+        assert_eq!(app.export_path.as_deref(), Some("chat_export.md"));
+        app.export_path = Some(String::new());
+        for c in "out.md".chars() {
+            app.handle_key_event(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        }
+        // Keys go to the prompt, not the input
+        assert!(app.is_input_empty());
+        app.handle_key_event(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert!(app.export_path.is_none());
+
+        let text = std::fs::read_to_string(dir.path().join("out.md")).unwrap();
+        assert!(text.contains("## 👤 User"));
+        assert!(text.contains("## 🤖 Assistant"));
+        assert!(text.contains("## 🔧 Tool result"));
+        assert!(
+            text.contains("Tool: run_cmd ls  \nExit code: 0  \n\n````text\na.txt\n```b```\n````")
+        );
+        assert!(text.contains("```sh\ngit status\n```"));
+        assert!(!text.contains("MUST use tools"));
+        assert!(
+            app.toasts
+                .iter()
+                .any(|t| t.text.starts_with("✓ Exported to"))
+        );
+    }
+
+    // **Feature: Sabi-TUI, Property: HTML Export**
+    #[test]
+    fn test_slash_command_export_html() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = test_app();
+        app.cwd = dir.path().to_path_buf();
+        app.add_message(Message::user("hello"));
+
+        app.input_textarea.insert_str("/export html");
+        app.submit_input();
+        assert_eq!(app.export_path.as_deref(), Some("chat_export.html"));
+        app.handle_key_event(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        let page = std::fs::read_to_string(dir.path().join("chat_export.html")).unwrap();
+        assert!(page.contains("<h2>👤 User</h2>"));
+        assert!(page.contains("<p>hello</p>"));
+
+        app.input_textarea.insert_str("/export md page.html");
+        app.submit_input();
+        let text = std::fs::read_to_string(dir.path().join("page.html")).unwrap();
+        assert!(text.starts_with("# Sabi Chat Export"));
+    }
+
+    // **Feature: Sabi-TUI, Property: Tab Cycles Focus**
+    #[test]
+    fn test_tab_cycles_focus() {
+        let mut app = test_app();
+        let tab = KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE);
+        let back = KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT);
+
+        app.handle_key_event(tab);
+        assert_eq!(app.focus, Pane::Chat);
+        app.handle_key_event(tab);
+        assert_eq!(app.focus, Pane::Input);
+        app.handle_key_event(back);
+        assert_eq!(app.focus, Pane::Chat);
+
+        // Tab still completes slash commands
+        app.handle_key_event(tab);
+        app.input_textarea.insert_str("/mul");
+        app.handle_key_event(tab);
+        assert_eq!(app.get_input_text(), "/multiline");
+        assert_eq!(app.focus, Pane::Input);
+
+        // While a command runs the output pane takes the place of the input
+        app.clear_input();
+        app.state = AppState::ReviewAction;
+        app.transition(StateEvent::ConfirmCommand);
+        assert_eq!(app.focus, Pane::Output);
+        app.execution_output = (1..=50).map(|i| format!("line {}\n", i)).collect();
+        app.handle_key_event(KeyEvent::new(KeyCode::PageUp, KeyModifiers::NONE));
+        assert_eq!(app.output_scroll, CHAT_PAGE as usize);
+        app.handle_key_event(KeyEvent::new(KeyCode::End, KeyModifiers::NONE));
+        assert_eq!(app.output_scroll, 0);
+
+        // The input below it takes messages typed ahead
+        app.handle_key_event(tab);
+        assert_eq!(app.focus, Pane::Input);
+        app.handle_key_event(tab);
+        assert_eq!(app.focus, Pane::Chat);
+        app.handle_key_event(KeyEvent::new(KeyCode::Up, KeyModifiers::NONE));
+        assert_eq!(app.scroll_offset, 1);
+        app.handle_key_event(tab);
+        assert_eq!(app.focus, Pane::Output);
+    }
+
+    // **Feature: Sabi-TUI, Property: Explain Before Execute**
+    #[test]
+    fn test_review_action_explain() {
+        let mut app = test_app();
+        app.state = AppState::ReviewAction;
+        app.action_textarea.insert_str("rm -rf build");
+        app.dangerous_command_detected = true;
+
+        let key = KeyEvent::new(KeyCode::Char('x'), KeyModifiers::CONTROL);
+        assert_eq!(app.handle_key_event(key), InputResult::ExplainCommand);
+        assert!(app.explaining);
+        assert_eq!(app.state, AppState::ReviewAction);
+        assert_eq!(app.get_action_text(), "rm -rf build");
+
+        // A second request while one is pending is ignored
+        assert_eq!(app.handle_key_event(key), InputResult::Ignored);
+
+        let request = app.explain_request();
+        let question = &request.last().unwrap().content;
+        assert!(question.contains("rm -rf build"));
+        assert!(question.contains("DANGEROUS"));
+        assert!(app.messages.is_empty());
+    }
+
+    // Strategy to generate async (blocking) states
+    fn arb_async_state() -> impl Strategy<Value = AppState> {
+        prop_oneof![
+            Just(AppState::Thinking),
+            Just(AppState::Finalizing),
+            Just(AppState::Executing),
         ]
     }
 
@@ -1610,9 +4486,10 @@ mod tests {
     }
 
     // **Feature: agent-rs, Property 15: Input Blocking in Async States**
-    // *For any* application in Thinking or Finalizing state, keyboard input events
-    // (except Escape for emergency quit) SHALL NOT modify input_textarea or
-    // action_textarea content.
+    // *For any* application in Thinking, Executing or Finalizing state, keyboard
+    // input events (except Escape for emergency quit) SHALL NOT modify
+    // action_textarea content or leave the state; typing only goes to the
+    // input, to be queued.
     // **Validates: Requirements 7.3**
     proptest! {
         #![proptest_config(ProptestConfig::with_cases(100))]
@@ -1636,27 +4513,21 @@ mod tests {
             app.state = state;
 
             // Record initial content
-            let input_before = app.get_input_text();
             let action_before = app.get_action_text();
+            let messages_before = app.messages.len();
 
             // Handle the key event
             let result = app.handle_key_event(key);
 
-            // Property: result should be Blocked
-            prop_assert_eq!(
-                result,
-                InputResult::Blocked,
-                "Non-escape keys should be blocked in {:?} state",
-                state
-            );
-
-            // Property: input_textarea content should be unchanged
-            prop_assert_eq!(
-                app.get_input_text(),
-                input_before,
-                "input_textarea should not change in {:?} state",
-                state
+            // Property: nothing is sent while the agent is busy
+            prop_assert!(
+                matches!(result, InputResult::Handled | InputResult::Blocked),
+                "Keys should only edit or queue input in {:?} state, got {:?}",
+                state,
+                result
             );
+            prop_assert_eq!(app.state, state);
+            prop_assert_eq!(app.messages.len(), messages_before);
 
             // Property: action_textarea content should be unchanged
             prop_assert_eq!(
@@ -1667,6 +4538,24 @@ mod tests {
             );
         }
 
+        #[test]
+        fn prop_output_focus_blocks_input(
+            key in arb_non_escape_key(),
+            initial_input in "[a-zA-Z0-9]{0,20}",
+        ) {
+            let mut app = test_app();
+            app.input_textarea.insert_str(&initial_input);
+            app.state = AppState::Executing;
+            app.focus = Pane::Output;
+
+            let input_before = app.get_input_text();
+            app.handle_key_event(key);
+
+            // Property: the input only takes keys while it has the focus
+            prop_assert_eq!(app.get_input_text(), input_before);
+            prop_assert!(app.queued.is_empty());
+        }
+
         #[test]
         fn prop_escape_allowed_in_async_states(state in arb_async_state()) {
             let mut app = test_app();
@@ -1678,152 +4567,632 @@ mod tests {
             let key = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
             let result = app.handle_key_event(key);
 
-            // Property: result should be CancelCommand for Executing/Finalizing, Quit for Thinking
-            match state {
-                AppState::Executing | AppState::Finalizing => {
-                    prop_assert_eq!(
-                        result,
-                        InputResult::CancelCommand,
-                        "Escape should cancel command in {:?} state",
-                        state
-                    );
-                }
-                AppState::Thinking => {
-                    prop_assert_eq!(
-                        result,
-                        InputResult::Quit,
-                        "Escape should quit in {:?} state",
-                        state
-                    );
-                }
-                _ => {}
-            }
+            // Property: result should be CancelCommand for Executing/Finalizing, Quit for Thinking
+            match state {
+                AppState::Executing | AppState::Finalizing => {
+                    prop_assert_eq!(
+                        result,
+                        InputResult::CancelCommand,
+                        "Escape should cancel command in {:?} state",
+                        state
+                    );
+                }
+                AppState::Thinking => {
+                    prop_assert_eq!(
+                        result,
+                        InputResult::Quit,
+                        "Escape should quit in {:?} state",
+                        state
+                    );
+                }
+                _ => {}
+            }
+        }
+
+        #[test]
+        fn prop_ctrl_c_allowed_in_async_states(state in arb_async_state()) {
+            let mut app = test_app();
+
+            // Set the async state
+            app.state = state;
+
+            // Simulate pressing Ctrl+C
+            let key = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+            let result = app.handle_key_event(key);
+
+            // Property: result should be Quit
+            prop_assert_eq!(
+                result,
+                InputResult::Quit,
+                "Ctrl+C should allow quit in {:?} state",
+                state
+            );
+
+            // Property: should_quit flag should be set
+            prop_assert!(
+                app.should_quit,
+                "should_quit should be true after Ctrl+C in {:?} state",
+                state
+            );
+        }
+
+        #[test]
+        fn prop_thinking_state_blocks_input(key in arb_non_escape_key()) {
+            let mut app = test_app();
+
+            // Get to Thinking state legitimately
+            app.input_textarea.insert_str("test query");
+            app.submit_input();
+
+            prop_assert_eq!(app.state, AppState::Thinking);
+
+            // Record initial state
+            let action_before = app.get_action_text();
+            let messages_before = app.messages.len();
+
+            // Try to input
+            app.handle_key_event(key);
+
+            // Property: nothing reaches the AI until it's back at the input
+            prop_assert_eq!(app.state, AppState::Thinking);
+            prop_assert_eq!(app.messages.len(), messages_before);
+            prop_assert_eq!(app.get_action_text(), action_before);
+        }
+
+        #[test]
+        fn prop_finalizing_state_blocks_input(key in arb_non_escape_key()) {
+            let mut app = test_app();
+
+            // Set to Finalizing state
+            app.state = AppState::Finalizing;
+
+            // Add some content to verify it's not modified
+            app.input_textarea.insert_str("previous input");
+            app.action_textarea.insert_str("previous action");
+
+            let action_before = app.get_action_text();
+
+            // Try to input
+            app.handle_key_event(key);
+
+            // Property: only the input for typing ahead changes
+            prop_assert_eq!(app.state, AppState::Finalizing);
+            prop_assert_eq!(app.get_action_text(), action_before);
+        }
+    }
+
+    // **Feature: Sabi-TUI, Property: Input Queue**
+    #[test]
+    fn test_thinking_queues_typed_input() {
+        let mut app = test_app();
+
+        // Get to Thinking state
+        app.input_textarea.insert_str("test");
+        app.submit_input();
+        assert_eq!(app.state, AppState::Thinking);
+        assert!(app.get_input_text().is_empty()); // Was cleared on submit
+
+        // Typing goes to the input, Enter queues it
+        for c in "next".chars() {
+            app.handle_key_event(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        }
+        let enter = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+        assert_eq!(app.handle_key_event(enter), InputResult::Handled);
+        assert_eq!(app.queued, ["next"]);
+        assert!(app.get_input_text().is_empty());
+        assert_eq!(app.handle_key_event(enter), InputResult::Blocked);
+
+        // Once back at the input the queued message is sent, keeping the draft
+        app.input_textarea.insert_str("draft");
+        app.state = AppState::Input;
+        assert_eq!(app.send_queued(), InputResult::SubmitQuery);
+        assert_eq!(app.state, AppState::Thinking);
+        assert_eq!(app.messages.last().unwrap().content, "next");
+        assert_eq!(app.get_input_text(), "draft");
+        assert!(app.queued.is_empty());
+    }
+
+    #[test]
+    fn test_thinking_allows_escape() {
+        let mut app = test_app();
+
+        // Get to Thinking state
+        app.input_textarea.insert_str("test");
+        app.submit_input();
+        assert_eq!(app.state, AppState::Thinking);
+
+        // Press Escape
+        let key = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
+        let result = app.handle_key_event(key);
+
+        assert_eq!(result, InputResult::Quit);
+        assert!(app.should_quit);
+    }
+
+    #[test]
+    fn test_executing_blocks_input() {
+        let mut app = test_app();
+
+        // Set to Executing state, with the output focused
+        app.state = AppState::Executing;
+        app.focus = Pane::Output;
+
+        // Try to type
+        let key = KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE);
+        let result = app.handle_key_event(key);
+
+        assert_eq!(result, InputResult::Blocked);
+    }
+
+    // **Feature: Sabi-TUI, Property: Masked Sudo Prompt**
+    #[test]
+    fn test_sudo_prompt_collects_masked_password() {
+        let mut app = test_app();
+        app.state = AppState::Executing;
+        app.request_sudo_password(ToolCall::run_cmd("sudo ls /root"));
+
+        for c in "pw1x".chars() {
+            app.handle_key_event(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        }
+        app.handle_key_event(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
+
+        let prompt = app.sudo_prompt.as_ref().unwrap();
+        assert_eq!(prompt.masked(), "•••");
+        assert!(!format!("{:?}", prompt).contains("pw1"));
+
+        let result = app.handle_key_event(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert_eq!(result, InputResult::SubmitSudoPassword);
+        let (tool, password) = app.take_sudo_submission().unwrap();
+        assert_eq!(tool.command, "sudo ls /root");
+        assert_eq!(password, "pw1");
+        assert!(app.sudo_prompt.is_none());
+    }
+
+    #[test]
+    fn test_sudo_prompt_escape_cancels() {
+        let mut app = test_app();
+        app.state = AppState::Executing;
+        app.request_sudo_password(ToolCall::run_cmd("sudo ls"));
+
+        let result = app.handle_key_event(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        assert_eq!(result, InputResult::CancelCommand);
+        assert!(app.sudo_prompt.is_none());
+    }
+
+    // **Feature: Sabi-TUI, Property: Snippets Skip the AI**
+    #[test]
+    fn test_snippet_fill_goes_to_review() {
+        let mut app = test_app();
+        let snippet = snippets::Snippet {
+            command: "echo {{who:world}} {{n}}".to_string(),
+            description: String::new(),
+        };
+        app.snippet_fill = Some(snippets::Fill::new("greet", snippet));
+        assert_eq!(app.advance_snippet(), SubmitResult::Handled);
+
+        // Empty input takes the default
+        assert_eq!(app.submit_input(), SubmitResult::Handled);
+        // No default: a value is required
+        assert_eq!(app.submit_input(), SubmitResult::Handled);
+        assert!(app.snippet_fill.is_some());
+
+        app.input_textarea.insert_str("3");
+        assert_eq!(
+            app.submit_input(),
+            SubmitResult::ReviewTool(Box::new(ToolCall::run_cmd("echo world 3")))
+        );
+        assert!(app.snippet_fill.is_none());
+        assert_eq!(app.state, AppState::Input);
+    }
+
+    #[test]
+    fn test_snippet_escape_cancels() {
+        let mut app = test_app();
+        let snippet = snippets::Snippet {
+            command: "ls {{dir}}".to_string(),
+            description: String::new(),
+        };
+        app.snippet_fill = Some(snippets::Fill::new("ls", snippet));
+
+        let result = app.handle_key_event(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        assert_eq!(result, InputResult::Handled);
+        assert!(app.snippet_fill.is_none());
+        assert!(!app.should_quit);
+    }
+
+    #[test]
+    fn test_target_command() {
+        let mut app = test_app();
+        assert!(
+            app.set_target(None)
+                .starts_with("Commands run on the host.")
+        );
+        assert_eq!(
+            app.set_target(Some("local")),
+            "Commands already run on the host."
+        );
+        assert!(app.set_target(Some("vm:box")).starts_with("Usage:"));
+        assert!(app.target.is_none());
+
+        // Connecting is left to the main loop, off the UI thread
+        app.input_textarea.insert_str("/target container:dev");
+        assert_eq!(
+            app.submit_input(),
+            SubmitResult::ConnectTarget("dev".to_string())
+        );
+        app.input_textarea.insert_str("/target container:");
+        assert_eq!(app.submit_input(), SubmitResult::Handled);
+
+        app.finish_target(Err("no such container: dev".to_string()));
+        assert!(app.target.is_none());
+        assert_eq!(
+            app.messages.last().unwrap().content,
+            "✗ no such container: dev"
+        );
+    }
+
+    // **Feature: Sabi-TUI, Property: Write Conflict Needs Confirmation**
+    #[test]
+    fn test_write_conflict_flags_danger() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = test_app();
+        app.cwd = dir.path().to_path_buf();
+        std::fs::write(dir.path().join("a.txt"), "old").unwrap();
+        app.write_guard.record(&dir.path().join("a.txt"));
+
+        let mut tool = ToolCall::run_cmd("true");
+        tool.tool = "write_file".to_string();
+        tool.path = "a.txt".to_string();
+        app.current_tool = Some(tool);
+        app.check_write_conflict();
+        assert!(!app.dangerous_command_detected);
+        assert_eq!(app.write_conflict, None);
+
+        std::fs::write(dir.path().join("a.txt"), "changed").unwrap();
+        app.check_write_conflict();
+        assert!(app.dangerous_command_detected);
+        assert!(app.danger_reasons[0].starts_with("Write conflict:"));
+        assert!(app.write_conflict.is_some());
+    }
+
+    // **Feature: Sabi-TUI, Property: Mouse Scrolls and Focuses Panes**
+    #[test]
+    fn test_mouse_scroll_and_focus() {
+        use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+
+        let mut app = test_app();
+        let panes = [
+            Rect::new(0, 0, 80, 20),
+            Rect::new(0, 20, 80, 3),
+            Rect::new(0, 23, 80, 3),
+        ];
+        let mouse = |kind, column, row| MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        };
+
+        app.handle_mouse_event(mouse(MouseEventKind::ScrollUp, 5, 5), &panes);
+        assert_eq!(app.scroll_offset, MOUSE_SCROLL);
+        app.handle_mouse_event(mouse(MouseEventKind::ScrollDown, 5, 5), &panes);
+        assert_eq!(app.scroll_offset, 0);
+
+        // Clicking the chat focuses it; arrows then scroll it
+        app.handle_mouse_event(mouse(MouseEventKind::Down(MouseButton::Left), 5, 5), &panes);
+        assert_eq!(app.focus, Pane::Chat);
+        app.handle_key_event(KeyEvent::new(KeyCode::PageUp, KeyModifiers::NONE));
+        assert_eq!(app.scroll_offset, CHAT_PAGE);
+
+        // Typing hands focus back to the input
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
+        assert_eq!(app.focus, Pane::Input);
+        assert_eq!(app.get_input_text(), "x");
+        assert!(app.chat_selection.is_none());
+
+        app.handle_mouse_event(
+            mouse(MouseEventKind::Down(MouseButton::Left), 5, 21),
+            &panes,
+        );
+        assert_eq!(app.focus, Pane::Input);
+    }
+
+    #[test]
+    fn test_chat_selection_text() {
+        let lines: Vec<String> = ["🧑 You:", "  hello world", "  second line", ""]
+            .iter()
+            .map(|l| l.to_string())
+            .collect();
+        let selection = ChatSelection {
+            anchor: (2, 7),
+            cursor: (1, 8),
+        };
+        assert_eq!(selection.text(&lines), "world\n  second");
+        assert_eq!(selection.columns(0, 7), None);
+        assert_eq!(selection.columns(1, 13), Some((8, 13)));
+    }
+
+    // **Feature: Sabi-TUI, Property: Collapsible Messages**
+    #[test]
+    fn test_long_tool_result_collapses_and_expands() {
+        let mut app = test_app();
+        let output: Vec<String> = (1..=40).map(|i| format!("line {i}")).collect();
+        app.add_message(Message::user(format!(
+            "Tool: run_cmd\nExit code: 0\n{}",
+            output.join("\n")
+        )));
+        let index = app.messages.len() - 1;
+        let area = Rect::new(0, 0, 80, 200);
+
+        let shows_end = |app: &App| {
+            let view = crate::ui::chat_view(app, area);
+            view.lines()
+                .any(|l| crate::ui::line_text(l).contains("line 40"))
+        };
+        let view = crate::ui::chat_view(&app, area);
+        assert_eq!(view.markers.len(), 1);
+        let marker = crate::ui::line_text(view.line(view.markers[0]).unwrap());
+        assert!(marker.contains("▸") && marker.contains("more lines"));
+        assert!(!shows_end(&app));
+
+        let ctrl_up = KeyEvent::new(KeyCode::Up, KeyModifiers::CONTROL);
+        app.handle_key_event(ctrl_up);
+        assert_eq!(app.selected_message, Some(index));
+        assert_eq!(app.focus, Pane::Chat);
+
+        app.handle_key_event(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert!(crate::ui::chat_view(&app, area).markers.is_empty());
+        assert!(shows_end(&app));
+
+        app.handle_key_event(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert_eq!(crate::ui::chat_view(&app, area).markers.len(), 1);
+    }
+
+    // **Feature: Sabi-TUI, Property: Chat Search Navigation**
+    #[test]
+    fn test_find_jumps_between_matches() {
+        let mut app = test_app();
+        app.screen = Rect::new(0, 0, 80, 20);
+        app.add_message(Message::user("needle at the top"));
+        for i in 0..60 {
+            app.add_message(Message::model(format!("filler {i}")));
         }
+        app.add_message(Message::user("another Needle near the end"));
 
-        #[test]
-        fn prop_ctrl_c_allowed_in_async_states(state in arb_async_state()) {
-            let mut app = test_app();
+        app.input_textarea.insert_str("/find needle");
+        app.submit_input();
+        let search = app.search.clone().unwrap();
+        assert_eq!(search.current, 1);
+        assert_eq!(app.focus, Pane::Chat);
+        assert_eq!(app.scroll_offset, 0);
 
-            // Set the async state
-            app.state = state;
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('n'), KeyModifiers::NONE));
+        assert_eq!(app.search.as_ref().unwrap().current, 0);
+        let at_top = app.scroll_offset;
+        assert!(at_top > 50);
 
-            // Simulate pressing Ctrl+C
-            let key = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
-            let result = app.handle_key_event(key);
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('N'), KeyModifiers::SHIFT));
+        assert_eq!(app.search.as_ref().unwrap().current, 1);
+        assert!(app.scroll_offset < 5);
 
-            // Property: result should be Quit
-            prop_assert_eq!(
-                result,
-                InputResult::Quit,
-                "Ctrl+C should allow quit in {:?} state",
-                state
-            );
+        app.handle_key_event(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        assert!(app.search.is_none());
 
-            // Property: should_quit flag should be set
-            prop_assert!(
-                app.should_quit,
-                "should_quit should be true after Ctrl+C in {:?} state",
-                state
-            );
-        }
+        app.input_textarea.insert_str("/find missing");
+        app.submit_input();
+        assert!(app.search.is_none());
+        assert!(app.messages.last().unwrap().content.contains("No matches"));
+    }
 
-        #[test]
-        fn prop_thinking_state_blocks_input(key in arb_non_escape_key()) {
-            let mut app = test_app();
+    // **Feature: Sabi-TUI, Property: Reply Reveal**
+    #[test]
+    fn test_reply_reveal_and_skip() {
+        let mut app = test_app();
+        app.config.reveal_replies = true;
+        app.add_message(Message::model("x".repeat(60)));
+        app.start_reveal();
+        assert_eq!(app.reveal.unwrap().shown, 0);
+        app.advance_reveal();
+        assert_eq!(app.reveal.unwrap().shown, 24);
+        app.advance_reveal();
+        app.advance_reveal();
+        assert!(app.reveal.is_none());
+
+        // Esc only skips the animation, it doesn't quit
+        app.start_reveal();
+        let result = app.handle_key_event(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        assert_eq!(result, InputResult::Handled);
+        assert!(app.reveal.is_none() && !app.should_quit);
+
+        // Neither does a key that would approve a tool call
+        app.state = AppState::ReviewAction;
+        app.start_reveal();
+        let result = app.handle_key_event(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert_eq!(result, InputResult::Handled);
+        assert_eq!(app.state, AppState::ReviewAction);
+        assert!(app.reveal.is_none());
 
-            // Get to Thinking state legitimately
-            app.input_textarea.insert_str("test query");
-            app.submit_input();
+        app.config.reveal_replies = false;
+        app.start_reveal();
+        assert!(app.reveal.is_none());
+    }
 
-            prop_assert_eq!(app.state, AppState::Thinking);
+    // **Feature: Sabi-TUI, Property: Idle Tick Rate**
+    #[test]
+    fn test_tick_rate_slows_when_idle() {
+        let mut app = test_app();
+        let fast = Duration::from_millis(100);
+        let slow = Duration::from_millis(1000);
+        assert_eq!(app.tick_rate(Duration::ZERO), fast);
+        assert_eq!(app.tick_rate(IDLE_AFTER), slow);
 
-            // Record initial state
-            let input_before = app.get_input_text();
-            let action_before = app.get_action_text();
+        app.state = AppState::Thinking;
+        assert_eq!(app.tick_rate(IDLE_AFTER), fast);
 
-            // Try to input
-            let result = app.handle_key_event(key);
+        app.state = AppState::Input;
+        app.config.tick_rate_ms = 50;
+        app.config.idle_tick_rate_ms = 0;
+        assert_eq!(app.tick_rate(IDLE_AFTER), Duration::from_millis(50));
+    }
 
-            // Property: should be blocked
-            prop_assert_eq!(result, InputResult::Blocked);
+    // **Feature: Sabi-TUI, Property: Copy Targets**
+    #[test]
+    fn test_copy_targets() {
+        let mut app = test_app();
+        assert!(app.last_code_block().is_err());
+        assert!(app.last_command().is_err());
+
+        app.add_message(Message::model("Try:\n\n```sh\nmake test\n```"));
+        app.add_message(Message::model("Or:\n\n```\ncargo test\n```\n\nThat's all."));
+        app.add_message(Message::model("No code this time."));
+        assert_eq!(app.last_code_block().unwrap(), "cargo test");
+        assert_eq!(app.message_to_copy().unwrap(), "No code this time.");
+
+        app.selected_message = Some(app.messages.len() - 3);
+        assert!(app.message_to_copy().unwrap().contains("make test"));
+
+        app.last_tool = Some(ToolCall::run_cmd("git status"));
+        assert_eq!(app.last_command().unwrap(), "git status");
+        app.last_tool = Some(ToolCall::new("read_file", ""));
+        assert!(app.last_command().unwrap_err().contains("read_file"));
+    }
 
-            // Property: content unchanged
-            prop_assert_eq!(app.get_input_text(), input_before);
-            prop_assert_eq!(app.get_action_text(), action_before);
-        }
+    // **Feature: Sabi-TUI, Property: Vi Normal Mode**
+    #[test]
+    fn test_vi_normal_mode() {
+        let press = |app: &mut App, c: char| {
+            app.handle_key_event(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+        };
+        let esc = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
 
-        #[test]
-        fn prop_finalizing_state_blocks_input(key in arb_non_escape_key()) {
-            let mut app = test_app();
+        let mut app = test_app();
+        app.config.vi_mode = true;
+        for i in 0..80 {
+            app.add_message(Message::model(format!("line {i}")));
+        }
 
-            // Set to Finalizing state
-            app.state = AppState::Finalizing;
+        assert_eq!(app.handle_key_event(esc), InputResult::Handled);
+        assert!(!app.should_quit);
+        assert_eq!(app.focus, Pane::Chat);
 
-            // Add some content to verify it's not modified
-            app.input_textarea.insert_str("previous input");
-            app.action_textarea.insert_str("previous action");
+        press(&mut app, 'k');
+        assert_eq!(app.scroll_offset, 1);
+        press(&mut app, 'j');
+        assert_eq!(app.scroll_offset, 0);
 
-            let input_before = app.get_input_text();
-            let action_before = app.get_action_text();
+        press(&mut app, 'g');
+        assert_eq!(app.scroll_offset, 0);
+        press(&mut app, 'g');
+        assert!(app.scroll_offset > 100);
+        press(&mut app, 'G');
+        assert_eq!(app.scroll_offset, 0);
 
-            // Try to input
-            let result = app.handle_key_event(key);
+        // Other keys don't leak into the input
+        press(&mut app, 'x');
+        assert_eq!(app.get_input_text(), "");
 
-            // Property: should be blocked
-            prop_assert_eq!(result, InputResult::Blocked);
+        press(&mut app, 'i');
+        assert_eq!(app.focus, Pane::Input);
+        press(&mut app, 'x');
+        assert_eq!(app.get_input_text(), "x");
 
-            // Property: content unchanged
-            prop_assert_eq!(app.get_input_text(), input_before);
-            prop_assert_eq!(app.get_action_text(), action_before);
-        }
+        // Without vi mode Esc still quits
+        let mut app = test_app();
+        assert_eq!(app.handle_key_event(esc), InputResult::Quit);
     }
 
+    // **Feature: Sabi-TUI, Property: Multi-line Input**
     #[test]
-    fn test_thinking_blocks_character_input() {
+    fn test_multiline_input() {
         let mut app = test_app();
+        app.input_textarea.insert_str("first");
+        app.handle_key_event(KeyEvent::new(KeyCode::Enter, KeyModifiers::ALT));
+        app.input_textarea.insert_str("second");
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('j'), KeyModifiers::CONTROL));
+        app.input_textarea.insert_str("third");
+        assert_eq!(app.get_input_text(), "first\nsecond\nthird");
+
+        // Up moves the cursor in a multi-line draft instead of scrolling
+        app.handle_key_event(KeyEvent::new(KeyCode::Up, KeyModifiers::NONE));
+        assert_eq!(app.scroll_offset, 0);
+        assert_eq!(app.input_textarea.cursor().0, 1);
 
-        // Get to Thinking state
-        app.input_textarea.insert_str("test");
-        app.submit_input();
-        assert_eq!(app.state, AppState::Thinking);
+        app.clear_input();
+        app.input_textarea.insert_str("/multiline");
+        app.handle_key_event(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert!(app.multiline);
 
-        // Try to type a character
-        let key = KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE);
-        let result = app.handle_key_event(key);
+        app.input_textarea.insert_str("a");
+        app.handle_key_event(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        app.input_textarea.insert_str("b");
+        assert_eq!(app.get_input_text(), "a\nb");
 
-        assert_eq!(result, InputResult::Blocked);
-        assert!(app.get_input_text().is_empty()); // Was cleared on submit
+        let result = app.handle_key_event(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL));
+        assert_eq!(result, InputResult::SubmitQuery);
     }
 
+    // **Feature: Sabi-TUI, Property: Status Reports Are Toasts**
     #[test]
-    fn test_thinking_allows_escape() {
+    fn test_status_reports_are_toasts() {
         let mut app = test_app();
+        let count = app.messages.len();
 
-        // Get to Thinking state
-        app.input_textarea.insert_str("test");
+        app.input_textarea.insert_str("/compact");
         app.submit_input();
-        assert_eq!(app.state, AppState::Thinking);
-
-        // Press Escape
-        let key = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
-        let result = app.handle_key_event(key);
+        assert!(app.compact);
+        assert_eq!(app.messages.len(), count);
+        let toasts: Vec<_> = app.toasts.iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(toasts, ["Compact layout on."]);
+    }
 
-        assert_eq!(result, InputResult::Quit);
-        assert!(app.should_quit);
+    // **Feature: Sabi-TUI, Property: Help Overlay**
+    #[test]
+    fn test_help_overlay_toggles() {
+        let mut app = test_app();
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        app.handle_key_event(key(KeyCode::F(1)));
+        assert_eq!(app.help_scroll, Some(0));
+        app.handle_key_event(key(KeyCode::Down));
+        assert_eq!(app.help_scroll, Some(1));
+        // Keys don't reach the input while help is open
+        app.handle_key_event(key(KeyCode::Char('x')));
+        assert_eq!(app.get_input_text(), "");
+        app.handle_key_event(key(KeyCode::Esc));
+        assert_eq!(app.help_scroll, None);
+        assert!(!app.should_quit);
+
+        // `?` opens help only on an empty input
+        app.handle_key_event(key(KeyCode::Char('?')));
+        assert_eq!(app.help_scroll, Some(0));
+        app.handle_key_event(key(KeyCode::Char('?')));
+        assert_eq!(app.help_scroll, None);
+        app.input_textarea.insert_str("why");
+        app.handle_key_event(key(KeyCode::Char('?')));
+        assert_eq!(app.help_scroll, None);
+        assert_eq!(app.get_input_text(), "why?");
     }
 
+    // **Feature: Sabi-TUI, Property: File Preview Pane**
     #[test]
-    fn test_executing_blocks_input() {
+    fn test_f2_toggles_file_preview_in_review() {
         let mut app = test_app();
+        app.screen = Rect::new(0, 0, 120, 40);
+        let full = app.chat_area();
 
-        // Set to Executing state
-        app.state = AppState::Executing;
+        let mut tool = ToolCall::new("write_file", "");
+        tool.path = "notes.txt".to_string();
+        app.current_tool = Some(tool);
+        app.state = AppState::ReviewAction;
+        assert_eq!(app.chat_area(), full);
 
-        // Try to type
-        let key = KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE);
-        let result = app.handle_key_event(key);
+        app.handle_key_event(KeyEvent::new(KeyCode::F(2), KeyModifiers::NONE));
+        assert!(app.show_preview);
+        assert!(app.chat_area().width < full.width);
 
-        assert_eq!(result, InputResult::Blocked);
+        // Only file tools get a preview
+        app.current_tool = Some(ToolCall::run_cmd("ls"));
+        assert_eq!(app.chat_area(), full);
     }
 
     #[test]
@@ -2026,7 +5395,7 @@ mod tests {
         #[test]
         fn prop_error_clears_on_new_input(
             error_msg in "[a-zA-Z0-9 ]{1,50}",
-            new_input in non_empty_string()
+            _new_input in non_empty_string()
         ) {
             let mut app = test_app();
 
@@ -2442,6 +5811,135 @@ mod tests {
         );
     }
 
+    // **Feature: Sabi-TUI, Property: Slash Command /rename**
+    #[test]
+    fn test_slash_command_rename() {
+        let mut app = test_app();
+        app.current_session_id = "20260301_143022".to_string();
+        assert_eq!(app.session_name(), "Session 143022");
+
+        app.input_textarea.insert_str("/rename");
+        app.submit_input();
+        let usage = &app.messages.last().unwrap().content;
+        assert!(usage.contains("Usage: /rename"));
+
+        app.input_textarea.insert_str("/rename  Build fixes ");
+        app.submit_input();
+        assert_eq!(app.session_name(), "Build fixes");
+        let markdown = app.conversation_markdown();
+        assert!(markdown.contains("Session: Build fixes |"));
+    }
+
+    // **Feature: Sabi-TUI, Property: Crash Recovery**
+    #[cfg(unix)]
+    #[test]
+    fn test_take_crashed_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut gone = std::process::Command::new("true").spawn().unwrap();
+        gone.wait().unwrap();
+        let dead_marker = dir.path().join(format!("{}{}", RUNNING_MARKER, gone.id()));
+        std::fs::write(&dead_marker, "20260301_143022\n").unwrap();
+        std::fs::write(crash_marker(dir.path()), "20260301_150000").unwrap();
+        let partial = temp_path(&dir.path().join("20260301_143022.json"), gone.id());
+        std::fs::write(&partial, "{").unwrap();
+        // Another running sabi's save in progress
+        let writing = temp_path(&dir.path().join("20260301_150000.json"), std::process::id());
+        std::fs::write(&writing, "{").unwrap();
+
+        assert_eq!(
+            take_crashed_session(dir.path()),
+            Some("20260301_143022".to_string())
+        );
+        assert!(!dead_marker.exists());
+        assert!(!partial.exists());
+        assert!(writing.exists());
+        // This process is still running, so its own marker stays
+        assert!(crash_marker(dir.path()).exists());
+        assert_eq!(take_crashed_session(dir.path()), None);
+    }
+
+    // **Feature: Sabi-TUI, Property: Resume Prompt**
+    #[test]
+    fn test_resume_offer_keys() {
+        let mut app = test_app();
+        let mut session = Session::new();
+        session.id = "19990101_000000".to_string();
+        session.messages.push(Message::user("old question"));
+        app.resume_offer = Some(session.clone());
+
+        // Other keys keep the prompt open and don't reach the input
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
+        assert!(app.resume_offer.is_some());
+        assert!(app.is_input_empty());
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('n'), KeyModifiers::NONE));
+        assert!(app.resume_offer.is_none());
+        assert!(!app.messages.iter().any(|m| m.content == "old question"));
+
+        app.resume_offer = Some(session);
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('y'), KeyModifiers::NONE));
+        assert!(app.resume_offer.is_none());
+        let failed = app
+            .toasts
+            .iter()
+            .any(|t| t.text.starts_with("✗ Failed to resume"));
+        assert!(failed);
+    }
+
+    // **Feature: Sabi-TUI, Property: Debounced Autosave**
+    #[test]
+    fn test_finished_turn_schedules_autosave() {
+        let mut app = test_app();
+        app.transition(StateEvent::SubmitInput { is_empty: false });
+        assert!(app.autosave_due.is_none());
+        app.transition(StateEvent::TextResponseReceived);
+        let due = app.autosave_due.expect("autosave scheduled");
+
+        // A second turn within the delay is saved with the first
+        app.transition(StateEvent::SubmitInput { is_empty: false });
+        app.transition(StateEvent::TextResponseReceived);
+        assert_eq!(app.autosave_due, Some(due));
+        app.autosave_if_due(due - Duration::from_millis(1));
+        assert_eq!(app.autosave_due, Some(due));
+        // Nothing said yet, so nothing is written
+        app.autosave_if_due(due);
+        assert!(app.autosave_due.is_none());
+    }
+
+    // **Feature: Sabi-TUI, Property: Atomic Session Writes**
+    #[test]
+    fn test_write_atomic_replaces_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("s.json");
+        std::fs::write(&path, "old").unwrap();
+        write_atomic(&path, "new").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    // **Feature: Sabi-TUI, Property: Slash Command /artifact**
+    #[test]
+    fn test_slash_command_artifact() {
+        let mut app = test_app();
+        app.input_textarea.insert_str("/artifact");
+        app.submit_input();
+        let empty = &app.messages.last().unwrap().content;
+        assert!(empty.starts_with("No artifacts"));
+
+        let id = "0123456789abcdef".to_string();
+        let tool = Message::tool("Tool: run_cmd seq 100000\nExit code: 0\nOutput:\n1");
+        app.add_message(tool.with_artifacts(vec![id]));
+        app.input_textarea.insert_str("/artifact");
+        app.submit_input();
+        let list = &app.messages.last().unwrap().content;
+        assert!(list.contains("  0123456789abcdef  Tool: run_cmd seq 100000"));
+
+        app.input_textarea.insert_str("/artifact zz");
+        app.submit_input();
+        assert!(app.pager.is_none());
+        let error = &app.messages.last().unwrap().content;
+        assert!(error.contains("Not an artifact id"));
+    }
+
     // **Feature: Sabi-TUI, Property: Slash Command /new**
     #[test]
     fn test_slash_command_new() {
@@ -2453,6 +5951,160 @@ mod tests {
         assert_eq!(result, SubmitResult::Handled);
     }
 
+    // **Feature: Sabi-TUI, Property: Session Templates**
+    #[test]
+    fn test_slash_command_new_from_template() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut app = test_app();
+        app.cwd = temp.path().to_path_buf();
+        app.add_message(Message::system("You MUST use tools."));
+        app.config.templates.insert(
+            "review".to_string(),
+            crate::template::SessionTemplate {
+                instructions: "Don't write files.".to_string(),
+                cwd: ".".to_string(),
+                model: "gpt-4o".to_string(),
+                ..Default::default()
+            },
+        );
+
+        app.input_textarea.insert_str("/new nope");
+        assert_eq!(app.submit_input(), SubmitResult::Handled);
+        assert!(app.template.is_none());
+
+        app.input_textarea.insert_str("/new review");
+        let result = app.submit_input();
+        let model = Some("gpt-4o".to_string());
+        assert_eq!(result, SubmitResult::FetchModels(model));
+        assert_eq!(app.template.as_deref(), Some("review"));
+        assert_eq!(app.cwd, temp.path().join("."));
+        let prompt = &app.context_messages()[0].content;
+        assert!(prompt.starts_with("You MUST use tools."));
+        assert!(prompt.ends_with("whole session:\nDon't write files."));
+        // The template's text is only added to what is sent
+        assert_eq!(app.messages[0].content, "You MUST use tools.");
+
+        app.new_session();
+        assert!(app.template.is_none());
+    }
+
+    // **Feature: Sabi-TUI, Property: Workspaces**
+    #[test]
+    fn test_slash_command_workspace() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let root = temp.path().canonicalize().unwrap();
+        let mut app = test_app();
+        app.config.workspaces.insert(
+            "api".to_string(),
+            crate::workspace::Workspace {
+                root: root.to_string_lossy().into_owned(),
+                scope_tools: true,
+            },
+        );
+
+        app.input_textarea.insert_str("/workspace switch nope");
+        app.submit_input();
+        assert!(app.workspace.is_none());
+        app.input_textarea.insert_str("/workspace switch api");
+        app.submit_input();
+        assert_eq!(app.workspace.as_deref(), Some("api"));
+        assert_eq!(app.cwd, root);
+
+        let read = |path: &str| ToolCall {
+            path: path.to_string(),
+            ..ToolCall::new("read_file", "")
+        };
+        assert!(app.check_workspace_scope(&read("src/lib.rs")).is_none());
+        assert!(app.check_workspace_scope(&read("/etc/hosts")).is_some());
+
+        // Sessions remember their workspace
+        let file = root.join("session.json");
+        app.save_session(file.to_str().unwrap()).unwrap();
+        let mut other = test_app();
+        other.load_session(file.to_str().unwrap()).unwrap();
+        assert_eq!(other.workspace.as_deref(), Some("api"));
+
+        app.input_textarea.insert_str("/workspace");
+        app.submit_input();
+        let listing = &app.messages.last().unwrap().content;
+        assert!(listing.contains("→ api"));
+        app.input_textarea.insert_str("/workspace off");
+        app.submit_input();
+        assert!(app.workspace.is_none());
+        assert!(app.check_workspace_scope(&read("/etc/hosts")).is_none());
+    }
+
+    // **Feature: Sabi-TUI, Property: Slash Command /config**
+    #[test]
+    fn test_slash_command_config() {
+        let mut app = test_app();
+        app.config.safe_mode = true;
+        app.input_textarea.insert_str("/config");
+        assert_eq!(app.submit_input(), SubmitResult::Handled);
+        let pager = app.pager.take().unwrap();
+        assert!(pager.title.ends_with("config.toml"));
+        let safe_mode = pager.lines.iter().find(|l| l.starts_with("safe_mode = "));
+        assert!(safe_mode.unwrap().starts_with("safe_mode = true  ("));
+
+        // Nothing is saved for a key that doesn't exist
+        app.input_textarea.insert_str("/config set no_such_key 1");
+        app.submit_input();
+        let toast = app.toasts.iter().last().unwrap();
+        assert_eq!(toast.text, "✗ Unknown setting 'no_such_key'");
+        app.input_textarea.insert_str("/config set safe_mode");
+        app.submit_input();
+        let usage = &app.messages.last().unwrap().content;
+        assert_eq!(usage, "Usage: /config [set <key> <value>]");
+    }
+
+    // **Feature: Sabi-TUI, Property: Slash Command /setup**
+    #[test]
+    fn test_slash_command_setup() {
+        let mut app = test_app();
+        app.input_textarea.insert_str("/setup");
+        assert_eq!(app.submit_input(), SubmitResult::Handled);
+        assert_eq!(app.setup.as_ref().unwrap().selected, 0);
+
+        // Keys go to the wizard, not the input
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        app.handle_key_event(key(KeyCode::Char('2')));
+        app.handle_key_event(key(KeyCode::Char('x')));
+        let wizard = app.setup.as_ref().unwrap();
+        assert_eq!(wizard.step, crate::setup::Step::ApiKey);
+        assert_eq!(wizard.api_key, "x");
+        assert!(app.is_input_empty());
+
+        app.handle_key_event(key(KeyCode::Esc));
+        app.handle_key_event(key(KeyCode::Esc));
+        assert!(app.setup.is_none() && !app.should_quit);
+        assert!(!app.client_changed);
+    }
+
+    // **Feature: Sabi-TUI, Property: Slash Command /patterns**
+    #[test]
+    fn test_slash_command_patterns() {
+        let mut app = test_app();
+        app.input_textarea.insert_str("/patterns");
+        app.submit_input();
+        let listing = &app.messages.last().unwrap().content;
+        assert!(listing.contains("1. [block] :\\(\\)\\s*\\{  (fork bomb)"));
+
+        app.input_textarea.insert_str("/patterns test :(){ :|: };:");
+        app.submit_input();
+        let verdict = &app.messages.last().unwrap().content;
+        assert_eq!(verdict, "⛔ Blocked: fork bomb");
+
+        // Bad input changes nothing
+        app.input_textarea.insert_str("/patterns add block rm (");
+        app.submit_input();
+        let error = &app.messages.last().unwrap().content;
+        assert!(error.contains("isn't a valid regex"));
+        app.input_textarea.insert_str("/patterns remove 7");
+        app.submit_input();
+        assert_eq!(app.config.dangerous_patterns.len(), 1);
+        assert!(!app.patterns_changed);
+    }
+
     // **Feature: Sabi-TUI, Property: Slash Command /sessions**
     #[test]
     fn test_slash_command_sessions() {
@@ -2480,6 +6132,130 @@ mod tests {
         );
     }
 
+    // **Feature: Sabi-TUI, Property: Streamed Output Is Bounded**
+    #[test]
+    fn test_append_output_chunk_keeps_tail() {
+        let mut app = test_app();
+        app.config.max_output_lines = 3;
+
+        for i in 0..5 {
+            app.append_output_chunk(&format!("line {}", i));
+        }
+
+        assert_eq!(app.execution_output, "line 2\nline 3\nline 4\n");
+    }
+
+    // **Feature: Sabi-TUI, Property: cd Updates Session cwd**
+    #[test]
+    fn test_intercept_cd() {
+        let temp = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(temp.path().join("sub")).unwrap();
+        let mut app = test_app();
+        app.cwd = temp.path().to_path_buf();
+
+        assert_eq!(app.intercept_cd("ls"), CdIntercept::NotCd);
+        assert_eq!(
+            app.intercept_cd("cd sub && ls"),
+            CdIntercept::Changed(Some("ls".to_string()))
+        );
+        assert!(app.cwd.ends_with("sub"));
+        assert!(matches!(
+            app.intercept_cd("cd nope"),
+            CdIntercept::Failed(_)
+        ));
+        assert!(app.cwd.ends_with("sub"));
+
+        // `cd -` goes back and forth
+        assert_eq!(app.intercept_cd("cd -"), CdIntercept::Changed(None));
+        assert!(!app.cwd.ends_with("sub"));
+        assert_eq!(app.intercept_cd("cd -"), CdIntercept::Changed(None));
+        assert!(app.cwd.ends_with("sub"));
+    }
+
+    #[test]
+    fn test_session_persists_cwd() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let file = temp.path().join("session.json");
+        let mut app = test_app();
+        app.cwd = temp.path().canonicalize().unwrap();
+        app.save_session(file.to_str().unwrap()).unwrap();
+
+        let mut other = test_app();
+        other.load_session(file.to_str().unwrap()).unwrap();
+        assert_eq!(other.cwd, app.cwd);
+
+        // A directory given with --cwd wins over the session's
+        let mut pinned = test_app();
+        pinned.cwd = PathBuf::from("/");
+        pinned.cwd_pinned = true;
+        pinned.load_session(file.to_str().unwrap()).unwrap();
+        assert_eq!(pinned.cwd, PathBuf::from("/"));
+    }
+
+    // **Feature: Sabi-TUI, Property: Persisted Summary**
+    #[test]
+    fn test_summary_saved_with_session() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let file = temp.path().join("session.json");
+        let mut app = test_app();
+        app.config.max_history_messages = 4;
+        for i in 0..4 {
+            app.add_message(Message::user(format!("question {}", i)));
+            app.add_message(Message::model(format!("answer {}", i)));
+        }
+
+        let (request, covers) = app.start_summary().expect("summary due");
+        assert_eq!(covers, 6);
+        assert!(request[0].content.contains("User:\nquestion 0"));
+        assert!(!request[0].content.contains("question 3"));
+        assert!(app.start_summary().is_none());
+        let id = app.current_session_id.clone();
+        app.finish_summary(&id, "Asked three questions.".to_string(), covers);
+        assert!(!app.summarizing);
+        app.save_session(file.to_str().unwrap()).unwrap();
+
+        let mut other = test_app();
+        other.config.max_history_messages = 4;
+        other.load_session(file.to_str().unwrap()).unwrap();
+        assert_eq!(other.summary, app.summary);
+        let sent = other.context_messages();
+        let history: Vec<&str> = sent
+            .iter()
+            .filter(|m| m.role != MessageRole::System)
+            .map(|m| m.content.as_str())
+            .collect();
+        assert!(history[0].ends_with("Asked three questions."));
+        assert_eq!(&history[1..], ["question 3", "answer 3"]);
+
+        // A summary for a session no longer open is dropped
+        other.current_session_id = "20260101_000000".to_string();
+        other.finish_summary(&id, "stale".to_string(), 2);
+        assert_eq!(other.summary, app.summary);
+    }
+
+    // **Feature: Sabi-TUI, Property: Slash Command /edit**
+    #[test]
+    fn test_slash_command_edit() {
+        let mut app = test_app();
+        app.input_textarea.insert_str("/edit notes.txt");
+
+        let result = app.submit_input();
+
+        assert_eq!(result, SubmitResult::OpenEditor("notes.txt".to_string()));
+    }
+
+    #[test]
+    fn test_slash_command_edit_without_path() {
+        let mut app = test_app();
+        app.input_textarea.insert_str("/edit");
+
+        let initial_count = app.messages.len();
+        let result = app.submit_input();
+
+        assert_eq!(result, SubmitResult::Handled);
+        assert!(app.messages.len() > initial_count);
+    }
+
     // **Feature: Sabi-TUI, Property: Slash Command /clear**
     #[test]
     fn test_slash_command_clear() {
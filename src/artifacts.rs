@@ -0,0 +1,111 @@
+//! Content-addressed store for bulky text
+//!
+//! An artifact is a file under `artifacts/` in the data directory
+//! (`~/.sabi` unless `data_dir` is set) named by a hash of its content, so
+//! storing the same output twice keeps a single copy. Messages refer to
+//! artifacts by that id.
+
+use std::path::{Path, PathBuf};
+
+/// Directory artifacts are kept in (`artifacts/` in the data directory)
+pub fn artifacts_dir() -> Option<PathBuf> {
+    crate::config::data_dir().map(|d| d.join("artifacts"))
+}
+
+/// Id of `content`: 16 hex digits of its 64-bit FNV-1a hash
+pub fn id_of(content: &str) -> String {
+    let hash = content
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    format!("{:016x}", hash)
+}
+
+/// Store `content` in `dir`, returning its id
+pub fn store_in(dir: &Path, content: &str) -> std::io::Result<String> {
+    let id = id_of(content);
+    let path = dir.join(format!("{}.txt", id));
+    if !path.exists() {
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(path, content)?;
+    }
+    Ok(id)
+}
+
+/// Store `content` in the artifacts directory, returning its id
+pub fn store(content: &str) -> std::io::Result<String> {
+    let dir = artifacts_dir()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "No home directory"))?;
+    store_in(&dir, content)
+}
+
+/// File of artifact `id`
+pub fn path(id: &str) -> Option<PathBuf> {
+    artifacts_dir().map(|d| d.join(format!("{}.txt", id)))
+}
+
+/// Shortest prefix accepted in place of a full id
+const MIN_PREFIX: usize = 4;
+
+/// Full id of the artifact in `dir` whose id starts with `prefix`
+pub fn resolve_in(dir: &Path, prefix: &str) -> Result<String, String> {
+    let prefix = prefix.trim().to_lowercase();
+    if prefix.len() < MIN_PREFIX || !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Not an artifact id: '{}'", prefix));
+    }
+    let entries = std::fs::read_dir(dir).map_err(|_| "No artifacts stored yet".to_string())?;
+    let ids: Vec<String> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().into_owned();
+            name.strip_suffix(".txt").map(String::from)
+        })
+        .filter(|id| id.starts_with(&prefix))
+        .collect();
+    match ids.as_slice() {
+        [id] => Ok(id.clone()),
+        [] => Err(format!("No artifact {}", prefix)),
+        _ => Err(format!("'{}' matches {} artifacts", prefix, ids.len())),
+    }
+}
+
+/// Id and text of the artifact `prefix` names
+pub fn load(prefix: &str) -> Result<(String, String), String> {
+    let dir = artifacts_dir().ok_or("No home directory")?;
+    let id = resolve_in(&dir, prefix)?;
+    let text = std::fs::read_to_string(dir.join(format!("{}.txt", id)))
+        .map_err(|e| format!("Failed to read artifact {}: {}", id, e))?;
+    Ok((id, text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // **Feature: Sabi-TUI, Property: Content-Addressed Artifacts**
+    #[test]
+    fn test_store_is_content_addressed() {
+        let dir = tempfile::tempdir().unwrap();
+        let id = store_in(dir.path(), "big output").unwrap();
+        assert_eq!(id.len(), 16);
+        assert_eq!(store_in(dir.path(), "big output").unwrap(), id);
+        assert_ne!(store_in(dir.path(), "other output").unwrap(), id);
+
+        let text = std::fs::read_to_string(dir.path().join(format!("{}.txt", id))).unwrap();
+        assert_eq!(text, "big output");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    // **Feature: Sabi-TUI, Property: Artifact Id Prefixes**
+    #[test]
+    fn test_resolve_prefix() {
+        let dir = tempfile::tempdir().unwrap();
+        let id = store_in(dir.path(), "big output").unwrap();
+        assert_eq!(resolve_in(dir.path(), &id[..6]).unwrap(), id);
+        assert_eq!(resolve_in(dir.path(), &id.to_uppercase()).unwrap(), id);
+        assert!(resolve_in(dir.path(), &id[..3]).is_err());
+        assert!(resolve_in(dir.path(), "../etc/passwd").is_err());
+        assert!(resolve_in(dir.path(), "ffffffffffffffff").is_err());
+    }
+}
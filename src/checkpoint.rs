@@ -0,0 +1,229 @@
+//! Git checkpoints before agent file changes
+//!
+//! With `git_checkpoints` on, the working tree is committed to
+//! `refs/sabi/checkpoints` before each file-modifying tool runs. The user's
+//! branch, index and stash are never touched: the snapshot is built with a
+//! throwaway index and chained onto the checkpoint ref.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Ref the checkpoint commits are chained on
+pub const CHECKPOINT_REF: &str = "refs/sabi/checkpoints";
+
+/// Commit message prefix of checkpoint commits
+const MESSAGE_PREFIX: &str = "sabi checkpoint: ";
+
+/// A saved checkpoint
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    /// Abbreviated commit hash, used as the id for /rollback
+    pub id: String,
+    /// Relative age, e.g. "5 minutes ago"
+    pub age: String,
+    /// What the agent was about to do
+    pub label: String,
+}
+
+/// Run git in `cwd`, returning trimmed stdout
+fn git(cwd: &Path, args: &[&str], index: Option<&Path>) -> Result<String, String> {
+    let mut cmd = Command::new("git");
+    cmd.args(args)
+        .current_dir(cwd)
+        .env("GIT_AUTHOR_NAME", "sabi")
+        .env("GIT_AUTHOR_EMAIL", "sabi@localhost")
+        .env("GIT_COMMITTER_NAME", "sabi")
+        .env("GIT_COMMITTER_EMAIL", "sabi@localhost");
+    if let Some(index) = index {
+        cmd.env("GIT_INDEX_FILE", index);
+    }
+
+    let output = cmd.output().map_err(|e| format!("git: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git {}: {}", args[0], stderr.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Top level of the git work tree containing `cwd`, if any
+pub fn repo_root(cwd: &Path) -> Option<PathBuf> {
+    git(cwd, &["rev-parse", "--show-toplevel"], None)
+        .ok()
+        .map(PathBuf::from)
+}
+
+/// Commit the current work tree (tracked and untracked, minus ignored files)
+///
+/// Returns None when `cwd` is not inside a git repository, or nothing
+/// changed since the last checkpoint.
+pub fn create(cwd: &Path, label: &str) -> Result<Option<Checkpoint>, String> {
+    let Some(root) = repo_root(cwd) else {
+        return Ok(None);
+    };
+
+    // Build the tree in a copy of the real index so `git add` stays fast
+    // and the user's staging area is left alone
+    let git_dir = PathBuf::from(git(&root, &["rev-parse", "--absolute-git-dir"], None)?);
+    let index_name = format!("sabi-checkpoint-{}.index", std::process::id());
+    let index = std::env::temp_dir().join(index_name);
+    let _ = std::fs::copy(git_dir.join("index"), &index);
+
+    let tree = git(&root, &["add", "-A"], Some(&index))
+        .and_then(|_| git(&root, &["write-tree"], Some(&index)));
+    let _ = std::fs::remove_file(&index);
+    let tree = tree?;
+
+    let parent = git(
+        &root,
+        &["rev-parse", "-q", "--verify", CHECKPOINT_REF],
+        None,
+    )
+    .ok();
+    if let Some(ref parent) = parent {
+        let parent_tree = git(&root, &["rev-parse", &format!("{}^{{tree}}", parent)], None);
+        if parent_tree.as_ref() == Ok(&tree) {
+            return Ok(None);
+        }
+    }
+
+    let message = format!("{}{}", MESSAGE_PREFIX, label);
+    let mut args = vec!["commit-tree", tree.as_str(), "-m", message.as_str()];
+    if let Some(ref parent) = parent {
+        args.extend(["-p", parent.as_str()]);
+    }
+    let commit = git(&root, &args, None)?;
+    git(&root, &["update-ref", CHECKPOINT_REF, &commit], None)?;
+
+    Ok(Some(Checkpoint {
+        id: commit.chars().take(10).collect(),
+        age: "just now".to_string(),
+        label: label.to_string(),
+    }))
+}
+
+/// Most recent checkpoints first
+pub fn list(cwd: &Path, limit: usize) -> Result<Vec<Checkpoint>, String> {
+    let root = repo_root(cwd).ok_or("Not inside a git repository")?;
+    if git(
+        &root,
+        &["rev-parse", "-q", "--verify", CHECKPOINT_REF],
+        None,
+    )
+    .is_err()
+    {
+        return Ok(Vec::new());
+    }
+
+    let count = format!("-n{}", limit);
+    let log = git(
+        &root,
+        &["log", &count, "--format=%h%x09%cr%x09%s", CHECKPOINT_REF],
+        None,
+    )?;
+    Ok(log.lines().filter_map(parse_log_line).collect())
+}
+
+/// Parse a `hash<TAB>age<TAB>subject` line from `git log`
+fn parse_log_line(line: &str) -> Option<Checkpoint> {
+    let mut parts = line.splitn(3, '\t');
+    let id = parts.next()?.to_string();
+    let age = parts.next()?.to_string();
+    let subject = parts.next()?;
+    Some(Checkpoint {
+        id,
+        age,
+        label: subject
+            .strip_prefix(MESSAGE_PREFIX)
+            .unwrap_or(subject)
+            .to_string(),
+    })
+}
+
+/// Restore the work tree files saved in checkpoint `id`
+///
+/// The current state is checkpointed first so a rollback can itself be
+/// rolled back. Files created after the checkpoint are left in place.
+pub fn rollback(cwd: &Path, id: &str) -> Result<(), String> {
+    let root = repo_root(cwd).ok_or("Not inside a git repository")?;
+    let commit = git(
+        &root,
+        &["rev-parse", "-q", "--verify", &format!("{}^{{commit}}", id)],
+        None,
+    )
+    .map_err(|_| format!("Unknown checkpoint: {}", id))?;
+    if git(
+        &root,
+        &["merge-base", "--is-ancestor", &commit, CHECKPOINT_REF],
+        None,
+    )
+    .is_err()
+    {
+        return Err(format!("{} is not a sabi checkpoint", id));
+    }
+
+    create(&root, &format!("before rollback to {}", id))?;
+    git(
+        &root,
+        &["restore", "--source", &commit, "--worktree", "--", ":/"],
+        None,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_log_line() {
+        let cp =
+            parse_log_line("abc1234\t5 minutes ago\tsabi checkpoint: write_file src/a.rs").unwrap();
+        assert_eq!(cp.id, "abc1234");
+        assert_eq!(cp.age, "5 minutes ago");
+        assert_eq!(cp.label, "write_file src/a.rs");
+        assert!(parse_log_line("garbage").is_none());
+    }
+
+    // **Feature: Sabi-TUI, Property: Checkpoint Round Trip**
+    #[test]
+    fn test_checkpoint_and_rollback() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().to_path_buf();
+        if git(&dir, &["init", "-q"], None).is_err() {
+            return; // git not installed
+        }
+
+        let file = dir.join("notes.txt");
+        std::fs::write(&file, "v1").unwrap();
+        let first = create(&dir, "first").unwrap().unwrap();
+        std::fs::write(&file, "v2").unwrap();
+        create(&dir, "second").unwrap().unwrap();
+        assert_eq!(create(&dir, "unchanged"), Ok(None));
+
+        let listed = list(&dir, 10).unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].label, "second");
+
+        std::fs::write(&file, "v3").unwrap();
+        rollback(&dir, &first.id).unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "v1");
+        // The pre-rollback state was saved too, and nothing was staged
+        assert_eq!(list(&dir, 10).unwrap().len(), 3);
+        assert!(
+            git(&dir, &["diff", "--cached", "--name-only"], None)
+                .unwrap_or_default()
+                .is_empty()
+        );
+
+        assert!(rollback(&dir, "deadbeef").is_err());
+    }
+
+    #[test]
+    fn test_outside_repo() {
+        let dir = tempfile::tempdir().unwrap();
+        if repo_root(dir.path()).is_none() {
+            assert_eq!(create(dir.path(), "x"), Ok(None));
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Copying to the system clipboard
+//!
+//! Text goes to the OS clipboard through arboard. Over SSH, or where there
+//! is no clipboard to talk to (a headless Linux box), it is sent to the
+//! terminal as an OSC 52 escape sequence instead, which most terminal
+//! emulators (and tmux with `set-clipboard on`) put on the clipboard of
+//! the machine the user is sitting at.
+
+use std::io::Write;
+
+use crate::message::base64_encode;
+
+/// Terminals drop OSC 52 payloads much larger than this
+const MAX_OSC52_BYTES: usize = 100_000;
+
+/// How the text reached the clipboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    System,
+    Osc52,
+}
+
+/// Put `text` on the clipboard
+pub fn copy(text: &str) -> Result<Method, String> {
+    if is_remote() {
+        return write_osc52(text).map(|()| Method::Osc52);
+    }
+    match arboard::Clipboard::new().and_then(|mut c| c.set_text(text.to_owned())) {
+        Ok(()) => Ok(Method::System),
+        Err(e) => write_osc52(text)
+            .map(|()| Method::Osc52)
+            .map_err(|osc| format!("{}; {}", e, osc)),
+    }
+}
+
+/// Running in an SSH session, where arboard would reach the wrong machine
+fn is_remote() -> bool {
+    std::env::var_os("SSH_TTY").is_some() || std::env::var_os("SSH_CONNECTION").is_some()
+}
+
+/// Wrap an escape sequence so tmux passes it on to the outer terminal
+pub fn tmux_passthrough(sequence: String, in_tmux: bool) -> String {
+    if in_tmux {
+        format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+    } else {
+        sequence
+    }
+}
+
+/// OSC 52 "set clipboard" sequence, wrapped for tmux passthrough if needed
+fn osc52_sequence(text: &str, in_tmux: bool) -> String {
+    let sequence = format!("\x1b]52;c;{}\x07", base64_encode(text.as_bytes()));
+    tmux_passthrough(sequence, in_tmux)
+}
+
+fn write_osc52(text: &str) -> Result<(), String> {
+    if text.len() > MAX_OSC52_BYTES {
+        return Err(format!(
+            "too large for OSC 52 ({} bytes, max {})",
+            text.len(),
+            MAX_OSC52_BYTES
+        ));
+    }
+    let sequence = osc52_sequence(text, std::env::var_os("TMUX").is_some());
+    let mut stdout = std::io::stdout();
+    stdout
+        .write_all(sequence.as_bytes())
+        .and_then(|()| stdout.flush())
+        .map_err(|e| format!("OSC 52 write failed: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // **Feature: Sabi-TUI, Property: OSC 52 Clipboard Fallback**
+    #[test]
+    fn test_osc52_sequence() {
+        assert_eq!(osc52_sequence("hi", false), "\x1b]52;c;aGk=\x07");
+        assert_eq!(
+            osc52_sequence("hi", true),
+            "\x1bPtmux;\x1b\x1b]52;c;aGk=\x07\x1b\\"
+        );
+    }
+
+    #[test]
+    fn test_oversized_text_is_refused() {
+        let text = "x".repeat(MAX_OSC52_BYTES + 1);
+        assert!(write_osc52(&text).unwrap_err().contains("too large"));
+    }
+}
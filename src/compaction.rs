@@ -0,0 +1,119 @@
+//! Keeping saved sessions small
+//!
+//! Tool results older than the last few messages that exceed
+//! `session_output_limit` are replaced by a short excerpt and the id of an
+//! artifact holding the complete output. Sessions stay quick to load, and a
+//! resumed session doesn't send old bulky output to the model again.
+
+use crate::message::Message;
+
+/// The most recent messages, which are never compacted
+pub const KEEP_RECENT: usize = 6;
+
+/// Lines kept from the start and the end of a compacted output
+const EXCERPT_LINES: usize = 5;
+
+/// Compact bulky tool results outside the last `KEEP_RECENT` messages
+///
+/// `store` saves a complete output and returns its artifact id; outputs it
+/// fails to store are left alone. A `limit` of 0 turns compaction off.
+/// Returns how many messages were compacted.
+pub fn compact(
+    messages: &mut [Message],
+    limit: usize,
+    mut store: impl FnMut(&str) -> std::io::Result<String>,
+) -> usize {
+    if limit == 0 {
+        return 0;
+    }
+    let old = messages.len().saturating_sub(KEEP_RECENT);
+    let mut compacted = 0;
+    for msg in messages[..old].iter_mut() {
+        if !msg.is_tool_result() || msg.content.len() <= limit {
+            continue;
+        }
+        let Some((header, output)) = msg.content.split_once("Output:\n") else {
+            continue;
+        };
+        let Ok(id) = store(output) else {
+            continue;
+        };
+        msg.content = format!("{}Output:\n{}", header, excerpt(output, &id));
+        if !msg.artifacts.contains(&id) {
+            msg.artifacts.push(id);
+        }
+        compacted += 1;
+    }
+    compacted
+}
+
+/// First and last lines of `output` around a note naming artifact `id`
+fn excerpt(output: &str, id: &str) -> String {
+    let lines: Vec<&str> = output.lines().collect();
+    // Few but long lines: keep none of them
+    let keep = if lines.len() > 2 * EXCERPT_LINES {
+        EXCERPT_LINES
+    } else {
+        0
+    };
+    let note = format!(
+        "[… {} of {} lines omitted; full output ({} bytes): artifact {}]",
+        lines.len() - 2 * keep,
+        lines.len(),
+        output.len(),
+        id
+    );
+    let mut kept = lines[..keep].to_vec();
+    kept.push(&note);
+    kept.extend_from_slice(&lines[lines.len() - keep..]);
+    kept.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool_result(lines: usize) -> Message {
+        let output: Vec<String> = (1..=lines).map(|i| format!("line {}", i)).collect();
+        Message::tool(format!(
+            "Tool: run_cmd seq\nExit code: 0\nOutput:\n{}",
+            output.join("\n")
+        ))
+    }
+
+    // **Feature: Sabi-TUI, Property: Session Compaction**
+    #[test]
+    fn test_compacts_old_bulky_outputs() {
+        let mut messages = vec![tool_result(100), Message::user("short"), tool_result(3)];
+        messages.extend((0..KEEP_RECENT).map(|_| tool_result(100)));
+        let mut stored = Vec::new();
+
+        let count = compact(&mut messages, 200, |output| {
+            stored.push(output.to_string());
+            Ok("0123456789abcdef".to_string())
+        });
+        assert_eq!(count, 1);
+        assert_eq!(messages[0].artifacts, ["0123456789abcdef"]);
+        assert_eq!(stored.len(), 1);
+        assert!(stored[0].starts_with("line 1\n") && stored[0].ends_with("line 100"));
+
+        let content = &messages[0].content;
+        assert!(content.starts_with("Tool: run_cmd seq\nExit code: 0\nOutput:\nline 1\n"));
+        assert!(content.contains(
+            "[… 90 of 100 lines omitted; full output (791 bytes): artifact 0123456789abcdef]"
+        ));
+        assert!(content.ends_with("line 96\nline 97\nline 98\nline 99\nline 100"));
+        assert_eq!(messages[2].content, tool_result(3).content);
+        // Recent messages stay complete
+        assert_eq!(messages.last().unwrap().content, tool_result(100).content);
+    }
+
+    #[test]
+    fn test_compaction_off_or_failing_store() {
+        let mut messages = vec![tool_result(100); KEEP_RECENT + 1];
+        assert_eq!(compact(&mut messages, 0, |_| Ok("id".to_string())), 0);
+        let failing = |_: &str| Err(std::io::Error::other("disk full"));
+        assert_eq!(compact(&mut messages, 10, failing), 0);
+        assert_eq!(messages[0].content, tool_result(100).content);
+    }
+}
@@ -2,10 +2,28 @@
 //!
 //! Handles loading configuration from files and environment variables.
 
-use serde::Deserialize;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::Duration;
 use thiserror::Error;
 
+use crate::container::ContainerSpec;
+use crate::executor::has_env_prefix;
+use crate::images::ImageProtocol;
+use crate::keychain;
+use crate::logging::LogLevel;
+use crate::migrate;
+use crate::notify::NotifyMode;
+use crate::session_sync::SessionSync;
+use crate::settings;
+use crate::template::SessionTemplate;
+use crate::theme::{ColorSupport, GlyphSupport, ThemeMode};
+use crate::tool_call::{self, ToolCall};
+use crate::workspace::Workspace;
+
 /// Configuration errors
 #[derive(Debug, Error)]
 pub enum ConfigError {
@@ -31,7 +49,7 @@ pub enum ConfigError {
 }
 
 /// AI Provider type
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum Provider {
     #[default]
@@ -39,9 +57,626 @@ pub enum Provider {
     OpenAI,
 }
 
+impl Provider {
+    /// Name used in config.toml
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Gemini => "gemini",
+            Self::OpenAI => "openai",
+        }
+    }
+}
+
+/// How the dangerous command detector treats a match
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Highlight the command and require 2-step confirmation
+    #[default]
+    Warn,
+    /// Refuse to run the command
+    Block,
+}
+
+impl Severity {
+    /// Name used in config.toml
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Warn => "warn",
+            Self::Block => "block",
+        }
+    }
+
+    /// `warn` or `block`
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "warn" => Some(Self::Warn),
+            "block" => Some(Self::Block),
+            _ => None,
+        }
+    }
+}
+
+/// Which tool calls run without stopping at the review step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalPolicy {
+    /// Review every tool call
+    #[default]
+    AlwaysAsk,
+    /// Auto-run read-only tools and commands passing `allowed_commands`
+    AutoSafe,
+    /// Auto-run everything that is not flagged as dangerous
+    AutoAllExceptDangerous,
+}
+
+impl ApprovalPolicy {
+    /// Whether `tool` may skip review
+    ///
+    /// `allowlisted` means the command passed a non-empty `allowed_commands`;
+    /// `dangerous` covers dangerous patterns, paths, and protected writes.
+    /// An allowlisted command with `NAME=value` prefixes still needs review,
+    /// since the environment can make the program run something else.
+    pub fn auto_approves(self, tool: &ToolCall, allowlisted: bool, dangerous: bool) -> bool {
+        if dangerous || !tool.is_allowed_tool() {
+            return false;
+        }
+        match self {
+            ApprovalPolicy::AlwaysAsk => false,
+            ApprovalPolicy::AutoSafe => {
+                tool.is_read_only()
+                    || (tool.is_run_cmd() && allowlisted && !has_env_prefix(&tool.command))
+            }
+            ApprovalPolicy::AutoAllExceptDangerous => true,
+        }
+    }
+
+    /// Name as written in config
+    pub fn name(self) -> &'static str {
+        match self {
+            ApprovalPolicy::AlwaysAsk => "always_ask",
+            ApprovalPolicy::AutoSafe => "auto_safe",
+            ApprovalPolicy::AutoAllExceptDangerous => "auto_all_except_dangerous",
+        }
+    }
+}
+
+/// A dangerous command regex with its severity and explanation
+///
+/// Plain strings in config are accepted as warn-level patterns.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(from = "PatternSpec")]
+pub struct DangerousPattern {
+    pub pattern: String,
+    pub severity: Severity,
+    /// Shown in the review pane, e.g. "recursive delete under /"
+    pub reason: String,
+}
+
+impl DangerousPattern {
+    pub fn new(pattern: &str, severity: Severity, reason: &str) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            severity,
+            reason: reason.to_string(),
+        }
+    }
+}
+
+/// Built-in dangerous pattern categories: (name, enabled by default, description)
+pub const DANGER_CATEGORIES: &[(&str, bool, &str)] = &[
+    (
+        "destructive-fs",
+        true,
+        "Recursive or irrecoverable file deletion",
+    ),
+    (
+        "disk-write",
+        true,
+        "Formatting, partitioning, or writing raw disks",
+    ),
+    (
+        "privilege-escalation",
+        false,
+        "sudo, su, setuid, world-writable permissions",
+    ),
+    (
+        "network-exfil",
+        true,
+        "Uploading files, piping downloads into a shell, reverse shells",
+    ),
+    (
+        "package-remove",
+        true,
+        "Uninstalling system or global packages",
+    ),
+    (
+        "windows",
+        true,
+        "Formatting drives, deleting drive roots, registry and shadow copies, iwr | iex",
+    ),
+];
+
+/// Patterns of a built-in category (empty for unknown names)
+pub fn category_patterns(category: &str) -> Vec<DangerousPattern> {
+    use Severity::{Block, Warn};
+    let rules: &[(&str, Severity, &str)] = match category {
+        "destructive-fs" => &[
+            (
+                r"rm\s+-rf\s+/\*?\s*$",
+                Block,
+                "deletes the entire root filesystem",
+            ),
+            (r"rm\s+-rf\s+/", Warn, "recursive delete under /"),
+            (
+                r"rm\s+-[a-zA-Z]*r[a-zA-Z]*\s+(~|\$HOME)",
+                Warn,
+                "recursive delete in the home directory",
+            ),
+            (r"\bshred\s", Warn, "irrecoverably overwrites files"),
+        ],
+        "disk-write" => &[
+            (r"mkfs", Warn, "creates a filesystem, erasing the device"),
+            (r"dd\s+if=", Warn, "raw block copy with dd"),
+            (
+                r">\s*/dev/(sd|hd|nvme|disk)",
+                Warn,
+                "overwrites a raw disk device",
+            ),
+            (
+                r"\b(fdisk|parted|wipefs)\s",
+                Warn,
+                "repartitions or wipes a disk",
+            ),
+        ],
+        "privilege-escalation" => &[
+            (
+                r"(^|[;&|(]\s*)((env|time|nohup|exec|xargs|nice|timeout|command)(\s+-\S*|\s+\d+)*\s+|\w+=\S*\s+)*sudo\s",
+                Warn,
+                "runs as root with sudo",
+            ),
+            (r"(^|[;&|(]\s*)su(\s|$)", Warn, "switches user with su"),
+            (
+                r"chmod\s+(-R\s+)?0?777\b",
+                Warn,
+                "makes files world-writable",
+            ),
+            (
+                r"chmod\s+([ugo]*\+s|[2467][0-7]{3}\b)",
+                Warn,
+                "sets setuid/setgid bits",
+            ),
+            (r"/etc/sudoers", Warn, "edits sudo configuration"),
+        ],
+        "network-exfil" => &[
+            (
+                r"(curl|wget)\s[^|]*\|\s*(sudo\s+)?(ba|z|da)?sh\b",
+                Warn,
+                "pipes a downloaded script into a shell",
+            ),
+            (
+                r"curl\s.*(-d\s*@|--data(-binary)?\s*@|-F\s*\S+=@|-T\s|--upload-file)",
+                Warn,
+                "uploads a local file",
+            ),
+            (r"wget\s.*--post-file", Warn, "uploads a local file"),
+            (
+                r"\b(nc|ncat|netcat)\b.*\s-[ec]\s",
+                Warn,
+                "opens a remote shell with netcat",
+            ),
+            (r"/dev/tcp/", Warn, "opens a raw network socket"),
+        ],
+        "package-remove" => &[
+            (
+                r"\b(apt|apt-get|yum|dnf|zypper)\s+(-y\s+)?(remove|purge|autoremove|erase)\b",
+                Warn,
+                "removes system packages",
+            ),
+            (r"\bpacman\s+-R", Warn, "removes system packages"),
+            (
+                r"\bbrew\s+(uninstall|remove|rm)\b",
+                Warn,
+                "removes Homebrew packages",
+            ),
+            (r"\bpip3?\s+uninstall\b", Warn, "uninstalls Python packages"),
+            (
+                r"\bnpm\s+(uninstall|remove|rm)\s+(-g|--global)\b",
+                Warn,
+                "removes global npm packages",
+            ),
+        ],
+        "windows" => &[
+            (r"(?i)\bformat(\.com)?\s+[a-z]:", Warn, "formats a drive"),
+            (
+                r#"(?i)\b(Remove-Item|ri|rm|rmdir|rd|del|erase)\b.*\s['"]?[a-z]:\\\*?['"]?(\s|$)"#,
+                Block,
+                "deletes a whole drive",
+            ),
+            (
+                r"(?i)\b(Remove-Item|ri|rmdir|rd)\b.*\s(-Recurse|/s)\b",
+                Warn,
+                "recursive delete",
+            ),
+            (
+                r"(?i)\b(diskpart|Clear-Disk|Format-Volume|Initialize-Disk|Remove-Partition)\b",
+                Warn,
+                "repartitions or wipes a disk",
+            ),
+            (
+                r"(?i)\breg(\.exe)?\s+delete\b|Remove-Item(Property)?\b.*\bHK(LM|CU|CR|U|CC):",
+                Warn,
+                "deletes registry keys",
+            ),
+            (
+                r"(?i)\b(iwr|irm|Invoke-WebRequest|Invoke-RestMethod|curl|wget)\b[^|]*\|\s*(iex|Invoke-Expression)\b",
+                Warn,
+                "pipes a downloaded script into PowerShell",
+            ),
+            (
+                r"(?i)\bvssadmin(\.exe)?\s+delete\s+shadows\b|\bwbadmin(\.exe)?\s+delete\b",
+                Warn,
+                "deletes backups or shadow copies",
+            ),
+            (
+                r"(?i)\bSet-ExecutionPolicy\s+(-ExecutionPolicy\s+)?(Unrestricted|Bypass)\b",
+                Warn,
+                "disables the PowerShell execution policy",
+            ),
+        ],
+        _ => &[],
+    };
+    rules
+        .iter()
+        .map(|(pattern, severity, reason)| DangerousPattern::new(pattern, *severity, reason))
+        .collect()
+}
+
+/// Config forms of a dangerous pattern
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PatternSpec {
+    Regex(String),
+    Rule {
+        pattern: String,
+        #[serde(default)]
+        severity: Severity,
+        #[serde(default)]
+        reason: String,
+    },
+}
+
+impl From<PatternSpec> for DangerousPattern {
+    fn from(spec: PatternSpec) -> Self {
+        match spec {
+            PatternSpec::Regex(pattern) => Self {
+                pattern,
+                severity: Severity::Warn,
+                reason: String::new(),
+            },
+            PatternSpec::Rule {
+                pattern,
+                severity,
+                reason,
+            } => Self {
+                pattern,
+                severity,
+                reason,
+            },
+        }
+    }
+}
+
+/// A regex for commands that need a terminal, with the hint shown when one is refused
+///
+/// Plain strings in config are accepted as patterns without a hint.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(from = "InteractiveSpec")]
+pub struct InteractivePattern {
+    pub pattern: String,
+    /// e.g. "Use the man_page tool instead"
+    pub suggestion: String,
+}
+
+impl InteractivePattern {
+    pub fn new(pattern: &str, suggestion: &str) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            suggestion: suggestion.to_string(),
+        }
+    }
+}
+
+/// Built-in interactive command categories: (name, description)
+pub const INTERACTIVE_CATEGORIES: &[(&str, &str)] = &[
+    ("editors", "nano, vim, emacs and other terminal editors"),
+    ("remote", "ssh, telnet, ftp, sftp sessions"),
+    ("pagers", "less, more, man, top, htop"),
+    ("databases", "mysql, psql, sqlite3, mongo shells"),
+    ("repls", "python, node, irb, ghci without arguments"),
+    ("containers", "docker/podman with -it"),
+    ("windows", "bare cmd/PowerShell, Read-Host, pause, diskpart"),
+];
+
+/// Patterns of a built-in interactive category (empty for unknown names)
+pub fn interactive_category_patterns(category: &str) -> Vec<InteractivePattern> {
+    let rules: &[(&str, &str)] = match category {
+        "editors" => &[(
+            r"^(nano|vim?|emacs|pico|ne|joe)\b",
+            "Use /edit <path> or the open_editor tool instead",
+        )],
+        "remote" => &[(
+            r"^(ssh|telnet|ftp|sftp)\b",
+            "Interactive sessions not supported",
+        )],
+        "pagers" => &[
+            (r"^(less|more)\b", "Use cat or read_file tool instead"),
+            (r"^man\b", "Use the man_page tool instead"),
+            (r"^(htop|top)\b", "Use 'ps aux' or 'ps aux | head' instead"),
+        ],
+        "databases" => &[(
+            r"^(mysql|psql|sqlite3|mongo)\b",
+            "Pass the query on the command line (e.g. psql -c, sqlite3 db 'SELECT ...')",
+        )],
+        "repls" => &[(r"^(python|node|irb|ghci)$", "")],
+        "containers" => &[(
+            r"\b(docker|podman)\s+.*\s-it\b",
+            "Drop -it to run the container non-interactively",
+        )],
+        "windows" => &[
+            (
+                r"(?i)^(cmd|powershell|pwsh)(\.exe)?\s*$",
+                "Run the command directly instead of opening a shell",
+            ),
+            (
+                r"(?i)\b(Read-Host|Get-Credential)\b",
+                "Pass values as arguments instead of prompting",
+            ),
+            (
+                r"(?i)(^|[;&|]\s*)(pause|choice(\.exe)?)\b",
+                "Remove the key-press prompt",
+            ),
+            (
+                r"(?i)^(Enter-PSSession|plink)\b",
+                "Interactive sessions not supported",
+            ),
+            (
+                r"(?i)^(diskpart|netsh|nslookup)(\.exe)?\s*$",
+                "Pass a script or arguments instead (e.g. diskpart /s script.txt)",
+            ),
+        ],
+        _ => &[],
+    };
+    rules
+        .iter()
+        .map(|(pattern, suggestion)| InteractivePattern::new(pattern, suggestion))
+        .collect()
+}
+
+/// Config forms of an interactive pattern
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum InteractiveSpec {
+    Regex(String),
+    Rule {
+        pattern: String,
+        #[serde(default)]
+        suggestion: String,
+    },
+}
+
+impl From<InteractiveSpec> for InteractivePattern {
+    fn from(spec: InteractiveSpec) -> Self {
+        match spec {
+            InteractiveSpec::Regex(pattern) => Self {
+                pattern,
+                suggestion: String::new(),
+            },
+            InteractiveSpec::Rule {
+                pattern,
+                suggestion,
+            } => Self {
+                pattern,
+                suggestion,
+            },
+        }
+    }
+}
+
+/// I/O scheduling class for spawned commands (Linux only)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum IoPriority {
+    /// Leave I/O priority alone
+    #[default]
+    Normal,
+    /// Lowest best-effort level (`ionice -c2 -n7`)
+    Low,
+    /// Only get disk time when nothing else wants it (`ionice -c3`)
+    Idle,
+}
+
+/// Which part of over-long output is kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TruncationStrategy {
+    /// The first lines
+    Head,
+    /// The last lines, after a gap marker
+    Tail,
+    /// The first and last lines around a gap marker (errors are usually at the end)
+    #[default]
+    HeadTail,
+}
+
+/// What startup does with the last saved session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ResumeMode {
+    /// Start a new session
+    #[default]
+    New,
+    /// Ask "Resume last session? (y/n)"
+    Ask,
+    /// Continue the last session
+    Last,
+    /// Pick one of the most recent sessions
+    Pick,
+}
+
+/// Per-process resource limits (0 = unlimited)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceLimits {
+    /// RLIMIT_CPU in seconds
+    pub cpu_seconds: u64,
+    /// RLIMIT_AS in bytes
+    pub memory_bytes: u64,
+    /// RLIMIT_FSIZE in bytes
+    pub file_size_bytes: u64,
+    /// Niceness added to the command (0 = unchanged, max 19)
+    pub nice: i32,
+    /// I/O scheduling class
+    pub io_priority: IoPriority,
+}
+
+impl ResourceLimits {
+    /// Whether any limit is set
+    pub fn is_unlimited(&self) -> bool {
+        self.cpu_seconds == 0 && self.memory_bytes == 0 && self.file_size_bytes == 0
+    }
+
+    /// Whether commands run at normal CPU and I/O priority
+    pub fn is_normal_priority(&self) -> bool {
+        self.nice == 0 && self.io_priority == IoPriority::Normal
+    }
+}
+
+/// Which environment variables spawned commands receive
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnvPolicy {
+    /// Start from an empty environment instead of inheriting everything
+    pub scrub: bool,
+    /// Variables passed through when scrubbing (`PREFIX_*` matches a prefix)
+    pub allow: Vec<String>,
+    /// Variables always set for spawned commands
+    pub extra: BTreeMap<String, String>,
+}
+
+impl EnvPolicy {
+    /// Whether `key` passes the allowlist
+    pub fn allows(&self, key: &str) -> bool {
+        self.allow
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => key.starts_with(prefix),
+                None => key == pattern,
+            })
+    }
+}
+
+/// Program and flag used to run shell commands
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShellInvocation {
+    /// Shell program (e.g. "sh", "/bin/zsh")
+    pub program: String,
+    /// Flag preceding the command string (e.g. "-c", "-lc")
+    pub flag: String,
+}
+
+impl Default for ShellInvocation {
+    fn default() -> Self {
+        if cfg!(target_os = "windows") {
+            Self {
+                program: "cmd".to_string(),
+                flag: "/C".to_string(),
+            }
+        } else {
+            Self {
+                program: "sh".to_string(),
+                flag: "-c".to_string(),
+            }
+        }
+    }
+}
+
+impl ShellInvocation {
+    /// Invocation of `program` with the flag its syntax expects
+    ///
+    /// PowerShell takes `-Command` and cmd takes `/C`; other shells get
+    /// `-c`, or `-lc` as a login shell.
+    pub fn for_program(program: &str, login: bool) -> Self {
+        let flag = match shell_name(program).as_str() {
+            "powershell" | "pwsh" => "-Command",
+            "cmd" => "/C",
+            _ if login => "-lc",
+            _ => "-c",
+        };
+        Self {
+            program: program.to_string(),
+            flag: flag.to_string(),
+        }
+    }
+
+    /// Whether commands run in Windows PowerShell or PowerShell 7
+    pub fn is_powershell(&self) -> bool {
+        matches!(shell_name(&self.program).as_str(), "powershell" | "pwsh")
+    }
+
+    /// Short label for UI, e.g. "zsh -lc"
+    pub fn label(&self) -> String {
+        let name = std::path::Path::new(&self.program)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.program.clone());
+        format!("{} {}", name, self.flag)
+    }
+}
+
+/// Lowercase program name without directory or `.exe`
+fn shell_name(program: &str) -> String {
+    let name = program.rsplit(['/', '\\']).next().unwrap_or(program);
+    let name = name.to_ascii_lowercase();
+    name.strip_suffix(".exe").unwrap_or(&name).to_string()
+}
+
+/// Older environment variable names that are still read: (variable, key)
+const ENV_ALIASES: &[(&str, &str)] = &[("SABI_MAX_HISTORY", "max_history_messages")];
+
+/// Environment variables that override config key `key`: `SABI_<KEY>`,
+/// then any older name
+pub fn env_vars(key: &str) -> Vec<String> {
+    let mut vars = vec![format!("SABI_{}", key.to_uppercase())];
+    vars.extend(
+        ENV_ALIASES
+            .iter()
+            .filter(|(_, k)| *k == key)
+            .map(|(var, _)| var.to_string()),
+    );
+    vars
+}
+
+/// `data_dir` of the config in use, set at startup by [`set_data_dir`]
+static DATA_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Directory for sessions and artifacts: `data_dir` (or `SABI_DATA_DIR`)
+/// once the config is loaded, ~/.sabi otherwise
+pub fn data_dir() -> Option<PathBuf> {
+    let dir = DATA_DIR.read().unwrap_or_else(|e| e.into_inner()).clone();
+    dir.or_else(|| dirs::home_dir().map(|home| home.join(".sabi")))
+}
+
+/// Keep sessions and artifacts where `config` says from now on
+pub fn set_data_dir(config: &Config) {
+    *DATA_DIR.write().unwrap_or_else(|e| e.into_inner()) = config.data_path();
+}
+
 /// Application configuration
-#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 pub struct Config {
+    /// Schema version of the file, upgraded on load by `migrate`
+    #[serde(default = "default_version")]
+    pub version: u32,
+
     /// AI provider (gemini or openai)
     #[serde(default)]
     pub provider: Provider,
@@ -58,6 +693,11 @@ pub struct Config {
     #[serde(default = "default_model")]
     pub model: String,
 
+    /// Context window of the model in tokens, for the status bar gauge
+    /// (known from the model name when unset)
+    #[serde(default)]
+    pub context_window: Option<usize>,
+
     /// Maximum messages to keep in history
     #[serde(default = "default_max_history")]
     pub max_history_messages: usize,
@@ -70,19 +710,257 @@ pub struct Config {
     #[serde(default = "default_max_output_lines")]
     pub max_output_lines: usize,
 
-    /// Dangerous command patterns
+    /// Tool calls in one turn before the AI is asked to sum up and wait
+    /// for the user (0 = unlimited)
+    #[serde(default = "default_max_tool_iterations")]
+    pub max_tool_iterations: usize,
+
+    /// Which part of over-long output the AI sees: head, tail or head_tail
+    #[serde(default)]
+    pub truncate_strategy: TruncationStrategy,
+
+    /// `truncate_strategy` per tool, e.g. `read_file = "head"`
+    #[serde(default)]
+    pub truncate_overrides: BTreeMap<String, TruncationStrategy>,
+
+    /// Stop capturing a command's output after this many seconds (0 = never)
+    ///
+    /// The command keeps running and its full output is still spooled.
+    #[serde(default)]
+    pub output_capture_secs: u64,
+
+    /// Extra dangerous command patterns (regex strings or `{pattern, severity, reason}` tables)
     #[serde(default = "default_dangerous_patterns")]
-    pub dangerous_patterns: Vec<String>,
+    pub dangerous_patterns: Vec<DangerousPattern>,
+
+    /// Enable or disable built-in pattern categories, e.g. `network-exfil = false`
+    #[serde(default)]
+    pub danger_categories: BTreeMap<String, bool>,
+
+    /// Tool writes must land under these directories (empty = anywhere; `.` = session cwd)
+    #[serde(default)]
+    pub writable_roots: Vec<String>,
+
+    /// Writes under these paths need confirmation (`~/.*` = home dotfiles)
+    #[serde(default = "default_protected_paths")]
+    pub protected_paths: Vec<String>,
+
+    /// `warn` asks for 2-step confirmation on guarded writes, `block` refuses them
+    #[serde(default)]
+    pub protected_path_severity: Severity,
+
+    /// Programs run_cmd may invoke (empty = no allowlist)
+    #[serde(default)]
+    pub allowed_commands: Vec<String>,
+
+    /// Refuse every run_cmd program; set when a project file narrows
+    /// `allowed_commands` to nothing
+    #[serde(skip)]
+    pub deny_all_commands: bool,
+
+    /// Extra interactive command patterns (regex strings or `{pattern, suggestion}` tables)
+    #[serde(default)]
+    pub interactive_patterns: Vec<InteractivePattern>,
+
+    /// Enable or disable built-in interactive categories, e.g. `databases = false`
+    #[serde(default)]
+    pub interactive_categories: BTreeMap<String, bool>,
+
+    /// Commands matching these regexes are never treated as interactive
+    #[serde(default)]
+    pub interactive_allow: Vec<String>,
+
+    /// Which tool calls skip review: always_ask, auto_safe, auto_all_except_dangerous
+    #[serde(default)]
+    pub approval_policy: ApprovalPolicy,
+
+    /// Commit the work tree to `refs/sabi/checkpoints` before file-modifying tools
+    #[serde(default)]
+    pub git_checkpoints: bool,
 
     /// Safe mode - show commands but don't execute
     #[serde(default)]
     pub safe_mode: bool,
+
+    /// CPU time limit for spawned commands in seconds (0 = unlimited, Unix only)
+    #[serde(default)]
+    pub max_cpu_seconds: u64,
+
+    /// Address space limit for spawned commands in MB (0 = unlimited, Unix only)
+    #[serde(default)]
+    pub max_memory_mb: u64,
+
+    /// Largest file a spawned command may write in MB (0 = unlimited, Unix only)
+    #[serde(default)]
+    pub max_file_size_mb: u64,
+
+    /// Niceness added to spawned commands (0 = unchanged, 19 = lowest CPU priority, Unix only)
+    #[serde(default)]
+    pub command_nice: i32,
+
+    /// I/O priority for spawned commands: normal, low, idle (Linux only)
+    #[serde(default)]
+    pub command_io_priority: IoPriority,
+
+    /// Run commands with only `env_allowlist` variables from the parent environment
+    #[serde(default)]
+    pub scrub_env: bool,
+
+    /// Variables passed through when `scrub_env` is on (`LC_*` style prefixes allowed)
+    #[serde(default = "default_env_allowlist")]
+    pub env_allowlist: Vec<String>,
+
+    /// Shell used for run_cmd (empty = `sh`, or `$SHELL` with `login_shell`)
+    #[serde(default)]
+    pub shell: String,
+
+    /// Run commands as `<shell> -lc` so profile PATH and functions apply
+    #[serde(default)]
+    pub login_shell: bool,
+
+    /// Keep one Python interpreter for run_python so variables persist between calls
+    #[serde(default)]
+    pub python_session: bool,
+
+    /// Run shell commands in a pseudo-terminal (for tools that need a TTY)
+    #[serde(default)]
+    pub use_pty: bool,
+
+    /// Capture the mouse for wheel scrolling, clicks and chat selection
+    #[serde(default = "default_mouse")]
+    pub mouse: bool,
+
+    /// Redraw interval in ms while something animates or input just arrived
+    #[serde(default = "default_tick_rate_ms")]
+    pub tick_rate_ms: u64,
+
+    /// Redraw interval in ms when idle, to save CPU
+    #[serde(default = "default_idle_tick_rate_ms")]
+    pub idle_tick_rate_ms: u64,
+
+    /// Color palette: "auto" (detect the terminal background; `$NO_COLOR`
+    /// means "mono"), "dark", "light", "colorblind" or "mono"
+    #[serde(default)]
+    pub theme: ThemeMode,
+
+    /// Colors the terminal shows: "auto" (detect from `$COLORTERM` and
+    /// `$TERM`), "truecolor", "256" or "16"
+    #[serde(default)]
+    pub colors: ColorSupport,
+
+    /// "ascii" draws the spinner and status bar without Braille and emoji;
+    /// "auto" picks it on basic terminals and non-UTF-8 locales
+    #[serde(default)]
+    pub glyphs: GlyphSupport,
+
+    /// Esc in the input enters a vi-like normal mode instead of quitting
+    #[serde(default)]
+    pub vi_mode: bool,
+
+    /// Compact layout: no boxes, a one-line status bar, denser chat
+    #[serde(default)]
+    pub compact: bool,
+
+    /// Inline images: "auto" (detect), "kitty", "iterm2", "sixel" or "off"
+    #[serde(default)]
+    pub inline_images: ImageProtocol,
+
+    /// Type out AI replies in the chat instead of showing them at once
+    #[serde(default)]
+    pub reveal_replies: bool,
+
+    /// Show when each message was added and how long thinking or a tool took
+    #[serde(default)]
+    pub show_timestamps: bool,
+
+    /// How to announce long phases that end while the terminal is unfocused:
+    /// "both" (bell and desktop notification), "bell", "desktop" or "off"
+    #[serde(default)]
+    pub notify: NotifyMode,
+
+    /// Only notify after thinking or running for at least this many seconds
+    #[serde(default = "default_notify_after_secs")]
+    pub notify_after_secs: u64,
+
+    /// Startup: "new" session, "ask" to resume the last one, resume the "last" one,
+    /// or "pick" a recent one (`--continue` / `--resume` override it)
+    #[serde(default)]
+    pub resume: ResumeMode,
+
+    /// Back up the sessions directory after each save: "off" or "git"
+    #[serde(default)]
+    pub session_sync: SessionSync,
+
+    /// Shell command run in the sessions directory after each save (empty = none)
+    #[serde(default)]
+    pub session_hook: String,
+
+    /// Directory sessions and artifacts are kept in (empty = ~/.sabi);
+    /// a relative path is taken from the directory sabi starts in
+    #[serde(default)]
+    pub data_dir: String,
+
+    /// Older tool outputs bigger than this many bytes are saved as artifacts
+    /// and kept in sessions as an excerpt (0 = never)
+    #[serde(default = "default_session_output_limit")]
+    pub session_output_limit: usize,
+
+    /// Ask the AI to summarize messages that no longer fit in
+    /// `max_history_messages`, and send the summary in their place
+    #[serde(default = "default_summarize_history")]
+    pub summarize_history: bool,
+
+    /// Record replies and tool runs in stats.jsonl in the data directory
+    /// for `sabi stats`; nothing leaves the machine
+    #[serde(default)]
+    pub stats: bool,
+
+    /// How much goes to the log file: "off", "error", "warn", "info",
+    /// "debug" or "trace" (`--verbose` raises it to "debug")
+    #[serde(default)]
+    pub log_level: LogLevel,
+
+    /// Text appended to the system prompt
+    #[serde(default)]
+    pub system_prompt_append: String,
+
+    /// House rules the AI always follows, e.g. "never use sudo"
+    #[serde(default)]
+    pub rules: Vec<String>,
+
+    /// Extra variables set for every command
+    #[serde(default)]
+    pub env_vars: BTreeMap<String, String>,
+
+    /// Container runtime for `/target container:` ("docker" or "podman"; empty = whichever is installed)
+    #[serde(default)]
+    pub container_runtime: String,
+
+    /// Containers sabi starts for `/target container:<name>`
+    #[serde(default)]
+    pub containers: BTreeMap<String, ContainerSpec>,
+
+    /// Session templates for `/new <name>`
+    #[serde(default)]
+    pub templates: BTreeMap<String, SessionTemplate>,
+
+    /// Project roots that group sessions, for `/workspace`
+    #[serde(default)]
+    pub workspaces: BTreeMap<String, Workspace>,
 }
 
 fn default_model() -> String {
     "gemini-2.5-flash".to_string()
 }
 
+fn default_version() -> u32 {
+    migrate::VERSION
+}
+
+fn default_max_tool_iterations() -> usize {
+    25
+}
+
 fn default_max_history() -> usize {
     20
 }
@@ -95,28 +973,124 @@ fn default_max_output_lines() -> usize {
     500
 }
 
-fn default_dangerous_patterns() -> Vec<String> {
-    vec![
-        r"rm\s+-rf\s+/".to_string(),
-        r"mkfs".to_string(),
-        r"dd\s+if=".to_string(),
-        r":\(\)\s*\{".to_string(), // fork bomb
-        r">\s*/dev/sd".to_string(),
+fn default_mouse() -> bool {
+    true
+}
+
+fn default_tick_rate_ms() -> u64 {
+    100
+}
+
+fn default_idle_tick_rate_ms() -> u64 {
+    1000
+}
+
+fn default_notify_after_secs() -> u64 {
+    10
+}
+
+fn default_session_output_limit() -> usize {
+    8 * 1024
+}
+
+fn default_summarize_history() -> bool {
+    true
+}
+
+fn default_dangerous_patterns() -> Vec<DangerousPattern> {
+    vec![DangerousPattern::new(
+        r":\(\)\s*\{",
+        Severity::Block,
+        "fork bomb",
+    )]
+}
+
+fn default_protected_paths() -> Vec<String> {
+    [
+        "/etc", "/usr", "/bin", "/sbin", "/boot", "/System", "/Library", "~/.*",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()
+}
+
+fn default_env_allowlist() -> Vec<String> {
+    [
+        "PATH", "HOME", "USER", "LOGNAME", "SHELL", "TERM", "LANG", "LC_*", "TMPDIR", "TZ",
     ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            version: default_version(),
             provider: Provider::default(),
             api_key: String::new(),
             base_url: None,
             model: default_model(),
+            context_window: None,
             max_history_messages: default_max_history(),
             max_output_bytes: default_max_output_bytes(),
             max_output_lines: default_max_output_lines(),
+            max_tool_iterations: default_max_tool_iterations(),
+            truncate_strategy: TruncationStrategy::default(),
+            truncate_overrides: BTreeMap::new(),
+            output_capture_secs: 0,
             dangerous_patterns: default_dangerous_patterns(),
+            danger_categories: BTreeMap::new(),
+            writable_roots: Vec::new(),
+            protected_paths: default_protected_paths(),
+            protected_path_severity: Severity::Warn,
+            allowed_commands: Vec::new(),
+            deny_all_commands: false,
+            interactive_patterns: Vec::new(),
+            interactive_categories: BTreeMap::new(),
+            interactive_allow: Vec::new(),
+            approval_policy: ApprovalPolicy::AlwaysAsk,
+            git_checkpoints: false,
             safe_mode: false,
+            max_cpu_seconds: 0,
+            max_memory_mb: 0,
+            max_file_size_mb: 0,
+            command_nice: 0,
+            command_io_priority: IoPriority::Normal,
+            scrub_env: false,
+            env_allowlist: default_env_allowlist(),
+            shell: String::new(),
+            login_shell: false,
+            python_session: false,
+            use_pty: false,
+            mouse: default_mouse(),
+            tick_rate_ms: default_tick_rate_ms(),
+            idle_tick_rate_ms: default_idle_tick_rate_ms(),
+            theme: ThemeMode::Auto,
+            colors: ColorSupport::Auto,
+            glyphs: GlyphSupport::Auto,
+            vi_mode: false,
+            compact: false,
+            inline_images: ImageProtocol::Auto,
+            reveal_replies: false,
+            show_timestamps: false,
+            notify: NotifyMode::Both,
+            notify_after_secs: default_notify_after_secs(),
+            resume: ResumeMode::New,
+            session_sync: SessionSync::Off,
+            session_hook: String::new(),
+            data_dir: String::new(),
+            session_output_limit: default_session_output_limit(),
+            summarize_history: default_summarize_history(),
+            stats: false,
+            log_level: LogLevel::Off,
+            system_prompt_append: String::new(),
+            rules: Vec::new(),
+            env_vars: BTreeMap::new(),
+            container_runtime: String::new(),
+            containers: BTreeMap::new(),
+            templates: BTreeMap::new(),
+            workspaces: BTreeMap::new(),
         }
     }
 }
@@ -127,10 +1101,18 @@ impl Config {
     /// Precedence (highest to lowest):
     /// 1. Environment variables (SABI_API_KEY, etc.)
     /// 2. Config file (~/.config/sabi/config.toml)
-    /// 3. Default values
+    /// 3. The OS keychain, for the API key
+    /// 4. Default values
+    ///
+    /// A config file that doesn't parse is an error rather than falling
+    /// back to the defaults.
     pub fn load() -> Result<Self, ConfigError> {
-        let mut config = Self::load_from_file().unwrap_or_default();
+        let mut config = match Self::load_from_file() {
+            Err(ConfigError::NotFound) => Self::default(),
+            result => result?,
+        };
         config.apply_env_overrides();
+        config.fill_api_key(|provider| keychain::get(provider).ok().flatten());
         Ok(config)
     }
 
@@ -154,50 +1136,131 @@ impl Config {
             return Err(ConfigError::NotFound);
         }
         let content = std::fs::read_to_string(&config_path)?;
-        let config: Config = toml::from_str(&content)?;
-        Ok(config)
+        toml::from_str(&content)
+            .map_err(|e| ConfigError::InvalidFormat(format!("{}: {}", config_path.display(), e)))
     }
 
     /// Get the config file path (~/.sabi/config.toml)
-    fn config_path() -> Result<PathBuf, ConfigError> {
+    pub fn config_path() -> Result<PathBuf, ConfigError> {
         let home = dirs::home_dir().ok_or(ConfigError::NotFound)?;
         Ok(home.join(".sabi").join("config.toml"))
     }
 
-    /// Save configuration to file
-    pub fn save(&self) -> Result<(), ConfigError> {
-        let config_path = Self::config_path()?;
-        if let Some(parent) = config_path.parent() {
-            std::fs::create_dir_all(parent)?;
+    /// `data_dir` as a path, `None` when unset
+    pub fn data_path(&self) -> Option<PathBuf> {
+        let dir = self.data_dir.trim();
+        if dir.is_empty() {
+            return None;
         }
+        let cwd = std::env::current_dir().unwrap_or_default();
+        Some(crate::export::resolve_path(&cwd, dir))
+    }
 
-        let provider_str = match self.provider {
-            Provider::Gemini => "gemini",
-            Provider::OpenAI => "openai",
-        };
+    /// Resource limits applied to spawned commands
+    pub fn resource_limits(&self) -> ResourceLimits {
+        ResourceLimits {
+            cpu_seconds: self.max_cpu_seconds,
+            memory_bytes: self.max_memory_mb.saturating_mul(1024 * 1024),
+            file_size_bytes: self.max_file_size_mb.saturating_mul(1024 * 1024),
+            // Raising priority needs root, so only lowering is supported
+            nice: self.command_nice.clamp(0, 19),
+            io_priority: self.command_io_priority,
+        }
+    }
 
-        let mut content = format!(
-            r#"provider = "{}"
-api_key = "{}"
-model = "{}"
-"#,
-            provider_str, self.api_key, self.model,
-        );
+    /// Tick intervals while active and while idle
+    ///
+    /// At least 10ms, and idle is never faster than active.
+    pub fn tick_rates(&self) -> (Duration, Duration) {
+        let tick = self.tick_rate_ms.max(10);
+        let idle = self.idle_tick_rate_ms.max(tick);
+        (Duration::from_millis(tick), Duration::from_millis(idle))
+    }
+
+    /// Whether a built-in danger category is enabled, honoring overrides
+    pub fn danger_category_enabled(&self, category: &str) -> bool {
+        self.danger_categories
+            .get(category)
+            .copied()
+            .unwrap_or_else(|| {
+                DANGER_CATEGORIES
+                    .iter()
+                    .any(|(name, enabled, _)| *name == category && *enabled)
+            })
+    }
+
+    /// Patterns of the enabled categories followed by `dangerous_patterns`
+    pub fn effective_dangerous_patterns(&self) -> Vec<DangerousPattern> {
+        DANGER_CATEGORIES
+            .iter()
+            .filter(|(name, _, _)| self.danger_category_enabled(name))
+            .flat_map(|(name, _, _)| category_patterns(name))
+            .chain(self.dangerous_patterns.iter().cloned())
+            .collect()
+    }
 
-        if let Some(ref url) = self.base_url {
-            content.push_str(&format!("base_url = \"{}\"\n", url));
+    /// Interactive patterns: `interactive_patterns` first, so their
+    /// suggestions win, then the enabled built-in categories
+    pub fn effective_interactive_patterns(&self) -> Vec<InteractivePattern> {
+        let builtin = INTERACTIVE_CATEGORIES
+            .iter()
+            .filter(|(name, _)| self.interactive_categories.get(*name) != Some(&false))
+            .flat_map(|(name, _)| interactive_category_patterns(name));
+        self.interactive_patterns
+            .iter()
+            .cloned()
+            .chain(builtin)
+            .collect()
+    }
+
+    /// Environment policy for spawned commands
+    pub fn env_policy(&self) -> EnvPolicy {
+        EnvPolicy {
+            scrub: self.scrub_env,
+            allow: self.env_allowlist.clone(),
+            extra: self.env_vars.clone(),
         }
+    }
 
-        content.push_str(&format!(
-            r#"max_history_messages = {}
-max_output_bytes = {}
-max_output_lines = {}
-"#,
-            self.max_history_messages, self.max_output_bytes, self.max_output_lines
-        ));
+    /// Shell used to run commands, from `shell` / `login_shell`
+    pub fn shell_invocation(&self) -> ShellInvocation {
+        let program = if !self.shell.trim().is_empty() {
+            self.shell.trim().to_string()
+        } else if cfg!(target_os = "windows") {
+            return ShellInvocation::default();
+        } else if self.login_shell {
+            std::env::var("SHELL")
+                .ok()
+                .filter(|s| !s.is_empty())
+                .unwrap_or_else(|| "sh".to_string())
+        } else {
+            "sh".to_string()
+        };
+        ShellInvocation::for_program(&program, self.login_shell)
+    }
 
-        std::fs::write(&config_path, content)?;
-        Ok(())
+    /// `rules` and `system_prompt_append` as an addition to the system
+    /// prompt, empty when neither is set
+    pub fn prompt_extensions(&self) -> String {
+        let mut text = String::new();
+        let rules: Vec<&str> = self
+            .rules
+            .iter()
+            .map(|r| r.trim())
+            .filter(|r| !r.is_empty())
+            .collect();
+        if !rules.is_empty() {
+            text.push_str("\n\n## House rules\nAlways follow these rules:\n");
+            for rule in rules {
+                text.push_str(&format!("- {}\n", rule));
+            }
+            text.pop();
+        }
+        let append = self.system_prompt_append.trim();
+        if !append.is_empty() {
+            text.push_str(&format!("\n\n{}", append));
+        }
+        text
     }
 
     /// Check if API key is configured
@@ -205,30 +1268,155 @@ max_output_lines = {}
         !self.api_key.is_empty()
     }
 
-    /// Apply environment variable overrides
-    fn apply_env_overrides(&mut self) {
-        if let Ok(api_key) = std::env::var("SABI_API_KEY") {
-            self.api_key = api_key;
-        }
-        if let Ok(model) = std::env::var("SABI_MODEL") {
-            self.model = model;
+    /// Problems with the settings, shown as warnings at startup
+    ///
+    /// None of them stop sabi: invalid patterns are skipped, limits are
+    /// clamped, and unknown categories or misplaced settings have no effect.
+    pub fn validate(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.version > migrate::VERSION {
+            warnings.push(format!(
+                "version {} is newer than this sabi understands ({}); some settings may be ignored",
+                self.version,
+                migrate::VERSION
+            ));
         }
-        if let Ok(max_history) = std::env::var("SABI_MAX_HISTORY")
-            && let Ok(val) = max_history.parse()
+        if let Some(dir) = self.data_path()
+            && dir.exists()
+            && !dir.is_dir()
         {
-            self.max_history_messages = val;
+            warnings.push(format!(
+                "data_dir {} isn't a directory; sessions can't be saved",
+                dir.display()
+            ));
         }
-        if let Ok(max_bytes) = std::env::var("SABI_MAX_OUTPUT_BYTES")
-            && let Ok(val) = max_bytes.parse()
-        {
-            self.max_output_bytes = val;
+        let patterns = self
+            .dangerous_patterns
+            .iter()
+            .map(|p| ("dangerous_patterns", &p.pattern))
+            .chain(
+                self.interactive_patterns
+                    .iter()
+                    .map(|p| ("interactive_patterns", &p.pattern)),
+            )
+            .chain(
+                self.interactive_allow
+                    .iter()
+                    .map(|p| ("interactive_allow", p)),
+            );
+        for (key, pattern) in patterns {
+            if let Err(e) = Regex::new(pattern) {
+                let error = e.to_string();
+                let reason = error.lines().last().unwrap_or_default().trim();
+                warnings.push(format!("{}: '{}' is skipped, {}", key, pattern, reason));
+            }
+        }
+
+        let danger: Vec<&str> = DANGER_CATEGORIES.iter().map(|(name, _, _)| *name).collect();
+        let interactive: Vec<&str> = INTERACTIVE_CATEGORIES
+            .iter()
+            .map(|(name, _)| *name)
+            .collect();
+        for (key, names, known) in [
+            ("danger_categories", &self.danger_categories, danger),
+            (
+                "interactive_categories",
+                &self.interactive_categories,
+                interactive,
+            ),
+        ] {
+            for name in names.keys().filter(|n| !known.contains(&n.as_str())) {
+                warnings.push(format!(
+                    "{}: no category '{}' (categories: {})",
+                    key,
+                    name,
+                    known.join(", ")
+                ));
+            }
+        }
+
+        for tool in self.truncate_overrides.keys() {
+            if !tool_call::ALLOWED_TOOLS.contains(&tool.as_str()) {
+                warnings.push(format!(
+                    "truncate_overrides: no tool '{}' (tools: {})",
+                    tool,
+                    tool_call::ALLOWED_TOOLS.join(", ")
+                ));
+            }
+        }
+
+        for (key, value) in [
+            ("max_history_messages", self.max_history_messages),
+            ("max_output_bytes", self.max_output_bytes),
+            ("max_output_lines", self.max_output_lines),
+        ] {
+            if value == 0 {
+                warnings.push(format!("{} is 0, so nothing is kept", key));
+            }
+        }
+        if !(0..=19).contains(&self.command_nice) {
+            warnings.push(format!(
+                "command_nice {} is used as {} (0-19)",
+                self.command_nice,
+                self.command_nice.clamp(0, 19)
+            ));
+        }
+
+        let model = self.model.to_lowercase();
+        match (&self.provider, &self.base_url) {
+            (Provider::Gemini, Some(_)) => {
+                warnings.push("base_url is only used with provider = \"openai\"".to_string())
+            }
+            (Provider::Gemini, None)
+                if !model.starts_with("gemini") && !model.starts_with("gemma") =>
+            {
+                warnings.push(format!(
+                    "model '{}' isn't a Gemini model; set provider = \"openai\" for other APIs",
+                    self.model
+                ))
+            }
+            (Provider::OpenAI, None) if model.starts_with("gemini") => warnings.push(format!(
+                "model '{}' is a Gemini model; set provider = \"gemini\"",
+                self.model
+            )),
+            (Provider::OpenAI, Some(url))
+                if !url.starts_with("http://") && !url.starts_with("https://") =>
+            {
+                warnings.push(format!(
+                    "base_url '{}' should start with http:// or https://",
+                    url
+                ))
+            }
+            _ => {}
+        }
+
+        for (name, workspace) in &self.workspaces {
+            if !workspace.root_path().is_dir() {
+                warnings.push(format!(
+                    "workspaces.{}: root {} doesn't exist",
+                    name, workspace.root
+                ));
+            }
         }
-        if let Ok(max_lines) = std::env::var("SABI_MAX_OUTPUT_LINES")
-            && let Ok(val) = max_lines.parse()
+        warnings
+    }
+
+    /// Take the API key from `lookup` (the OS keychain) when none is set
+    fn fill_api_key(&mut self, lookup: impl FnOnce(&Provider) -> Option<String>) {
+        if self.api_key.is_empty()
+            && let Some(key) = lookup(&self.provider)
         {
-            self.max_output_lines = val;
+            self.api_key = key;
         }
     }
+
+    /// Apply environment variable overrides
+    ///
+    /// Every key can be set as `SABI_<KEY>`; values that don't fit the key
+    /// are skipped (startup lists them with the other config warnings).
+    fn apply_env_overrides(&mut self) {
+        *self = settings::with_env(self, |var| std::env::var(var).ok()).0;
+    }
 }
 
 #[cfg(test)]
@@ -279,7 +1467,7 @@ max_history_messages = {}
             unsafe {
                 std::env::set_var("SABI_API_KEY", &env_api_key);
                 std::env::set_var("SABI_MODEL", &env_model);
-                std::env::set_var("SABI_MAX_HISTORY", &env_max_history.to_string());
+                std::env::set_var("SABI_MAX_HISTORY", env_max_history.to_string());
             }
 
             // Load config
@@ -365,4 +1553,269 @@ max_history_messages = {}
             prop_assert_eq!(config.max_output_lines, defaults.max_output_lines);
         }
     }
+
+    #[test]
+    fn test_resource_limits_from_config() {
+        let config: Config = toml::from_str("max_cpu_seconds = 30\nmax_memory_mb = 512\n").unwrap();
+        let limits = config.resource_limits();
+
+        assert_eq!(limits.cpu_seconds, 30);
+        assert_eq!(limits.memory_bytes, 512 * 1024 * 1024);
+        assert_eq!(limits.file_size_bytes, 0);
+        assert!(!limits.is_unlimited());
+        assert!(Config::default().resource_limits().is_unlimited());
+    }
+
+    #[test]
+    fn test_command_priority_from_config() {
+        let config: Config =
+            toml::from_str("command_nice = 40\ncommand_io_priority = \"idle\"\n").unwrap();
+        let limits = config.resource_limits();
+
+        assert_eq!(limits.nice, 19);
+        assert_eq!(limits.io_priority, IoPriority::Idle);
+        assert!(!limits.is_normal_priority());
+        assert!(Config::default().resource_limits().is_normal_priority());
+    }
+
+    #[test]
+    fn test_dangerous_patterns_with_severity() {
+        let config: Config = toml::from_str(
+            "dangerous_patterns = [\"shutdown\", { pattern = \"reboot\", severity = \"block\", reason = \"restarts the host\" }]\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.dangerous_patterns,
+            vec![
+                DangerousPattern::new("shutdown", Severity::Warn, ""),
+                DangerousPattern::new("reboot", Severity::Block, "restarts the host"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_danger_categories() {
+        let defaults = Config::default();
+        assert!(defaults.danger_category_enabled("destructive-fs"));
+        assert!(!defaults.danger_category_enabled("privilege-escalation"));
+        assert!(!defaults.danger_category_enabled("no-such-category"));
+
+        let config: Config = toml::from_str(
+            "[danger_categories]\nprivilege-escalation = true\npackage-remove = false\n",
+        )
+        .unwrap();
+        assert!(config.danger_category_enabled("privilege-escalation"));
+        assert!(!config.danger_category_enabled("package-remove"));
+
+        let patterns = config.effective_dangerous_patterns();
+        assert!(
+            patterns
+                .iter()
+                .any(|p| p.reason == "runs as root with sudo")
+        );
+        assert!(
+            !patterns
+                .iter()
+                .any(|p| p.reason == "removes system packages")
+        );
+        // Custom patterns come after the categories
+        assert_eq!(patterns.last().unwrap().reason, "fork bomb");
+    }
+
+    #[test]
+    fn test_shell_invocation() {
+        let default = Config::default().shell_invocation();
+        assert_eq!(default, ShellInvocation::default());
+
+        let config: Config = toml::from_str("shell = \"/bin/zsh\"\nlogin_shell = true\n").unwrap();
+        let shell = config.shell_invocation();
+        if !cfg!(target_os = "windows") {
+            assert_eq!(shell.program, "/bin/zsh");
+            assert_eq!(shell.flag, "-lc");
+            assert_eq!(shell.label(), "zsh -lc");
+        }
+
+        let config: Config = toml::from_str("shell = \"powershell\"\n").unwrap();
+        let shell = config.shell_invocation();
+        assert_eq!(shell.flag, "-Command");
+        assert!(shell.is_powershell());
+        assert!(
+            ShellInvocation::for_program(r"C:\Program Files\PowerShell\7\pwsh.exe", false)
+                .is_powershell()
+        );
+        assert_eq!(ShellInvocation::for_program("CMD.EXE", false).flag, "/C");
+        assert!(!ShellInvocation::default().is_powershell());
+    }
+
+    #[test]
+    fn test_env_policy_from_config() {
+        let config: Config = toml::from_str(
+            "scrub_env = true\nenv_allowlist = [\"PATH\", \"LC_*\"]\n\n[env_vars]\nFOO = \"bar\"\n",
+        )
+        .unwrap();
+        let policy = config.env_policy();
+
+        assert!(policy.scrub);
+        assert!(policy.allows("PATH"));
+        assert!(policy.allows("LC_ALL"));
+        assert!(!policy.allows("AWS_SECRET_ACCESS_KEY"));
+        assert_eq!(policy.extra.get("FOO").map(String::as_str), Some("bar"));
+        assert!(!Config::default().env_policy().scrub);
+    }
+
+    // **Feature: Sabi-TUI, Property: Resume Mode**
+    #[test]
+    fn test_resume_mode() {
+        let config: Config = toml::from_str("resume = \"ask\"\n").unwrap();
+        assert_eq!(config.resume, ResumeMode::Ask);
+        let config: Config = toml::from_str("resume = \"last\"\n").unwrap();
+        assert_eq!(config.resume, ResumeMode::Last);
+        assert_eq!(Config::default().resume, ResumeMode::New);
+        assert!(toml::from_str::<Config>("resume = \"always\"\n").is_err());
+    }
+
+    // **Feature: Sabi-TUI, Property: Data Directory**
+    #[test]
+    fn test_data_path() {
+        assert_eq!(Config::default().data_path(), None);
+        let config = Config {
+            data_dir: "~/sync/sabi".to_string(),
+            ..Config::default()
+        };
+        assert_eq!(
+            config.data_path(),
+            dirs::home_dir().map(|home| home.join("sync/sabi"))
+        );
+        let config = Config {
+            data_dir: ".sabi-data".to_string(),
+            ..Config::default()
+        };
+        let cwd = std::env::current_dir().unwrap();
+        assert_eq!(config.data_path(), Some(cwd.join(".sabi-data")));
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let config = Config {
+            data_dir: file.path().display().to_string(),
+            ..Config::default()
+        };
+        assert_eq!(config.validate().len(), 1);
+        assert!(config.validate()[0].contains("isn't a directory"));
+    }
+
+    // **Feature: Sabi-TUI, Property: Config Validation**
+    #[test]
+    fn test_validate() {
+        assert!(Config::default().validate().is_empty());
+
+        let config: Config = toml::from_str(
+            r#"
+model = "gpt-4o"
+max_output_lines = 0
+command_nice = 25
+dangerous_patterns = ["rm -rf (", "^shutdown"]
+interactive_allow = ["["]
+
+[danger_categories]
+network = false
+"#,
+        )
+        .unwrap();
+        let warnings = config.validate();
+        assert_eq!(warnings.len(), 6, "{:#?}", warnings);
+        assert!(warnings[0].starts_with("dangerous_patterns: 'rm -rf (' is skipped, "));
+        assert!(warnings[0].contains("unclosed group"));
+        assert!(warnings[1].starts_with("interactive_allow: '[' is skipped"));
+        assert!(warnings[2].starts_with("danger_categories: no category 'network' ("));
+        assert_eq!(warnings[3], "max_output_lines is 0, so nothing is kept");
+        assert_eq!(warnings[4], "command_nice 25 is used as 19 (0-19)");
+        assert!(warnings[5].starts_with("model 'gpt-4o' isn't a Gemini model"));
+
+        let config = Config {
+            provider: Provider::OpenAI,
+            base_url: Some("localhost:11434/v1".to_string()),
+            model: "llama3.2".to_string(),
+            ..Config::default()
+        };
+        assert_eq!(
+            config.validate(),
+            ["base_url 'localhost:11434/v1' should start with http:// or https://"]
+        );
+    }
+
+    // **Feature: Sabi-TUI, Property: System Prompt Extensions**
+    #[test]
+    fn test_prompt_extensions() {
+        assert_eq!(Config::default().prompt_extensions(), "");
+        let config: Config = toml::from_str(
+            r#"
+rules = ["never use sudo", " ", "prefer fd over find"]
+system_prompt_append = """
+Answer in British English.
+"""
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.prompt_extensions(),
+            "\n\n## House rules\nAlways follow these rules:\n- never use sudo\n\
+             - prefer fd over find\n\nAnswer in British English."
+        );
+    }
+
+    // **Feature: Sabi-TUI, Property: Keychain API Key**
+    #[test]
+    fn test_api_key_from_keychain_only_when_unset() {
+        let mut config = Config {
+            provider: Provider::OpenAI,
+            ..Config::default()
+        };
+        config.fill_api_key(|provider| Some(format!("{}-key", provider.name())));
+        assert_eq!(config.api_key, "openai-key");
+
+        config.api_key = "from-file".to_string();
+        config.fill_api_key(|_| panic!("keychain read with a key set"));
+        assert_eq!(config.api_key, "from-file");
+
+        let mut config = Config::default();
+        config.fill_api_key(|_| None);
+        assert!(!config.has_api_key());
+    }
+
+    // **Feature: Sabi-TUI, Property: Approval Policy**
+    #[test]
+    fn test_approval_policy() {
+        let config: Config = toml::from_str("approval_policy = \"auto_safe\"\n").unwrap();
+        assert_eq!(config.approval_policy, ApprovalPolicy::AutoSafe);
+        assert_eq!(Config::default().approval_policy, ApprovalPolicy::AlwaysAsk);
+
+        let read = ToolCall::new("read_file", "");
+        let cmd = ToolCall::run_cmd("cargo test");
+        let write = ToolCall::new("write_file", "");
+
+        assert!(!ApprovalPolicy::AlwaysAsk.auto_approves(&read, true, false));
+
+        let safe = ApprovalPolicy::AutoSafe;
+        assert!(safe.auto_approves(&read, false, false));
+        assert!(safe.auto_approves(&cmd, true, false));
+        assert!(!safe.auto_approves(&cmd, false, false));
+        assert!(!safe.auto_approves(&write, true, false));
+        assert!(!safe.auto_approves(&read, false, true));
+        for command in [
+            "LD_PRELOAD=./x.so git status",
+            "PATH=.:$PATH git log",
+            "GIT_EXTERNAL_DIFF=./x git diff",
+            "git fetch && BASH_ENV=./x git log",
+            "(ENV=./x git status)",
+        ] {
+            let prefixed = ToolCall::run_cmd(command);
+            assert!(!safe.auto_approves(&prefixed, true, false), "{}", command);
+        }
+        assert!(safe.auto_approves(&ToolCall::run_cmd("git log --format=%H"), true, false));
+
+        let all = ApprovalPolicy::AutoAllExceptDangerous;
+        assert!(all.auto_approves(&write, false, false));
+        assert!(!all.auto_approves(&cmd, true, true));
+        assert!(!all.auto_approves(&ToolCall::new("format_disk", ""), false, false));
+    }
 }
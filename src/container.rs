@@ -0,0 +1,294 @@
+//! Container execution target
+//!
+//! `/target container:<name>` runs run_cmd and run_python inside a Docker
+//! or Podman container instead of on the host, for experimenting on a
+//! throwaway environment. Containers listed under `[containers.<name>]` in
+//! the config are started on demand from their image with the workspace
+//! bind-mounted at the same path, so paths mean the same inside and out;
+//! any other name must be a container that is already running.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::ResourceLimits;
+
+/// A container sabi can start, from `[containers.<name>]`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ContainerSpec {
+    /// Image to run (e.g. "python:3.12-slim")
+    pub image: String,
+    /// "docker" or "podman"; defaults to `container_runtime`
+    #[serde(default)]
+    pub runtime: Option<String>,
+    /// Extra `run` arguments (e.g. ["--network=none"])
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+/// Where run_cmd and run_python execute inside a container
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContainerTarget {
+    /// Name shown to the user (from `/target container:<name>`)
+    pub name: String,
+    /// Runtime binary
+    pub runtime: String,
+    /// Container name or ID to exec into
+    pub container: String,
+    /// Image, for containers sabi started
+    pub image: Option<String>,
+    /// Host directory mounted at the same path inside the container
+    pub workspace: Option<PathBuf>,
+}
+
+impl ContainerTarget {
+    /// Start of a command running `program` in the container
+    ///
+    /// The working directory follows `cwd` while it is inside the mounted
+    /// workspace. `env` is passed into the container, since variables set
+    /// on the runtime client don't reach the process; the client keeps its
+    /// own environment (`DOCKER_HOST`, ...). `limits` are set inside the
+    /// container by a `sh` that then execs `program`.
+    pub fn command(
+        &self,
+        cwd: Option<&Path>,
+        env: &BTreeMap<String, String>,
+        limits: ResourceLimits,
+        program: &str,
+    ) -> Command {
+        let mut cmd = Command::new(&self.runtime);
+        cmd.args(["exec", "-i"]);
+        if let Some(ref workspace) = self.workspace {
+            let dir = cwd
+                .filter(|dir| dir.starts_with(workspace))
+                .unwrap_or(workspace);
+            cmd.arg("-w").arg(dir);
+        }
+        for (key, value) in env {
+            cmd.arg("-e").arg(format!("{}={}", key, value));
+        }
+        cmd.arg(&self.container);
+        if let Some(script) = crate::pty::limits_script(limits) {
+            cmd.args(["sh", "-c", &script]);
+        }
+        cmd.arg(program);
+        cmd
+    }
+
+    /// Short label, e.g. "dev (python:3.12-slim, docker)"
+    pub fn label(&self) -> String {
+        match self.image {
+            Some(ref image) => format!("{} ({}, {})", self.name, image, self.runtime),
+            None => format!("{} ({})", self.name, self.runtime),
+        }
+    }
+
+    /// Note telling the AI where its commands now run
+    pub fn context_note(&self) -> String {
+        let mount = match self.workspace {
+            Some(ref dir) => format!(
+                " The workspace {} is mounted at the same path.",
+                dir.display()
+            ),
+            None => String::new(),
+        };
+        format!(
+            "[Execution target: run_cmd and run_python now run inside the container {}.{} \
+             read_file and write_file still act on the host.]",
+            self.label(),
+            mount
+        )
+    }
+}
+
+/// `docker`, else `podman`, whichever is installed
+pub fn detect_runtime() -> Option<String> {
+    ["docker", "podman"]
+        .into_iter()
+        .find(|runtime| {
+            Command::new(runtime)
+                .arg("--version")
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .is_ok_and(|s| s.success())
+        })
+        .map(String::from)
+}
+
+/// Name of the container sabi runs for spec `name`
+pub fn managed_name(name: &str) -> String {
+    let safe: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    format!("sabi-{}", safe)
+}
+
+/// Resolve `/target container:<name>` to a running container
+///
+/// Configured containers are started (image pulled if needed) with
+/// `workspace` mounted, and the memory limit of `limits` for the whole
+/// container; others must already be running.
+pub fn connect(
+    name: &str,
+    spec: Option<&ContainerSpec>,
+    default_runtime: &str,
+    workspace: &Path,
+    limits: ResourceLimits,
+) -> Result<ContainerTarget, String> {
+    let runtime = match spec.and_then(|s| s.runtime.clone()) {
+        Some(runtime) => runtime,
+        None if !default_runtime.is_empty() => default_runtime.to_string(),
+        None => detect_runtime().ok_or("Neither docker nor podman is installed")?,
+    };
+
+    let Some(spec) = spec else {
+        if !is_running(&runtime, name) {
+            return Err(format!(
+                "Container {} is not running (start it, or add [containers.{}] with an image to the config)",
+                name, name
+            ));
+        }
+        return Ok(ContainerTarget {
+            name: name.to_string(),
+            runtime,
+            container: name.to_string(),
+            image: None,
+            workspace: None,
+        });
+    };
+
+    let container = managed_name(name);
+    if !is_running(&runtime, &container) {
+        // A stopped leftover would block the name
+        let _ = Command::new(&runtime)
+            .args(["rm", "-f", &container])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+        let mount = format!("{}:{}", workspace.display(), workspace.display());
+        let mut run = Command::new(&runtime);
+        run.args(["run", "-d", "--rm", "--name", &container, "-v", &mount]);
+        if limits.memory_bytes > 0 {
+            run.arg(format!("--memory={}", limits.memory_bytes));
+        }
+        let output = run
+            .arg("-w")
+            .arg(workspace)
+            .args(&spec.args)
+            .args([&spec.image, "sleep", "infinity"])
+            .output()
+            .map_err(|e| format!("Failed to run {}: {}", runtime, e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "Failed to start {}: {}",
+                spec.image,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+    }
+    Ok(ContainerTarget {
+        name: name.to_string(),
+        runtime,
+        container,
+        image: Some(spec.image.clone()),
+        workspace: Some(workspace.to_path_buf()),
+    })
+}
+
+/// Stop a container sabi started; others are left alone
+pub fn stop(target: &ContainerTarget) {
+    if target.image.is_some() {
+        let _ = Command::new(&target.runtime)
+            .args(["stop", "-t", "1", &target.container])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+    }
+}
+
+fn is_running(runtime: &str, container: &str) -> bool {
+    Command::new(runtime)
+        .args(["inspect", "-f", "{{.State.Running}}", container])
+        .stderr(Stdio::null())
+        .output()
+        .is_ok_and(|o| o.status.success() && String::from_utf8_lossy(&o.stdout).trim() == "true")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // **Feature: Sabi-TUI, Property: Container Exec Command**
+    #[test]
+    fn test_exec_command() {
+        let target = ContainerTarget {
+            name: "dev".to_string(),
+            runtime: "podman".to_string(),
+            container: managed_name("dev"),
+            image: Some("alpine".to_string()),
+            workspace: Some(PathBuf::from("/work/app")),
+        };
+        let env = BTreeMap::from([("CI".to_string(), "1".to_string())]);
+
+        let args = |cwd: &str| -> Vec<String> {
+            let cmd = target.command(Some(Path::new(cwd)), &env, ResourceLimits::default(), "sh");
+            assert_eq!(cmd.get_program(), "podman");
+            cmd.get_args()
+                .map(|a| a.to_string_lossy().into_owned())
+                .collect()
+        };
+        assert_eq!(
+            args("/work/app/src"),
+            [
+                "exec",
+                "-i",
+                "-w",
+                "/work/app/src",
+                "-e",
+                "CI=1",
+                "sabi-dev",
+                "sh"
+            ]
+        );
+        // Outside the mount the workspace root is used
+        assert_eq!(args("/tmp")[3], "/work/app");
+        assert_eq!(target.label(), "dev (alpine, podman)");
+
+        // Limits apply to the process in the container, not the client
+        let limits = ResourceLimits {
+            cpu_seconds: 5,
+            ..Default::default()
+        };
+        let cmd = target.command(None, &BTreeMap::new(), limits, "python3");
+        assert_eq!(cmd.get_envs().count(), 0);
+        let args: Vec<String> = cmd
+            .get_args()
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(
+            args[4..],
+            [
+                "sabi-dev",
+                "sh",
+                "-c",
+                "ulimit -t 5; exec \"$0\" \"$@\"",
+                "python3"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_managed_name() {
+        assert_eq!(managed_name("py 3.12"), "sabi-py-3-12");
+    }
+}
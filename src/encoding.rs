@@ -0,0 +1,468 @@
+//! Output encoding detection
+//!
+//! Command output is usually UTF-8, but legacy tools still emit Latin-1,
+//! Windows-1252, Shift_JIS, EUC-JP or UTF-16, and some commands dump binary
+//! data. Non-UTF-8 text is transcoded, and binary output is summarized
+//! instead of being passed on as replacement characters. Streamed output
+//! goes through a [`StreamDecoder`], which detects the encoding once per
+//! stream and decodes it as it arrives.
+
+use encoding_rs::{CoderResult, Decoder, EUC_JP, Encoding, SHIFT_JIS, UTF_8, WINDOWS_1252};
+
+/// Printable runs shorter than this are noise in binary data
+const MIN_PRINTABLE_RUN: usize = 4;
+
+/// Bytes of a stream judged at most before its encoding is decided
+const DETECT_BYTES: usize = 4096;
+
+/// Bytes of binary output kept to find printable strings in
+const BINARY_HEAD_BYTES: usize = 64 * 1024;
+
+/// Printable runs shown in a binary summary
+pub const BINARY_PREVIEW_LINES: usize = 5;
+
+/// Bytes decoded into text, or recognized as binary
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decoded {
+    /// Text, with the source encoding if it was not UTF-8
+    Text {
+        text: String,
+        encoding: Option<&'static str>,
+    },
+    /// Binary data with its size and the first printable strings in it
+    Binary { size: usize, preview: Vec<String> },
+}
+
+/// Encoding of a byte-order mark at the start of `bytes`, and its length
+///
+/// The name is None for UTF-8.
+fn bom(bytes: &[u8]) -> Option<(&'static Encoding, Option<&'static str>, usize)> {
+    let (encoding, len) = Encoding::for_bom(bytes)?;
+    Some((encoding, (encoding != UTF_8).then(|| encoding.name()), len))
+}
+
+/// Encoding of text without a BOM judged from `sample`, or None for binary
+///
+/// Unless `whole`, `sample` may end in the middle of a UTF-8 character.
+fn detect(sample: &[u8], whole: bool) -> Option<(&'static Encoding, Option<&'static str>)> {
+    if looks_binary(sample) {
+        return None;
+    }
+    let utf8 = match std::str::from_utf8(sample) {
+        Ok(_) => true,
+        Err(e) => !whole && e.error_len().is_none(),
+    };
+    Some(if utf8 {
+        (UTF_8, None)
+    } else if looks_like_euc_jp(sample) {
+        (EUC_JP, Some("EUC-JP"))
+    } else if looks_like_shift_jis(sample) {
+        (SHIFT_JIS, Some("SHIFT_JIS"))
+    } else {
+        (WINDOWS_1252, Some("Windows-1252"))
+    })
+}
+
+/// Detect the encoding of `bytes` and decode them
+pub fn decode(bytes: &[u8]) -> Decoded {
+    let (encoding, name, start) = match bom(bytes) {
+        Some(found) => found,
+        None => match detect(bytes, true) {
+            Some((encoding, name)) => (encoding, name, 0),
+            None => {
+                return Decoded::Binary {
+                    size: bytes.len(),
+                    preview: printable_runs(bytes, BINARY_PREVIEW_LINES),
+                };
+            }
+        },
+    };
+    let (text, _) = encoding.decode_without_bom_handling(&bytes[start..]);
+    Decoded::Text {
+        text: text.into_owned(),
+        encoding: name,
+    }
+}
+
+/// Decodes one output stream as it arrives, line by line
+///
+/// The encoding is detected once per stream, from a BOM or the first line
+/// that isn't plain ASCII, and a stateful decoder carries characters split
+/// across reads. Lines are cut after decoding, so a UTF-16 newline isn't
+/// split from its second byte. Binary lines are left out and summarized by
+/// [`finish`](Self::finish).
+#[derive(Default)]
+pub struct StreamDecoder {
+    /// Bytes read before the encoding is known
+    undecided: Vec<u8>,
+    decoder: Option<Decoder>,
+    /// Name of the encoding if it isn't UTF-8
+    pub encoding: Option<&'static str>,
+    /// Decoded text after the last complete line
+    partial: String,
+    /// The whole stream is binary
+    binary: bool,
+    /// Size of the binary data and its first bytes
+    binary_size: usize,
+    binary_head: Vec<u8>,
+}
+
+impl StreamDecoder {
+    /// Take the next `bytes` of the stream; the lines they complete
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        if self.binary {
+            self.add_binary(bytes);
+            return Vec::new();
+        }
+        if self.decoder.is_some() {
+            return self.decode(bytes, false);
+        }
+        self.undecided.extend_from_slice(bytes);
+        self.decide(false)
+    }
+
+    /// End of the stream: the last line, and binary data seen as
+    /// (size, printable strings)
+    pub fn finish(mut self) -> (Vec<String>, Option<(usize, Vec<String>)>) {
+        let mut lines = if self.binary {
+            Vec::new()
+        } else if self.decoder.is_some() {
+            self.decode(&[], true)
+        } else {
+            let mut lines = self.decide(true);
+            lines.extend(self.decode(&[], true));
+            lines
+        };
+        if !self.partial.is_empty() {
+            let line = std::mem::take(&mut self.partial);
+            self.add_line(&mut lines, line);
+        }
+        let binary = (self.binary_size > 0).then(|| {
+            (
+                self.binary_size,
+                printable_runs(&self.binary_head, BINARY_PREVIEW_LINES),
+            )
+        });
+        (lines, binary)
+    }
+
+    /// Settle the encoding once enough is known; lines decoded so far
+    fn decide(&mut self, at_end: bool) -> Vec<String> {
+        let bytes = &self.undecided;
+        // A BOM needs its first bytes; then only the start of the stream counts
+        let boms: [&[u8]; 3] = [b"\xEF\xBB\xBF", b"\xFF\xFE", b"\xFE\xFF"];
+        if !at_end && bytes.len() < 3 && boms.iter().any(|b| b.starts_with(bytes)) {
+            return Vec::new();
+        }
+        if let Some((encoding, name, len)) = bom(bytes) {
+            self.undecided.drain(..len);
+            return self.start(encoding, name);
+        }
+
+        // Plain ASCII lines read the same in any of the other encodings,
+        // and binary ones say nothing about the text
+        let mut lines = Vec::new();
+        while let Some(pos) = self.undecided.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.undecided[..pos].to_vec();
+            if looks_binary(&line) {
+                self.add_binary(&line);
+            } else if line.is_ascii() {
+                let line = line.strip_suffix(b"\r").unwrap_or(&line);
+                lines.push(String::from_utf8_lossy(line).into_owned());
+            } else {
+                break;
+            }
+            self.undecided.drain(..=pos);
+        }
+        let first_line = self.undecided.iter().position(|&b| b == b'\n');
+        if first_line.is_none() && self.undecided.len() < DETECT_BYTES && !at_end {
+            return lines;
+        }
+        let sample_len = first_line.unwrap_or(self.undecided.len()).min(DETECT_BYTES);
+        let whole = at_end || first_line.is_some_and(|pos| pos <= DETECT_BYTES);
+        match detect(&self.undecided[..sample_len], whole) {
+            Some((encoding, name)) => lines.extend(self.start(encoding, name)),
+            None => {
+                self.binary = true;
+                let bytes = std::mem::take(&mut self.undecided);
+                self.add_binary(&bytes);
+            }
+        }
+        lines
+    }
+
+    /// Decode with `encoding` from now on, starting with the undecided bytes
+    fn start(&mut self, encoding: &'static Encoding, name: Option<&'static str>) -> Vec<String> {
+        self.decoder = Some(encoding.new_decoder_without_bom_handling());
+        self.encoding = name;
+        let bytes = std::mem::take(&mut self.undecided);
+        self.decode(&bytes, false)
+    }
+
+    /// Decode `bytes` (the last of the stream if `last`); the completed lines
+    fn decode(&mut self, mut bytes: &[u8], last: bool) -> Vec<String> {
+        let Some(ref mut decoder) = self.decoder else {
+            return Vec::new();
+        };
+        loop {
+            let needed = decoder
+                .max_utf8_buffer_length(bytes.len())
+                .unwrap_or(bytes.len() * 3 + 16);
+            self.partial.reserve(needed);
+            let (result, read, _) = decoder.decode_to_string(bytes, &mut self.partial, last);
+            bytes = &bytes[read..];
+            if result == CoderResult::InputEmpty {
+                break;
+            }
+        }
+
+        let mut lines = Vec::new();
+        while let Some(pos) = self.partial.find('\n') {
+            let rest = self.partial.split_off(pos + 1);
+            let mut line = std::mem::replace(&mut self.partial, rest);
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
+            }
+            self.add_line(&mut lines, line);
+        }
+        lines
+    }
+
+    /// Add a decoded line, or count it as binary if it looks like it
+    fn add_line(&mut self, lines: &mut Vec<String>, line: String) {
+        if looks_binary(line.as_bytes()) {
+            self.add_binary(line.as_bytes());
+        } else {
+            lines.push(line);
+        }
+    }
+
+    fn add_binary(&mut self, bytes: &[u8]) {
+        self.binary_size += bytes.len();
+        let room = BINARY_HEAD_BYTES.saturating_sub(self.binary_head.len());
+        self.binary_head
+            .extend_from_slice(&bytes[..room.min(bytes.len())]);
+        // Keeps printable runs from merging across pieces
+        if room > bytes.len() {
+            self.binary_head.push(0);
+        }
+    }
+}
+
+/// Decode command output for display and the AI, noting what was done
+pub fn decode_output(bytes: &[u8]) -> String {
+    match decode(bytes) {
+        Decoded::Text {
+            text,
+            encoding: Some(name),
+        } if !text.is_empty() => format!("[decoded from {}]\n{}", name, text),
+        Decoded::Text { text, .. } => text,
+        Decoded::Binary { size, preview } => binary_summary(size, &preview),
+    }
+}
+
+/// Short description of binary output
+pub fn binary_summary(size: usize, preview: &[String]) -> String {
+    let mut out = format!("[binary output: {} bytes, not shown]", size);
+    if !preview.is_empty() {
+        out.push_str("\nFirst printable strings:\n");
+        out.push_str(&preview.join("\n"));
+    }
+    out
+}
+
+/// Runs of printable ASCII at least MIN_PRINTABLE_RUN long (like `strings`)
+pub fn printable_runs(bytes: &[u8], max: usize) -> Vec<String> {
+    bytes
+        .split(|b| !(b.is_ascii_graphic() || *b == b' ' || *b == b'\t'))
+        .filter(|run| run.len() >= MIN_PRINTABLE_RUN)
+        .take(max)
+        .map(|run| String::from_utf8_lossy(run).trim().to_string())
+        .collect()
+}
+
+/// NUL bytes or a high share of control characters mean binary data
+fn looks_binary(bytes: &[u8]) -> bool {
+    if bytes.contains(&0) {
+        return true;
+    }
+    let control = bytes
+        .iter()
+        .filter(|&&b| b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x08 | 0x0C | 0x1B))
+        .count();
+    control * 10 > bytes.len()
+}
+
+/// Every high byte is part of a valid EUC-JP sequence
+fn looks_like_euc_jp(bytes: &[u8]) -> bool {
+    let mut i = 0;
+    let mut multibyte = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            0x00..=0x7F => i += 1,
+            // Half-width katakana
+            0x8E if matches!(bytes.get(i + 1), Some(0xA1..=0xDF)) => {
+                multibyte += 1;
+                i += 2;
+            }
+            0xA1..=0xFE if matches!(bytes.get(i + 1), Some(0xA1..=0xFE)) => {
+                multibyte += 1;
+                i += 2;
+            }
+            _ => return false,
+        }
+    }
+    multibyte > 0
+}
+
+/// Valid Shift_JIS with at least two double-byte characters in a row
+///
+/// Isolated accented Latin-1 letters followed by ASCII can also form valid
+/// Shift_JIS pairs; Japanese text almost always has runs of them.
+fn looks_like_shift_jis(bytes: &[u8]) -> bool {
+    let mut i = 0;
+    let mut run = 0;
+    let mut longest = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            0x81..=0x9F | 0xE0..=0xFC => match bytes.get(i + 1) {
+                Some(0x40..=0x7E | 0x80..=0xFC) => {
+                    run += 1;
+                    longest = longest.max(run);
+                    i += 2;
+                }
+                _ => return false,
+            },
+            // Half-width katakana
+            0xA1..=0xDF => {
+                run += 1;
+                longest = longest.max(run);
+                i += 1;
+            }
+            0x00..=0x7F => {
+                run = 0;
+                i += 1;
+            }
+            _ => return false,
+        }
+    }
+    longest >= 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    // **Feature: Sabi-TUI, Property: Legacy Encodings Transcoded**
+    #[test]
+    fn test_decode_latin1() {
+        // "café – ok" in Windows-1252
+        let bytes = b"caf\xE9 \x96 ok";
+        assert_eq!(
+            decode(bytes),
+            Decoded::Text {
+                text: "café – ok".to_string(),
+                encoding: Some("Windows-1252"),
+            }
+        );
+    }
+
+    #[test]
+    fn test_decode_japanese() {
+        // "日本語" in Shift_JIS and EUC-JP
+        let sjis = b"\x93\xFA\x96\x7B\x8C\xEA";
+        let euc = b"\xC6\xFC\xCB\xDC\xB8\xEC";
+        assert!(looks_like_shift_jis(sjis));
+        assert!(looks_like_euc_jp(euc));
+        assert!(!looks_like_shift_jis(b"caf\xE9 ok"));
+
+        assert_eq!(decode_output(sjis), "[decoded from SHIFT_JIS]\n日本語");
+        assert_eq!(decode_output(euc), "[decoded from EUC-JP]\n日本語");
+    }
+
+    #[test]
+    fn test_decode_utf16_bom() {
+        let bytes = b"\xFF\xFEh\x00i\x00";
+        assert_eq!(decode_output(bytes), "[decoded from UTF-16LE]\nhi");
+    }
+
+    #[test]
+    fn test_binary_summary() {
+        let mut bytes = vec![0x7F, b'E', b'L', b'F', 0, 0, 1];
+        bytes.extend_from_slice(b"GLIBC_2.34\0\x01\x02main\0");
+        match decode(&bytes) {
+            Decoded::Binary { size, preview } => {
+                assert_eq!(size, bytes.len());
+                assert_eq!(preview, vec!["GLIBC_2.34", "main"]);
+            }
+            other => panic!("expected binary, got {:?}", other),
+        }
+        assert!(decode_output(&bytes).starts_with("[binary output:"));
+    }
+
+    /// Lines of `bytes` pushed in pieces of `size` bytes
+    fn stream(bytes: &[u8], size: usize) -> (Vec<String>, Option<(usize, Vec<String>)>) {
+        let mut decoder = StreamDecoder::default();
+        let mut lines: Vec<String> = bytes
+            .chunks(size)
+            .flat_map(|chunk| decoder.push(chunk))
+            .collect();
+        let (last, binary) = decoder.finish();
+        lines.extend(last);
+        (lines, binary)
+    }
+
+    // **Feature: Sabi-TUI, Property: Stream Encoding Detected Once**
+    #[test]
+    fn test_stream_decoder() {
+        // UTF-16 newlines are two bytes; pieces of 3 split code units
+        let mut utf16 = vec![0xFF, 0xFE];
+        for unit in "héllo\r\nwörld\n日本".encode_utf16() {
+            utf16.extend_from_slice(&unit.to_le_bytes());
+        }
+        let (lines, binary) = stream(&utf16, 3);
+        assert_eq!(lines, ["héllo", "wörld", "日本"]);
+        assert!(binary.is_none());
+
+        // Shift_JIS after ASCII lines, with characters split across pieces
+        let mut sjis = b"build ok\n".to_vec();
+        sjis.extend_from_slice(b"\x93\xFA\x96\x7B\x8C\xEA\n\x93\xFA\x96\x7B\n");
+        let mut decoder = StreamDecoder::default();
+        let mut lines = Vec::new();
+        for chunk in sjis.chunks(1) {
+            lines.extend(decoder.push(chunk));
+        }
+        assert_eq!(decoder.encoding, Some("SHIFT_JIS"));
+        lines.extend(decoder.finish().0);
+        assert_eq!(lines, ["build ok", "日本語", "日本"]);
+
+        // UTF-8 split inside a character
+        let (lines, _) = stream("naïve ✓\nno newline".as_bytes(), 2);
+        assert_eq!(lines, ["naïve ✓", "no newline"]);
+
+        // Binary lines are summarized, the text around them kept
+        let (lines, binary) = stream(b"start\n\x7fELF\0\0\x01GLIBC_2.34\0\nend\n", 4);
+        assert_eq!(lines, ["start", "end"]);
+        let (size, preview) = binary.unwrap();
+        assert_eq!(size, 18);
+        assert_eq!(preview, ["GLIBC_2.34"]);
+
+        let (lines, binary) = stream(b"\0\x01\x02\x03", 2);
+        assert!(lines.is_empty());
+        assert_eq!(binary.unwrap().0, 4);
+        assert_eq!(stream(b"", 1), (Vec::new(), None));
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(100))]
+
+        // **Feature: Sabi-TUI, Property: UTF-8 Passes Through**
+        // *For any* printable UTF-8 text, decoding SHALL return it unchanged.
+        #[test]
+        fn prop_utf8_unchanged(s in "[\\PC\\n\\t]{0,200}") {
+            prop_assert_eq!(decode_output(s.as_bytes()), s);
+        }
+    }
+}
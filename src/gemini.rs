@@ -12,8 +12,10 @@ use crate::message::{
 };
 
 /// System prompt defining the AI's behavior as a system expert
+///
+/// `{os}` is replaced by the platform; use `system_prompt()`.
 pub const SYSTEM_PROMPT: &str = r#"
-You are a macOS/Linux system expert assistant.
+You are a {os} system expert assistant.
 
 You MUST use tools when performing any system task. Available tools:
 
@@ -29,20 +31,56 @@ You MUST use tools when performing any system task. Available tools:
 4. Search for files:
    {"tool": "search", "pattern": "<filename pattern>", "directory": "<dir>"}
 
+5. Open a file in the user's editor (use instead of nano/vim, which are blocked):
+   {"tool": "open_editor", "path": "<file path>"}
+
+6. Read a man page (use instead of man, which is blocked; "section" is optional, e.g. "OPTIONS"):
+   {"tool": "man_page", "topic": "<command>", "section": "<section>"}
+
+7. Check which commands are installed (on PATH) before relying on them; add "versions": true to also run each with --version (the user approves that first):
+   {"tool": "check_command", "command": "<space-separated names>"}
+
+8. Query a JSON/YAML/TOML file instead of reading all of it (jq-like: .a.b, .items[0], .items[].name, .deps | keys):
+   {"tool": "query_json", "path": "<file path>", "query": "<path expression>"}
+
 RULES:
 1. ALWAYS use tools for file operations, commands, or system tasks - NEVER just describe what to do
 2. Output ONLY the raw JSON tool call - no markdown, no explanation before it
 3. After seeing tool output, provide a helpful summary
 4. For dangerous operations (rm -rf, etc.), warn the user
 5. Only respond with plain text if the question needs no system action (e.g., "what is 2+2?")
+6. To run several independent read-only tools at once (read_file, search, man_page, check_command, query_json), output a JSON array of tool calls
 
 EXAMPLES:
 - "list files" → {"tool": "run_cmd", "command": "ls -la"}
 - "show Cargo.toml" → {"tool": "read_file", "path": "Cargo.toml"}
 - "find rust files" → {"tool": "search", "pattern": "*.rs", "directory": "."}
 - "create hello.txt with 'hi'" → {"tool": "write_file", "path": "hello.txt", "content": "hi"}
+- "let me edit notes.txt" → {"tool": "open_editor", "path": "notes.txt"}
+- "what flags does tar take?" → {"tool": "man_page", "topic": "tar", "section": "OPTIONS"}
+- "is docker installed?" → {"tool": "check_command", "command": "docker"}
+- "compare a.txt and b.txt" → [{"tool": "read_file", "path": "a.txt"}, {"tool": "read_file", "path": "b.txt"}]
+- "which dependencies does Cargo.toml have?" → {"tool": "query_json", "path": "Cargo.toml", "query": ".dependencies | keys"}
 "#;
 
+/// System prompt claiming expertise in the platform sabi runs on
+pub fn system_prompt() -> String {
+    SYSTEM_PROMPT.replace("{os}", os_expertise())
+}
+
+/// Platform named in the system prompt
+fn os_expertise() -> &'static str {
+    if cfg!(target_os = "windows") {
+        "Windows (cmd and PowerShell)"
+    } else if cfg!(target_os = "macos") {
+        "macOS"
+    } else if cfg!(target_os = "linux") {
+        "Linux"
+    } else {
+        "Unix"
+    }
+}
+
 /// Errors that can occur during Gemini API operations
 #[derive(Debug, Error)]
 pub enum GeminiError {
@@ -211,7 +249,7 @@ impl GeminiClient {
                         role: match msg.role {
                             MessageRole::User => "user".to_string(),
                             MessageRole::Model => "model".to_string(),
-                            MessageRole::System => "user".to_string(),
+                            MessageRole::System | MessageRole::Tool => "user".to_string(),
                         },
                         parts,
                     });
@@ -306,6 +344,7 @@ impl GeminiClient {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::message::GeminiCandidate;
     use proptest::prelude::*;
 
     // Strategy to generate arbitrary MessageRole
@@ -547,7 +586,7 @@ mod tests {
             max_history_messages: 10,
         };
 
-        let messages = vec![Message::system("Be helpful"), Message::user("Hello")];
+        let messages = [Message::system("Be helpful"), Message::user("Hello")];
 
         let refs: Vec<&Message> = messages.iter().collect();
         let request = client.build_request(&refs);
@@ -608,9 +647,7 @@ mod tests {
             candidates: vec![GeminiCandidate {
                 content: GeminiContent {
                     role: "model".to_string(),
-                    parts: vec![GeminiPart {
-                        text: "".to_string(),
-                    }],
+                    parts: vec![GeminiPart::text("")],
                 },
             }],
         };
@@ -619,6 +656,13 @@ mod tests {
         assert!(matches!(result, Err(GeminiError::EmptyResponse)));
     }
 
+    #[test]
+    fn test_system_prompt_names_platform() {
+        let prompt = system_prompt();
+        assert!(!prompt.contains("{os}"));
+        assert!(prompt.contains(&format!("You are a {} system expert", os_expertise())));
+    }
+
     #[test]
     fn test_extract_text_success() {
         let client = GeminiClient {
@@ -632,9 +676,7 @@ mod tests {
             candidates: vec![GeminiCandidate {
                 content: GeminiContent {
                     role: "model".to_string(),
-                    parts: vec![GeminiPart {
-                        text: "Hello, world!".to_string(),
-                    }],
+                    parts: vec![GeminiPart::text("Hello, world!")],
                 },
             }],
         };
@@ -0,0 +1,213 @@
+//! Contents of the F1 help overlay
+//!
+//! Keybindings for what the user is doing right now come first, then the
+//! keys that work anywhere, the slash commands and the tools the AI can
+//! call.
+
+use crate::app::{App, Pane, SLASH_COMMANDS};
+use crate::state::AppState;
+
+/// Tools the AI can call, with what they are for
+const TOOLS: &[(&str, &str)] = &[
+    ("run_cmd", "Run a shell command (after your review)"),
+    ("read_file", "Read a file"),
+    ("write_file", "Create or overwrite a file (undo with /undo)"),
+    ("search", "Search files for a pattern"),
+    ("run_python", "Run Python code"),
+    ("query_json", "Query a JSON file"),
+    ("man_page", "Look up a man page"),
+    (
+        "check_command",
+        "Check which commands are installed (and versions)",
+    ),
+    ("open_editor", "Open a file in $EDITOR"),
+    ("mcp", "Call a tool on a configured MCP server"),
+];
+
+/// A titled list of (key or name, description) rows
+pub struct Section {
+    pub title: String,
+    pub rows: Vec<(String, String)>,
+}
+
+fn rows(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(key, what)| (key.to_string(), what.to_string()))
+        .collect()
+}
+
+/// Keys for the focused pane and current state
+fn contextual(app: &App) -> Section {
+    if app.pager.is_some() {
+        return Section {
+            title: "Pager".to_string(),
+            rows: rows(&[
+                ("↑ / ↓ / j / k", "Scroll"),
+                ("PgUp / PgDn / g / G", "Scroll a page / to the top / bottom"),
+                ("/", "Search"),
+                ("n / N", "Next / previous match"),
+                ("w / #", "Wrap lines / show line numbers"),
+                ("q / z / Esc", "Close"),
+            ]),
+        };
+    }
+    if app.focus == Pane::Chat && app.config.vi_mode {
+        return Section {
+            title: "Normal mode (chat)".to_string(),
+            rows: rows(&[
+                ("j / k", "Scroll down / up"),
+                ("gg / G", "Jump to the top / bottom"),
+                ("z", "Zoom the chat to full screen"),
+                ("/", "Search the chat"),
+                ("n / N", "Older / newer match"),
+                ("y", "Copy the selected message (or the last AI reply)"),
+                ("Enter / Space", "Expand or collapse the selected message"),
+                ("i", "Back to the input"),
+            ]),
+        };
+    }
+    if app.focus == Pane::Chat {
+        return Section {
+            title: "Chat".to_string(),
+            rows: rows(&[
+                ("↑ / ↓", "Scroll"),
+                ("PgUp / PgDn / End", "Scroll a page / to the bottom"),
+                ("Enter / Space", "Expand or collapse the selected message"),
+                ("y", "Copy the selected message"),
+                ("n / N", "Older / newer search match"),
+                ("/", "Search the chat"),
+                ("z", "Zoom the chat to full screen"),
+                ("Tab / Shift+Tab", "Focus the next / previous pane"),
+                ("Esc", "Back to the input"),
+            ]),
+        };
+    }
+    let (title, pairs): (&str, &[(&str, &str)]) = match app.state {
+        AppState::Input => (
+            "Input",
+            &[
+                ("Enter", "Send (a newline with /multiline on)"),
+                ("Ctrl+S", "Send"),
+                ("Alt+Enter / Ctrl+J", "New line"),
+                ("Tab", "Complete a slash command, otherwise focus the chat"),
+                ("↑ / ↓", "Scroll the chat"),
+                ("Ctrl+O", "Attach an image from the clipboard"),
+                ("Ctrl+R", "Review the last tool call again"),
+                ("Ctrl+G", "Full output of the last command"),
+                ("Ctrl+E", "Export the chat (Markdown, or HTML for .html)"),
+                ("Esc", "Quit (normal mode with vi_mode)"),
+            ],
+        ),
+        AppState::Thinking => (
+            "Waiting for the AI",
+            &[
+                ("Esc", "Cancel"),
+                ("typing, Enter", "Queue a message to send once it's done"),
+            ],
+        ),
+        AppState::ReviewAction => (
+            "Review",
+            &[
+                ("Enter", "Run (dangerous commands ask twice)"),
+                ("Esc", "Cancel"),
+                ("Ctrl+X", "Explain the command and its risks"),
+                (
+                    "F2",
+                    "Show or hide the file preview (read_file / write_file)",
+                ),
+                ("typing", "Edit the command before running it"),
+                ("Tab", "Focus the chat"),
+            ],
+        ),
+        AppState::Executing => (
+            "Running",
+            &[
+                ("Esc", "Cancel (kills the whole process tree)"),
+                ("Tab", "Focus the output / the input / the chat"),
+                ("typing, Enter", "Queue a message (input focused)"),
+                ("↑ / ↓", "Scroll the focused output"),
+                (
+                    "PgUp / PgDn / End",
+                    "Scroll a page / back to the newest output",
+                ),
+                ("w", "Wrap long output lines on or off"),
+                ("#", "Show or hide line numbers"),
+                ("z", "Zoom the output to full screen"),
+            ],
+        ),
+        AppState::Finalizing => (
+            "Analyzing output",
+            &[
+                ("Esc", "Cancel"),
+                ("typing, Enter", "Queue a message to send once it's done"),
+            ],
+        ),
+        AppState::Done => (
+            "Done",
+            &[
+                ("Enter", "Continue"),
+                ("Tab", "Focus the chat"),
+                ("Esc / q", "Quit"),
+            ],
+        ),
+    };
+    Section {
+        title: title.to_string(),
+        rows: rows(pairs),
+    }
+}
+
+/// All help sections, contextual keys first
+pub fn sections(app: &App) -> Vec<Section> {
+    vec![
+        contextual(app),
+        Section {
+            title: "Anywhere".to_string(),
+            rows: rows(&[
+                ("F1", "Toggle this help"),
+                ("Ctrl+↑ / Ctrl+↓", "Select the previous / next message"),
+                ("Alt+Y", "Copy the last code block from the AI"),
+                ("Alt+C", "Copy the last executed command"),
+                ("Alt+M", "Copy the selected message"),
+                ("Ctrl+C", "Quit"),
+            ]),
+        },
+        Section {
+            title: "Slash commands".to_string(),
+            rows: rows(SLASH_COMMANDS),
+        },
+        Section {
+            title: "Tools the AI can use".to_string(),
+            rows: rows(TOOLS),
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    // **Feature: Sabi-TUI, Property: Contextual Help**
+    #[test]
+    fn test_sections_follow_state_and_focus() {
+        let mut app = App::new(Config::default());
+        let help = sections(&app);
+        assert_eq!(help[0].title, "Input");
+        assert_eq!(help[2].rows.len(), SLASH_COMMANDS.len());
+
+        app.state = AppState::ReviewAction;
+        assert!(
+            sections(&app)[0]
+                .rows
+                .iter()
+                .any(|(key, _)| key == "Ctrl+X")
+        );
+
+        app.focus = Pane::Chat;
+        assert_eq!(sections(&app)[0].title, "Chat");
+        app.config.vi_mode = true;
+        assert_eq!(sections(&app)[0].title, "Normal mode (chat)");
+    }
+}
@@ -0,0 +1,440 @@
+//! Inline images in the chat
+//!
+//! A message with an image attachment gets a block of blank chat lines,
+//! and after each frame the image is drawn over that block with the
+//! terminal's graphics protocol: kitty, iTerm2 (also WezTerm) or sixel.
+//! Terminals without one, and images a protocol can't take (kitty and
+//! sixel are given PNGs only), keep the one-line text placeholder.
+//!
+//! Images written by tools (a screenshot, a plot) are picked up from the
+//! paths mentioned in the command and its output and shown the same way.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crossterm::{cursor::MoveTo, queue};
+use ratatui::layout::Rect;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::message::{ImageData, Message, base64_decode};
+
+/// Chat lines an inline image takes below its placeholder line
+pub const IMAGE_ROWS: usize = 12;
+
+/// Widest an inline image is drawn, in columns
+pub const IMAGE_COLUMNS: u16 = 60;
+
+/// Cell size assumed when scaling sixel images, in pixels
+const CELL_PIXELS: (u32, u32) = (10, 20);
+
+/// Image files written by tools larger than this are not shown
+const MAX_IMAGE_BYTES: u64 = 5 * 1024 * 1024;
+
+/// kitty takes base64 payloads in chunks of at most this size
+const KITTY_CHUNK: usize = 4096;
+
+/// `inline_images` config setting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageProtocol {
+    /// Detect from the environment
+    #[default]
+    Auto,
+    Kitty,
+    Iterm2,
+    Sixel,
+    /// Text placeholders only
+    Off,
+}
+
+impl ImageProtocol {
+    /// Resolve `Auto` from `$TERM`, `$TERM_PROGRAM` and `$KITTY_WINDOW_ID`
+    pub fn resolve(self) -> Self {
+        match self {
+            Self::Auto => detect(
+                &std::env::var("TERM").unwrap_or_default(),
+                &std::env::var("TERM_PROGRAM").unwrap_or_default(),
+                std::env::var_os("KITTY_WINDOW_ID").is_some(),
+            ),
+            other => other,
+        }
+    }
+
+    /// Whether `image` can be drawn with this protocol
+    pub fn can_show(self, image: &ImageData) -> bool {
+        match self {
+            Self::Iterm2 => image.mime_type.starts_with("image/"),
+            Self::Kitty | Self::Sixel => image.mime_type == "image/png",
+            Self::Auto | Self::Off => false,
+        }
+    }
+
+    /// Images end up in the cells and are only removed by redrawing them
+    fn paints_cells(self) -> bool {
+        matches!(self, Self::Iterm2 | Self::Sixel)
+    }
+}
+
+fn detect(term: &str, term_program: &str, kitty_window: bool) -> ImageProtocol {
+    if kitty_window || term.contains("kitty") || term.contains("ghostty") {
+        ImageProtocol::Kitty
+    } else if matches!(term_program, "iTerm.app" | "WezTerm") {
+        ImageProtocol::Iterm2
+    } else if term.contains("sixel") || term.starts_with("foot") || term.starts_with("mlterm") {
+        ImageProtocol::Sixel
+    } else {
+        ImageProtocol::Off
+    }
+}
+
+/// Width and height in pixels from a PNG or GIF header
+fn dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") && bytes.len() >= 24 {
+        let be = |at: usize| u32::from_be_bytes(bytes[at..at + 4].try_into().unwrap());
+        return Some((be(16), be(20)));
+    }
+    if bytes.starts_with(b"GIF8") && bytes.len() >= 10 {
+        let le = |at: usize| u16::from_le_bytes([bytes[at], bytes[at + 1]]) as u32;
+        return Some((le(6), le(8)));
+    }
+    None
+}
+
+/// Text line shown for an image (above it when it is drawn)
+pub fn placeholder(image: &ImageData) -> String {
+    // The header is all that's needed for the size
+    let head = &image.base64[..image.base64.len().min(64)];
+    let size = base64_decode(head)
+        .and_then(|bytes| dimensions(&bytes))
+        .map(|(w, h)| format!(" {}×{}", w, h))
+        .unwrap_or_default();
+    format!("🖼 {}{}", image.mime_type, size)
+}
+
+fn image_path_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"(?i)[\w./~+-]+\.(?:png|jpe?g|gif|webp)\b").expect("valid image path regex")
+    })
+}
+
+/// Image files mentioned in `text` that exist (relative to `cwd`), at most 3
+pub fn mentioned_images(text: &str, cwd: &Path) -> Vec<PathBuf> {
+    let mut found: Vec<PathBuf> = Vec::new();
+    for m in image_path_regex().find_iter(text) {
+        let path = match m.as_str().strip_prefix("~/") {
+            Some(rest) => match dirs::home_dir() {
+                Some(home) => home.join(rest),
+                None => continue,
+            },
+            None => cwd.join(m.as_str()),
+        };
+        let small_file = std::fs::metadata(&path)
+            .is_ok_and(|meta| meta.is_file() && meta.len() <= MAX_IMAGE_BYTES);
+        if small_file && !found.contains(&path) {
+            found.push(path);
+        }
+        if found.len() == 3 {
+            break;
+        }
+    }
+    found
+}
+
+/// Where an image is drawn, in terminal cells
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placement {
+    /// Index into `app.messages`
+    pub message: usize,
+    pub area: Rect,
+}
+
+/// Escape sequence drawing `image` in `area`-sized cells at the cursor
+fn encode(protocol: ImageProtocol, image: &ImageData, area: Rect) -> Option<String> {
+    match protocol {
+        ImageProtocol::Kitty => {
+            let chunks: Vec<&str> = image
+                .base64
+                .as_bytes()
+                .chunks(KITTY_CHUNK)
+                .map(|c| std::str::from_utf8(c).unwrap_or_default())
+                .collect();
+            let mut out = String::new();
+            for (index, chunk) in chunks.iter().enumerate() {
+                let more = u8::from(index + 1 < chunks.len());
+                if index == 0 {
+                    out.push_str(&format!(
+                        "\x1b_Ga=T,f=100,q=2,C=1,c={},r={},m={};{}\x1b\\",
+                        area.width, area.height, more, chunk
+                    ));
+                } else {
+                    out.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, chunk));
+                }
+            }
+            Some(out)
+        }
+        ImageProtocol::Iterm2 => Some(format!(
+            "\x1b]1337;File=inline=1;width={};height={};preserveAspectRatio=1;doNotMoveCursor=1:{}\x07",
+            area.width, area.height, image.base64
+        )),
+        ImageProtocol::Sixel => {
+            let (rgba, width, height) = decode_png(&base64_decode(&image.base64)?)?;
+            Some(sixel(
+                &rgba,
+                width,
+                height,
+                area.width as u32 * CELL_PIXELS.0,
+                area.height as u32 * CELL_PIXELS.1,
+            ))
+        }
+        ImageProtocol::Auto | ImageProtocol::Off => None,
+    }
+}
+
+/// Decode a PNG to 8-bit RGBA
+fn decode_png(bytes: &[u8]) -> Option<(Vec<u8>, u32, u32)> {
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().ok()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).ok()?;
+    buf.truncate(info.buffer_size());
+    let rgba = match info.color_type {
+        png::ColorType::Rgba => buf,
+        png::ColorType::Rgb => buf
+            .chunks(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => buf
+            .chunks(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Grayscale => buf.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+        png::ColorType::Indexed => return None,
+    };
+    Some((rgba, info.width, info.height))
+}
+
+/// Sixel image of `rgba`, scaled down to fit `max_width`×`max_height`
+///
+/// Colors are quantized to a 6×6×6 cube; transparent pixels are skipped.
+fn sixel(rgba: &[u8], width: u32, height: u32, max_width: u32, max_height: u32) -> String {
+    let scale = (max_width as f32 / width.max(1) as f32)
+        .min(max_height as f32 / height.max(1) as f32)
+        .min(1.0);
+    let out_width = ((width as f32 * scale) as u32).max(1);
+    let out_height = ((height as f32 * scale) as u32).max(1);
+
+    // Nearest-neighbour scaling into palette indexes
+    let level = |v: u8| v as usize * 6 / 256;
+    let mut pixels: Vec<Option<usize>> = Vec::with_capacity((out_width * out_height) as usize);
+    for y in 0..out_height {
+        for x in 0..out_width {
+            let sx = x * width / out_width;
+            let sy = y * height / out_height;
+            let at = ((sy * width + sx) * 4) as usize;
+            let p = &rgba[at..at + 4];
+            pixels.push((p[3] >= 128).then(|| level(p[0]) * 36 + level(p[1]) * 6 + level(p[2])));
+        }
+    }
+
+    let mut out = format!("\x1bP0;1;0q\"1;1;{};{}", out_width, out_height);
+    let mut used = [false; 216];
+    for color in pixels.iter().flatten() {
+        used[*color] = true;
+    }
+    for (color, _) in used.iter().enumerate().filter(|(_, used)| **used) {
+        let percent = |l: usize| l * 100 / 5;
+        out.push_str(&format!(
+            "#{};2;{};{};{}",
+            color,
+            percent(color / 36),
+            percent(color / 6 % 6),
+            percent(color % 6)
+        ));
+    }
+
+    let (w, h) = (out_width as usize, out_height as usize);
+    for band in (0..h).step_by(6) {
+        let mut colors: Vec<usize> = (band..(band + 6).min(h))
+            .flat_map(|y| pixels[y * w..(y + 1) * w].iter().flatten().copied())
+            .collect();
+        colors.sort_unstable();
+        colors.dedup();
+        for color in colors {
+            out.push_str(&format!("#{}", color));
+            let mut run: Option<(char, usize)> = None;
+            for x in 0..w {
+                let bits = (0..6)
+                    .filter(|dy| band + dy < h && pixels[(band + dy) * w + x] == Some(color))
+                    .fold(0u8, |bits, dy| bits | 1 << dy);
+                let ch = (63 + bits) as char;
+                run = match run {
+                    Some((c, n)) if c == ch => Some((c, n + 1)),
+                    Some((c, n)) => {
+                        push_run(&mut out, c, n);
+                        Some((ch, 1))
+                    }
+                    None => Some((ch, 1)),
+                };
+            }
+            if let Some((c, n)) = run {
+                push_run(&mut out, c, n);
+            }
+            out.push('$');
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+fn push_run(out: &mut String, ch: char, count: usize) {
+    if count > 3 {
+        out.push_str(&format!("!{}{}", count, ch));
+    } else {
+        out.extend(std::iter::repeat_n(ch, count));
+    }
+}
+
+/// Images currently on screen, so unchanged frames don't resend them
+#[derive(Debug)]
+pub struct InlineImages {
+    protocol: ImageProtocol,
+    drawn: Vec<Placement>,
+}
+
+impl InlineImages {
+    pub fn new(protocol: ImageProtocol) -> Self {
+        Self {
+            protocol,
+            drawn: Vec::new(),
+        }
+    }
+
+    /// Whether the screen has to be redrawn from scratch before `draw`
+    ///
+    /// iTerm2 and sixel images live in the cells, so moving or removing one
+    /// means repainting the cells it covered.
+    pub fn needs_clear(&self, placements: &[Placement]) -> bool {
+        self.protocol.paints_cells() && !self.drawn.is_empty() && placements != self.drawn
+    }
+
+    /// Draw `placements` over the frame just rendered
+    pub fn draw(
+        &mut self,
+        out: &mut impl Write,
+        placements: Vec<Placement>,
+        messages: &[Message],
+    ) -> std::io::Result<()> {
+        if placements == self.drawn {
+            return Ok(());
+        }
+        if self.protocol == ImageProtocol::Kitty {
+            // Delete the images placed last frame
+            out.write_all(b"\x1b_Ga=d,q=2\x1b\\")?;
+        }
+        for placement in &placements {
+            let image = messages
+                .get(placement.message)
+                .and_then(|m| m.image.as_ref());
+            if let Some(sequence) = image.and_then(|i| encode(self.protocol, i, placement.area)) {
+                queue!(out, MoveTo(placement.area.x, placement.area.y))?;
+                out.write_all(sequence.as_bytes())?;
+            }
+        }
+        out.flush()?;
+        self.drawn = placements;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::base64_encode;
+
+    /// 2×1 RGB PNG: one red pixel, one blue
+    fn tiny_png() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, 2, 1);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[255, 0, 0, 0, 0, 255]).unwrap();
+        drop(writer);
+        bytes
+    }
+
+    fn png_image() -> ImageData {
+        ImageData {
+            base64: base64_encode(&tiny_png()),
+            mime_type: "image/png".to_string(),
+        }
+    }
+
+    // **Feature: Sabi-TUI, Property: Inline Image Protocols**
+    #[test]
+    fn test_detect_protocol() {
+        assert_eq!(detect("xterm-kitty", "", false), ImageProtocol::Kitty);
+        assert_eq!(detect("xterm-256color", "", true), ImageProtocol::Kitty);
+        assert_eq!(
+            detect("xterm-256color", "iTerm.app", false),
+            ImageProtocol::Iterm2
+        );
+        assert_eq!(detect("foot", "", false), ImageProtocol::Sixel);
+        assert_eq!(
+            detect("xterm-256color", "Apple_Terminal", false),
+            ImageProtocol::Off
+        );
+    }
+
+    #[test]
+    fn test_placeholder_and_support() {
+        let png = png_image();
+        assert_eq!(placeholder(&png), "🖼 image/png 2×1");
+
+        let jpeg = ImageData {
+            base64: base64_encode(b"\xff\xd8\xff"),
+            mime_type: "image/jpeg".to_string(),
+        };
+        assert_eq!(placeholder(&jpeg), "🖼 image/jpeg");
+        assert!(ImageProtocol::Iterm2.can_show(&jpeg));
+        assert!(!ImageProtocol::Kitty.can_show(&jpeg));
+        assert!(ImageProtocol::Sixel.can_show(&png));
+        assert!(!ImageProtocol::Off.can_show(&png));
+    }
+
+    #[test]
+    fn test_encodings() {
+        let image = png_image();
+        let area = Rect::new(0, 0, 4, 2);
+
+        let kitty = encode(ImageProtocol::Kitty, &image, area).unwrap();
+        assert!(kitty.starts_with("\x1b_Ga=T,f=100,q=2,C=1,c=4,r=2,m=0;"));
+
+        let iterm = encode(ImageProtocol::Iterm2, &image, area).unwrap();
+        assert!(iterm.contains("width=4;height=2"));
+        assert!(iterm.ends_with(&format!("{}\x07", image.base64)));
+
+        let sixel = encode(ImageProtocol::Sixel, &image, area).unwrap();
+        assert!(sixel.starts_with("\x1bP0;1;0q\"1;1;2;1"));
+        // Red is color 180 (5,0,0), blue is 5 (0,0,5)
+        assert!(sixel.contains("#180;2;100;0;0") && sixel.contains("#5;2;0;0;100"));
+        assert!(sixel.ends_with("-\x1b\\"));
+    }
+
+    #[test]
+    fn test_mentioned_images_exist() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("plot.png"), tiny_png()).unwrap();
+        std::fs::write(dir.path().join("b.gif"), b"GIF89a").unwrap();
+        let text = "Saved plot.png and ./plot.png, b.gif (not missing.jpg)";
+        let found = mentioned_images(text, dir.path());
+        assert_eq!(found.len(), 2);
+        assert!(found[0].ends_with("plot.png"));
+        assert!(found[1].ends_with("b.gif"));
+    }
+}
@@ -0,0 +1,50 @@
+//! API keys in the OS keychain
+//!
+//! Onboarding stores the API key in the macOS Keychain, the Windows
+//! Credential Manager or the Secret Service (GNOME Keyring, KWallet) and
+//! leaves it out of config.toml. `Config::load` reads it back when neither
+//! the file nor `SABI_API_KEY` has one. Without a keychain the key is kept
+//! in config.toml as before.
+
+use crate::config::Provider;
+
+/// Service the keys are stored under, one entry per provider
+const SERVICE: &str = "sabi";
+
+/// Run `f` on its own thread
+///
+/// The Secret Service client blocks on its own async runtime, which
+/// deadlocks when called from a thread of the app's tokio runtime.
+fn on_thread<T: Send + 'static>(
+    f: impl FnOnce() -> keyring::Result<T> + Send + 'static,
+) -> Result<T, String> {
+    std::thread::spawn(f)
+        .join()
+        .map_err(|_| "keychain access panicked".to_string())?
+        .map_err(|e| e.to_string())
+}
+
+fn entry(provider: &Provider) -> keyring::Result<keyring::Entry> {
+    keyring::Entry::new(SERVICE, provider.name())
+}
+
+/// The key stored for `provider`, `None` when the keychain has none
+///
+/// An error means the keychain couldn't be read: there is no Secret
+/// Service (over SSH, in a container) or the keyring is locked.
+pub fn get(provider: &Provider) -> Result<Option<String>, String> {
+    let provider = provider.clone();
+    let key = on_thread(move || match entry(&provider)?.get_password() {
+        Ok(key) => Ok(Some(key)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e),
+    })?;
+    Ok(key.filter(|key| !key.is_empty()))
+}
+
+/// Store `key` for `provider`, replacing any stored before
+pub fn store(provider: &Provider, key: &str) -> Result<(), String> {
+    let provider = provider.clone();
+    let key = key.to_string();
+    on_thread(move || entry(&provider)?.set_password(&key))
+}
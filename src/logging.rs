@@ -0,0 +1,135 @@
+//! Log file for debugging
+//!
+//! The TUI owns the terminal, so diagnostics go to a file instead:
+//! `~/.local/state/sabi/sabi.log`, or `sabi.log` in the data directory on
+//! systems without a state directory. `log_level` sets how much is written
+//! and `--verbose` raises it to `debug`. Only sabi's own events are logged,
+//! not those of the HTTP and terminal libraries.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::prelude::*;
+
+use crate::config;
+
+/// A log bigger than this is moved to `sabi.log.1` at startup
+const MAX_LOG_BYTES: u64 = 10 * 1024 * 1024;
+
+/// `log_level` config setting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    #[default]
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    fn filter(self) -> LevelFilter {
+        match self {
+            LogLevel::Off => LevelFilter::OFF,
+            LogLevel::Error => LevelFilter::ERROR,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Trace => LevelFilter::TRACE,
+        }
+    }
+}
+
+/// Log file path
+pub fn log_path() -> Option<PathBuf> {
+    dirs::state_dir()
+        .map(|d| d.join("sabi"))
+        .or_else(config::data_dir)
+        .map(|d| d.join("sabi.log"))
+}
+
+/// Move the log at `path` aside once it's over `max_bytes`
+fn rotate(path: &Path, max_bytes: u64) -> std::io::Result<()> {
+    match std::fs::metadata(path) {
+        Ok(meta) if meta.len() > max_bytes => {
+            let mut old = path.as_os_str().to_owned();
+            old.push(".1");
+            std::fs::rename(path, PathBuf::from(old))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Start writing events at `level` and above to the log file
+///
+/// Returns the file's path, or `None` with logging off.
+pub fn init(level: LogLevel) -> Result<Option<PathBuf>, String> {
+    if level == LogLevel::Off {
+        return Ok(None);
+    }
+    let path = log_path().ok_or("Can't find a directory for the log file")?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    rotate(&path, MAX_LOG_BYTES).map_err(|e| e.to_string())?;
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("Can't open {}: {}", path.display(), e))?;
+
+    let filter = Targets::new().with_target(env!("CARGO_CRATE_NAME"), level.filter());
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(Mutex::new(file))
+                .with_ansi(false),
+        )
+        .with(filter)
+        .try_init()
+        .map_err(|e| e.to_string())?;
+    Ok(Some(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    // **Feature: Sabi-TUI, Property: Log Level**
+    #[test]
+    fn test_log_level() {
+        assert_eq!(Config::default().log_level, LogLevel::Off);
+        let config: Config = toml::from_str("log_level = \"debug\"\n").unwrap();
+        assert_eq!(config.log_level, LogLevel::Debug);
+        assert!(toml::from_str::<Config>("log_level = \"loud\"\n").is_err());
+
+        // --verbose raises the level, never lowers it
+        assert_eq!(LogLevel::Warn.max(LogLevel::Debug), LogLevel::Debug);
+        assert_eq!(LogLevel::Trace.max(LogLevel::Debug), LogLevel::Trace);
+        assert_eq!(LogLevel::Info.filter(), LevelFilter::INFO);
+    }
+
+    // **Feature: Sabi-TUI, Property: Log Rotation**
+    #[test]
+    fn test_rotate() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("sabi.log");
+        rotate(&path, 4).unwrap();
+
+        std::fs::write(&path, "1234").unwrap();
+        rotate(&path, 4).unwrap();
+        assert!(path.exists());
+        std::fs::write(&path, "12345").unwrap();
+        rotate(&path, 4).unwrap();
+        assert!(!path.exists());
+        assert_eq!(
+            std::fs::read_to_string(temp.path().join("sabi.log.1")).unwrap(),
+            "12345"
+        );
+    }
+}
@@ -0,0 +1,219 @@
+//! Loop guard for the ReAct cycle
+//!
+//! Catches the model re-proposing a tool call that just failed, or the same
+//! call over and over, so the loop is steered instead of spinning. It also
+//! pauses a turn after `max_tool_iterations` tool calls.
+
+use crate::tool_call::ToolCall;
+
+/// Identical proposals in a row before the guard steps in
+const MAX_REPEATS: usize = 3;
+
+/// Interventions per user prompt before the loop is stopped
+const MAX_NUDGES: usize = 2;
+
+/// What to do with a repeated tool call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoopVerdict {
+    /// Skip the call and ask the model to change approach
+    Nudge(String),
+    /// The model ignored earlier nudges; hand control back to the user
+    Stop(String),
+}
+
+/// Tracks consecutive tool calls within one user prompt
+#[derive(Debug, Default)]
+pub struct LoopGuard {
+    last: Vec<ToolCall>,
+    repeats: usize,
+    last_failed: bool,
+    nudges: usize,
+    iterations: usize,
+}
+
+impl LoopGuard {
+    /// Forget history; called when the user sends a new prompt
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Check a proposed tool call before it is shown for review
+    pub fn check(&mut self, tool: &ToolCall) -> Option<LoopVerdict> {
+        self.check_batch(std::slice::from_ref(tool))
+    }
+
+    /// Check calls proposed together; the same batch again counts as a repeat
+    pub fn check_batch(&mut self, tools: &[ToolCall]) -> Option<LoopVerdict> {
+        if self.last == tools {
+            self.repeats += 1;
+        } else {
+            self.last = tools.to_vec();
+            self.repeats = 1;
+            self.last_failed = false;
+        }
+
+        let reason = if self.repeats >= 2 && self.last_failed {
+            "the same call just failed"
+        } else if self.repeats >= MAX_REPEATS {
+            "it was proposed several times in a row"
+        } else {
+            return None;
+        };
+
+        self.nudges += 1;
+        if self.nudges > MAX_NUDGES {
+            Some(LoopVerdict::Stop(reason.to_string()))
+        } else {
+            Some(LoopVerdict::Nudge(reason.to_string()))
+        }
+    }
+
+    /// Count a tool call against `max` calls per prompt (0 = no limit)
+    ///
+    /// The first call past the limit is skipped and the model asked to sum
+    /// up; another one ends the turn.
+    pub fn count_iteration(&mut self, max: usize) -> Option<LoopVerdict> {
+        self.iterations += 1;
+        if max == 0 || self.iterations <= max {
+            None
+        } else if self.iterations == max + 1 {
+            Some(LoopVerdict::Nudge(format!(
+                "{} tool calls in this turn (max_tool_iterations), asking the AI to sum up",
+                max
+            )))
+        } else {
+            Some(LoopVerdict::Stop(format!(
+                "Stopped after {} tool calls in this turn (max_tool_iterations). Reply to continue.",
+                max
+            )))
+        }
+    }
+
+    /// Whether the turn hit `max` tool calls
+    pub fn paused(&self, max: usize) -> bool {
+        max > 0 && self.iterations > max
+    }
+
+    /// Record the outcome of the last executed call
+    pub fn record_result(&mut self, success: bool) {
+        self.last_failed = !success;
+    }
+}
+
+/// Message sent to the model in place of the repeated call's output
+pub fn nudge_message(tool_desc: &str, reason: &str) -> String {
+    format!(
+        "Skipped: {} ({}). Repeating it will not help. \
+         Try a different approach, inspect the error more closely, or explain what is blocking you.",
+        tool_desc, reason
+    )
+}
+
+/// Message sent to the model in place of a call past `max_tool_iterations`
+pub fn limit_message(max: usize) -> String {
+    format!(
+        "Skipped: you have made {} tool calls for this request, the limit for one turn. \
+         Don't call any more tools now. Summarize what you have done so far and what is left, \
+         then ask the user whether to continue.",
+        max
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    // **Feature: Sabi-TUI, Property: Failed Call Repeated**
+    #[test]
+    fn test_repeat_after_failure_is_nudged() {
+        let mut guard = LoopGuard::default();
+        let tool = ToolCall::run_cmd("make");
+
+        assert_eq!(guard.check(&tool), None);
+        guard.record_result(false);
+        assert!(matches!(guard.check(&tool), Some(LoopVerdict::Nudge(_))));
+
+        // A different call clears the failure
+        assert_eq!(guard.check(&ToolCall::run_cmd("make clean")), None);
+        guard.record_result(true);
+        assert_eq!(guard.check(&tool), None);
+    }
+
+    #[test]
+    fn test_persistent_loop_is_stopped() {
+        let mut guard = LoopGuard::default();
+        let tool = ToolCall::run_cmd("ls");
+        let verdicts: Vec<_> = (0..5).map(|_| guard.check(&tool)).collect();
+
+        assert_eq!(verdicts[0], None);
+        assert_eq!(verdicts[1], None);
+        assert!(matches!(verdicts[2], Some(LoopVerdict::Nudge(_))));
+        assert!(matches!(verdicts[3], Some(LoopVerdict::Nudge(_))));
+        assert!(matches!(verdicts[4], Some(LoopVerdict::Stop(_))));
+
+        guard.reset();
+        assert_eq!(guard.check(&tool), None);
+    }
+
+    #[test]
+    fn test_repeated_batch_is_nudged() {
+        let mut guard = LoopGuard::default();
+        let batch = [
+            ToolCall::new("check_command", "cargo"),
+            ToolCall::new("check_command", "jq"),
+        ];
+
+        assert_eq!(guard.check_batch(&batch), None);
+        guard.record_result(false);
+        assert!(matches!(
+            guard.check_batch(&batch),
+            Some(LoopVerdict::Nudge(_))
+        ));
+
+        // A single call from the batch is a different proposal
+        assert_eq!(guard.check(&batch[0]), None);
+    }
+
+    // **Feature: Sabi-TUI, Property: Tool Iteration Limit**
+    #[test]
+    fn test_iteration_limit() {
+        let mut guard = LoopGuard::default();
+        for _ in 0..3 {
+            assert_eq!(guard.count_iteration(3), None);
+        }
+        assert!(!guard.paused(3));
+        assert!(matches!(
+            guard.count_iteration(3),
+            Some(LoopVerdict::Nudge(_))
+        ));
+        assert!(guard.paused(3));
+        assert!(matches!(
+            guard.count_iteration(3),
+            Some(LoopVerdict::Stop(_))
+        ));
+
+        // A new prompt starts a new budget
+        guard.reset();
+        assert_eq!(guard.count_iteration(3), None);
+        assert!(!guard.paused(3));
+
+        let mut unlimited = LoopGuard::default();
+        assert!((0..100).all(|_| unlimited.count_iteration(0).is_none()));
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(100))]
+
+        // **Feature: Sabi-TUI, Property: Distinct Calls Pass**
+        // *For any* sequence of distinct successful calls, the guard SHALL not intervene.
+        #[test]
+        fn prop_distinct_calls_pass(commands in prop::collection::hash_set("[a-z]{1,10}", 1..20)) {
+            let mut guard = LoopGuard::default();
+            for cmd in commands {
+                prop_assert_eq!(guard.check(&ToolCall::run_cmd(cmd)), None);
+                guard.record_result(true);
+            }
+        }
+    }
+}
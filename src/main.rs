@@ -4,41 +4,93 @@
 
 mod ai_client;
 mod app;
+mod artifacts;
+mod bundle;
+mod checkpoint;
+mod clipboard;
+mod compaction;
 mod config;
+mod container;
+mod encoding;
 mod event;
 mod executor;
+mod export;
 mod gemini;
+mod help;
+mod images;
+mod keychain;
+mod logging;
+mod loop_guard;
+mod markdown;
 mod mcp;
 mod message;
+mod migrate;
+mod models;
+mod notify;
 mod onboarding;
+mod oneshot;
 mod openai;
+mod path_guard;
+mod patterns;
+mod preview;
+mod process_group;
+mod project;
+mod pty;
+mod py_session;
+mod pyenv;
+mod query;
+mod session_picker;
+mod session_sync;
+mod settings;
+mod setup;
+mod share;
+mod snippets;
 mod state;
+mod stats;
+mod summary;
+mod template;
+mod theme;
+mod toast;
 mod tool_call;
 mod ui;
+mod undo;
+mod workspace;
+mod write_guard;
+mod wsl;
 
-use std::io::{self, stdout};
-use std::time::Duration;
+use std::io::{self, Write, stdout};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
 use crossterm::{
+    event::{DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture},
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
-use ratatui::{Terminal, backend::CrosstermBackend};
+use ratatui::{Terminal, backend::CrosstermBackend, layout::Rect};
 
 use ai_client::AIClient;
-use app::{App, InputResult};
-use config::Config;
+use app::{App, CdIntercept, InputResult};
+use config::{ApprovalPolicy, Config, ResumeMode, Severity, ShellInvocation};
 use event::{Event, EventHandler};
-use executor::{CommandExecutor, DangerousCommandDetector, InteractiveCommandDetector};
-use gemini::SYSTEM_PROMPT;
+use executor::{
+    CommandAllowlist, CommandExecutor, CommandResult, DangerAssessment, DangerousCommandDetector,
+    InteractiveCommandDetector,
+};
+use gemini::system_prompt;
+use images::{ImageProtocol, InlineImages};
+use logging::LogLevel;
+use loop_guard::LoopVerdict;
 use mcp::McpClient;
-use message::Message;
-use state::StateEvent;
-use tool_call::ParsedResponse;
+use message::{ImageData, Message, ToolRun};
+use notify::Notifier;
+use path_guard::PathGuard;
+use state::{AppState, StateEvent};
+use tool_call::{MIXED_BATCH_REASON, ParsedResponse, ToolCall};
 
-/// Tick rate for UI updates (100ms = 10 FPS)
-const TICK_RATE: Duration = Duration::from_millis(100);
+/// Full-output spool files older than this are deleted at startup
+const SPOOL_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -48,17 +100,57 @@ fn print_help() {
     println!("  sabi              Start interactive TUI");
     println!("  sabi -q 'prompt'  Quick query (text response only)");
     println!("  sabi -x 'prompt'  Execute command from prompt");
-    println!("  sabi mcp <cmd>    Manage MCP servers\n");
+    println!("  sabi -p 'prompt'  Run the agent without the TUI, print the answer");
+    println!("  sabi mcp <cmd>    Manage MCP servers");
+    println!("  sabi export <id>  Export a saved session (Markdown or HTML)");
+    println!("  sabi share <id>   Share a session as one self-contained HTML file");
+    println!("  sabi config <cmd> Export or import settings");
+    println!("  sabi stats        Summarize local usage stats (stats = true)\n");
     println!("Options:");
     println!("  -q, --query      Quick mode: get text response");
     println!("  -x, --exec       Execute mode: run command");
+    println!("  -p, --prompt     One-shot mode: tool calls until a final answer");
+    println!("  -y, --yes        With -p: run tool calls without asking (not dangerous ones)");
+    println!("  --output json    With -p: print events as JSON lines");
+    println!("  --cwd <path>     Start in <path> instead of the current directory");
     println!("  --safe           Safe mode: show commands but don't execute");
+    println!("  --verbose        Write debug logs (see log_level in the config)");
+    println!("  --continue       Continue the last session");
+    println!("  --resume         Pick one of the recent sessions");
     println!("  -v, --version    Show version");
     println!("  -h, --help       Show this help message\n");
     println!("MCP Commands:");
     println!("  sabi mcp add <name> <cmd> [args]  Add MCP server");
     println!("  sabi mcp remove <name>            Remove MCP server");
-    println!("  sabi mcp list                     List MCP servers");
+    println!("  sabi mcp list                     List MCP servers\n");
+    println!("Export:");
+    println!("  sabi export <id|name> [md|html] [path]  Export (stdout without a path)");
+    println!("  sabi share <id|name> [path]             Styled page (sabi-<id>.html)\n");
+    println!("Settings:");
+    println!("  sabi config export [path] [--theme] [--snippets]  Bundle without secrets");
+    println!("  sabi config import <path> [--allow-unsafe]       Merge a bundle");
+}
+
+/// Find sessions where the config keeps them, for commands run before it's loaded
+fn use_data_dir() {
+    if let Ok(config) = Config::load_with_path(Config::config_path().ok().as_ref()) {
+        config::set_data_dir(&config);
+    }
+}
+
+/// Directory given with `--cwd <path>`, checked and made absolute
+fn cwd_arg(args: &[String]) -> Result<Option<std::path::PathBuf>, String> {
+    let Some(pos) = args.iter().position(|a| a == "--cwd") else {
+        return Ok(None);
+    };
+    let dir = args
+        .get(pos + 1)
+        .filter(|a| !a.starts_with('-'))
+        .ok_or("--cwd needs a directory")?;
+    let base = std::env::current_dir().unwrap_or_default();
+    executor::resolve_dir(&base, dir)
+        .map(Some)
+        .map_err(|e| format!("--cwd {}", e.trim_start_matches("cd: ")))
 }
 
 fn print_version() {
@@ -100,11 +192,11 @@ fn is_newer(latest: &str, current: &str) -> bool {
 }
 
 /// Get system context for AI
-fn get_system_context() -> String {
+fn get_system_context(shell: &ShellInvocation) -> String {
     let time = chrono::Local::now()
         .format("%Y-%m-%d %H:%M:%S %Z")
         .to_string();
-    let shell = std::env::var("SHELL").unwrap_or_else(|_| "unknown".into());
+    let login_shell = std::env::var("SHELL").unwrap_or_else(|_| "unknown".into());
     let cwd = std::env::current_dir()
         .map(|p| p.to_string_lossy().into_owned())
         .unwrap_or_else(|_| "unknown".into());
@@ -112,15 +204,32 @@ fn get_system_context() -> String {
 
     let (os_name, os_version) = get_os_info();
 
-    format!(
+    let mut context = format!(
         "SYSTEM CONTEXT:\n\
          - Current time: {}\n\
          - User: {}\n\
          - Shell: {}\n\
+         - Commands run with: {}{}\n\
          - Working directory: {}\n\
          - OS: {} {}",
-        time, user, shell, cwd, os_name, os_version
-    )
+        time,
+        user,
+        login_shell,
+        shell.label(),
+        if shell.is_powershell() {
+            " (use PowerShell syntax)"
+        } else {
+            ""
+        },
+        cwd,
+        os_name,
+        os_version
+    );
+    if let Some(note) = wsl::context_note(&cwd) {
+        context.push('\n');
+        context.push_str(&note);
+    }
+    context
 }
 
 fn get_os_info() -> (String, String) {
@@ -157,14 +266,14 @@ fn get_os_info() -> (String, String) {
     }
 }
 
-/// Quick CLI mode - single query without TUI
-async fn run_quick_mode(config: &Config, prompt: &str, execute: bool) -> Result<()> {
-    let ai_client = AIClient::new(config)?;
-    let executor = CommandExecutor::new(config);
-
-    // Build system prompt
-    let system_context = get_system_context();
-    let mut system_prompt = format!("{}\n\n{}", SYSTEM_PROMPT, system_context);
+/// System prompt for the modes without the TUI, with the MCP tools
+fn cli_system_prompt(config: &Config, project: Option<&project::Project>) -> String {
+    let system_context = get_system_context(&config.shell_invocation());
+    let mut system_prompt = format!("{}\n\n{}", system_prompt(), system_context);
+    system_prompt.push_str(&config.prompt_extensions());
+    if let Some(project) = project {
+        system_prompt.push_str(&project.prompt_section());
+    }
 
     // Add MCP tools if available
     if let Ok(mcp_client) = crate::mcp::McpClient::load() {
@@ -172,7 +281,7 @@ async fn run_quick_mode(config: &Config, prompt: &str, execute: bool) -> Result<
         if let Ok(all_tools) = mcp_client.list_all_tools()
             && !all_tools.is_empty()
         {
-            system_prompt.push_str("\n\n6. Call MCP external tools:\n   {\"tool\": \"mcp\", \"server\": \"<server>\", \"name\": \"<tool_name>\", \"arguments\": {<args>}}\n\nAvailable MCP tools:\n");
+            system_prompt.push_str("\n\n10. Call MCP external tools:\n   {\"tool\": \"mcp\", \"server\": \"<server>\", \"name\": \"<tool_name>\", \"arguments\": {<args>}}\n\nAvailable MCP tools:\n");
             for (server, tools) in &all_tools {
                 for tool in tools {
                     let desc = tool.description.as_deref().unwrap_or("").lines().next().unwrap_or("");
@@ -190,7 +299,20 @@ async fn run_quick_mode(config: &Config, prompt: &str, execute: bool) -> Result<
             }
         }
     }
+    system_prompt
+}
 
+/// Quick CLI mode - single query without TUI
+async fn run_quick_mode(
+    config: &Config,
+    project: Option<&project::Project>,
+    prompt: &str,
+    execute: bool,
+) -> Result<()> {
+    let ai_client = AIClient::new(config)?;
+    let executor = CommandExecutor::new(config);
+
+    let system_prompt = cli_system_prompt(config, project);
     let messages = vec![Message::system(&system_prompt), Message::user(prompt)];
 
     // Get AI response
@@ -215,8 +337,21 @@ async fn run_quick_mode(config: &Config, prompt: &str, execute: bool) -> Result<
                     }
                 }
             } else if execute {
+                // Same checks as -p: allowlist, interactive, danger and paths
+                let guards = oneshot::Guards::new(config);
+                let explanation = match guards.decide(config, &tool, oneshot::Approval::Ask) {
+                    oneshot::Decision::Skip(reason) => {
+                        eprintln!("⛔ Refused {}: {}", describe_tool(&tool), reason);
+                        return Ok(());
+                    }
+                    oneshot::Decision::Confirm(reasons) if !reasons.is_empty() => {
+                        format!("⚠ Dangerous: {}\n\n{}", reasons.join("; "), response)
+                    }
+                    _ => response.clone(),
+                };
+
                 // Show confirmation dialog
-                if !show_confirmation_dialog(&tool.command, &response)? {
+                if !show_confirmation_dialog(&tool.command, &explanation)? {
                     println!("❌ Cancelled");
                     return Ok(());
                 }
@@ -260,6 +395,48 @@ async fn run_quick_mode(config: &Config, prompt: &str, execute: bool) -> Result<
                 println!("{}", tool.command);
             }
         }
+        ParsedResponse::ToolCalls(tools) => {
+            if execute {
+                // Each call is screened and confirmed as it would be on its own
+                let guards = oneshot::Guards::new(config);
+                let mut approved = Vec::new();
+                for tool in tools {
+                    let description = describe_tool(&tool);
+                    let run = match guards.decide(config, &tool, oneshot::Approval::Ask) {
+                        oneshot::Decision::Run => true,
+                        oneshot::Decision::Confirm(reasons) => {
+                            oneshot::confirm(&description, &reasons)
+                        }
+                        oneshot::Decision::Skip(reason) => {
+                            eprintln!("⛔ Refused {}: {}", description, reason);
+                            false
+                        }
+                    };
+                    if run {
+                        approved.push(tool);
+                    }
+                }
+                if approved.is_empty() {
+                    println!("❌ Cancelled");
+                    return Ok(());
+                }
+
+                println!("🔧 Running {} tools in parallel...", approved.len());
+                let results = Arc::new(executor).execute_batch(approved).await;
+                for (tool, result) in results {
+                    println!("── {}", describe_tool(&tool));
+                    println!("{}{}", result.stdout, result.stderr);
+                }
+            } else {
+                for tool in &tools {
+                    println!("{}", describe_tool(tool));
+                }
+            }
+        }
+        ParsedResponse::MixedToolCalls(tools) => {
+            eprintln!("{}", batch_refusal(&tools));
+            std::process::exit(1);
+        }
         ParsedResponse::TextResponse(text) => {
             println!("{}", text);
         }
@@ -268,6 +445,94 @@ async fn run_quick_mode(config: &Config, prompt: &str, execute: bool) -> Result<
     Ok(())
 }
 
+/// One-line description of a tool call for feedback and listings
+fn describe_tool(tool: &ToolCall) -> String {
+    format!(
+        "{}: {}",
+        tool.tool,
+        match tool.tool.as_str() {
+            "run_cmd" | "check_command" => tool.command.clone(),
+            "man_page" => tool.topic.clone(),
+            "search" => format!(
+                "{} in {}",
+                tool.pattern,
+                if tool.directory.is_empty() {
+                    "."
+                } else {
+                    &tool.directory
+                }
+            ),
+            "query_json" => format!("{} {}", tool.path, tool.query),
+            _ => tool.path.clone(),
+        }
+    )
+}
+
+/// Format a tool result as feedback for the AI
+fn tool_feedback(tool_desc: &str, result: &CommandResult) -> String {
+    let output = command_output(result);
+    let signal = result
+        .signal_reason()
+        .map(|reason| format!("{}\n", reason))
+        .unwrap_or_default();
+    let wsl_note = wsl::is_wsl()
+        .then(|| wsl::path_note(&output))
+        .flatten()
+        .map(|note| format!("\n{}", note))
+        .unwrap_or_default();
+    format!(
+        "Tool: {}\nExit code: {}\n{}Output:\n{}{}",
+        tool_desc, result.exit_code, signal, output, wsl_note
+    )
+}
+
+/// Feedback naming each call of a refused mixed batch
+fn batch_refusal(tools: &[ToolCall]) -> String {
+    let sections: Vec<String> = tools
+        .iter()
+        .map(|tool| {
+            format!(
+                "Tool: {}\nNot run: {}",
+                describe_tool(tool),
+                MIXED_BATCH_REASON
+            )
+        })
+        .collect();
+    sections.join("\n\n")
+}
+
+/// Keep what the AI only sees part of as artifacts: the complete output of
+/// a truncated command and the content `write_file` wrote
+///
+/// Returns the id of the stored output, if any, and all ids stored.
+fn store_artifacts(
+    tool: Option<&ToolCall>,
+    result: &CommandResult,
+) -> (Option<String>, Vec<String>) {
+    let output = result
+        .spool
+        .as_ref()
+        .filter(|_| result.truncated)
+        .and_then(|spool| std::fs::read_to_string(spool).ok())
+        .and_then(|text| artifacts::store(&text).ok());
+    let written = tool
+        .filter(|tool| tool.is_write_file() && result.success)
+        .and_then(|tool| artifacts::store(&tool.content).ok());
+    let stored = output.iter().chain(&written).cloned().collect();
+    (output, stored)
+}
+
+/// Output text of a command, interleaving stdout and stderr when both were written
+fn command_output(result: &CommandResult) -> String {
+    if let Some(interleaved) = result.interleaved() {
+        interleaved
+    } else if result.success {
+        result.stdout.clone()
+    } else {
+        format!("{}\n{}", result.stdout, result.stderr)
+    }
+}
+
 /// Show TUI confirmation dialog for command execution
 fn show_confirmation_dialog(command: &str, explanation: &str) -> Result<bool> {
     use crossterm::event::{self, Event, KeyCode};
@@ -525,69 +790,288 @@ async fn main() -> Result<()> {
         return Ok(());
     }
 
-    let mut config = Config::load().context("Failed to load configuration")?;
+    // Share a saved session as one HTML file: sabi share <id|name> [path]
+    if args.get(1).map(|s| s.as_str()) == Some("share") {
+        use_data_dir();
+        if let Err(e) = share::handle_share_command(&args[2..]) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
 
-    // CLI flag overrides config
-    if args.iter().any(|a| a == "--safe") {
-        config.safe_mode = true;
+    // Usage statistics: sabi stats [clear]
+    if args.get(1).map(|s| s.as_str()) == Some("stats") {
+        use_data_dir();
+        if let Err(e) = stats::handle_stats_command(&args[2..]) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
     }
 
-    // Run onboarding if no API key configured
-    if !config.has_api_key() {
+    // Settings bundles: sabi config export|import
+    if args.get(1).map(|s| s.as_str()) == Some("config") {
+        if let Err(e) = bundle::handle_config_command(&args[2..]) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // Export a saved session: sabi export <id|name> [md|html] [path]
+    if args.get(1).map(|s| s.as_str()) == Some("export") {
+        use_data_dir();
+        if let Err(e) = export::handle_export_command(&args[2..]) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // Start in another directory: --cwd <path>
+    let start_dir = match cwd_arg(&args) {
+        Ok(dir) => dir,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Some(ref dir) = start_dir
+        && let Err(e) = std::env::set_current_dir(dir)
+    {
+        eprintln!("Error: can't change to {}: {}", dir.display(), e);
+        std::process::exit(1);
+    }
+
+    // Bring an older config.toml up to the current schema first
+    let upgrade = match Config::config_path().map(|path| migrate::upgrade_file(&path)) {
+        Ok(Ok(upgrade)) => upgrade,
+        Ok(Err(e)) => {
+            eprintln!("⚠ {}", e);
+            None
+        }
+        Err(_) => None,
+    };
+    let mut config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("✗ {}\n", e);
+            eprintln!("Fix the file, or move it away to start from the defaults.");
+            std::process::exit(1);
+        }
+    };
+    // Onboarding is for the first run only, before the project settings
+    // and flags are applied on top; with a config.toml in place a missing
+    // key is reported below instead
+    let first_run = Config::config_path().is_ok_and(|path| !path.exists());
+    if first_run && !config.has_api_key() {
         config = onboarding::run_onboarding().context("Onboarding failed")?;
         // Create default mcp.toml during onboarding
         let _ = mcp::McpConfig::create_default_if_missing();
     }
+    // Project settings from the nearest .sabi.toml (trust is kept in the data dir)
+    config::set_data_dir(&config);
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let project = match project::find(&cwd).map(|path| project::load(&path)) {
+        Some(Ok(mut project)) => {
+            if !project::check_trust(&project) {
+                project.distrust();
+            }
+            project.config.apply(&mut config);
+            Some(project)
+        }
+        Some(Err(e)) => {
+            eprintln!("✗ {}\n", e);
+            eprintln!("Fix the file, or move it away to start without project settings.");
+            std::process::exit(1);
+        }
+        None => None,
+    };
+    let mut config_warnings = match Config::config_path() {
+        Ok(path) => settings::warnings(&config, &settings::file_keys(&path)),
+        Err(_) => config.validate(),
+    };
+    if let Some(ref project) = project {
+        config_warnings.extend(project.warnings.iter().cloned());
+    }
+    if !config.has_api_key() {
+        match keychain::get(&config.provider) {
+            Err(e) => config_warnings.push(format!(
+                "the API key can't be read from the keychain ({}); set it with /setup or SABI_API_KEY",
+                e
+            )),
+            Ok(_) if config.base_url.is_none() => config_warnings
+                .push("no API key; set one with /setup or SABI_API_KEY".to_string()),
+            Ok(_) => {}
+        }
+    }
+
+    // CLI flag overrides config
+    if args.iter().any(|a| a == "--safe") {
+        config.safe_mode = true;
+    }
+    if args.iter().any(|a| a == "--resume") {
+        config.resume = ResumeMode::Pick;
+    } else if args.iter().any(|a| a == "--continue") {
+        config.resume = ResumeMode::Last;
+    }
+
+    config::set_data_dir(&config);
+
+    // Diagnostics go to a log file; the TUI owns the terminal
+    let log_level = if args.iter().any(|a| a == "--verbose") {
+        config.log_level.max(LogLevel::Debug)
+    } else {
+        config.log_level
+    };
+    match logging::init(log_level) {
+        Ok(Some(path)) => tracing::info!(
+            version = VERSION,
+            provider = config.provider.name(),
+            model = %config.model,
+            "sabi started, logging to {}",
+            path.display()
+        ),
+        Ok(None) => {}
+        Err(e) => eprintln!("⚠ Logging is off: {}", e),
+    }
 
-    // Quick mode: -q "prompt" (text only) or -x "prompt" (execute)
+    // Quick mode: -q "prompt" (text only) or -x "prompt" (execute),
+    // or the whole agent loop without the TUI: -p "prompt"
     let query_mode = args.iter().position(|a| a == "-q" || a == "--query");
     let exec_mode = args.iter().position(|a| a == "-x" || a == "--exec");
+    let prompt_mode = args.iter().position(|a| a == "-p" || a == "--prompt");
 
-    if let Some(pos) = query_mode.or(exec_mode) {
+    if let Some(pos) = prompt_mode.or(query_mode).or(exec_mode) {
         let execute = exec_mode.is_some();
         let prompt = args.get(pos + 1).map(|s| s.as_str()).unwrap_or("");
 
+        // --output json: events as JSON lines, for -p
+        let output = args.iter().position(|a| a == "--output");
+        let format = match output.map(|i| args.get(i + 1).map(String::as_str)) {
+            Some(name) => match name.and_then(oneshot::OutputFormat::parse) {
+                Some(format) if prompt_mode.is_some() => format,
+                Some(_) => {
+                    eprintln!("Error: --output works with -p");
+                    std::process::exit(1);
+                }
+                None => {
+                    eprintln!("Error: --output takes text or json");
+                    std::process::exit(1);
+                }
+            },
+            None => oneshot::OutputFormat::Text,
+        };
+
         if prompt.is_empty() {
             eprintln!("Error: No prompt provided");
-            eprintln!("Usage: sabi -q 'prompt' or sabi -x 'prompt'");
+            eprintln!("Usage: sabi -p 'prompt', sabi -q 'prompt' or sabi -x 'prompt'");
             std::process::exit(1);
         }
 
-        return run_quick_mode(&config, prompt, execute).await;
+        if let Some(ref upgrade) = upgrade {
+            eprintln!(
+                "⬆ Upgraded the config from version {} (backup in {})",
+                upgrade.from,
+                upgrade.backup.display()
+            );
+        }
+        for warning in &config_warnings {
+            eprintln!("⚠ config: {}", warning);
+        }
+        if prompt_mode.is_some() {
+            let approval = if config.safe_mode {
+                oneshot::Approval::Safe
+            } else if args.iter().any(|a| a == "--yes" || a == "-y") {
+                oneshot::Approval::Yes
+            } else {
+                oneshot::Approval::Ask
+            };
+            let code = oneshot::run(&config, project.as_ref(), prompt, approval, format).await?;
+            std::process::exit(code);
+        }
+        return run_quick_mode(&config, project.as_ref(), prompt, execute).await;
     }
 
+    executor::prune_spools(SPOOL_MAX_AGE);
+
     enable_raw_mode().context("Failed to enable raw mode")?;
     let mut stdout = stdout();
     execute!(stdout, EnterAlternateScreen).context("Failed to enter alternate screen")?;
+    let _ = execute!(stdout, EnableFocusChange);
+    if config.mouse {
+        execute!(stdout, EnableMouseCapture).context("Failed to enable mouse capture")?;
+    }
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend).context("Failed to create terminal")?;
 
     let mut app = App::new(config.clone());
-    let mut events = EventHandler::new(TICK_RATE);
+    app.cwd_pinned = start_dir.is_some();
+    app.project = project.clone();
+    // Ask the terminal for its background before the event handler reads input
+    app.palette = theme::Palette::resolve(config.theme).fit(config.colors.resolve());
+    app.ascii = config.glyphs.ascii_only();
+    app.image_protocol = config.inline_images.resolve();
+    if let Ok(size) = terminal.size() {
+        app.screen = Rect::new(0, 0, size.width, size.height);
+    }
+    let mut events = EventHandler::new(config.tick_rates().0);
 
     // Start MCP servers if configured
     let mcp_servers = app.start_mcp_servers();
 
     // Gather system context
-    let system_context = get_system_context();
+    let mut system_context = get_system_context(&app.config.shell_invocation());
+    if let Some(ref env) = app.python_env {
+        system_context.push('\n');
+        system_context.push_str(&env.context_note());
+    }
+    if app.python_available && app.config.python_session {
+        system_context.push_str(
+            "\n- run_python keeps one interpreter: variables, imports and loaded data persist \
+             between calls, and a trailing expression's value is printed",
+        );
+    }
 
     // Build system prompt (include Python tool if available)
-    let mut system_prompt = if app.python_available {
+    let mut prompt = if app.python_available {
         format!(
-            "{}\n\n5. Run Python code:\n   {{\"tool\": \"run_python\", \"code\": \"<python code>\"}}\n\nEXAMPLE:\n- \"calculate 2^100\" → {{\"tool\": \"run_python\", \"code\": \"print(2**100)\"}}\n\n{}",
-            SYSTEM_PROMPT, system_context
+            "{}\n\n9. Run Python code:\n   {{\"tool\": \"run_python\", \"code\": \"<python code>\"}}\n\nEXAMPLE:\n- \"calculate 2^100\" → {{\"tool\": \"run_python\", \"code\": \"print(2**100)\"}}\n\n{}",
+            system_prompt(),
+            system_context
         )
     } else {
-        format!("{}\n\n{}", SYSTEM_PROMPT, system_context)
+        format!("{}\n\n{}", system_prompt(), system_context)
     };
 
     // Add MCP tools to system prompt
     let mcp_tools_prompt = app.get_mcp_tools_prompt();
     if !mcp_tools_prompt.is_empty() {
-        system_prompt.push_str(&mcp_tools_prompt);
+        prompt.push_str(&mcp_tools_prompt);
+    }
+    prompt.push_str(&app.config.prompt_extensions());
+    if let Some(ref project) = project {
+        prompt.push_str(&project.prompt_section());
     }
 
-    app.add_message(Message::system(&system_prompt));
+    app.add_message(Message::system(&prompt));
+
+    if let Some(ref project) = project {
+        app.add_message(Message::system(format!(
+            "📁 Project settings from {}",
+            project.path.display()
+        )));
+    }
+    if let Some(ref upgrade) = upgrade {
+        let list: Vec<String> = upgrade.changes.iter().map(|c| format!("- {}", c)).collect();
+        app.add_message(Message::system(format!(
+            "⬆ Upgraded the config from version {} (backup in {}):\n{}",
+            upgrade.from,
+            upgrade.backup.display(),
+            list.join("\n")
+        )));
+    }
 
     // Show MCP status if servers started
     if !mcp_servers.is_empty() {
@@ -597,12 +1081,30 @@ async fn main() -> Result<()> {
         )));
     }
 
-    // Auto-load previous session
-    app.auto_load();
+    // Start fresh unless resuming was asked for; a session a crash
+    // interrupted always comes back
+    match config.resume {
+        _ if app.recover_crashed_session() => {}
+        ResumeMode::Last => app.resume_last(),
+        ResumeMode::Ask => app.offer_resume(),
+        ResumeMode::Pick => {
+            app.open_recent();
+        }
+        ResumeMode::New => {}
+    }
+
+    if !config_warnings.is_empty() {
+        let list: Vec<String> = config_warnings.iter().map(|w| format!("- {}", w)).collect();
+        app.add_message(Message::system(format!(
+            "⚠ Problems in the config (see /config):\n{}",
+            list.join("\n")
+        )));
+    }
 
     let ai_client = AIClient::new(&config).ok();
-    let detector = DangerousCommandDetector::new(&config.dangerous_patterns);
-    let interactive_detector = InteractiveCommandDetector::new();
+    let detector = DangerousCommandDetector::from_rules(&config.effective_dangerous_patterns());
+    let interactive_detector = InteractiveCommandDetector::from_config(&config);
+    let allowlist = CommandAllowlist::from_config(&config);
 
     let result = run_loop(
         &mut terminal,
@@ -611,156 +1113,804 @@ async fn main() -> Result<()> {
         ai_client,
         detector,
         interactive_detector,
+        allowlist,
     )
     .await;
 
     // Auto-save session before exit
     app.auto_save();
+    let (killed, escalate) = app.kill_processes();
+    if let Some(ref target) = app.target {
+        container::stop(target);
+    }
+    if let Some(escalate) = escalate {
+        let _ = escalate.await;
+    }
 
     disable_raw_mode().context("Failed to disable raw mode")?;
+    if config.mouse {
+        let _ = execute!(terminal.backend_mut(), DisableMouseCapture);
+    }
+    let _ = execute!(terminal.backend_mut(), DisableFocusChange);
     execute!(terminal.backend_mut(), LeaveAlternateScreen)
         .context("Failed to leave alternate screen")?;
     terminal.show_cursor().context("Failed to show cursor")?;
 
+    for process in killed {
+        eprintln!("Killed on exit: {}", process);
+    }
+
     result
 }
 
+/// Suspend the TUI, run the user's editor on `path`, then restore the TUI
+///
+/// Terminal input polling is paused for the duration so keystrokes reach the
+/// editor instead of the event handler. Mouse capture (if `mouse`) is
+/// released so the editor gets plain terminal behaviour.
+fn run_editor(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    events: &EventHandler,
+    executor: CommandExecutor,
+    path: &str,
+    mouse: bool,
+) -> CommandResult {
+    events.pause();
+    let _ = disable_raw_mode();
+    if mouse {
+        let _ = execute!(terminal.backend_mut(), DisableMouseCapture);
+    }
+    let _ = execute!(terminal.backend_mut(), LeaveAlternateScreen);
+    let _ = terminal.show_cursor();
+
+    let result = executor.open_editor(path);
+
+    let _ = execute!(terminal.backend_mut(), EnterAlternateScreen);
+    if mouse {
+        let _ = execute!(terminal.backend_mut(), EnableMouseCapture);
+    }
+    let _ = enable_raw_mode();
+    let _ = terminal.clear();
+    events.resume();
+
+    result
+}
+
+/// Run the reviewed (or auto-approved) tool call or read-only batch
+///
+/// The app must already be in the Executing state.
+fn execute_pending(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    events: &EventHandler,
+    app: &mut App<'_>,
+    tx: &tokio::sync::mpsc::UnboundedSender<Event>,
+) {
+    // Independent read-only tools run concurrently
+    if !app.tool_batch.is_empty() {
+        let tools = std::mem::take(&mut app.tool_batch);
+        if app.config.safe_mode {
+            let list: Vec<String> = tools.iter().map(describe_tool).collect();
+            app.add_message(Message::system(format!(
+                "🔒 [SAFE MODE] Would run in parallel:\n{}",
+                list.join("\n")
+            )));
+            app.transition(StateEvent::AnalysisComplete);
+            return;
+        }
+
+        let exec = Arc::new(app.executor());
+        let tx_clone = tx.clone();
+        let handle = tokio::spawn(async move {
+            let results = exec.execute_batch(tools).await;
+            let _ = tx_clone.send(Event::ToolBatchComplete(results));
+        });
+        app.running_task = Some(handle);
+        return;
+    }
+
+    if let Some(ref tool) = app.current_tool {
+        // Safe mode: don't execute, just show what would run
+        if app.config.safe_mode {
+            let desc = match tool.tool.as_str() {
+                "run_cmd" => format!("Would run: {}", tool.command),
+                "run_python" => format!("Would run Python:\n{}", tool.code),
+                "read_file" => format!("Would read: {}", tool.path),
+                "write_file" => {
+                    format!("Would write {} bytes to: {}", tool.content.len(), tool.path)
+                }
+                "search" => {
+                    format!("Would search '{}' in {}", tool.pattern, tool.directory)
+                }
+                "mcp" => {
+                    format!("Would call MCP: {}/{}", tool.server, tool.name)
+                }
+                "open_editor" => format!("Would open in editor: {}", tool.path),
+                "man_page" => format!("Would read man page: {}", tool.topic),
+                "check_command" if tool.versions => {
+                    format!("Would check commands and versions: {}", tool.command)
+                }
+                "check_command" => {
+                    format!("Would check commands: {}", tool.command)
+                }
+                "query_json" => {
+                    format!("Would query {}: {}", tool.path, tool.query)
+                }
+                _ => format!("Would execute: {:?}", tool),
+            };
+            app.add_message(Message::system(format!("🔒 [SAFE MODE] {}", desc)));
+            app.transition(StateEvent::AnalysisComplete);
+        } else if tool.is_mcp() {
+            // Execute MCP tool asynchronously
+            if app.mcp_client.is_some() {
+                let server = tool.server.clone();
+                let name = tool.name.clone();
+                let arguments = tool.arguments.clone();
+                let tx_clone = tx.clone();
+
+                // Clone what we need for the blocking task
+                let mcp = McpClient::load();
+
+                tokio::task::spawn_blocking(move || {
+                    let result = match mcp {
+                        Ok(client) => {
+                            // Start the server if needed
+                            let _ = client.start_server(&server);
+                            client
+                                .call_tool(&server, &name, arguments)
+                                .map_err(|e| e.to_string())
+                        }
+                        Err(e) => Err(e.to_string()),
+                    };
+                    let _ = tx_clone.send(Event::McpResult(result, server, name));
+                });
+                // State already transitioned to Executing by handle_key_event
+            } else {
+                app.add_message(Message::system("❌ MCP client not available"));
+                app.transition(StateEvent::AnalysisComplete);
+            }
+        } else if tool.is_open_editor() {
+            // Editor needs the real terminal, so run it in the foreground
+            let path = tool.path.clone();
+            let result = run_editor(terminal, events, app.executor(), &path, app.config.mouse);
+            let _ = tx.send(Event::CommandComplete(result));
+        } else {
+            let mut tool = tool.clone();
+
+            // Track `cd` in the session instead of a throwaway shell
+            if tool.is_run_cmd() {
+                match app.intercept_cd(&tool.command) {
+                    CdIntercept::NotCd => {}
+                    CdIntercept::Changed(Some(rest)) => tool.command = rest,
+                    CdIntercept::Changed(None) => {
+                        let _ = tx.send(Event::CommandComplete(CommandResult::ok(format!(
+                            "Changed directory to {}",
+                            app.cwd.display()
+                        ))));
+                        return;
+                    }
+                    CdIntercept::Failed(e) => {
+                        let _ = tx.send(Event::CommandComplete(CommandResult::failure(e)));
+                        return;
+                    }
+                }
+            }
+
+            // sudo has no TTY to prompt on, so ask in the TUI if it needs a
+            // password; finding out runs sudo, so it's done off the UI thread
+            if tool.is_run_cmd() && executor::uses_sudo(&tool.command) {
+                let tx = tx.clone();
+                app.running_task = Some(tokio::spawn(async move {
+                    let needs_password = tokio::task::spawn_blocking(executor::sudo_needs_password)
+                        .await
+                        .unwrap_or(true);
+                    let _ = tx.send(Event::SudoChecked(Box::new(tool), needs_password));
+                }));
+                return;
+            }
+
+            spawn_tool(app, tool, None, tx);
+        }
+    }
+}
+
+/// Put a tool call up for review without asking the AI (re-run, snippets)
+///
+/// Danger checks apply as for AI tool calls; blocked calls are refused.
+fn review_tool(
+    app: &mut App,
+    mut tool: ToolCall,
+    allowlist: &CommandAllowlist,
+    interactive_detector: &InteractiveCommandDetector,
+    detector: &DangerousCommandDetector,
+    path_guard: &PathGuard,
+) {
+    let screened = match screen_tool(
+        app,
+        &mut tool,
+        allowlist,
+        interactive_detector,
+        detector,
+        path_guard,
+    ) {
+        Ok(screened) => screened,
+        Err(refusal) => {
+            app.add_message(*refusal);
+            return;
+        }
+    };
+    let danger = screened.danger;
+    let display = match screened.display {
+        Some(display) => display,
+        None if tool.is_run_cmd() => tool.command.clone(),
+        None => describe_tool(&tool),
+    };
+    app.set_action_text(&display);
+    app.reasoning = None;
+    app.dangerous_command_detected = tool.is_destructive() || danger.is_some();
+    app.danger_reasons = danger.map(|d| d.reasons).unwrap_or_default();
+    app.current_tool = Some(tool);
+    app.check_write_conflict();
+    app.transition(StateEvent::Rerun);
+}
+
+/// A tool call that passed the checks before review
+struct Screened {
+    /// What to show instead of the call itself (a tmux launch)
+    display: Option<String>,
+    /// The call was rewritten to open in a tmux window
+    launches_tmux: bool,
+    /// Why the call needs the 2-step confirmation, if it does
+    danger: Option<DangerAssessment>,
+}
+
+/// Checks every tool call passes before review, wherever it came from
+///
+/// Interactive commands are rewritten into a tmux launch when tmux is
+/// available. Err holds the message saying why the call was refused.
+fn screen_tool(
+    app: &App,
+    tool: &mut ToolCall,
+    allowlist: &CommandAllowlist,
+    interactive_detector: &InteractiveCommandDetector,
+    detector: &DangerousCommandDetector,
+    path_guard: &PathGuard,
+) -> Result<Screened, Box<Message>> {
+    // Locked-down setups only allow listed programs
+    if tool.is_run_cmd()
+        && let Err(program) = allowlist.check(&tool.command)
+    {
+        return Err(Box::new(Message::system(format!(
+            "⛔ Blocked by command allowlist: `{}`\nAllowed: {}",
+            program,
+            allowlist.commands().join(", ")
+        ))));
+    }
+
+    // Offer a tmux window for interactive commands instead of refusing outright
+    let mut display = None;
+    if tool.is_run_cmd() && interactive_detector.is_interactive(&tool.command) {
+        let Some(wrapped) = executor::tmux_launch_command(&tool.command, &app.cwd) else {
+            let suggestion = interactive_detector
+                .suggestion(&tool.command)
+                .unwrap_or("This command requires an interactive terminal");
+            return Err(Box::new(Message::model(format!(
+                "⚠️ Cannot run interactive command: `{}`\n{}",
+                tool.command, suggestion
+            ))));
+        };
+        display = Some(format!("Launch in tmux: {}", tool.command));
+        tool.command = wrapped;
+    }
+
+    // Block-level patterns refuse the command outright
+    let danger = tool
+        .is_run_cmd()
+        .then(|| detector.assess(&tool.command))
+        .flatten();
+    let danger = DangerAssessment::combine(danger, path_guard.check_tool(&app.cwd, tool));
+    let danger = DangerAssessment::combine(danger, app.check_workspace_scope(tool));
+    if let Some(ref danger) = danger
+        && danger.severity == Severity::Block
+    {
+        return Err(Box::new(Message::system(format!(
+            "⛔ Refused: {}\nMatched: {}",
+            describe_tool(tool),
+            danger.reasons.join("; ")
+        ))));
+    }
+
+    if tool.tool == "run_python" && !app.python_available {
+        return Err(Box::new(Message::model(
+            "⚠️ Python is not available on this system.\nPlease install Python 3 to use this feature.",
+        )));
+    }
+
+    Ok(Screened {
+        launches_tmux: display.is_some(),
+        display,
+        danger,
+    })
+}
+
+/// Send the conversation to the AI; the reply arrives as `Event::ApiResponse`
+///
+/// Returns false without a configured client.
+fn request_reply(
+    app: &App<'_>,
+    ai_client: Option<&AIClient>,
+    tx: &tokio::sync::mpsc::UnboundedSender<Event>,
+) -> bool {
+    let Some(client) = ai_client else {
+        return false;
+    };
+    let messages = app.context_messages();
+    let client = client.clone();
+    let tx = tx.clone();
+    tokio::spawn(async move {
+        let response = client.chat(&messages).await;
+        let _ = tx.send(Event::ApiResponse(response));
+    });
+    true
+}
+
+/// Run a tool in the background, streaming its output into the TUI
+///
+/// `sudo_password` is handed to the command's sudo and never stored
+/// anywhere else.
+fn spawn_tool(
+    app: &mut App<'_>,
+    tool: ToolCall,
+    sudo_password: Option<String>,
+    tx: &tokio::sync::mpsc::UnboundedSender<Event>,
+) {
+    if tool.is_write_file()
+        && let Err(e) = app.undo.record(&app.cwd.join(&tool.path))
+    {
+        app.add_message(Message::system(format!(
+            "⚠ Can't snapshot for /undo: {}",
+            e
+        )));
+    }
+
+    // git can be slow on big trees, so the checkpoint is taken in the task
+    let checkpoint = (app.config.git_checkpoints && (tool.is_write_file() || tool.is_run_cmd()))
+        .then(|| (app.cwd.clone(), describe_tool(&tool)));
+
+    app.last_tool = Some(tool.clone());
+    let chunk_tx = tx.clone();
+    let mut exec = app.executor().with_output_sink(Arc::new(move |line| {
+        let _ = chunk_tx.send(Event::CommandOutputChunk(line));
+    }));
+    if let Some(password) = sudo_password {
+        exec = exec.with_sudo_password(password);
+    }
+    let tx_clone = tx.clone();
+    let handle = tokio::spawn(async move {
+        if let Some((cwd, label)) = checkpoint {
+            let created = tokio::task::spawn_blocking(move || checkpoint::create(&cwd, &label))
+                .await
+                .unwrap_or_else(|e| Err(e.to_string()));
+            if let Err(e) = created {
+                let _ = tx_clone.send(Event::CheckpointFailed(e));
+            }
+        }
+        let result = exec.execute_tool_async(&tool).await;
+        let _ = tx_clone.send(Event::CommandComplete(result));
+    });
+    app.running_task = Some(handle);
+}
+
+/// Show images the command or its output mention, e.g. a saved plot
+fn show_tool_images(app: &mut App, result: &CommandResult) {
+    let Some(ref tool) = app.current_tool else {
+        return;
+    };
+    let source = match tool.tool.as_str() {
+        "run_cmd" => &tool.command,
+        "run_python" => &tool.code,
+        _ => return,
+    };
+    let text = format!("{}\n{}\n{}", source, result.stdout, result.stderr);
+    for path in images::mentioned_images(&text, &app.cwd) {
+        if let Ok(image) = ImageData::from_file(&path.to_string_lossy()) {
+            let name = path
+                .strip_prefix(&app.cwd)
+                .unwrap_or(&path)
+                .display()
+                .to_string();
+            app.add_message(Message::system_with_image(format!("🖼 {}", name), image));
+        }
+    }
+}
+
 async fn run_loop(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
     app: &mut App<'_>,
     events: &mut EventHandler,
     mut ai_client: Option<AIClient>,
-    detector: DangerousCommandDetector,
+    mut detector: DangerousCommandDetector,
     interactive_detector: InteractiveCommandDetector,
+    allowlist: CommandAllowlist,
 ) -> Result<()> {
     let tx = events.sender();
+    let mut path_guard = PathGuard::new(&app.config);
+    let mut inline_images = InlineImages::new(app.image_protocol);
+    let mut notifier = Notifier::new(
+        app.config.notify,
+        Duration::from_secs(app.config.notify_after_secs),
+    );
+    let mut last_event = Instant::now();
 
     loop {
+        if std::mem::take(&mut app.paths_changed) {
+            path_guard = PathGuard::new(&app.config);
+        }
+        if std::mem::take(&mut app.patterns_changed) {
+            let rules = app.config.effective_dangerous_patterns();
+            detector = DangerousCommandDetector::from_rules(&rules);
+        }
+        if std::mem::take(&mut app.client_changed) {
+            ai_client = AIClient::new(&app.config).ok();
+        }
         terminal.draw(|frame| ui::render(frame, app))?;
+        if app.image_protocol != ImageProtocol::Off {
+            let area = terminal.get_frame().area();
+            let chat = ui::split_preview(ui::create_main_layout(area, app)[0], app).0;
+            let placements = ui::image_placements(app, chat);
+            if inline_images.needs_clear(&placements) {
+                terminal.clear()?;
+                terminal.draw(|frame| ui::render(frame, app))?;
+            }
+            inline_images.draw(terminal.backend_mut(), placements, &app.messages)?;
+        }
+        if let Some(text) = notifier.update(app.state, Instant::now()) {
+            let term = std::env::var("TERM").unwrap_or_default();
+            let in_tmux = std::env::var_os("TMUX").is_some();
+            let sequence = notifier.sequence(&text, &term, in_tmux);
+            let mut out = stdout();
+            out.write_all(sequence.as_bytes())?;
+            out.flush()?;
+        }
 
-        if let Some(event) = events.next().await {
+        // Tick slowly while nothing animates and nothing happens
+        events.set_tick_rate(app.tick_rate(last_event.elapsed()));
+
+        // Messages typed while the agent was busy go out once it's back
+        let next = if app.state == AppState::Input && !app.queued.is_empty() {
+            Some(Event::SendQueued)
+        } else {
+            events.next().await
+        };
+        if !matches!(next, Some(Event::Tick)) {
+            last_event = Instant::now();
+        }
+        if let Some(event) = next {
             match event {
-                Event::Key(key) => {
-                    let result = app.handle_key_event(key);
+                Event::Key(_) | Event::SendQueued => {
+                    let result = match event {
+                        Event::Key(key) => app.handle_key_event(key),
+                        _ => app.send_queued(),
+                    };
 
                     // Handle command cancellation
                     if result == InputResult::CancelCommand {
+                        app.write_conflict = None;
                         app.add_message(Message::system("⚠️ Command cancelled"));
                         app.transition(StateEvent::AnalysisComplete);
                         continue;
                     }
 
-                    // Handle /model command
-                    if let InputResult::FetchModels(model_arg) = result.clone() {
+                    // Run the pending sudo command with the password for its sudo
+                    if result == InputResult::SubmitSudoPassword {
+                        if let Some((mut tool, password)) = app.take_sudo_submission() {
+                            tool.command = executor::sudo_askpass_command(&tool.command);
+                            spawn_tool(app, tool, Some(password), &tx);
+                        }
+                        continue;
+                    }
+
+                    // Put the last tool call back up for review, skipping the AI
+                    if result == InputResult::Rerun {
+                        match app.last_tool.clone() {
+                            Some(tool) => review_tool(
+                                app,
+                                tool,
+                                &allowlist,
+                                &interactive_detector,
+                                &detector,
+                                &path_guard,
+                            ),
+                            None => app.toast("Nothing to re-run yet."),
+                        }
+                        continue;
+                    }
+
+                    // A filled-in snippet goes straight to review
+                    if let InputResult::ReviewTool(tool) = result.clone() {
+                        review_tool(
+                            app,
+                            *tool,
+                            &allowlist,
+                            &interactive_detector,
+                            &detector,
+                            &path_guard,
+                        );
+                        continue;
+                    }
+
+                    // Explain the pending action without leaving review
+                    if result == InputResult::ExplainCommand {
                         if let Some(ref client) = ai_client {
+                            let messages = app.explain_request();
                             let client_clone = client.clone();
                             let tx_clone = tx.clone();
                             tokio::spawn(async move {
-                                let models = client_clone.list_models().await;
-                                let _ = tx_clone.send(Event::ModelsResponse(models, model_arg));
+                                let response = client_clone.chat(&messages).await;
+                                let _ = tx_clone.send(Event::ExplainResponse(response));
                             });
                         } else {
-                            app.add_message(Message::system("API key not configured"));
+                            app.explaining = false;
+                            app.toast("✗ API key not configured");
                         }
                         continue;
                     }
 
-                    // 12.1: Input → Thinking transition
-                    if result == InputResult::SubmitQuery {
+                    // Handle /model command
+                    if let InputResult::FetchModels(model_arg) = result.clone() {
                         if let Some(ref client) = ai_client {
-                            let messages = app.messages.clone();
                             let client_clone = client.clone();
                             let tx_clone = tx.clone();
                             tokio::spawn(async move {
-                                let response = client_clone.chat(&messages).await;
-                                let _ = tx_clone.send(Event::ApiResponse(response));
+                                let models = client_clone.list_models().await;
+                                let _ = tx_clone.send(Event::ModelsResponse(models, model_arg));
                             });
                         } else {
+                            app.toast("✗ API key not configured");
+                        }
+                        continue;
+                    }
+
+                    // Handle /target container:<name>; starting one can pull an image
+                    if let InputResult::ConnectTarget(name) = result.clone() {
+                        let spec = app.config.containers.get(&name).cloned();
+                        let runtime = app.config.container_runtime.clone();
+                        let workspace = app.cwd.clone();
+                        let limits = app.config.resource_limits();
+                        let tx_clone = tx.clone();
+                        tokio::spawn(async move {
+                            let result = tokio::task::spawn_blocking(move || {
+                                container::connect(
+                                    &name,
+                                    spec.as_ref(),
+                                    &runtime,
+                                    &workspace,
+                                    limits,
+                                )
+                            })
+                            .await
+                            .unwrap_or_else(|e| Err(e.to_string()));
+                            let _ = tx_clone.send(Event::TargetConnected(result));
+                        });
+                        continue;
+                    }
+
+                    // Handle /edit command (user-initiated, not sent to AI)
+                    if let InputResult::OpenEditor(path) = result.clone() {
+                        let outcome =
+                            run_editor(terminal, events, app.executor(), &path, app.config.mouse);
+                        if outcome.success {
+                            app.add_message(Message::system(format!("✓ Edited {}", path)));
+                        } else {
+                            app.add_message(Message::system(format!(
+                                "✗ Editor failed: {}",
+                                outcome.stderr
+                            )));
+                        }
+                        continue;
+                    }
+
+                    // 12.1: Input → Thinking transition
+                    if result == InputResult::SubmitQuery {
+                        app.loop_guard.reset();
+                        if !request_reply(app, ai_client.as_ref(), &tx) {
                             app.set_error("API key not configured");
                             app.transition(StateEvent::ApiError);
                         }
                     }
 
                     // 12.4: ReviewAction → Executing transition
-                    if result == InputResult::ExecuteCommand
-                        && let Some(ref tool) = app.current_tool
-                    {
-                        // Safe mode: don't execute, just show what would run
-                        if app.config.safe_mode {
-                            let desc = match tool.tool.as_str() {
-                                "run_cmd" => format!("Would run: {}", tool.command),
-                                "run_python" => format!("Would run Python:\n{}", tool.code),
-                                "read_file" => format!("Would read: {}", tool.path),
-                                "write_file" => format!(
-                                    "Would write {} bytes to: {}",
-                                    tool.content.len(),
-                                    tool.path
-                                ),
-                                "search" => {
-                                    format!("Would search '{}' in {}", tool.pattern, tool.directory)
-                                }
-                                "mcp" => {
-                                    format!("Would call MCP: {}/{}", tool.server, tool.name)
-                                }
-                                _ => format!("Would execute: {:?}", tool),
-                            };
-                            app.add_message(Message::system(format!("🔒 [SAFE MODE] {}", desc)));
-                            app.transition(StateEvent::AnalysisComplete);
-                        } else if tool.is_mcp() {
-                            // Execute MCP tool asynchronously
-                            if app.mcp_client.is_some() {
-                                let server = tool.server.clone();
-                                let name = tool.name.clone();
-                                let arguments = tool.arguments.clone();
-                                let tx_clone = tx.clone();
-                                
-                                // Clone what we need for the blocking task
-                                let mcp = McpClient::load();
-                                
-                                tokio::task::spawn_blocking(move || {
-                                    let result = match mcp {
-                                        Ok(client) => {
-                                            // Start the server if needed
-                                            let _ = client.start_server(&server);
-                                            client.call_tool(&server, &name, arguments)
-                                                .map_err(|e| e.to_string())
-                                        }
-                                        Err(e) => Err(e.to_string()),
-                                    };
-                                    let _ = tx_clone.send(Event::McpResult(result, server, name));
-                                });
-                                // State already transitioned to Executing by handle_key_event
-                            } else {
-                                app.add_message(Message::system("❌ MCP client not available"));
-                                app.transition(StateEvent::AnalysisComplete);
-                            }
-                        } else {
-                            let tool = tool.clone();
-                            let exec = CommandExecutor::new(&app.config);
-                            let tx_clone = tx.clone();
-                            let handle = tokio::spawn(async move {
-                                let result = exec.execute_tool_async(&tool).await;
-                                let _ = tx_clone.send(Event::CommandComplete(result));
-                            });
-                            app.running_task = Some(handle);
-                        }
+                    if result == InputResult::ExecuteCommand {
+                        execute_pending(terminal, events, app, &tx);
                     }
                 }
                 Event::Tick => {
                     app.tick_spinner();
+                    app.advance_reveal();
+                    app.toasts.expire(Instant::now());
+                    app.autosave_if_due(Instant::now());
+                    // Summarize what fell out of the window while idle
+                    if app.state == AppState::Input
+                        && let Some(ref client) = ai_client
+                        && let Some((request, covers)) = app.start_summary()
+                    {
+                        let client_clone = client.clone();
+                        let tx_clone = tx.clone();
+                        let id = app.current_session_id.clone();
+                        tokio::spawn(async move {
+                            let response = client_clone.chat(&request).await;
+                            let _ = tx_clone.send(Event::SummaryResponse(response, id, covers));
+                        });
+                    }
+                }
+                Event::Mouse(mouse) => {
+                    let size = terminal.size()?;
+                    let panes =
+                        ui::create_main_layout(Rect::new(0, 0, size.width, size.height), app);
+                    app.handle_mouse_event(mouse, &panes);
+                }
+                Event::Resize(width, height) => {
+                    app.screen = Rect::new(0, 0, width, height);
+                }
+                Event::Focus(focused) => notifier.set_focus(focused),
+                Event::CommandOutputChunk(line) => {
+                    // Late chunks from a cancelled command are dropped
+                    if app.state == AppState::Executing {
+                        app.append_output_chunk(&line);
+                    }
                 }
-                Event::Resize(_, _) => {}
 
                 // 12.2: Thinking → ReviewAction/Input transition
                 Event::ApiResponse(response) => {
                     match response {
                         Ok(text) => {
-                            app.add_message(Message::model(&text));
+                            let parsed = ParsedResponse::parse(&text);
+                            app.add_message(
+                                Message::model(&text)
+                                    .took(app.phase_elapsed())
+                                    .with_tool_calls(parsed.tool_calls()),
+                            );
+                            app.start_reveal();
+
+                            app.tool_batch.clear();
+                            app.reasoning = ToolCall::reasoning(&text);
+
+                            // Pause a turn after max_tool_iterations tool calls
+                            let max = app.config.max_tool_iterations;
+                            let verdict = match parsed {
+                                ParsedResponse::TextResponse(_) => None,
+                                _ => app.loop_guard.count_iteration(max),
+                            };
+                            match verdict {
+                                Some(LoopVerdict::Nudge(note)) => {
+                                    app.add_message(Message::system(format!("⏸ {}", note)));
+                                    app.add_message(Message::user(loop_guard::limit_message(max)));
+                                    request_reply(app, ai_client.as_ref(), &tx);
+                                    continue;
+                                }
+                                Some(LoopVerdict::Stop(note)) => {
+                                    app.add_message(Message::system(format!("⏸ {}", note)));
+                                    app.transition(StateEvent::TextResponseReceived);
+                                    continue;
+                                }
+                                None => {}
+                            }
+                            if matches!(parsed, ParsedResponse::TextResponse(_))
+                                && app.loop_guard.paused(max)
+                            {
+                                app.add_message(Message::system(
+                                    "⏸ Paused at max_tool_iterations: reply to continue",
+                                ));
+                            }
+
+                            match parsed {
+                                ParsedResponse::MixedToolCalls(tools) => {
+                                    app.add_message(Message::system(format!(
+                                        "⛔ Refused {} tool calls mixing read-only and other tools",
+                                        tools.len()
+                                    )));
+                                    app.add_message(Message::tool(batch_refusal(&tools)));
+                                    request_reply(app, ai_client.as_ref(), &tx);
+                                }
+                                ParsedResponse::ToolCalls(mut tools) => {
+                                    if wsl::is_wsl() {
+                                        tools.iter_mut().for_each(|t| {
+                                            wsl::translate_tool_paths(t);
+                                        });
+                                    }
+                                    let list: Vec<String> =
+                                        tools.iter().map(describe_tool).collect();
+
+                                    // A repeated batch is caught like a repeated call
+                                    match app.loop_guard.check_batch(&tools) {
+                                        Some(LoopVerdict::Nudge(reason)) => {
+                                            let desc = list.join(", ");
+                                            app.add_message(Message::system(format!(
+                                                "🔁 Skipped repeated calls: {} ({})",
+                                                desc, reason
+                                            )));
+                                            app.add_message(Message::user(
+                                                loop_guard::nudge_message(&desc, &reason),
+                                            ));
+                                            request_reply(app, ai_client.as_ref(), &tx);
+                                            continue;
+                                        }
+                                        Some(LoopVerdict::Stop(reason)) => {
+                                            app.add_message(Message::system(format!(
+                                                "🔁 Stopped: the AI keeps proposing {} ({}). Try rephrasing the request.",
+                                                list.join(", "),
+                                                reason
+                                            )));
+                                            app.transition(StateEvent::TextResponseReceived);
+                                            continue;
+                                        }
+                                        None => {}
+                                    }
+
+                                    // Every call gets the checks a single call gets
+                                    let mut dangerous = false;
+                                    let mut reasons = Vec::new();
+                                    let mut refused = None;
+                                    for tool in tools.iter_mut() {
+                                        match screen_tool(
+                                            app,
+                                            tool,
+                                            &allowlist,
+                                            &interactive_detector,
+                                            &detector,
+                                            &path_guard,
+                                        ) {
+                                            Ok(screened) => {
+                                                if let Some(danger) = screened.danger {
+                                                    dangerous = true;
+                                                    reasons.extend(danger.reasons);
+                                                }
+                                            }
+                                            Err(refusal) => {
+                                                refused = Some(refusal);
+                                                break;
+                                            }
+                                        }
+                                    }
+                                    if let Some(refusal) = refused {
+                                        app.add_message(*refusal);
+                                        app.transition(StateEvent::TextResponseReceived);
+                                        continue;
+                                    }
+
+                                    app.set_action_text(&format!(
+                                        "Run {} read-only tools in parallel:\n{}",
+                                        tools.len(),
+                                        list.join("\n")
+                                    ));
+                                    app.current_tool = None;
+                                    app.dangerous_command_detected =
+                                        dangerous || tools.iter().any(ToolCall::has_dangerous_path);
+                                    app.danger_reasons = reasons;
+                                    app.tool_batch = tools;
+
+                                    // Batches are read-only, so any auto policy covers them
+                                    if !app.dangerous_command_detected
+                                        && app.config.approval_policy != ApprovalPolicy::AlwaysAsk
+                                    {
+                                        app.add_message(Message::system(format!(
+                                            "✓ Auto-approved ({}): {} read-only tools",
+                                            app.config.approval_policy.name(),
+                                            app.tool_batch.len()
+                                        )));
+                                        app.transition(StateEvent::AutoApprove);
+                                        execute_pending(terminal, events, app, &tx);
+                                        continue;
+                                    }
+                                    app.transition(StateEvent::ToolCallReceived);
+                                }
+                                ParsedResponse::ToolCall(mut tc) => {
+                                    // Under WSL, C:\ paths from the AI become /mnt/c paths
+                                    if wsl::is_wsl() {
+                                        wsl::translate_tool_paths(&mut tc);
+                                    }
 
-                            match ParsedResponse::parse(&text) {
-                                ParsedResponse::ToolCall(tc) => {
                                     // Format display text based on tool type
-                                    let display = match tc.tool.as_str() {
+                                    let mut display = match tc.tool.as_str() {
                                         "run_cmd" => tc.command.clone(),
                                         "run_python" => format!("python:\n{}", tc.code),
                                         "read_file" => format!("read_file: {}", tc.path),
@@ -778,6 +1928,20 @@ async fn run_loop(
                                                 &tc.directory
                                             }
                                         ),
+                                        "open_editor" => format!("open_editor: {}", tc.path),
+                                        "man_page" => format!("man_page: {}", tc.topic),
+                                        "check_command" if tc.versions => {
+                                            format!(
+                                                "check_command: {} (runs each with --version)",
+                                                tc.command
+                                            )
+                                        }
+                                        "check_command" => {
+                                            format!("check_command: {}", tc.command)
+                                        }
+                                        "query_json" => {
+                                            format!("query_json: {} {}", tc.path, tc.query)
+                                        }
                                         "mcp" => format!(
                                             "mcp: {}/{}\n{}",
                                             tc.server,
@@ -787,48 +1951,98 @@ async fn run_loop(
                                         _ => format!("{:?}", tc),
                                     };
 
-                                    // Check for interactive commands
-                                    if tc.is_run_cmd()
-                                        && interactive_detector.is_interactive(&tc.command)
-                                    {
-                                        let suggestion =
-                                            interactive_detector.suggestion(&tc.command).unwrap_or(
-                                                "This command requires an interactive terminal",
-                                            );
-                                        app.add_message(Message::model(format!(
-                                            "⚠️ Cannot run interactive command: `{}`\n{}",
-                                            tc.command, suggestion
-                                        )));
-                                        app.transition(StateEvent::TextResponseReceived);
-                                        continue;
+                                    // Don't let the model retry the same call forever
+                                    match app.loop_guard.check(&tc) {
+                                        Some(LoopVerdict::Nudge(reason)) => {
+                                            app.add_message(Message::system(format!(
+                                                "🔁 Skipped repeated call: {} ({})",
+                                                describe_tool(&tc),
+                                                reason
+                                            )));
+                                            app.add_message(Message::user(
+                                                loop_guard::nudge_message(
+                                                    &describe_tool(&tc),
+                                                    &reason,
+                                                ),
+                                            ));
+                                            request_reply(app, ai_client.as_ref(), &tx);
+                                            continue;
+                                        }
+                                        Some(LoopVerdict::Stop(reason)) => {
+                                            app.add_message(Message::system(format!(
+                                                "🔁 Stopped: the AI keeps proposing {} ({}). Try rephrasing the request.",
+                                                describe_tool(&tc),
+                                                reason
+                                            )));
+                                            app.transition(StateEvent::TextResponseReceived);
+                                            continue;
+                                        }
+                                        None => {}
                                     }
 
-                                    // Check Python availability
-                                    if tc.tool == "run_python" && !app.python_available {
-                                        app.add_message(Message::model(
-                                            "⚠️ Python is not available on this system.\nPlease install Python 3 to use this feature."
-                                        ));
-                                        app.transition(StateEvent::TextResponseReceived);
-                                        continue;
+                                    // Allowlist, interactive, danger and path checks
+                                    let screened = match screen_tool(
+                                        app,
+                                        &mut tc,
+                                        &allowlist,
+                                        &interactive_detector,
+                                        &detector,
+                                        &path_guard,
+                                    ) {
+                                        Ok(screened) => screened,
+                                        Err(refusal) => {
+                                            app.add_message(*refusal);
+                                            app.transition(StateEvent::TextResponseReceived);
+                                            continue;
+                                        }
+                                    };
+                                    let launches_tmux = screened.launches_tmux;
+                                    let danger = screened.danger;
+                                    if let Some(tmux_display) = screened.display {
+                                        display = tmux_display;
                                     }
 
                                     app.set_action_text(&display);
                                     app.current_tool = Some((*tc).clone());
 
                                     // Check for dangerous operations
-                                    app.dangerous_command_detected = tc.is_destructive()
-                                        || (tc.is_run_cmd() && detector.is_dangerous(&tc.command));
+                                    app.dangerous_command_detected =
+                                        tc.is_destructive() || danger.is_some();
+                                    app.danger_reasons =
+                                        danger.map(|d| d.reasons).unwrap_or_default();
+                                    app.check_write_conflict();
 
                                     // Block unknown tools entirely
                                     if !tc.is_allowed_tool() {
                                         app.add_message(Message::system(format!(
-                                            "⛔ Blocked unknown tool: '{}'\nAllowed: run_cmd, read_file, write_file, search, run_python",
+                                            "⛔ Blocked unknown tool: '{}'\nAllowed: run_cmd, read_file, write_file, search, run_python, open_editor, man_page, check_command, query_json",
                                             tc.tool
                                         )));
                                         app.transition(StateEvent::TextResponseReceived);
                                         continue;
                                     }
 
+                                    // The approval policy may skip review for safe calls;
+                                    // tmux launches and sudo always get a look first
+                                    let policy = app.config.approval_policy;
+                                    let always_review = launches_tmux
+                                        || (tc.is_run_cmd() && executor::uses_sudo(&tc.command));
+                                    if !always_review
+                                        && policy.auto_approves(
+                                            &tc,
+                                            allowlist.is_enabled(),
+                                            app.dangerous_command_detected,
+                                        )
+                                    {
+                                        app.add_message(Message::system(format!(
+                                            "✓ Auto-approved ({}): {}",
+                                            policy.name(),
+                                            describe_tool(&tc)
+                                        )));
+                                        app.transition(StateEvent::AutoApprove);
+                                        execute_pending(terminal, events, app, &tx);
+                                        continue;
+                                    }
                                     app.transition(StateEvent::ToolCallReceived);
                                 }
                                 _ => {
@@ -846,45 +2060,98 @@ async fn run_loop(
                 // 12.5: Executing → Finalizing → Input loop
                 Event::CommandComplete(result) => {
                     app.running_task = None;
-                    app.execution_output = if result.success {
-                        result.stdout.clone()
-                    } else {
-                        format!("{}\n{}", result.stdout, result.stderr)
+                    notifier.command_finished(result.exit_code);
+                    app.loop_guard.record_result(result.success);
+                    app.last_spool = result.spool.clone();
+                    let (output_artifact, stored) =
+                        store_artifacts(app.current_tool.as_ref(), &result);
+                    if let Some(id) = output_artifact {
+                        app.add_message(Message::system(format!(
+                            "📄 Output truncated for the AI; /artifact {} or Ctrl+G shows all of it",
+                            id
+                        )));
+                    } else if result.truncated && result.spool.is_some() {
+                        app.add_message(Message::system(
+                            "📄 Output truncated for the AI; /output or Ctrl+G shows all of it",
+                        ));
+                    }
+                    app.execution_output = match result.signal_reason() {
+                        Some(reason) => format!("{}\n{}", reason, command_output(&result)),
+                        None => command_output(&result),
                     };
 
                     let tool_desc = app
                         .current_tool
                         .as_ref()
-                        .map(|t| {
-                            format!(
-                                "{}: {}",
-                                t.tool,
-                                if t.tool == "run_cmd" {
-                                    &t.command
-                                } else {
-                                    &t.path
-                                }
-                            )
-                        })
+                        .map(describe_tool)
                         .unwrap_or_default();
 
-                    let feedback = format!(
-                        "Tool: {}\nExit code: {}\nOutput:\n{}",
-                        tool_desc, result.exit_code, &app.execution_output
+                    if let Some(ref tool) = app.current_tool
+                        && (tool.is_read_file() || tool.is_write_file())
+                    {
+                        let path = app.cwd.join(&tool.path);
+                        app.write_guard.record(&path);
+                    }
+
+                    show_tool_images(app, &result);
+
+                    let section = tool_feedback(&tool_desc, &result);
+                    let feedback = match app.write_conflict.take() {
+                        Some(conflict) => format!(
+                            "Note: {}; the user confirmed overwriting it.\n\n{}",
+                            conflict, section
+                        ),
+                        None => section.clone(),
+                    };
+                    let runs = app
+                        .current_tool
+                        .iter()
+                        .map(|tool| ToolRun::new(tool.clone(), &result).with_section(&section))
+                        .collect();
+                    app.add_message(
+                        Message::tool(&feedback)
+                            .took(app.phase_elapsed())
+                            .with_artifacts(stored)
+                            .with_tool_runs(runs),
                     );
-                    app.add_message(Message::user(&feedback));
                     app.transition(StateEvent::CommandComplete);
 
                     // Send to AI for analysis
-                    if let Some(ref client) = ai_client {
-                        let messages = app.messages.clone();
-                        let client_clone = client.clone();
-                        let tx_clone = tx.clone();
-                        tokio::spawn(async move {
-                            let response = client_clone.chat(&messages).await;
-                            let _ = tx_clone.send(Event::ApiResponse(response));
-                        });
-                    } else {
+                    if !request_reply(app, ai_client.as_ref(), &tx) {
+                        app.transition(StateEvent::AnalysisComplete);
+                    }
+                }
+
+                Event::ToolBatchComplete(results) => {
+                    app.running_task = None;
+                    app.loop_guard
+                        .record_result(results.iter().all(|(_, result)| result.success));
+                    for (tool, _) in results.iter().filter(|(t, _)| t.is_read_file()) {
+                        let path = app.cwd.join(&tool.path);
+                        app.write_guard.record(&path);
+                    }
+                    let runs: Vec<ToolRun> = results
+                        .iter()
+                        .map(|(tool, result)| {
+                            ToolRun::new(tool.clone(), result)
+                                .with_section(tool_feedback(&describe_tool(tool), result))
+                        })
+                        .collect();
+                    let feedback = runs
+                        .iter()
+                        .map(|run| run.section.as_str())
+                        .collect::<Vec<_>>()
+                        .join("\n\n");
+                    app.execution_output = feedback.clone();
+                    app.add_message(
+                        Message::tool(&feedback)
+                            .took(app.phase_elapsed())
+                            .with_tool_runs(runs),
+                    );
+                    app.transition(StateEvent::CommandComplete);
+
+                    // Send to AI for analysis
+                    if !request_reply(app, ai_client.as_ref(), &tx) {
                         app.transition(StateEvent::AnalysisComplete);
                     }
                 }
@@ -893,6 +2160,27 @@ async fn run_loop(
                     // Task was cancelled, already handled in key event
                 }
 
+                Event::SummaryResponse(result, id, covers) => match result {
+                    Ok(text) => app.finish_summary(&id, text, covers),
+                    Err(e) => {
+                        app.summarizing = false;
+                        app.toast(format!("✗ Failed to summarize older messages: {}", e));
+                    }
+                },
+                Event::ExplainResponse(result) => {
+                    app.explaining = false;
+                    // Shown as a system message so it isn't sent back to the AI
+                    match result {
+                        Ok(text) => app.add_message(Message::system(format!(
+                            "💡 Explanation:\n{}",
+                            text.trim()
+                        ))),
+                        Err(e) => {
+                            app.add_message(Message::system(format!("✗ Failed to explain: {}", e)))
+                        }
+                    }
+                }
+
                 Event::ModelsResponse(result, model_arg) => {
                     match result {
                         Ok(models) => {
@@ -903,16 +2191,10 @@ async fn run_loop(
                                 {
                                     if let Some(ref mut client) = ai_client {
                                         client.set_model(matched.clone());
-                                        app.add_message(Message::system(format!(
-                                            "✓ Switched to: {}",
-                                            matched
-                                        )));
+                                        app.toast(format!("✓ Switched to: {}", matched));
                                     }
                                 } else {
-                                    app.add_message(Message::system(format!(
-                                        "✗ Model '{}' not found",
-                                        model_name
-                                    )));
+                                    app.toast(format!("✗ Model '{}' not found", model_name));
                                 }
                             } else {
                                 // List all models
@@ -936,14 +2218,26 @@ async fn run_loop(
                             }
                         }
                         Err(e) => {
-                            app.add_message(Message::system(format!(
-                                "✗ Failed to fetch models: {}",
-                                e
-                            )));
+                            app.toast(format!("✗ Failed to fetch models: {}", e));
                         }
                     }
                 }
 
+                Event::TargetConnected(result) => app.finish_target(result),
+
+                Event::CheckpointFailed(e) => {
+                    app.add_message(Message::system(format!("⚠ Git checkpoint failed: {}", e)));
+                }
+
+                Event::SudoChecked(tool, needs_password) => {
+                    app.running_task = None;
+                    if needs_password {
+                        app.request_sudo_password(*tool);
+                    } else {
+                        spawn_tool(app, *tool, None, &tx);
+                    }
+                }
+
                 Event::McpResult(result, server, tool_name) => {
                     app.running_task = None;
                     match result {
@@ -953,19 +2247,22 @@ async fn run_loop(
                                 "Tool: mcp/{}/{}\nOutput:\n{}",
                                 server, tool_name, output
                             );
-                            app.add_message(Message::user(&feedback));
+                            let runs = app
+                                .current_tool
+                                .iter()
+                                .map(|tool| {
+                                    ToolRun::without_exit_code(tool.clone()).with_section(&feedback)
+                                })
+                                .collect();
+                            app.add_message(
+                                Message::tool(&feedback)
+                                    .took(app.phase_elapsed())
+                                    .with_tool_runs(runs),
+                            );
                             app.transition(StateEvent::CommandComplete);
 
                             // Send to AI for analysis
-                            if let Some(ref client) = ai_client {
-                                let messages = app.messages.clone();
-                                let client_clone = client.clone();
-                                let tx_clone = tx.clone();
-                                tokio::spawn(async move {
-                                    let response = client_clone.chat(&messages).await;
-                                    let _ = tx_clone.send(Event::ApiResponse(response));
-                                });
-                            } else {
+                            if !request_reply(app, ai_client.as_ref(), &tx) {
                                 app.transition(StateEvent::AnalysisComplete);
                             }
                         }
@@ -0,0 +1,685 @@
+//! Markdown rendering for AI messages
+//!
+//! Turns CommonMark (plus tables, strikethrough and task lists) into
+//! ratatui lines wrapped to the chat pane. Wrapping works on styled spans
+//! at word boundaries by display width, and nested blocks (lists, block
+//! quotes) repeat their prefix on every wrapped line.
+
+use pulldown_cmark::{Alignment, CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::theme::Palette;
+
+/// Render `text` as lines at most `width` columns wide
+pub fn render(text: &str, width: usize, base: Style, palette: &Palette) -> Vec<Line<'static>> {
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut renderer = Renderer::new(width.max(1), base, *palette);
+    for event in Parser::new_ext(text, options) {
+        renderer.event(event);
+    }
+    renderer.flush();
+    renderer.lines
+}
+
+/// Contents of the fenced and indented code blocks in `text`, in order
+pub fn code_blocks(text: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut current: Option<String> = None;
+    for event in Parser::new(text) {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => current = Some(String::new()),
+            Event::Text(code) => {
+                if let Some(ref mut block) = current {
+                    block.push_str(&code);
+                }
+            }
+            Event::End(TagEnd::CodeBlock) => blocks.extend(current.take()),
+            _ => {}
+        }
+    }
+    blocks
+}
+
+/// Prefix a block puts in front of its lines
+struct Prefix {
+    /// On the block's first line (e.g. "• ")
+    first: Span<'static>,
+    /// On the lines after it (e.g. "  ")
+    rest: Span<'static>,
+    used: bool,
+}
+
+/// A table being collected, rendered once complete
+struct Table {
+    alignments: Vec<Alignment>,
+    rows: Vec<Vec<Vec<Span<'static>>>>,
+    header_rows: usize,
+}
+
+struct Renderer {
+    width: usize,
+    base: Style,
+    palette: Palette,
+    lines: Vec<Line<'static>>,
+    /// Inline styles in effect; the last one applies
+    styles: Vec<Style>,
+    /// Text of the current paragraph, heading or list item
+    inline: Vec<Span<'static>>,
+    prefixes: Vec<Prefix>,
+    /// Next number of each open list (None = bulleted)
+    lists: Vec<Option<u64>>,
+    /// Destination and start (in `inline`) of the open link
+    link: Option<(String, usize)>,
+    /// Language and text of the open code block
+    code: Option<(String, String)>,
+    table: Option<Table>,
+    /// A blank line goes before the next block
+    gap: bool,
+}
+
+impl Renderer {
+    fn new(width: usize, base: Style, palette: Palette) -> Self {
+        Self {
+            width,
+            base,
+            palette,
+            lines: Vec::new(),
+            styles: vec![base],
+            inline: Vec::new(),
+            prefixes: Vec::new(),
+            lists: Vec::new(),
+            link: None,
+            code: None,
+            table: None,
+            gap: false,
+        }
+    }
+
+    fn style(&self) -> Style {
+        *self.styles.last().unwrap_or(&self.base)
+    }
+
+    fn push_style(&mut self, f: impl FnOnce(Style) -> Style) {
+        let style = f(self.style());
+        self.styles.push(style);
+    }
+
+    fn pop_style(&mut self) {
+        if self.styles.len() > 1 {
+            self.styles.pop();
+        }
+    }
+
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) => match self.code {
+                Some((_, ref mut code)) => code.push_str(&text),
+                None => self.text(&text, self.style()),
+            },
+            Event::Code(code) => self.text(&code, self.base.fg(self.palette.success)),
+            Event::InlineHtml(html) | Event::Html(html) => self.text(&html, self.style()),
+            Event::SoftBreak => self.text(" ", self.style()),
+            Event::HardBreak => self.flush(),
+            Event::Rule => {
+                self.start_block();
+                let width = self.width.saturating_sub(self.prefix_width());
+                let rule = Span::styled("─".repeat(width), Style::default().fg(self.palette.muted));
+                self.emit(vec![rule]);
+                self.gap = true;
+            }
+            Event::TaskListMarker(done) => {
+                let marker = if done { "☑ " } else { "☐ " };
+                self.text(marker, self.base.fg(self.palette.accent));
+            }
+            _ => {}
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph => self.start_block(),
+            Tag::Heading { level, .. } => {
+                self.start_block();
+                let color = self.palette.accent;
+                self.push_style(|s| {
+                    let s = s.fg(color).add_modifier(Modifier::BOLD);
+                    if level == HeadingLevel::H1 {
+                        s.add_modifier(Modifier::UNDERLINED)
+                    } else {
+                        s
+                    }
+                });
+            }
+            Tag::BlockQuote(_) => {
+                self.flush();
+                self.start_block();
+                let bar = Span::styled("▎ ", Style::default().fg(self.palette.muted));
+                self.prefixes.push(Prefix {
+                    first: bar.clone(),
+                    rest: bar,
+                    used: false,
+                });
+                self.push_style(|s| s.add_modifier(Modifier::ITALIC));
+            }
+            Tag::CodeBlock(kind) => {
+                self.flush();
+                self.start_block();
+                let lang = match kind {
+                    CodeBlockKind::Fenced(lang) => {
+                        lang.split_whitespace().next().unwrap_or("").to_string()
+                    }
+                    CodeBlockKind::Indented => String::new(),
+                };
+                self.code = Some((lang, String::new()));
+            }
+            Tag::List(start) => {
+                self.flush();
+                if self.lists.is_empty() {
+                    self.start_block();
+                }
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                self.flush();
+                self.start_block();
+                let depth = self.lists.len().saturating_sub(1);
+                let marker = match self.lists.last_mut() {
+                    Some(Some(n)) => {
+                        *n += 1;
+                        format!("{}. ", *n - 1)
+                    }
+                    _ => format!("{} ", ["•", "◦", "▪"][depth % 3]),
+                };
+                let indent = " ".repeat(marker.width());
+                self.prefixes.push(Prefix {
+                    first: Span::styled(marker, self.base.fg(self.palette.accent)),
+                    rest: Span::raw(indent),
+                    used: false,
+                });
+            }
+            Tag::Emphasis => self.push_style(|s| s.add_modifier(Modifier::ITALIC)),
+            Tag::Strong => {
+                let color = self.palette.highlight;
+                self.push_style(|s| s.fg(color).add_modifier(Modifier::BOLD));
+            }
+            Tag::Strikethrough => self.push_style(|s| s.add_modifier(Modifier::CROSSED_OUT)),
+            Tag::Link { dest_url, .. } => {
+                self.link = Some((dest_url.to_string(), self.inline.len()));
+                let color = self.palette.info;
+                self.push_style(|s| s.fg(color).add_modifier(Modifier::UNDERLINED));
+            }
+            Tag::Image { .. } => self.text("[image: ", self.base.fg(self.palette.muted)),
+            Tag::Table(alignments) => {
+                self.flush();
+                self.start_block();
+                self.table = Some(Table {
+                    alignments,
+                    rows: Vec::new(),
+                    header_rows: 0,
+                });
+            }
+            Tag::TableHead | Tag::TableRow => {
+                if let Some(ref mut table) = self.table {
+                    table.rows.push(Vec::new());
+                }
+            }
+            Tag::TableCell => self.inline.clear(),
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph => {
+                self.flush();
+                self.gap = true;
+            }
+            TagEnd::Heading(_) => {
+                self.flush();
+                self.pop_style();
+                self.gap = true;
+            }
+            TagEnd::BlockQuote(_) => {
+                self.flush();
+                self.prefixes.pop();
+                self.pop_style();
+                self.gap = true;
+            }
+            TagEnd::CodeBlock => {
+                if let Some((lang, code)) = self.code.take() {
+                    self.code_block(&lang, &code);
+                }
+                self.gap = true;
+            }
+            TagEnd::List(_) => {
+                self.flush();
+                self.lists.pop();
+                self.gap = self.lists.is_empty();
+            }
+            TagEnd::Item => {
+                self.flush();
+                self.prefixes.pop();
+            }
+            TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough => self.pop_style(),
+            TagEnd::Link => {
+                self.pop_style();
+                if let Some((url, start)) = self.link.take() {
+                    let text: String = self.inline[start..]
+                        .iter()
+                        .map(|s| s.content.as_ref())
+                        .collect();
+                    if text != url && !url.is_empty() {
+                        self.text(&format!(" ({})", url), self.base.fg(self.palette.muted));
+                    }
+                }
+            }
+            TagEnd::Image => self.text("]", self.base.fg(self.palette.muted)),
+            TagEnd::TableCell => {
+                let cell = std::mem::take(&mut self.inline);
+                if let Some(row) = self.table.as_mut().and_then(|t| t.rows.last_mut()) {
+                    row.push(cell);
+                }
+            }
+            TagEnd::TableHead => {
+                if let Some(ref mut table) = self.table {
+                    table.header_rows = table.rows.len();
+                }
+            }
+            TagEnd::Table => {
+                if let Some(table) = self.table.take() {
+                    self.render_table(table);
+                }
+                self.gap = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn text(&mut self, text: &str, style: Style) {
+        self.inline.push(Span::styled(text.to_string(), style));
+    }
+
+    /// Put the blank line between blocks, if one is due
+    fn start_block(&mut self) {
+        if self.gap && !self.lines.is_empty() {
+            let prefix: Vec<Span<'static>> = self
+                .prefixes
+                .iter()
+                .filter(|p| p.used)
+                .map(|p| Span::styled(p.rest.content.trim_end().to_string(), p.rest.style))
+                .collect();
+            self.lines.push(Line::from(prefix));
+        }
+        self.gap = false;
+    }
+
+    fn prefix_width(&self) -> usize {
+        self.prefixes.iter().map(|p| p.first.width()).sum()
+    }
+
+    /// Add a line behind the prefixes of the enclosing blocks
+    fn emit(&mut self, spans: Vec<Span<'static>>) {
+        let mut line: Vec<Span<'static>> = Vec::new();
+        for prefix in &mut self.prefixes {
+            line.push(if prefix.used {
+                prefix.rest.clone()
+            } else {
+                prefix.first.clone()
+            });
+            prefix.used = true;
+        }
+        line.extend(spans);
+        self.lines.push(Line::from(line));
+    }
+
+    /// Wrap and emit the collected inline text
+    fn flush(&mut self) {
+        if self.inline.is_empty() {
+            return;
+        }
+        let spans = std::mem::take(&mut self.inline);
+        let width = self.width.saturating_sub(self.prefix_width()).max(1);
+        for line in wrap(spans, width) {
+            self.emit(line);
+        }
+    }
+
+    fn code_block(&mut self, lang: &str, code: &str) {
+        let frame = Style::default().fg(self.palette.muted);
+        let code_style = self.base.fg(self.palette.success);
+        let header = if lang.is_empty() {
+            "╭─".to_string()
+        } else {
+            format!("╭─ {}", lang)
+        };
+        self.emit(vec![Span::styled(header, frame)]);
+        let width = self.width.saturating_sub(self.prefix_width() + 2).max(1);
+        for line in code.trim_end_matches('\n').split('\n') {
+            // Code keeps its spacing, so long lines are cut rather than reflowed
+            for chunk in split_width(&line.replace('\t', "    "), width) {
+                self.emit(vec![
+                    Span::styled("│ ", frame),
+                    Span::styled(chunk, code_style),
+                ]);
+            }
+        }
+        self.emit(vec![Span::styled("╰─", frame)]);
+    }
+
+    fn render_table(&mut self, table: Table) {
+        let columns = table.rows.iter().map(Vec::len).max().unwrap_or(0);
+        if columns == 0 {
+            return;
+        }
+        let cell_text = |cell: &Vec<Span<'static>>| -> String {
+            cell.iter().map(|s| s.content.as_ref()).collect::<String>()
+        };
+        let mut widths = vec![1; columns];
+        for row in &table.rows {
+            for (i, cell) in row.iter().enumerate() {
+                widths[i] = widths[i].max(cell_text(cell).width());
+            }
+        }
+
+        // Shrink the widest columns until the table fits
+        let available = self
+            .width
+            .saturating_sub(self.prefix_width() + 3 * (columns - 1));
+        while widths.iter().sum::<usize>() > available {
+            let widest = (0..columns).max_by_key(|&i| widths[i]).unwrap_or(0);
+            if widths[widest] <= 3 {
+                break;
+            }
+            widths[widest] -= 1;
+        }
+
+        let separator = Style::default().fg(self.palette.muted);
+        for (r, row) in table.rows.iter().enumerate() {
+            let mut spans = Vec::new();
+            for (i, &width) in widths.iter().enumerate() {
+                if i > 0 {
+                    spans.push(Span::styled(" │ ", separator));
+                }
+                let text = row.get(i).map(cell_text).unwrap_or_default();
+                let style = match row.get(i).and_then(|c| c.first()) {
+                    Some(span) if r >= table.header_rows => span.style,
+                    Some(span) => span.style.add_modifier(Modifier::BOLD),
+                    None => self.base,
+                };
+                let text = fit(&text, width);
+                let pad = width - text.width();
+                let (left, right) = match table.alignments.get(i) {
+                    Some(Alignment::Right) => (pad, 0),
+                    Some(Alignment::Center) => (pad / 2, pad - pad / 2),
+                    _ => (0, pad),
+                };
+                spans.push(Span::styled(
+                    format!("{}{}{}", " ".repeat(left), text, " ".repeat(right)),
+                    style,
+                ));
+            }
+            self.emit(spans);
+            if r + 1 == table.header_rows {
+                let rule: Vec<String> = widths.iter().map(|&w| "─".repeat(w)).collect();
+                self.emit(vec![Span::styled(rule.join("─┼─"), separator)]);
+            }
+        }
+    }
+}
+
+/// Cut `text` to `width` columns, marking the cut with "…"
+fn fit(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut out = String::new();
+    let mut used = 0;
+    for (g, w) in graphemes(text) {
+        if used + w + 1 > width {
+            break;
+        }
+        out.push_str(g);
+        used += w;
+    }
+    out.push('…');
+    out
+}
+
+/// Grapheme clusters of `text` with their width in columns
+///
+/// Wrapping by cluster keeps combining marks and emoji sequences whole and
+/// counts double-width (CJK) characters as two columns.
+fn graphemes(text: &str) -> impl Iterator<Item = (&str, usize)> {
+    text.graphemes(true).map(|g| (g, g.width()))
+}
+
+/// Split `text` into pieces at most `width` columns wide
+pub fn split_width(text: &str, width: usize) -> Vec<String> {
+    let mut pieces = vec![String::new()];
+    let mut used = 0;
+    for (g, w) in graphemes(text) {
+        if used + w > width && used > 0 {
+            pieces.push(String::new());
+            used = 0;
+        }
+        pieces.last_mut().expect("never empty").push_str(g);
+        used += w;
+    }
+    pieces
+}
+
+/// Word-wrap styled spans to `width` columns
+///
+/// Breaks at whitespace; words longer than a line are split. Whitespace at
+/// a break is dropped.
+pub fn wrap(spans: Vec<Span<'static>>, width: usize) -> Vec<Vec<Span<'static>>> {
+    let mut lines: Vec<Vec<Span<'static>>> = Vec::new();
+    let mut line: Vec<Span<'static>> = Vec::new();
+    let mut used = 0;
+    // Whitespace seen since the last word, placed only if a word follows on the same line
+    let mut space: Vec<Span<'static>> = Vec::new();
+
+    fn push(line: &mut Vec<Span<'static>>, text: &str, style: Style) {
+        match line.last_mut() {
+            Some(last) if last.style == style => last.content.to_mut().push_str(text),
+            _ => line.push(Span::styled(text.to_string(), style)),
+        }
+    }
+
+    for span in spans {
+        let style = span.style;
+        for token in tokens(&span.content) {
+            if token.chars().all(char::is_whitespace) {
+                if used > 0 {
+                    space.push(Span::styled(token.to_string(), style));
+                }
+                continue;
+            }
+            let space_width: usize = space.iter().map(|s| s.width()).sum();
+            let word_width = token.width();
+            if used > 0 && used + space_width + word_width > width {
+                lines.push(std::mem::take(&mut line));
+                used = 0;
+                space.clear();
+            }
+            for s in space.drain(..) {
+                used += s.width();
+                push(&mut line, &s.content, s.style);
+            }
+            if word_width <= width - used.min(width) {
+                push(&mut line, token, style);
+                used += word_width;
+                continue;
+            }
+            // Longer than a line: split it
+            for (g, w) in graphemes(token) {
+                if used + w > width && used > 0 {
+                    lines.push(std::mem::take(&mut line));
+                    used = 0;
+                }
+                push(&mut line, g, style);
+                used += w;
+            }
+        }
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Alternating runs of whitespace and non-whitespace
+fn tokens(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut in_space = None;
+    for (i, c) in text.char_indices() {
+        let space = c.is_whitespace();
+        if in_space.is_some_and(|s| s != space) {
+            tokens.push(&text[start..i]);
+            start = i;
+        }
+        in_space = Some(space);
+    }
+    if start < text.len() {
+        tokens.push(&text[start..]);
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn plain(lines: &[Line]) -> Vec<String> {
+        lines
+            .iter()
+            .map(|l| l.spans.iter().map(|s| s.content.as_ref()).collect())
+            .collect()
+    }
+
+    // **Feature: Sabi-TUI, Property: Markdown Blocks Rendered**
+    #[test]
+    fn test_render_blocks() {
+        let text = "# Title\n\nSome **bold** and `code`.\n\n- one\n  - nested\n- two\n\n1. first\n2. second\n\n> quoted\n\n```rust\nfn main() {}\n```";
+        let lines = plain(&render(text, 40, Style::default(), &Palette::dark()));
+        assert_eq!(
+            lines,
+            vec![
+                "Title",
+                "",
+                "Some bold and code.",
+                "",
+                "• one",
+                "  ◦ nested",
+                "• two",
+                "",
+                "1. first",
+                "2. second",
+                "",
+                "▎ quoted",
+                "",
+                "╭─ rust",
+                "│ fn main() {}",
+                "╰─",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_styles_and_links() {
+        let lines = render(
+            "**bold** [docs](https://x.dev) ~~old~~",
+            80,
+            Style::default(),
+            &Palette::dark(),
+        );
+        let spans = &lines[0].spans;
+        assert!(spans[0].style.add_modifier.contains(Modifier::BOLD));
+        assert_eq!(plain(&lines), vec!["bold docs (https://x.dev) old"]);
+        let old = spans.iter().find(|s| s.content == "old").unwrap();
+        assert!(old.style.add_modifier.contains(Modifier::CROSSED_OUT));
+    }
+
+    #[test]
+    fn test_table() {
+        let text = "| Name | Size |\n|------|-----:|\n| a | 1 |\n| long name | 200 |";
+        assert_eq!(
+            plain(&render(text, 80, Style::default(), &Palette::dark())),
+            vec![
+                "Name      │ Size",
+                "──────────┼─────",
+                "a         │    1",
+                "long name │  200",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_list_item_wraps_under_its_text() {
+        let lines = plain(&render(
+            "- alpha beta gamma delta",
+            12,
+            Style::default(),
+            &Palette::dark(),
+        ));
+        assert_eq!(lines, vec!["• alpha beta", "  gamma", "  delta"]);
+    }
+
+    // **Feature: Sabi-TUI, Property: Wrapped Lines Fit the Width**
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(100))]
+
+        #[test]
+        fn prop_wrapped_lines_fit(
+            words in prop::collection::vec("[a-zA-Z0-9界]{1,15}", 1..30),
+            width in 5usize..40,
+        ) {
+            let text = words.join(" ");
+            let lines = wrap(vec![Span::raw(text.clone())], width);
+            for line in &lines {
+                let w: usize = line.iter().map(|s| s.width()).sum();
+                prop_assert!(w <= width, "line too wide: {} > {}", w, width);
+            }
+            // Only whitespace is dropped
+            let joined: String = lines
+                .iter()
+                .flat_map(|l| l.iter().map(|s| s.content.to_string()))
+                .collect();
+            prop_assert_eq!(joined.replace(' ', ""), text.replace(' ', ""));
+        }
+    }
+
+    #[test]
+    fn test_wrap_keeps_graphemes_whole() {
+        // "é" as e + combining accent, and a family emoji joined with ZWJ
+        let accent = "e\u{301}";
+        let family = "👨\u{200d}👩\u{200d}👧";
+        let text = format!("{}{}{}{}", accent, accent, family, accent);
+        for piece in split_width(&text, 2) {
+            assert!(piece.width() <= 2, "{:?} too wide", piece);
+            assert!(!piece.starts_with('\u{301}') && !piece.starts_with('\u{200d}'));
+        }
+
+        // Double-width characters take two columns each
+        assert_eq!(
+            split_width("日本語テキスト", 4),
+            vec!["日本", "語テ", "キス", "ト"]
+        );
+        assert_eq!(fit("日本語", 4), "日…");
+    }
+
+    #[test]
+    fn test_code_blocks_extracted_in_order() {
+        let text = "Run:\n\n```bash\nls -la\ncd /tmp\n```\n\nthen\n\n    indented\n\nand `inline`.";
+        assert_eq!(code_blocks(text), vec!["ls -la\ncd /tmp\n", "indented\n"]);
+        assert!(code_blocks("no code here").is_empty());
+    }
+}
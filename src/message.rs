@@ -3,8 +3,13 @@
 //! Defines Message and MessageRole for AI conversation tracking,
 //! with serialization support for the Gemini API format.
 
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 
+use crate::executor::CommandResult;
+use crate::tool_call::ToolCall;
+
 /// Role of a message in the conversation
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -15,6 +20,8 @@ pub enum MessageRole {
     Model,
     /// System instructions (not sent as regular content)
     System,
+    /// Tool output fed back to the AI (sent as user content)
+    Tool,
 }
 
 /// Image data for multimodal messages
@@ -26,6 +33,71 @@ pub struct ImageData {
     pub mime_type: String,
 }
 
+/// One tool's part of a tool result message
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ToolOutput<'a> {
+    /// What ran, from the "Tool: " line (empty for notes before the first)
+    pub tool: &'a str,
+    /// From the "Exit code: " line; MCP tools have none
+    pub exit_code: Option<i32>,
+    /// Output and any other lines, without trailing blank lines
+    pub lines: Vec<&'a str>,
+}
+
+/// A tool run reported by a tool result message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolRun {
+    /// The call that ran
+    pub call: ToolCall,
+    /// Exit code; MCP tools have none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// Signal that terminated the command
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signal: Option<i32>,
+    /// Whether the output the AI got was cut short
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    /// This run's "Tool: " section of the message, as the AI got it
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub section: String,
+}
+
+impl ToolRun {
+    /// Run of `call` that ended with `result`
+    pub fn new(call: ToolCall, result: &CommandResult) -> Self {
+        Self {
+            call,
+            exit_code: Some(result.exit_code),
+            signal: result.signal,
+            truncated: result.truncated,
+            section: String::new(),
+        }
+    }
+
+    /// Run of a tool that has no exit code (MCP)
+    pub fn without_exit_code(call: ToolCall) -> Self {
+        Self {
+            call,
+            exit_code: None,
+            signal: None,
+            truncated: false,
+            section: String::new(),
+        }
+    }
+
+    /// Record the section of the tool result message this run wrote
+    pub fn with_section(mut self, section: impl Into<String>) -> Self {
+        self.section = section.into();
+        self
+    }
+
+    /// Whether the run succeeded (tools without an exit code always do)
+    pub fn succeeded(&self) -> bool {
+        self.exit_code.is_none_or(|code| code == 0) && self.signal.is_none()
+    }
+}
+
 /// A single message in the conversation history
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Message {
@@ -36,6 +108,22 @@ pub struct Message {
     /// Optional image attachment
     #[serde(skip_serializing_if = "Option::is_none")]
     pub image: Option<ImageData>,
+    /// When the message was added (RFC 3339, local time)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<String>,
+    /// How long the AI thought about a reply or a tool ran, in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    /// Artifacts with the complete text this message only has part of: a
+    /// truncated command output or a file a tool wrote
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub artifacts: Vec<String>,
+    /// Tool calls a reply makes, as parsed from it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// Tool runs a tool result reports, in the order of its sections
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_runs: Vec<ToolRun>,
 }
 
 impl Message {
@@ -45,6 +133,56 @@ impl Message {
             role,
             content: content.into(),
             image: None,
+            time: Some(chrono::Local::now().to_rfc3339()),
+            duration_ms: None,
+            artifacts: Vec::new(),
+            tool_calls: Vec::new(),
+            tool_runs: Vec::new(),
+        }
+    }
+
+    /// Refer to artifacts with the complete text behind this message
+    pub fn with_artifacts(mut self, ids: Vec<String>) -> Self {
+        self.artifacts = ids;
+        self
+    }
+
+    /// Record the tool calls a reply makes
+    pub fn with_tool_calls(mut self, calls: Vec<ToolCall>) -> Self {
+        self.tool_calls = calls;
+        self
+    }
+
+    /// Record the tool runs a tool result reports
+    pub fn with_tool_runs(mut self, runs: Vec<ToolRun>) -> Self {
+        self.tool_runs = runs;
+        self
+    }
+
+    /// Record how long producing this message took
+    pub fn took(mut self, duration: Duration) -> Self {
+        self.duration_ms = Some(duration.as_millis() as u64);
+        self
+    }
+
+    /// "14:03:22 · thought for 6.2s" for the chat, if anything is known
+    pub fn timing(&self) -> Option<String> {
+        let time = self
+            .time
+            .as_deref()
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.format("%H:%M:%S").to_string());
+        let took = self.duration_ms.map(|ms| {
+            let verb = if self.role == MessageRole::Model {
+                "thought for"
+            } else {
+                "ran in"
+            };
+            format!("{} {:.1}s", verb, ms as f64 / 1000.0)
+        });
+        match (time, took) {
+            (Some(time), Some(took)) => Some(format!("{} · {}", time, took)),
+            (time, took) => time.or(took),
         }
     }
 
@@ -56,9 +194,8 @@ impl Message {
     /// Create a user message with image
     pub fn user_with_image(content: impl Into<String>, image: ImageData) -> Self {
         Self {
-            role: MessageRole::User,
-            content: content.into(),
             image: Some(image),
+            ..Self::user(content)
         }
     }
 
@@ -67,10 +204,131 @@ impl Message {
         Self::new(MessageRole::Model, content)
     }
 
+    /// Create a tool result message
+    pub fn tool(content: impl Into<String>) -> Self {
+        Self::new(MessageRole::Tool, content)
+    }
+
     /// Create a system message
     pub fn system(content: impl Into<String>) -> Self {
         Self::new(MessageRole::System, content)
     }
+
+    /// Create a system message showing an image in the chat
+    pub fn system_with_image(content: impl Into<String>, image: ImageData) -> Self {
+        Self {
+            image: Some(image),
+            ..Self::system(content)
+        }
+    }
+
+    /// Whether this is the system prompt with the tool definitions
+    pub fn is_system_prompt(&self) -> bool {
+        self.role == MessageRole::System && self.content.contains("MUST use tools")
+    }
+
+    /// Whether this is a reply that calls tools
+    ///
+    /// Only known for replies recorded since tool calls were stored.
+    pub fn is_tool_call(&self) -> bool {
+        !self.tool_calls.is_empty()
+    }
+
+    /// Whether this is tool output fed back to the AI ("Tool: ...\nExit code: ...")
+    ///
+    /// Sessions saved before tool results had their own role stored them as
+    /// user messages.
+    pub fn is_tool_result(&self) -> bool {
+        match self.role {
+            MessageRole::Tool => true,
+            MessageRole::User => self
+                .content
+                .lines()
+                .take(3)
+                .any(|line| line.starts_with("Tool: ")),
+            _ => false,
+        }
+    }
+
+    /// The tools of a tool result message, one per "Tool: " section
+    ///
+    /// Sections recorded with the tool runs are used as they are, so output
+    /// that itself has "Tool: " lines stays with its tool. Messages without
+    /// them (older sessions) are split at every "Tool: " line.
+    pub fn tool_outputs(&self) -> Vec<ToolOutput<'_>> {
+        let sections: Vec<&str> = self.tool_runs.iter().map(|r| r.section.as_str()).collect();
+        if sections.is_empty() || sections.iter().any(|s| s.is_empty()) {
+            return split_tool_outputs(&self.content);
+        }
+        // Notes (e.g. a write conflict) come before the first section
+        let notes = self
+            .content
+            .find(sections[0])
+            .map_or("", |start| &self.content[..start]);
+        let mut outputs = split_tool_outputs(notes);
+        outputs.retain(|output| !output.lines.is_empty());
+        outputs.extend(sections.into_iter().map(parse_tool_section));
+        outputs
+    }
+}
+
+/// Split tool result text at every "Tool: " line
+fn split_tool_outputs(content: &str) -> Vec<ToolOutput<'_>> {
+    let mut outputs: Vec<ToolOutput> = Vec::new();
+    for line in content.lines() {
+        if let Some(tool) = line.strip_prefix("Tool: ") {
+            outputs.push(ToolOutput {
+                tool,
+                ..Default::default()
+            });
+            continue;
+        }
+        if outputs.is_empty() {
+            outputs.push(ToolOutput::default());
+        }
+        let output = outputs.last_mut().expect("pushed above");
+        if let Some(code) = line.strip_prefix("Exit code: ")
+            && let Ok(code) = code.trim().parse()
+        {
+            output.exit_code = Some(code);
+        } else if line != "Output:" {
+            output.lines.push(line);
+        }
+    }
+    for output in &mut outputs {
+        trim_trailing_blank(&mut output.lines);
+    }
+    outputs
+}
+
+/// One "Tool: " section; everything after its "Output:" line is output
+fn parse_tool_section(section: &str) -> ToolOutput<'_> {
+    let mut lines = section.lines();
+    let mut output = ToolOutput {
+        tool: lines
+            .next()
+            .and_then(|line| line.strip_prefix("Tool: "))
+            .unwrap_or_default(),
+        ..Default::default()
+    };
+    for line in lines.by_ref() {
+        if line == "Output:" {
+            break;
+        }
+        match line.strip_prefix("Exit code: ").map(|c| c.trim().parse()) {
+            Some(Ok(code)) => output.exit_code = Some(code),
+            _ => output.lines.push(line),
+        }
+    }
+    output.lines.extend(lines);
+    trim_trailing_blank(&mut output.lines);
+    output
+}
+
+fn trim_trailing_blank(lines: &mut Vec<&str>) {
+    while lines.last().is_some_and(|l| l.trim().is_empty()) {
+        lines.pop();
+    }
 }
 
 impl ImageData {
@@ -89,7 +347,8 @@ impl ImageData {
     }
 }
 
-fn base64_encode(data: &[u8]) -> String {
+/// Standard base64 with padding
+pub fn base64_encode(data: &[u8]) -> String {
     use std::io::Write;
     let mut enc = Vec::new();
     let mut encoder = Base64Encoder::new(&mut enc);
@@ -162,6 +421,27 @@ impl<W: std::io::Write> Drop for Base64Encoder<W> {
     }
 }
 
+/// Decode standard base64, ignoring whitespace; None on invalid input
+pub fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for byte in text.bytes() {
+        let value = match byte {
+            b'=' => break,
+            b' ' | b'\n' | b'\r' | b'\t' => continue,
+            _ => BASE64_CHARS.iter().position(|&c| c == byte)? as u32,
+        };
+        acc = (acc << 6) | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
 fn mime_from_path(path: &str) -> String {
     let ext = path.rsplit('.').next().unwrap_or("").to_lowercase();
     match ext.as_str() {
@@ -257,7 +537,7 @@ impl Message {
             role: match self.role {
                 MessageRole::User => "user".to_string(),
                 MessageRole::Model => "model".to_string(),
-                MessageRole::System => "user".to_string(),
+                MessageRole::System | MessageRole::Tool => "user".to_string(),
             },
             parts,
         }
@@ -321,6 +601,14 @@ mod tests {
     use super::*;
     use proptest::prelude::*;
 
+    // **Feature: Sabi-TUI, Property: Base64 Round Trip**
+    proptest! {
+        #[test]
+        fn prop_base64_round_trip(data in proptest::collection::vec(any::<u8>(), 0..200)) {
+            prop_assert_eq!(base64_decode(&base64_encode(&data)), Some(data));
+        }
+    }
+
     #[test]
     fn test_message_creation() {
         let msg = Message::user("Hello");
@@ -332,22 +620,117 @@ mod tests {
         assert_eq!(msg.content, "Hi there");
     }
 
+    // **Feature: Sabi-TUI, Property: Message Timing**
+    #[test]
+    fn test_message_timing() {
+        let mut msg = Message::model("Done").took(Duration::from_millis(6240));
+        msg.time = Some("2026-03-01T14:03:22+01:00".to_string());
+        assert_eq!(msg.timing().as_deref(), Some("14:03:22 · thought for 6.2s"));
+
+        let mut tool = Message::user("Tool: ls\nExit code: 0").took(Duration::from_millis(1400));
+        tool.time = None;
+        assert_eq!(tool.timing().as_deref(), Some("ran in 1.4s"));
+
+        // Sessions saved before messages had times still load
+        let old: Message = serde_json::from_str(r#"{"role":"user","content":"hi"}"#).unwrap();
+        assert_eq!(old.timing(), None);
+    }
+
+    // **Feature: Sabi-TUI, Property: Tool Result Sections**
+    #[test]
+    fn test_tool_outputs() {
+        let batch = Message::tool(
+            "Note: a.txt changed; the user confirmed overwriting it.\n\n\
+             Tool: run_cmd ls\nExit code: 0\nOutput:\na.txt\nb.txt\n\n\
+             Tool: mcp/git/status\nOutput:\n{}",
+        );
+        assert!(batch.is_tool_result());
+        let outputs = batch.tool_outputs();
+        assert_eq!(outputs.len(), 3);
+        assert_eq!(outputs[0].tool, "");
+        assert_eq!(outputs[1].tool, "run_cmd ls");
+        assert_eq!(outputs[1].exit_code, Some(0));
+        assert_eq!(outputs[1].lines, ["a.txt", "b.txt"]);
+        assert_eq!(outputs[2].exit_code, None);
+        assert_eq!(outputs[2].lines, ["{}"]);
+
+        // Recorded sections keep output that looks like a section header
+        let section = "Tool: run_cmd cat log\nExit code: 1\nOutput:\nTool: fake\nExit code: 0\n";
+        let call = ToolCall::run_cmd("cat log");
+        let structured = Message::tool(format!("Note: retried.\n\n{}", section))
+            .with_tool_runs(vec![ToolRun::without_exit_code(call).with_section(section)]);
+        let outputs = structured.tool_outputs();
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(outputs[0].lines, ["Note: retried."]);
+        assert_eq!(outputs[1].tool, "run_cmd cat log");
+        assert_eq!(outputs[1].exit_code, Some(1));
+        assert_eq!(outputs[1].lines, ["Tool: fake", "Exit code: 0"]);
+
+        // Older sessions stored tool results as user messages
+        assert!(Message::user("Tool: ls\nExit code: 2").is_tool_result());
+        assert!(!Message::user("ls").is_tool_result());
+
+        let saved = serde_json::to_string(&Message::tool("Tool: ls")).unwrap();
+        assert!(saved.contains(r#""role":"tool""#));
+        assert_eq!(Message::tool("Tool: ls").to_gemini_content().role, "user");
+    }
+
+    // **Feature: Sabi-TUI, Property: Artifact References**
+    #[test]
+    fn test_artifact_references_serialization() {
+        let plain = serde_json::to_string(&Message::tool("Tool: ls")).unwrap();
+        assert!(!plain.contains("artifacts"));
+
+        let msg = Message::tool("Tool: ls").with_artifacts(vec!["0123456789abcdef".into()]);
+        let saved = serde_json::to_string(&msg).unwrap();
+        assert!(saved.contains(r#""artifacts":["0123456789abcdef"]"#));
+        let loaded: Message = serde_json::from_str(&saved).unwrap();
+        assert_eq!(loaded.artifacts, msg.artifacts);
+        let old: Message = serde_json::from_str(&plain).unwrap();
+        assert!(old.artifacts.is_empty());
+    }
+
+    // **Feature: Sabi-TUI, Property: Structured Tool Calls**
+    #[test]
+    fn test_tool_calls_and_runs_serialization() {
+        let call: ToolCall =
+            serde_json::from_str(r#"{"tool": "run_cmd", "command": "false"}"#).unwrap();
+        let result = CommandResult {
+            exit_code: 1,
+            ..Default::default()
+        };
+        let reply = Message::model("running it").with_tool_calls(vec![call.clone()]);
+        let output = Message::tool("Tool: run_cmd false\nExit code: 1")
+            .with_tool_runs(vec![ToolRun::new(call.clone(), &result)]);
+        assert!(reply.is_tool_call() && !output.is_tool_call());
+
+        let saved = serde_json::to_string(&reply).unwrap();
+        assert!(saved.contains(r#""tool_calls":[{"tool":"run_cmd","command":"false"}]"#));
+        let saved = serde_json::to_string(&output).unwrap();
+        assert!(saved.contains(r#""exit_code":1}]"#));
+        let loaded: Message = serde_json::from_str(&saved).unwrap();
+        assert_eq!(loaded.tool_runs, output.tool_runs);
+        assert!(!loaded.tool_runs[0].succeeded());
+        assert!(ToolRun::without_exit_code(call).succeeded());
+
+        let plain = serde_json::to_string(&Message::model("hi")).unwrap();
+        assert!(!plain.contains("tool_calls") && !plain.contains("tool_runs"));
+    }
+
     #[test]
     fn test_to_gemini_content() {
         let msg = Message::user("Test message");
         let content = msg.to_gemini_content();
         assert_eq!(content.role, "user");
         assert_eq!(content.parts.len(), 1);
-        assert_eq!(content.parts[0].text, "Test message");
+        assert_eq!(content.parts[0], GeminiPart::text("Test message"));
     }
 
     #[test]
     fn test_from_gemini_content() {
         let content = GeminiContent {
             role: "model".to_string(),
-            parts: vec![GeminiPart {
-                text: "Response text".to_string(),
-            }],
+            parts: vec![GeminiPart::text("Response text")],
         };
         let msg = Message::from_gemini_content(&content);
         assert_eq!(msg.role, MessageRole::Model);
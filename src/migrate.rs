@@ -0,0 +1,240 @@
+//! Config schema versions and upgrades
+//!
+//! config.toml carries a `version`. Files from older versions (no `version`
+//! is version 0) are upgraded on startup, one migration per version, and
+//! the original is kept next to it as `config.toml.v<N>.bak`. Newer files
+//! are left alone; `Config::validate` warns about them.
+
+use std::path::{Path, PathBuf};
+
+use toml_edit::{DocumentMut, InlineTable, Item, Value};
+
+use crate::config::Config;
+use crate::settings;
+
+/// Current schema version
+pub const VERSION: u32 = 1;
+
+/// Migrations in order: `MIGRATIONS[n]` upgrades a version `n` file to `n + 1`
+/// and returns a line per change
+const MIGRATIONS: &[fn(&mut DocumentMut) -> Vec<String>] = &[to_v1];
+
+/// Keys renamed in version 1: (old, new)
+const RENAMED_V1: &[(&str, &str)] = &[("max_history", "max_history_messages")];
+
+/// Default dangerous patterns of version 0 that a built-in category now
+/// covers: (pattern, category)
+const CATEGORIZED_V1: &[(&str, &str)] = &[
+    (r"rm\s+-rf\s+/", "destructive-fs"),
+    (r"mkfs", "disk-write"),
+    (r"dd\s+if=", "disk-write"),
+    (r">\s*/dev/sd", "disk-write"),
+];
+
+/// A config file that was upgraded
+#[derive(Debug, Clone, PartialEq)]
+pub struct Upgrade {
+    pub from: u32,
+    /// Copy of the file before the upgrade
+    pub backup: PathBuf,
+    pub changes: Vec<String>,
+}
+
+/// Schema version of `document`
+pub fn version(document: &DocumentMut) -> u32 {
+    document
+        .get("version")
+        .and_then(Item::as_integer)
+        .and_then(|v| u32::try_from(v).ok())
+        .unwrap_or(0)
+}
+
+/// Upgrade `document` to the current version, returning the changes
+pub fn migrate(document: &mut DocumentMut) -> Vec<String> {
+    let from = version(document) as usize;
+    if from >= MIGRATIONS.len() {
+        return Vec::new();
+    }
+    let changes = MIGRATIONS[from..]
+        .iter()
+        .flat_map(|step| step(document))
+        .collect();
+    document["version"] = toml_edit::value(i64::from(VERSION));
+    changes
+}
+
+/// Upgrade the config file at `path` in place
+///
+/// Returns `None` when there's nothing to report: no file, a current or
+/// newer one, one that doesn't parse (`Config::load` reports that), or
+/// one that only needed the version stamped.
+pub fn upgrade_file(path: &Path) -> Result<Option<Upgrade>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let Ok(mut document) = settings::read_document(path) else {
+        return Ok(None);
+    };
+    let from = version(&document);
+    if from >= VERSION {
+        return Ok(None);
+    }
+    let changes = migrate(&mut document);
+    if changes.is_empty() {
+        settings::write_document(path, &document)?;
+        return Ok(None);
+    }
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(format!(".v{}.bak", from));
+    let backup = PathBuf::from(backup);
+    std::fs::copy(path, &backup).map_err(|e| format!("Can't back up {}: {}", path.display(), e))?;
+    settings::write_document(path, &document)?;
+    Ok(Some(Upgrade {
+        from,
+        backup,
+        changes,
+    }))
+}
+
+/// Version 1: renamed keys, and dangerous patterns as tables with a severity
+fn to_v1(document: &mut DocumentMut) -> Vec<String> {
+    let mut changes = Vec::new();
+    for (old, new) in RENAMED_V1 {
+        let Some(item) = document.remove(old) else {
+            continue;
+        };
+        if document.contains_key(new) {
+            changes.push(format!("removed {}, {} is already set", old, new));
+        } else {
+            document[*new] = item;
+            changes.push(format!("renamed {} to {}", old, new));
+        }
+    }
+
+    let Some(patterns) = document
+        .get_mut("dangerous_patterns")
+        .and_then(Item::as_array_mut)
+    else {
+        return changes;
+    };
+    let mut converted = 0;
+    let mut i = 0;
+    while i < patterns.len() {
+        let Some(pattern) = patterns.get(i).and_then(Value::as_str).map(str::to_string) else {
+            i += 1;
+            continue;
+        };
+        if let Some((_, category)) = CATEGORIZED_V1.iter().find(|(p, _)| *p == pattern) {
+            patterns.remove(i);
+            changes.push(format!(
+                "dangerous_patterns: dropped '{}', the {} category covers it",
+                pattern, category
+            ));
+            continue;
+        }
+        // The fork bomb is still a default pattern, now blocked outright
+        let default = Config::default()
+            .dangerous_patterns
+            .into_iter()
+            .find(|p| p.pattern == pattern);
+        let mut rule = InlineTable::new();
+        rule.insert("pattern", pattern.as_str().into());
+        match default {
+            Some(default) => {
+                rule.insert("severity", default.severity.name().into());
+                rule.insert("reason", default.reason.as_str().into());
+            }
+            None => {
+                rule.insert("severity", "warn".into());
+            }
+        }
+        patterns.replace(i, rule);
+        converted += 1;
+        i += 1;
+    }
+    if converted > 0 {
+        changes.push(format!(
+            "dangerous_patterns: {} pattern(s) written as {{ pattern, severity }} tables",
+            converted
+        ));
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{DangerousPattern, Severity};
+
+    const V0: &str = r#"# my settings
+api_key = "key"
+max_history = 40
+dangerous_patterns = [
+    "rm\\s+-rf\\s+/",
+    "mkfs",
+    ":\\(\\)\\s*\\{",
+    "kubectl delete",
+    { pattern = "reboot", severity = "block" },
+]
+"#;
+
+    // **Feature: Sabi-TUI, Property: Config Migration**
+    #[test]
+    fn test_migrate_v0() {
+        let mut document: DocumentMut = V0.parse().unwrap();
+        assert_eq!(version(&document), 0);
+        let changes = migrate(&mut document);
+        assert_eq!(version(&document), VERSION);
+        assert_eq!(changes.len(), 4, "{:#?}", changes);
+        assert_eq!(changes[0], "renamed max_history to max_history_messages");
+
+        let text = document.to_string();
+        assert!(text.starts_with("# my settings\n"));
+        let config: Config = toml::from_str(&text).unwrap();
+        assert_eq!(config.max_history_messages, 40);
+        assert_eq!(
+            config.dangerous_patterns,
+            [
+                DangerousPattern::new(r":\(\)\s*\{", Severity::Block, "fork bomb"),
+                DangerousPattern::new("kubectl delete", Severity::Warn, ""),
+                DangerousPattern::new("reboot", Severity::Block, ""),
+            ]
+        );
+
+        // Already current: nothing to do
+        let before = document.to_string();
+        assert!(migrate(&mut document).is_empty());
+        assert_eq!(document.to_string(), before);
+
+        let mut newer: DocumentMut = "version = 99\nmax_history = 1\n".parse().unwrap();
+        assert!(migrate(&mut newer).is_empty());
+        assert!(newer.contains_key("max_history"));
+    }
+
+    // **Feature: Sabi-TUI, Property: Config Migration Backup**
+    #[test]
+    fn test_upgrade_file_keeps_backup() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("config.toml");
+        assert_eq!(upgrade_file(&path), Ok(None));
+
+        std::fs::write(&path, V0).unwrap();
+        let upgrade = upgrade_file(&path).unwrap().unwrap();
+        assert_eq!(upgrade.from, 0);
+        assert_eq!(upgrade.backup, temp.path().join("config.toml.v0.bak"));
+        assert_eq!(std::fs::read_to_string(&upgrade.backup).unwrap(), V0);
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.contains("version = 1"));
+        assert_eq!(upgrade_file(&path), Ok(None));
+
+        // Only the version to add: no backup, nothing to report
+        let path = temp.path().join("plain.toml");
+        std::fs::write(&path, "model = \"m\"\n").unwrap();
+        assert_eq!(upgrade_file(&path), Ok(None));
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "model = \"m\"\nversion = 1\n"
+        );
+        assert!(!temp.path().join("plain.toml.v0.bak").exists());
+    }
+}
@@ -0,0 +1,85 @@
+//! What Sabi knows about models: the size of their context windows
+//!
+//! The status bar gauge compares the token estimate of what is sent to the
+//! model with its window. Unknown models get a conservative default; the
+//! `context_window` setting overrides both.
+
+/// Window assumed for models not listed below
+pub const DEFAULT_CONTEXT_WINDOW: usize = 128_000;
+
+/// Context windows in tokens by model name prefix, more specific first
+const CONTEXT_WINDOWS: &[(&str, usize)] = &[
+    ("gemini-1.5-pro", 2_097_152),
+    ("gemini-1.5-flash", 1_048_576),
+    ("gemini-2", 1_048_576),
+    ("gemini-3", 1_048_576),
+    ("gemini", 32_768),
+    ("gpt-4.1", 1_047_576),
+    ("gpt-5", 400_000),
+    ("gpt-4o", 128_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4", 8_192),
+    ("gpt-3.5", 16_385),
+    ("o1", 200_000),
+    ("o3", 200_000),
+    ("o4", 200_000),
+    ("claude", 200_000),
+    ("deepseek", 128_000),
+    ("llama3.1", 128_000),
+    ("llama3.2", 128_000),
+    ("llama3", 8_192),
+    ("qwen2.5", 32_768),
+    ("mistral", 32_768),
+];
+
+/// Context window of `model` in tokens
+///
+/// Provider prefixes like `openai/` or `models/` are ignored.
+pub fn context_window(model: &str) -> usize {
+    let name = model.rsplit('/').next().unwrap_or(model).to_lowercase();
+    CONTEXT_WINDOWS
+        .iter()
+        .find(|(prefix, _)| name.starts_with(prefix))
+        .map_or(DEFAULT_CONTEXT_WINDOW, |&(_, window)| window)
+}
+
+/// Rough token count of `text` (about 4 characters per token)
+pub fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(4)
+}
+
+/// "1M", "128k" or "8192" for a token count
+pub fn short_count(tokens: usize) -> String {
+    if tokens >= 1_000_000 {
+        format!("{}M", tokens / 1_000_000)
+    } else if tokens >= 10_000 {
+        format!("{}k", tokens / 1_000)
+    } else {
+        tokens.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // **Feature: Sabi-TUI, Property: Model Context Windows**
+    #[test]
+    fn test_context_window() {
+        assert_eq!(context_window("gemini-2.5-flash"), 1_048_576);
+        assert_eq!(context_window("models/gemini-1.5-pro-latest"), 2_097_152);
+        assert_eq!(context_window("gpt-4o-mini"), 128_000);
+        assert_eq!(context_window("gpt-4"), 8_192);
+        assert_eq!(context_window("openai/GPT-4.1"), 1_047_576);
+        assert_eq!(context_window("my-local-model"), DEFAULT_CONTEXT_WINDOW);
+    }
+
+    #[test]
+    fn test_estimates() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("hello"), 2);
+        assert_eq!(short_count(1_048_576), "1M");
+        assert_eq!(short_count(128_000), "128k");
+        assert_eq!(short_count(8_192), "8192");
+    }
+}
@@ -0,0 +1,192 @@
+//! Bell and desktop notification when a long phase ends unattended
+//!
+//! When thinking or running a command took longer than `notify_after_secs`
+//! and the terminal lost focus in the meantime, the terminal bell rings and
+//! a desktop notification is requested with OSC 9 (iTerm2, kitty, WezTerm)
+//! or OSC 777 (foot, rxvt, Ghostty). Terminals that don't report focus
+//! changes are treated as always focused.
+
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::clipboard::tmux_passthrough;
+use crate::state::AppState;
+
+/// `notify` config setting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifyMode {
+    Off,
+    Bell,
+    Desktop,
+    /// Bell and desktop notification
+    #[default]
+    Both,
+}
+
+/// States where the user waits for sabi
+fn is_busy(state: AppState) -> bool {
+    matches!(
+        state,
+        AppState::Thinking | AppState::Executing | AppState::Finalizing
+    )
+}
+
+/// Watches busy phases and decides when to notify
+#[derive(Debug)]
+pub struct Notifier {
+    mode: NotifyMode,
+    after: Duration,
+    /// The terminal has focus (from focus reports)
+    pub focused: bool,
+    /// Focus was lost at some point during the current busy phase
+    left: bool,
+    busy_since: Option<Instant>,
+    /// Exit code of the last command of the current busy phase
+    exit_code: Option<i32>,
+}
+
+impl Notifier {
+    pub fn new(mode: NotifyMode, after: Duration) -> Self {
+        Self {
+            mode,
+            after,
+            focused: true,
+            left: false,
+            busy_since: None,
+            exit_code: None,
+        }
+    }
+
+    /// Record a focus report from the terminal
+    pub fn set_focus(&mut self, focused: bool) {
+        self.focused = focused;
+        if !focused && self.busy_since.is_some() {
+            self.left = true;
+        }
+    }
+
+    /// Record the exit code of a command that just finished
+    pub fn command_finished(&mut self, exit_code: i32) {
+        self.exit_code = Some(exit_code);
+    }
+
+    /// Notification text if a long busy phase just ended while unfocused
+    pub fn update(&mut self, state: AppState, now: Instant) -> Option<String> {
+        if is_busy(state) {
+            if self.busy_since.is_none() {
+                self.busy_since = Some(now);
+                self.left = !self.focused;
+                self.exit_code = None;
+            }
+            return None;
+        }
+        let started = self.busy_since.take()?;
+        let unattended = std::mem::take(&mut self.left) || !self.focused;
+        if self.mode == NotifyMode::Off || !unattended || now - started < self.after {
+            return None;
+        }
+        Some(match (self.exit_code.take(), state) {
+            (Some(code), _) => format!("command finished, exit {}", code),
+            (None, AppState::ReviewAction) => "a command is waiting for review".to_string(),
+            (None, _) => "reply ready".to_string(),
+        })
+    }
+
+    /// Escape sequences announcing `text`
+    ///
+    /// `term` picks OSC 777 or OSC 9; inside tmux the desktop notification
+    /// is passed through to the outer terminal.
+    pub fn sequence(&self, text: &str, term: &str, in_tmux: bool) -> String {
+        let mut out = String::new();
+        if matches!(self.mode, NotifyMode::Bell | NotifyMode::Both) {
+            out.push('\x07');
+        }
+        if matches!(self.mode, NotifyMode::Desktop | NotifyMode::Both) {
+            let osc777 = ["foot", "rxvt", "xterm-ghostty"]
+                .iter()
+                .any(|prefix| term.starts_with(prefix));
+            let notification = if osc777 {
+                format!("\x1b]777;notify;sabi;{}\x07", text)
+            } else {
+                format!("\x1b]9;sabi: {}\x07", text)
+            };
+            out.push_str(&tmux_passthrough(notification, in_tmux));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notifier() -> Notifier {
+        Notifier::new(NotifyMode::Both, Duration::from_secs(10))
+    }
+
+    // **Feature: Sabi-TUI, Property: Completion Notification**
+    #[test]
+    fn test_notifies_only_long_unattended_phases() {
+        let start = Instant::now();
+        let later = start + Duration::from_secs(30);
+
+        // Focused the whole time
+        let mut n = notifier();
+        n.update(AppState::Thinking, start);
+        assert_eq!(n.update(AppState::Input, later), None);
+
+        // Unfocused but quick
+        n.update(AppState::Thinking, start);
+        n.set_focus(false);
+        assert_eq!(
+            n.update(AppState::Input, start + Duration::from_secs(2)),
+            None
+        );
+
+        // Left during a long command, back before it ended: still notified
+        n.set_focus(true);
+        n.update(AppState::Executing, start);
+        n.set_focus(false);
+        n.set_focus(true);
+        n.command_finished(0);
+        n.update(AppState::Finalizing, later);
+        assert_eq!(
+            n.update(AppState::Input, later).as_deref(),
+            Some("command finished, exit 0")
+        );
+
+        n.set_focus(false);
+        n.update(AppState::Thinking, start);
+        assert_eq!(
+            n.update(AppState::ReviewAction, later).as_deref(),
+            Some("a command is waiting for review")
+        );
+
+        let mut off = Notifier::new(NotifyMode::Off, Duration::from_secs(10));
+        off.set_focus(false);
+        off.update(AppState::Thinking, start);
+        assert_eq!(off.update(AppState::Input, later), None);
+    }
+
+    #[test]
+    fn test_notification_sequences() {
+        let n = notifier();
+        assert_eq!(
+            n.sequence("reply ready", "xterm-kitty", false),
+            "\x07\x1b]9;sabi: reply ready\x07"
+        );
+        assert_eq!(
+            n.sequence("reply ready", "foot", false),
+            "\x07\x1b]777;notify;sabi;reply ready\x07"
+        );
+        assert!(
+            n.sequence("reply ready", "tmux-256color", true)
+                .starts_with("\x07\x1bPtmux;\x1b\x1b]9;")
+        );
+
+        let bell = Notifier::new(NotifyMode::Bell, Duration::ZERO);
+        assert_eq!(bell.sequence("reply ready", "xterm", false), "\x07");
+    }
+}
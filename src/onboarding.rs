@@ -1,7 +1,10 @@
 //! First-run onboarding flow
 
 use crate::config::{Config, Provider};
+use crate::keychain;
+use crate::settings;
 use std::io::{self, Write};
+use std::path::Path;
 
 pub fn run_onboarding() -> io::Result<Config> {
     println!("\n🚀 Welcome to Sabi-TUI!\n");
@@ -76,11 +79,79 @@ pub fn run_onboarding() -> io::Result<Config> {
         ..Config::default()
     };
 
-    // Save config
-    config.save().map_err(|e| io::Error::other(e.to_string()))?;
+    // Keep the key out of config.toml when the OS keychain takes it
+    let stored =
+        !config.api_key.is_empty() && keychain::store(&config.provider, &config.api_key).is_ok();
+    let path = Config::config_path().map_err(|e| io::Error::other(e.to_string()))?;
+    save(&path, &config, stored).map_err(io::Error::other)?;
 
     println!("\n✓ Configuration saved to ~/.sabi/config.toml");
+    if stored {
+        println!("  API key stored in the system keychain");
+    }
     println!("  Run `sabi` to start!\n");
 
     Ok(config)
 }
+
+/// Write the provider settings from `config` to the config file at `path`,
+/// keeping everything else in it
+///
+/// The API key is left out when `key_stored` says the keychain has it.
+fn save(path: &Path, config: &Config, key_stored: bool) -> Result<(), String> {
+    let mut document = settings::read_document(path)?;
+    document["version"] = toml_edit::value(i64::from(config.version));
+    document["provider"] = toml_edit::value(config.provider.name());
+    document["model"] = toml_edit::value(config.model.as_str());
+    match config.base_url {
+        Some(ref url) => document["base_url"] = toml_edit::value(url.as_str()),
+        None => {
+            document.remove("base_url");
+        }
+    }
+    if key_stored || config.api_key.is_empty() {
+        document.remove("api_key");
+    } else {
+        document["api_key"] = toml_edit::value(config.api_key.as_str());
+    }
+    settings::write_document(path, &document)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // **Feature: Sabi-TUI, Property: Onboarding Keeps Settings**
+    #[test]
+    fn test_save_keeps_other_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(
+            &path,
+            "# mine\nprovider = \"gemini\"\napi_key = \"old\"\nsafe_mode = true\nstats = true\nallowed_commands = [\"ls\"]\n",
+        )
+        .unwrap();
+        let config = Config {
+            provider: Provider::OpenAI,
+            model: "gpt-4o".to_string(),
+            api_key: "sk-new".to_string(),
+            ..Config::default()
+        };
+
+        save(&path, &config, true).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        let saved: Config = toml::from_str(&text).unwrap();
+        assert_eq!(saved.provider, Provider::OpenAI);
+        assert_eq!(saved.model, "gpt-4o");
+        assert!(saved.safe_mode);
+        assert_eq!(saved.allowed_commands, vec!["ls".to_string()]);
+        assert!(saved.stats);
+        assert!(text.starts_with("# mine"));
+        assert!(!text.contains("api_key"));
+
+        save(&path, &config, false).unwrap();
+        let saved: Config = toml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved.api_key, "sk-new");
+        assert!(saved.safe_mode);
+    }
+}
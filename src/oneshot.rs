@@ -0,0 +1,534 @@
+//! `sabi -p "prompt"`: the whole agent loop without the TUI
+//!
+//! Runs the ReAct cycle like the app does: every tool call goes through
+//! the allowlist and the danger checks, is confirmed on the terminal (or
+//! run by `--yes` and the approval policy, or only shown with `--safe`),
+//! and its result goes back to the AI until it answers in text. Progress
+//! goes to stderr and the answer to stdout, so the answer can be piped.
+
+use std::io::{BufRead, IsTerminal, Write};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::ai_client::AIClient;
+use crate::config::{Config, Severity};
+use crate::executor::{
+    self, CommandAllowlist, CommandExecutor, CommandResult, DangerAssessment,
+    DangerousCommandDetector, InteractiveCommandDetector,
+};
+use crate::loop_guard::{self, LoopGuard, LoopVerdict};
+use crate::message::{Message, ToolRun};
+use crate::path_guard::PathGuard;
+use crate::project::Project;
+use crate::tool_call::{MIXED_BATCH_REASON, ParsedResponse, ToolCall};
+
+/// How tool calls are approved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Approval {
+    /// Ask on the terminal for each call the approval policy doesn't cover
+    Ask,
+    /// Run calls that aren't dangerous without asking (`--yes`)
+    Yes,
+    /// Show the calls without running them (`--safe`)
+    Safe,
+}
+
+/// What to do with a tool call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    Run,
+    /// Ask first; the reasons it is dangerous, if it is
+    Confirm(Vec<String>),
+    /// Don't run it, telling the AI why
+    Skip(String),
+}
+
+/// Checks a tool call goes through before it runs
+pub struct Guards {
+    pub allowlist: CommandAllowlist,
+    pub detector: DangerousCommandDetector,
+    pub interactive: InteractiveCommandDetector,
+    pub path_guard: PathGuard,
+}
+
+impl Guards {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            allowlist: CommandAllowlist::from_config(config),
+            detector: DangerousCommandDetector::from_rules(&config.effective_dangerous_patterns()),
+            interactive: InteractiveCommandDetector::from_config(config),
+            path_guard: PathGuard::new(config),
+        }
+    }
+
+    /// Decide about `tool` under `approval` and the configured policy
+    pub fn decide(&self, config: &Config, tool: &ToolCall, approval: Approval) -> Decision {
+        if !tool.is_allowed_tool() && !tool.is_mcp() {
+            return Decision::Skip(format!("'{}' is not a known tool", tool.tool));
+        }
+        if tool.is_run_cmd() {
+            if let Err(program) = self.allowlist.check(&tool.command) {
+                return Decision::Skip(format!(
+                    "'{}' is not in the command allowlist ({})",
+                    program,
+                    self.allowlist.commands().join(", ")
+                ));
+            }
+            if self.interactive.is_interactive(&tool.command) {
+                let suggestion = self
+                    .interactive
+                    .suggestion(&tool.command)
+                    .unwrap_or("it needs an interactive terminal");
+                return Decision::Skip(format!("can't run an interactive command: {}", suggestion));
+            }
+        }
+
+        let danger = tool
+            .is_run_cmd()
+            .then(|| self.detector.assess(&tool.command))
+            .flatten();
+        let cwd = std::env::current_dir().unwrap_or_default();
+        let danger = DangerAssessment::combine(danger, self.path_guard.check_tool(&cwd, tool));
+        if let Some(ref danger) = danger
+            && danger.severity == Severity::Block
+        {
+            return Decision::Skip(format!("refused: {}", danger.reasons.join("; ")));
+        }
+        if approval == Approval::Safe || config.safe_mode {
+            return Decision::Skip("safe mode shows tool calls without running them".to_string());
+        }
+
+        let dangerous = tool.is_destructive() || danger.is_some();
+        let reasons = danger.map(|d| d.reasons).unwrap_or_default();
+        let sudo = tool.is_run_cmd() && executor::uses_sudo(&tool.command);
+        if dangerous || sudo {
+            return Decision::Confirm(reasons);
+        }
+        let allowlisted = self.allowlist.is_enabled();
+        if approval == Approval::Yes
+            || config
+                .approval_policy
+                .auto_approves(tool, allowlisted, dangerous)
+        {
+            Decision::Run
+        } else {
+            Decision::Confirm(Vec::new())
+        }
+    }
+}
+
+/// Ask on the terminal whether to run `description`
+///
+/// Dangerous calls need "yes" typed out. Without a terminal to ask on,
+/// the answer is no.
+pub fn confirm(description: &str, reasons: &[String]) -> bool {
+    if !std::io::stdin().is_terminal() {
+        eprintln!("  Not run: it needs confirmation and stdin isn't a terminal (see --yes)");
+        return false;
+    }
+    let question = if reasons.is_empty() {
+        "Run it? [y/N] "
+    } else {
+        eprintln!("⚠ Dangerous: {}", reasons.join("; "));
+        "Type 'yes' to run it: "
+    };
+    eprint!("  {} {}", description, question);
+    let _ = std::io::stderr().flush();
+    let mut answer = String::new();
+    if std::io::stdin().lock().read_line(&mut answer).is_err() {
+        return false;
+    }
+    let answer = answer.trim().to_lowercase();
+    if reasons.is_empty() {
+        answer == "y" || answer == "yes"
+    } else {
+        answer == "yes"
+    }
+}
+
+/// Run `tool`, an MCP tool through its server
+async fn run_tool(executor: &CommandExecutor, tool: &ToolCall) -> CommandResult {
+    if !tool.is_mcp() {
+        return executor.execute_tool_async(tool).await;
+    }
+    let output = crate::mcp::McpClient::load()
+        .map_err(|e| format!("{:?}", e))
+        .and_then(|client| {
+            let _ = client.start_all();
+            client
+                .call_tool(&tool.server, &tool.name, tool.arguments.clone())
+                .map_err(|e| format!("{:?}", e))
+        });
+    let (stdout, stderr, exit_code) = match output {
+        Ok(value) => (
+            serde_json::to_string_pretty(&value).unwrap_or_default(),
+            String::new(),
+            0,
+        ),
+        Err(e) => (String::new(), format!("MCP error: {}", e), 1),
+    };
+    CommandResult {
+        stdout,
+        stderr,
+        exit_code,
+        success: exit_code == 0,
+        ..Default::default()
+    }
+}
+
+/// How `-p` reports what happens (`--output`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// Progress on stderr, the answer on stdout
+    #[default]
+    Text,
+    /// A JSON object per line on stdout, for other tools and CI jobs
+    Json,
+}
+
+impl OutputFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(OutputFormat::Text),
+            "json" => Some(OutputFormat::Json),
+            _ => None,
+        }
+    }
+
+    /// Show `report`
+    fn emit(self, report: &Report) {
+        if self == OutputFormat::Json {
+            println!("{}", serde_json::to_string(report).unwrap_or_default());
+            return;
+        }
+        match report {
+            Report::AssistantText { text } => eprintln!("💬 {}", text),
+            Report::ToolCall { call } => eprintln!("🔧 {}", crate::describe_tool(call)),
+            Report::ToolResult {
+                exit_code: Some(code),
+                output,
+                ..
+            } => {
+                if !output.trim().is_empty() {
+                    eprintln!("{}", output.trim_end());
+                }
+                eprintln!("  exit {}", code);
+            }
+            Report::ToolResult { reason, .. } => {
+                eprintln!("  Not run: {}", reason.as_deref().unwrap_or_default())
+            }
+            Report::Final { status, text } => match status {
+                Status::Answer => println!("{}", text),
+                Status::Stopped => eprintln!("⏸ {}", text),
+                Status::Error => eprintln!("Error: {}", text),
+            },
+        }
+    }
+
+    /// Progress note, only shown as text
+    fn note(self, text: &str) {
+        if self == OutputFormat::Text {
+            eprintln!("{}", text);
+        }
+    }
+}
+
+/// How a run ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    /// The AI answered
+    Answer,
+    /// The loop guard or `max_tool_iterations` ended the run
+    Stopped,
+    /// The AI couldn't be reached
+    Error,
+}
+
+/// Something that happened in a run; a line of `--output json`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Report {
+    /// What the AI said along with its tool calls
+    AssistantText {
+        text: String,
+    },
+    ToolCall {
+        call: Box<ToolCall>,
+    },
+    /// A tool call's outcome; `exit_code` is null when it didn't run
+    ToolResult {
+        tool: String,
+        exit_code: Option<i32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        signal: Option<i32>,
+        truncated: bool,
+        output: String,
+        /// Why it didn't run
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+    },
+    /// The end of the run, with the answer or what stopped it
+    Final {
+        status: Status,
+        text: String,
+    },
+}
+
+impl Report {
+    fn ran(tool: &ToolCall, result: &CommandResult) -> Self {
+        Report::ToolResult {
+            tool: tool.tool.clone(),
+            exit_code: Some(result.exit_code),
+            signal: result.signal,
+            truncated: result.truncated,
+            output: crate::command_output(result),
+            reason: None,
+        }
+    }
+
+    fn not_run(tool: &ToolCall, reason: &str) -> Self {
+        Report::ToolResult {
+            tool: tool.tool.clone(),
+            exit_code: None,
+            signal: None,
+            truncated: false,
+            output: String::new(),
+            reason: Some(reason.to_string()),
+        }
+    }
+}
+
+/// Report the end of the run; the exit code for the process
+fn finish(format: OutputFormat, status: Status, text: impl Into<String>) -> i32 {
+    format.emit(&Report::Final {
+        status,
+        text: text.into(),
+    });
+    if status == Status::Answer { 0 } else { 1 }
+}
+
+/// Run `prompt` to an answer; the exit code for the process
+pub async fn run(
+    config: &Config,
+    project: Option<&Project>,
+    prompt: &str,
+    approval: Approval,
+    format: OutputFormat,
+) -> Result<i32> {
+    let client = AIClient::new(config)?;
+    let executor = CommandExecutor::new(config);
+    let guards = Guards::new(config);
+    let mut guard = LoopGuard::default();
+    let mut messages = vec![
+        Message::system(crate::cli_system_prompt(config, project)),
+        Message::user(prompt),
+    ];
+
+    loop {
+        format.note("🤔 Thinking...");
+        let response = match client.chat(&messages).await {
+            Ok(response) => response,
+            Err(e) => return Ok(finish(format, Status::Error, e.to_string())),
+        };
+        let parsed = ParsedResponse::parse(&response);
+        let refused = matches!(parsed, ParsedResponse::MixedToolCalls(_));
+        let calls = parsed.tool_calls();
+        let tools = match parsed {
+            ParsedResponse::TextResponse(text) => {
+                return Ok(finish(format, Status::Answer, text));
+            }
+            ParsedResponse::ToolCall(tool) => vec![*tool],
+            ParsedResponse::ToolCalls(tools) | ParsedResponse::MixedToolCalls(tools) => tools,
+        };
+        messages.push(Message::model(&response).with_tool_calls(calls));
+        if let Some(text) = ToolCall::reasoning(&response) {
+            format.emit(&Report::AssistantText { text });
+        }
+
+        let max = config.max_tool_iterations;
+        match guard.count_iteration(max) {
+            Some(LoopVerdict::Nudge(note)) => {
+                format.note(&format!("⏸ {}", note));
+                messages.push(Message::user(loop_guard::limit_message(max)));
+                continue;
+            }
+            Some(LoopVerdict::Stop(note)) => return Ok(finish(format, Status::Stopped, note)),
+            None => {}
+        }
+
+        let mut feedback = Vec::new();
+        let mut runs = Vec::new();
+        for tool in tools {
+            let description = crate::describe_tool(&tool);
+            format.emit(&Report::ToolCall {
+                call: Box::new(tool.clone()),
+            });
+            if refused {
+                format.emit(&Report::not_run(&tool, MIXED_BATCH_REASON));
+                feedback.push(format!(
+                    "Tool: {}\nNot run: {}",
+                    description, MIXED_BATCH_REASON
+                ));
+                continue;
+            }
+            match guard.check(&tool) {
+                Some(LoopVerdict::Nudge(reason)) => {
+                    format.emit(&Report::not_run(&tool, &format!("loop guard: {}", reason)));
+                    feedback.push(loop_guard::nudge_message(&description, &reason));
+                    continue;
+                }
+                Some(LoopVerdict::Stop(reason)) => {
+                    let text = format!("{} keeps coming back ({})", description, reason);
+                    return Ok(finish(format, Status::Stopped, text));
+                }
+                None => {}
+            }
+
+            let run = match guards.decide(config, &tool, approval) {
+                Decision::Run => true,
+                Decision::Confirm(reasons) => confirm(&description, &reasons),
+                Decision::Skip(reason) => {
+                    format.emit(&Report::not_run(&tool, &reason));
+                    feedback.push(format!("Tool: {}\nNot run: {}", description, reason));
+                    continue;
+                }
+            };
+            if !run {
+                format.emit(&Report::not_run(&tool, "declined"));
+                feedback.push(format!(
+                    "Tool: {}\nNot run: the user declined it",
+                    description
+                ));
+                continue;
+            }
+
+            let result = run_tool(&executor, &tool).await;
+            guard.record_result(result.success);
+            format.emit(&Report::ran(&tool, &result));
+            let section = crate::tool_feedback(&description, &result);
+            runs.push(ToolRun::new(tool, &result).with_section(&section));
+            feedback.push(section);
+        }
+        messages.push(Message::tool(feedback.join("\n\n")).with_tool_runs(runs));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ApprovalPolicy;
+
+    // **Feature: Sabi-TUI, Property: One-Shot JSON Events**
+    #[test]
+    fn test_report_json() {
+        assert_eq!(OutputFormat::parse("json"), Some(OutputFormat::Json));
+        assert_eq!(OutputFormat::parse("yaml"), None);
+
+        let tool = ToolCall::run_cmd("df -h /");
+        let json = |report: &Report| serde_json::to_value(report).unwrap();
+        assert_eq!(
+            json(&Report::ToolCall {
+                call: Box::new(tool.clone())
+            }),
+            serde_json::json!({
+                "type": "tool_call",
+                "call": { "tool": "run_cmd", "command": "df -h /" }
+            })
+        );
+        assert_eq!(
+            json(&Report::not_run(&tool, "declined")),
+            serde_json::json!({
+                "type": "tool_result",
+                "tool": "run_cmd",
+                "exit_code": null,
+                "truncated": false,
+                "output": "",
+                "reason": "declined"
+            })
+        );
+        let result = CommandResult::ok("/dev/sda1  40G\n");
+        let ran = json(&Report::ran(&tool, &result));
+        assert_eq!(ran["exit_code"], 0);
+        assert_eq!(ran["output"], "/dev/sda1  40G\n");
+        assert_eq!(
+            json(&Report::Final {
+                status: Status::Answer,
+                text: "40G free".to_string()
+            }),
+            serde_json::json!({ "type": "final", "status": "answer", "text": "40G free" })
+        );
+    }
+
+    // **Feature: Sabi-TUI, Property: One-Shot Approval**
+    #[test]
+    fn test_decide() {
+        let config = Config::default();
+        let guards = Guards::new(&config);
+        let ls = ToolCall::run_cmd("ls -la");
+        assert_eq!(
+            guards.decide(&config, &ls, Approval::Ask),
+            Decision::Confirm(Vec::new())
+        );
+        assert_eq!(guards.decide(&config, &ls, Approval::Yes), Decision::Run);
+        assert!(matches!(
+            guards.decide(&config, &ls, Approval::Safe),
+            Decision::Skip(_)
+        ));
+
+        // --yes still asks about dangerous commands and sudo
+        let rm = ToolCall::run_cmd("rm -rf ./build");
+        assert!(matches!(
+            guards.decide(&config, &rm, Approval::Yes),
+            Decision::Confirm(_)
+        ));
+        let sudo = ToolCall::run_cmd("sudo apt update");
+        assert!(matches!(
+            guards.decide(&config, &sudo, Approval::Yes),
+            Decision::Confirm(_)
+        ));
+        let bomb = ToolCall::run_cmd(":(){ :|:& };:");
+        assert!(matches!(
+            guards.decide(&config, &bomb, Approval::Yes),
+            Decision::Skip(reason) if reason.starts_with("refused: ")
+        ));
+        let unknown = ToolCall::new("format_disk", "");
+        assert!(matches!(
+            guards.decide(&config, &unknown, Approval::Yes),
+            Decision::Skip(_)
+        ));
+    }
+
+    #[test]
+    fn test_decide_follows_config() {
+        let config = Config {
+            approval_policy: ApprovalPolicy::AutoSafe,
+            allowed_commands: vec!["git".to_string()],
+            ..Config::default()
+        };
+        let guards = Guards::new(&config);
+        let read = ToolCall {
+            path: "Cargo.toml".to_string(),
+            ..ToolCall::new("read_file", "")
+        };
+        assert_eq!(guards.decide(&config, &read, Approval::Ask), Decision::Run);
+        assert_eq!(
+            guards.decide(&config, &ToolCall::run_cmd("git status"), Approval::Ask),
+            Decision::Run
+        );
+        assert!(matches!(
+            guards.decide(&config, &ToolCall::run_cmd("curl example.com"), Approval::Yes),
+            Decision::Skip(reason) if reason.contains("allowlist")
+        ));
+
+        let safe = Config {
+            safe_mode: true,
+            ..Config::default()
+        };
+        assert!(matches!(
+            Guards::new(&safe).decide(&safe, &read, Approval::Yes),
+            Decision::Skip(_)
+        ));
+    }
+}
@@ -5,7 +5,7 @@ use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::config::Config;
-use crate::gemini::SYSTEM_PROMPT;
+use crate::gemini::system_prompt;
 use crate::message::{Message, MessageRole};
 
 #[derive(Debug, Error)]
@@ -74,10 +74,14 @@ impl OpenAIClient {
     pub async fn chat(&self, messages: &[Message]) -> Result<String, OpenAIError> {
         let url = format!("{}/chat/completions", self.base_url);
 
-        // Build messages with system prompt
+        // Build messages with the conversation's system prompt, or the default one
+        let prompt = messages
+            .iter()
+            .rfind(|m| m.is_system_prompt())
+            .map_or_else(system_prompt, |m| m.content.clone());
         let mut chat_messages = vec![ChatMessage {
             role: "system".to_string(),
-            content: SYSTEM_PROMPT.to_string(),
+            content: prompt,
         }];
 
         // Add conversation history (sliding window)
@@ -88,7 +92,7 @@ impl OpenAIClient {
             }
             chat_messages.push(ChatMessage {
                 role: match msg.role {
-                    MessageRole::User => "user",
+                    MessageRole::User | MessageRole::Tool => "user",
                     MessageRole::Model => "assistant",
                     MessageRole::System => "system",
                 }
@@ -0,0 +1,451 @@
+//! Workspace path protection
+//!
+//! Checks where write_file, shell redirections and file commands like `rm`
+//! or `sed -i` would write, so paths
+//! outside `writable_roots` or under `protected_paths` need an explicit
+//! confirmation (or are refused, depending on `protected_path_severity`).
+//!
+//! Paths are compared after following symlinks in the part that exists, so
+//! a link or `..` inside the workspace can't point a write somewhere else.
+//! Relative roots and patterns are taken from the current directory, and
+//! targets of a shell command from wherever its leading `cd`s move to.
+
+use std::path::{Component, Path, PathBuf};
+
+use regex::Regex;
+
+use crate::config::{Config, Severity};
+use crate::executor::{self, COMMAND_WRAPPERS, DangerAssessment};
+use crate::tool_call::ToolCall;
+
+/// Redirection targets that never write to disk
+const HARMLESS_TARGETS: &[&str] = &["/dev/null", "/dev/stdout", "/dev/stderr", "/dev/tty"];
+
+/// Guards writes against protected paths and roots outside the workspace
+pub struct PathGuard {
+    /// Writes must land under one of these (empty = anywhere)
+    writable_roots: Vec<String>,
+    /// Writes under these need confirmation; `dir/prefix*` matches by prefix
+    protected_paths: Vec<String>,
+    severity: Severity,
+}
+
+impl PathGuard {
+    /// Create a guard from `writable_roots` / `protected_paths` in config
+    pub fn new(config: &Config) -> Self {
+        Self {
+            writable_roots: config.writable_roots.clone(),
+            protected_paths: config.protected_paths.clone(),
+            severity: config.protected_path_severity,
+        }
+    }
+
+    /// Check the paths a tool call would write, relative to `cwd`
+    pub fn check_tool(&self, cwd: &Path, tool: &ToolCall) -> Option<DangerAssessment> {
+        let (dir, targets) = match tool.tool.as_str() {
+            "write_file" => (Some(cwd.to_path_buf()), vec![tool.path.clone()]),
+            "run_cmd" => {
+                let (dir, command) = after_cd(cwd, &tool.command);
+                (dir, write_targets(&command))
+            }
+            _ => return None,
+        };
+
+        let reasons: Vec<String> = targets
+            .iter()
+            .filter_map(|target| match &dir {
+                Some(dir) => self.check_write_in(cwd, dir, target),
+                None if is_absolute(target) => self.check_write(cwd, target),
+                // `cd -` or `cd $DIR`: a relative target could be anywhere
+                None => Some(format!(
+                    "writes {} after a cd that can't be followed",
+                    target
+                )),
+            })
+            .collect();
+        (!reasons.is_empty()).then_some(DangerAssessment {
+            severity: self.severity,
+            reasons,
+        })
+    }
+
+    /// Why writing `path` needs confirmation, or None if it is allowed
+    pub fn check_write(&self, cwd: &Path, path: &str) -> Option<String> {
+        self.check_write_in(cwd, cwd, path)
+    }
+
+    /// `check_write` for a `path` relative to `dir`, a command's new directory
+    fn check_write_in(&self, cwd: &Path, dir: &Path, path: &str) -> Option<String> {
+        let resolved = resolve_real(dir, path);
+
+        if let Some(pattern) = self
+            .protected_paths
+            .iter()
+            .find(|p| matches_pattern(cwd, p, &resolved))
+        {
+            return Some(format!("writes protected path {} ({})", path, pattern));
+        }
+
+        let inside_roots = self.writable_roots.is_empty()
+            || self
+                .writable_roots
+                .iter()
+                .any(|root| resolved.starts_with(resolve_real(cwd, root)));
+        if !inside_roots {
+            return Some(format!("writes {} outside the writable roots", path));
+        }
+        None
+    }
+}
+
+/// Files a shell command writes through `>`, `>>`, `&>`, `tee`, or as
+/// arguments of `rm`, `mv`, `cp`, `truncate` and `sed -i`
+pub fn write_targets(command: &str) -> Vec<String> {
+    let redirect =
+        Regex::new(r#"(?:&>>?|[0-9]*>>?\|?)\s*([^\s;&|<>()]+)"#).expect("valid redirection regex");
+    let tee =
+        Regex::new(r#"\btee\s+(?:-a\s+)?([^\s;&|<>()-][^\s;&|<>()]*)"#).expect("valid tee regex");
+    let input = Regex::new(r#"[0-9]*<\s*[^\s;&|<>()]+"#).expect("valid input regex");
+    let separators = Regex::new(r"\|\||&&|[;|&\n]").expect("valid separator regex");
+
+    let redirected = redirect
+        .captures_iter(command)
+        .chain(tee.captures_iter(command))
+        .map(|c| c[1].to_string());
+    let arguments = separators.split(command).flat_map(|segment| {
+        let segment = redirect.replace_all(segment, " ");
+        let segment = input.replace_all(&segment, " ");
+        let words = shell_words::split(&segment)
+            .unwrap_or_else(|_| segment.split_whitespace().map(String::from).collect());
+        argument_targets(&words)
+    });
+
+    redirected
+        .chain(arguments)
+        .map(|t| t.trim_matches(['"', '\'']).to_string())
+        .filter(|t| !t.is_empty() && !HARMLESS_TARGETS.contains(&t.as_str()))
+        .collect()
+}
+
+/// Files a single command writes through its arguments
+fn argument_targets(words: &[String]) -> Vec<String> {
+    // Skip `NAME=value` prefixes and wrappers like sudo or env
+    let start = words
+        .iter()
+        .map(|w| w.trim_start_matches(['(', '{']))
+        .position(|w| {
+            !w.is_empty()
+                && !executor::is_env_assignment(w)
+                && w != "sudo"
+                && !COMMAND_WRAPPERS.contains(&w)
+        });
+    let Some((program, args)) = start.and_then(|i| words[i..].split_first()) else {
+        return Vec::new();
+    };
+    let program = program.trim_start_matches(['(', '{']);
+    let name = program.rsplit('/').next().unwrap_or(program);
+    // Options whose value is the next word
+    let valued: &[&str] = match name {
+        "rm" => &[],
+        "mv" | "cp" => &["-t", "--target-directory", "-S", "--suffix"],
+        "truncate" => &["-s", "--size", "-r", "--reference"],
+        "sed" => &["-e", "--expression", "-f", "--file", "-l", "--line-length"],
+        _ => return Vec::new(),
+    };
+
+    let mut operands = Vec::new();
+    let mut target_dir = None;
+    let mut script_given = false;
+    let mut in_place = false;
+    let mut options_done = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if options_done || !arg.starts_with('-') || arg == "-" {
+            operands.push(arg.clone());
+        } else if arg == "--" {
+            options_done = true;
+        } else if valued.contains(&arg.as_str()) {
+            let value = args.next().cloned();
+            match arg.as_str() {
+                "-t" | "--target-directory" => target_dir = value,
+                "-e" | "--expression" | "-f" | "--file" => script_given = true,
+                _ => {}
+            }
+        } else if let Some(dir) = arg.strip_prefix("--target-directory=") {
+            target_dir = Some(dir.to_string());
+        } else if arg.starts_with("--expression=") || arg.starts_with("--file=") {
+            script_given = true;
+        } else if arg == "--in-place" || arg.starts_with("--in-place=") {
+            in_place = true;
+        } else if !arg.starts_with("--") && arg.contains('i') {
+            // `-i`, `-i.bak` or a cluster like `-Ei`
+            in_place = true;
+        }
+    }
+
+    match name {
+        "cp" => target_dir.or_else(|| operands.pop()).into_iter().collect(),
+        "mv" => operands.into_iter().chain(target_dir).collect(),
+        // The first operand is the script unless -e or -f gave one
+        "sed" if in_place => operands
+            .into_iter()
+            .skip(usize::from(!script_given))
+            .collect(),
+        "sed" => Vec::new(),
+        _ => operands,
+    }
+}
+
+/// The directory a command's leading `cd`s move to, and what runs there
+///
+/// The directory is None when it can't be known here, as for `cd -`.
+fn after_cd(cwd: &Path, command: &str) -> (Option<PathBuf>, String) {
+    let mut dir = Some(cwd.to_path_buf());
+    let mut command = command.to_string();
+    while let Some((target, rest)) = executor::split_cd(&command) {
+        let followable = target != "-" && !target.contains(['$', '`']);
+        dir = dir
+            .filter(|_| followable)
+            .map(|dir| resolve_real(&dir, &target));
+        command = rest.unwrap_or_default();
+    }
+    (dir, command)
+}
+
+/// Whether `path` names the same file from any directory
+fn is_absolute(path: &str) -> bool {
+    Path::new(&expand_home(path)).is_absolute()
+}
+
+/// Whether `resolved` falls under a protected path pattern
+fn matches_pattern(cwd: &Path, pattern: &str, resolved: &Path) -> bool {
+    match pattern.split_once('*') {
+        // `~/.*` protects every dotfile directly in (and below) the home dir
+        Some((prefix, _)) => {
+            let prefix = expand_home(prefix);
+            let (dir, start) = prefix.rsplit_once('/').unwrap_or((".", &prefix));
+            let dir = resolve_real(cwd, if dir.is_empty() { "/" } else { dir });
+            let prefix = dir.join(start);
+            resolved
+                .to_string_lossy()
+                .starts_with(&*prefix.to_string_lossy())
+        }
+        None => resolved.starts_with(resolve_real(cwd, pattern)),
+    }
+}
+
+/// Expand a leading `~` or `$HOME`
+fn expand_home(path: &str) -> String {
+    let home = dirs::home_dir().map(|h| h.to_string_lossy().into_owned());
+    match home {
+        Some(home) if path == "~" || path.starts_with("~/") => path.replacen('~', &home, 1),
+        Some(home) if path.starts_with("$HOME") => path.replacen("$HOME", &home, 1),
+        _ => path.to_string(),
+    }
+}
+
+/// Absolute, lexically normalized form of `path` relative to `cwd`
+pub fn resolve(cwd: &Path, path: &str) -> PathBuf {
+    let expanded = PathBuf::from(expand_home(path));
+    let joined = if expanded.is_absolute() {
+        expanded
+    } else {
+        cwd.join(expanded)
+    };
+
+    let mut normalized = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// `resolve`, following symlinks in the longest part of the path that exists
+///
+/// `..` after a symlink climbs from where the link points, as the kernel
+/// would; the part that doesn't exist yet is normalized lexically.
+pub fn resolve_real(cwd: &Path, path: &str) -> PathBuf {
+    let expanded = PathBuf::from(expand_home(path));
+    let joined = cwd.join(expanded);
+    let components: Vec<Component> = joined.components().collect();
+    for split in (1..=components.len()).rev() {
+        let existing: PathBuf = components[..split].iter().collect();
+        let Ok(mut real) = existing.canonicalize() else {
+            continue;
+        };
+        for component in &components[split..] {
+            match component {
+                Component::ParentDir => {
+                    real.pop();
+                }
+                Component::CurDir => {}
+                other => real.push(other),
+            }
+        }
+        return real;
+    }
+    resolve(cwd, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn guard(roots: &[&str], protected: &[&str]) -> PathGuard {
+        PathGuard {
+            writable_roots: roots.iter().map(|s| s.to_string()).collect(),
+            protected_paths: protected.iter().map(|s| s.to_string()).collect(),
+            severity: Severity::Warn,
+        }
+    }
+
+    // **Feature: Sabi-TUI, Property: Redirection Targets**
+    #[test]
+    fn test_write_targets() {
+        assert_eq!(write_targets("echo hi > /etc/motd"), vec!["/etc/motd"]);
+        assert_eq!(write_targets("cmd >> log.txt 2>&1"), vec!["log.txt"]);
+        assert_eq!(write_targets("make &> build.log"), vec!["build.log"]);
+        assert_eq!(
+            write_targets("echo x | sudo tee -a /etc/hosts"),
+            vec!["/etc/hosts"]
+        );
+        assert!(write_targets("ls > /dev/null 2>&1").is_empty());
+        assert!(write_targets("cat < input.txt").is_empty());
+    }
+
+    #[test]
+    fn test_argument_write_targets() {
+        assert_eq!(
+            write_targets("rm -rf build /etc/x"),
+            vec!["build", "/etc/x"]
+        );
+        assert_eq!(write_targets("sudo rm -- -weird"), vec!["-weird"]);
+        assert_eq!(write_targets("mv a.txt /etc/b"), vec!["a.txt", "/etc/b"]);
+        assert_eq!(write_targets("cp -r src /etc/dst"), vec!["/etc/dst"]);
+        assert_eq!(write_targets("cp -t /etc/dst a b"), vec!["/etc/dst"]);
+        assert_eq!(
+            write_targets("truncate -s 0 /var/log/app.log"),
+            vec!["/var/log/app.log"]
+        );
+        assert_eq!(
+            write_targets("sed -i 's/a/b/' /etc/hosts"),
+            vec!["/etc/hosts"]
+        );
+        assert_eq!(write_targets("sed -Ei.bak -e 's/a/b/' x y"), vec!["x", "y"]);
+        assert_eq!(write_targets("ls && rm x 2>/dev/null"), vec!["x"]);
+        assert!(write_targets("sed 's/a/b/' /etc/hosts").is_empty());
+        assert!(write_targets("cp /etc/hosts .").iter().all(|t| t == "."));
+        assert!(write_targets("echo rm /etc/hosts").is_empty());
+    }
+
+    // **Feature: Sabi-TUI, Property: Workspace Path Protection**
+    #[test]
+    fn test_check_write() {
+        let cwd = Path::new("/work/project");
+        let guard = guard(&[".", "/tmp"], &["/etc", "/work/project/.git"]);
+
+        assert!(guard.check_write(cwd, "src/main.rs").is_none());
+        assert!(guard.check_write(cwd, "/tmp/out.txt").is_none());
+        let reason = |path| guard.check_write(cwd, path).unwrap_or_default();
+        assert!(reason("/etc/hosts").contains("protected"));
+        assert!(reason(".git/config").contains("protected"));
+        assert!(reason("../other/file").contains("outside"));
+        assert!(reason("/var/log/x").contains("outside"));
+    }
+
+    #[test]
+    fn test_home_dotfiles_protected() {
+        let Some(home) = dirs::home_dir() else {
+            return;
+        };
+        let guard = guard(&[], &["~/.*"]);
+
+        assert!(guard.check_write(&home, "~/.bashrc").is_some());
+        assert!(guard.check_write(&home, ".ssh/authorized_keys").is_some());
+        assert!(guard.check_write(&home, "notes.txt").is_none());
+    }
+
+    #[test]
+    fn test_check_tool() {
+        let cwd = Path::new("/work");
+        let guard = guard(&[], &["/etc"]);
+
+        let write = ToolCall {
+            tool: "write_file".to_string(),
+            path: "/etc/fstab".to_string(),
+            ..ToolCall::run_cmd("")
+        };
+        let danger = guard.check_tool(cwd, &write).unwrap();
+        assert_eq!(danger.severity, Severity::Warn);
+
+        assert!(
+            guard
+                .check_tool(cwd, &ToolCall::run_cmd("echo 1 > /etc/x"))
+                .is_some()
+        );
+        assert!(
+            guard
+                .check_tool(cwd, &ToolCall::run_cmd("cat /etc/hosts"))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_leading_cd_followed() {
+        let cwd = Path::new("/work");
+        let guard = guard(&["."], &["/etc"]);
+        let check = |command| guard.check_tool(cwd, &ToolCall::run_cmd(command));
+
+        assert!(check("cd /etc && echo x > hosts").is_some());
+        assert!(check("cd / && cd etc && rm hosts").is_some());
+        assert!(check("cd .. && touch x > y").is_some());
+        assert!(check("cd - && echo y >> .bashrc").is_some());
+        assert!(check("cd src && echo x > main.rs").is_none());
+        assert!(check("cd /etc && cat hosts > /work/hosts").is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_followed() {
+        let tmp = tempfile::tempdir().unwrap();
+        let work = tmp.path().join("work");
+        let outside = tmp.path().join("outside");
+        std::fs::create_dir_all(work.join("src")).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::os::unix::fs::symlink(&outside, work.join("out")).unwrap();
+        let roots = guard(&["."], &[]);
+
+        assert!(roots.check_write(&work, "src/new.rs").is_none());
+        // The link itself is in the workspace, where it leads isn't
+        assert!(roots.check_write(&work, "out/file").is_some());
+        assert!(roots.check_write(&work, "out/../file").is_some());
+        // A root given through a link is the same root
+        let linked = tmp.path().join("linked");
+        std::os::unix::fs::symlink(&work, &linked).unwrap();
+        assert!(roots.check_write(&linked, "src/new.rs").is_none());
+
+        let protected = guard(&[], &[outside.to_str().unwrap()]);
+        assert!(protected.check_write(&work, "out/file").is_some());
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(100))]
+
+        // **Feature: Sabi-TUI, Property: Parent Traversal Cannot Escape Roots**
+        // *For any* path climbing above the root with `..`, the write SHALL be flagged.
+        #[test]
+        fn prop_parent_traversal_flagged(depth in 1usize..5, name in "[a-z]{1,8}") {
+            let cwd = Path::new("/work/project");
+            let guard = guard(&["."], &[]);
+            let path = format!("{}{}", "../".repeat(depth), name);
+            prop_assert!(guard.check_write(cwd, &path).is_some());
+        }
+    }
+}
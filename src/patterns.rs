@@ -0,0 +1,179 @@
+//! `/patterns`: managing the dangerous-command patterns from the app
+//!
+//! Lists the built-in categories and the `dangerous_patterns` of the
+//! config, tests a command against them, and adds or removes patterns,
+//! writing the list back to config.toml.
+
+use std::path::Path;
+
+use regex::Regex;
+
+use crate::config::{Config, DANGER_CATEGORIES, DangerousPattern, Severity, category_patterns};
+use crate::executor::DangerousCommandDetector;
+use crate::settings;
+
+/// Categories and configured patterns, numbered for `/patterns remove`
+pub fn list(config: &Config) -> String {
+    let mut out = String::from("Built-in categories (danger_categories in config):\n");
+    for (name, _, description) in DANGER_CATEGORIES {
+        let state = if config.danger_category_enabled(name) {
+            "on "
+        } else {
+            "off"
+        };
+        let count = category_patterns(name).len();
+        out.push_str(&format!(
+            "  {} {} ({} patterns): {}\n",
+            state, name, count, description
+        ));
+    }
+    out.push_str("\nPatterns (dangerous_patterns):\n");
+    if config.dangerous_patterns.is_empty() {
+        out.push_str("  none\n");
+    }
+    for (i, pattern) in config.dangerous_patterns.iter().enumerate() {
+        out.push_str(&format!(
+            "  {}. [{}] {}",
+            i + 1,
+            pattern.severity.name(),
+            pattern.pattern
+        ));
+        if !pattern.reason.is_empty() {
+            out.push_str(&format!("  ({})", pattern.reason));
+        }
+        out.push('\n');
+    }
+    out.push_str(
+        "\n/patterns add [warn|block] <regex> [-- reason], /patterns remove <n>, \
+         /patterns test <command>",
+    );
+    out
+}
+
+/// What the detector makes of `command`
+pub fn test(config: &Config, command: &str) -> String {
+    let detector = DangerousCommandDetector::from_rules(&config.effective_dangerous_patterns());
+    match detector.assess(command) {
+        Some(danger) => {
+            let verdict = match danger.severity {
+                Severity::Block => "⛔ Blocked",
+                Severity::Warn => "⚠ Needs 2-step confirmation",
+            };
+            format!("{}: {}", verdict, danger.reasons.join("; "))
+        }
+        None => "✓ No pattern matches".to_string(),
+    }
+}
+
+/// A pattern from `[warn|block] <regex> [-- reason]` (warn by default)
+pub fn parse(arg: &str) -> Result<DangerousPattern, String> {
+    let arg = arg.trim();
+    let (first, rest) = arg.split_once(char::is_whitespace).unwrap_or((arg, ""));
+    let (severity, rest) = match Severity::parse(first) {
+        Some(severity) => (severity, rest.trim()),
+        None => (Severity::Warn, arg),
+    };
+    let (pattern, reason) = rest.split_once(" -- ").unwrap_or((rest, ""));
+    let pattern = pattern.trim();
+    if pattern.is_empty() {
+        return Err("Usage: /patterns add [warn|block] <regex> [-- reason]".to_string());
+    }
+    if let Err(e) = Regex::new(pattern) {
+        let error = e.to_string();
+        let reason = error.lines().last().unwrap_or_default().trim().to_string();
+        return Err(format!("'{}' isn't a valid regex: {}", pattern, reason));
+    }
+    Ok(DangerousPattern::new(pattern, severity, reason.trim()))
+}
+
+/// Index of pattern `n` (1-based, as listed) or of the pattern written as `arg`
+pub fn find(patterns: &[DangerousPattern], arg: &str) -> Option<usize> {
+    let arg = arg.trim();
+    match arg.parse::<usize>() {
+        Ok(n) => (1..=patterns.len()).contains(&n).then(|| n - 1),
+        Err(_) => patterns.iter().position(|p| p.pattern == arg),
+    }
+}
+
+/// Write `patterns` to the config file at `path` as `dangerous_patterns`
+pub fn save(path: &Path, patterns: &[DangerousPattern]) -> Result<(), String> {
+    let mut array = toml_edit::Array::new();
+    for pattern in patterns {
+        let mut rule = toml_edit::InlineTable::new();
+        rule.insert("pattern", pattern.pattern.as_str().into());
+        rule.insert("severity", pattern.severity.name().into());
+        if !pattern.reason.is_empty() {
+            rule.insert("reason", pattern.reason.as_str().into());
+        }
+        array.push(rule);
+    }
+    // One pattern per line
+    for value in array.iter_mut() {
+        value.decor_mut().set_prefix("\n    ");
+    }
+    array.set_trailing("\n");
+    array.set_trailing_comma(true);
+    settings::write_key(path, "dangerous_patterns", toml_edit::value(array))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // **Feature: Sabi-TUI, Property: Pattern Arguments**
+    #[test]
+    fn test_parse() {
+        let pattern = parse("block terraform\\s+destroy -- wipes infrastructure").unwrap();
+        assert_eq!(
+            pattern,
+            DangerousPattern::new(
+                r"terraform\s+destroy",
+                Severity::Block,
+                "wipes infrastructure"
+            )
+        );
+        let pattern = parse("kubectl delete").unwrap();
+        assert_eq!(pattern.severity, Severity::Warn);
+        assert_eq!(pattern.pattern, "kubectl delete");
+        assert!(parse("block").is_err());
+        assert!(parse("rm (").unwrap_err().contains("isn't a valid regex"));
+    }
+
+    // **Feature: Sabi-TUI, Property: Pattern Test**
+    #[test]
+    fn test_test_and_find() {
+        let mut config = Config::default();
+        assert_eq!(test(&config, "terraform destroy"), "✓ No pattern matches");
+        config
+            .dangerous_patterns
+            .push(parse("block terraform destroy -- wipes infrastructure").unwrap());
+        assert_eq!(
+            test(&config, "terraform destroy -auto-approve"),
+            "⛔ Blocked: wipes infrastructure"
+        );
+
+        let patterns = &config.dangerous_patterns;
+        assert_eq!(find(patterns, "2"), Some(1));
+        assert_eq!(find(patterns, "terraform destroy"), Some(1));
+        assert_eq!(find(patterns, "9"), None);
+        assert_eq!(find(patterns, "0"), None);
+    }
+
+    // **Feature: Sabi-TUI, Property: Pattern Persistence**
+    #[test]
+    fn test_save_round_trips() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("config.toml");
+        std::fs::write(&path, "# settings\nmodel = \"m\"\n").unwrap();
+        let patterns = vec![
+            DangerousPattern::new(r":\(\)\s*\{", Severity::Block, "fork bomb"),
+            DangerousPattern::new("kubectl delete", Severity::Warn, ""),
+        ];
+        save(&path, &patterns).unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with("# settings\nmodel = \"m\"\n"));
+        let config: Config = toml::from_str(&text).unwrap();
+        assert_eq!(config.dangerous_patterns, patterns);
+    }
+}
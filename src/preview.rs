@@ -0,0 +1,156 @@
+//! File preview shown next to the chat while a file tool is reviewed
+//!
+//! For read_file the file is shown from the top. For write_file the new
+//! content is shown with the lines that differ from the file on disk
+//! marked, centered on the first change, so an overwrite can be checked
+//! without leaving the TUI.
+
+use std::path::Path;
+
+use crate::tool_call::ToolCall;
+
+/// Files larger than this are not previewed
+const MAX_PREVIEW_BYTES: u64 = 1024 * 1024;
+
+/// One numbered line of the preview
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreviewLine {
+    pub number: usize,
+    pub text: String,
+    /// Differs from the file on disk (write_file only)
+    pub changed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilePreview {
+    pub title: String,
+    pub lines: Vec<PreviewLine>,
+    /// Index of the line to center the view on
+    pub focus: usize,
+}
+
+/// Whether `tool` has a file worth previewing
+pub fn applies_to(tool: &ToolCall) -> bool {
+    (tool.is_read_file() || tool.is_write_file()) && !tool.path.is_empty()
+}
+
+/// Text of `path`, None if it doesn't exist
+fn read_text(path: &Path) -> Result<Option<String>, String> {
+    let meta = match std::fs::metadata(path) {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.to_string()),
+    };
+    if meta.len() > MAX_PREVIEW_BYTES {
+        return Err(format!("too large to preview ({} bytes)", meta.len()));
+    }
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+fn numbered<'a>(lines: impl Iterator<Item = &'a str>) -> Vec<PreviewLine> {
+    lines
+        .enumerate()
+        .map(|(index, text)| PreviewLine {
+            number: index + 1,
+            text: text.to_string(),
+            changed: false,
+        })
+        .collect()
+}
+
+/// Preview of the file `tool` reads or writes, relative to `cwd`
+pub fn build(tool: &ToolCall, cwd: &Path) -> Option<FilePreview> {
+    if !applies_to(tool) {
+        return None;
+    }
+    let on_disk = read_text(&cwd.join(&tool.path));
+
+    if tool.is_read_file() {
+        let (title, lines) = match on_disk {
+            Ok(Some(text)) => (tool.path.clone(), numbered(text.lines())),
+            Ok(None) => (format!("{} (does not exist)", tool.path), Vec::new()),
+            Err(e) => (format!("{} ({})", tool.path, e), Vec::new()),
+        };
+        return Some(FilePreview {
+            title,
+            lines,
+            focus: 0,
+        });
+    }
+
+    let old = on_disk.as_ref().ok().cloned().flatten();
+    let old_lines: Vec<&str> = old.as_deref().unwrap_or("").lines().collect();
+    let mut lines = numbered(tool.content.lines());
+    for (index, line) in lines.iter_mut().enumerate() {
+        line.changed = old_lines.get(index) != Some(&line.text.as_str());
+    }
+    let changed = lines.iter().filter(|l| l.changed).count();
+    let removed = old_lines.len().saturating_sub(lines.len());
+    let title = match on_disk {
+        Ok(None) => format!("{} (new file)", tool.path),
+        Ok(Some(_)) if removed > 0 => {
+            format!("{} ({} changed, {} removed)", tool.path, changed, removed)
+        }
+        Ok(Some(_)) => format!("{} ({} changed)", tool.path, changed),
+        Err(e) => format!("{} ({})", tool.path, e),
+    };
+    let focus = lines.iter().position(|l| l.changed).unwrap_or(0);
+    Some(FilePreview {
+        title,
+        lines,
+        focus,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_file(path: &str, content: &str) -> ToolCall {
+        let mut tool = ToolCall::new("write_file", "");
+        tool.path = path.to_string();
+        tool.content = content.to_string();
+        tool
+    }
+
+    // **Feature: Sabi-TUI, Property: File Preview**
+    #[test]
+    fn test_write_preview_marks_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "one\ntwo\nthree\nfour\n").unwrap();
+
+        let preview = build(&write_file("a.txt", "one\ntwo\nTHREE\n"), dir.path()).unwrap();
+        assert_eq!(preview.focus, 2);
+        assert_eq!(preview.title, "a.txt (1 changed, 1 removed)");
+        let changed: Vec<bool> = preview.lines.iter().map(|l| l.changed).collect();
+        assert_eq!(changed, vec![false, false, true]);
+
+        let preview = build(&write_file("new.txt", "x\n"), dir.path()).unwrap();
+        assert_eq!(preview.title, "new.txt (new file)");
+        assert!(preview.lines[0].changed);
+    }
+
+    #[test]
+    fn test_read_preview_and_other_tools() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("b.txt"), "alpha\nbeta\n").unwrap();
+
+        let mut read = ToolCall::new("read_file", "");
+        read.path = "b.txt".to_string();
+        let preview = build(&read, dir.path()).unwrap();
+        assert_eq!(preview.lines.len(), 2);
+        assert_eq!(preview.lines[1].number, 2);
+        assert_eq!(preview.focus, 0);
+
+        read.path = "missing.txt".to_string();
+        assert!(
+            build(&read, dir.path())
+                .unwrap()
+                .title
+                .contains("does not exist")
+        );
+
+        assert_eq!(build(&ToolCall::run_cmd("ls"), dir.path()), None);
+    }
+}
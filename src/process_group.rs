@@ -0,0 +1,153 @@
+//! Process group tracking
+//!
+//! Streaming commands run in their own process group (PTY commands get a
+//! new session, which is the same thing). Aborting the task only kills the
+//! shell, so cancel and exit kill every tracked group to take the shell's
+//! children down with it.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long a group gets to exit after SIGTERM before SIGKILL
+#[cfg(unix)]
+const KILL_GRACE: Duration = Duration::from_millis(500);
+
+/// Longest command label shown in kill reports
+const LABEL_CHARS: usize = 60;
+
+/// Process groups of running commands, shared between executor and app
+#[derive(Debug, Clone, Default)]
+pub struct ProcessGroups(Arc<Mutex<BTreeMap<u32, String>>>);
+
+impl ProcessGroups {
+    /// Track the group led by `pgid`, labelled with its command
+    pub fn register(&self, pgid: u32, command: &str) {
+        let label = if command.chars().count() > LABEL_CHARS {
+            let cut: String = command.chars().take(LABEL_CHARS).collect();
+            format!("{}…", cut)
+        } else {
+            command.to_string()
+        };
+        self.lock().insert(pgid, label);
+    }
+
+    /// Stop tracking a group whose leader has been waited on
+    pub fn unregister(&self, pgid: u32) {
+        self.lock().remove(&pgid);
+    }
+
+    /// Whether no groups are tracked
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<u32, String>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// SIGTERM every tracked group; a background task SIGKILLs the ones
+    /// still alive after `KILL_GRACE`
+    ///
+    /// Returns a line per group describing what was killed, and the task,
+    /// which callers about to exit should await. Leaders are left for the
+    /// runtime that spawned them to reap.
+    #[cfg(unix)]
+    pub fn kill_all(&self) -> (Vec<String>, Option<tokio::task::JoinHandle<()>>) {
+        let groups = std::mem::take(&mut *self.lock());
+        if groups.is_empty() {
+            return (Vec::new(), None);
+        }
+
+        for &pgid in groups.keys() {
+            signal_group(pgid, libc::SIGTERM);
+        }
+        let pgids: Vec<u32> = groups.keys().copied().collect();
+        let escalate = tokio::spawn(async move {
+            tokio::time::sleep(KILL_GRACE).await;
+            for pgid in pgids {
+                signal_group(pgid, libc::SIGKILL);
+            }
+        });
+
+        let report = groups
+            .into_iter()
+            .map(|(pgid, label)| format!("{} (pgid {})", label, pgid))
+            .collect();
+        (report, Some(escalate))
+    }
+
+    /// Without process groups, dropping the task's child is all we can do
+    #[cfg(not(unix))]
+    pub fn kill_all(&self) -> (Vec<String>, Option<tokio::task::JoinHandle<()>>) {
+        let report = std::mem::take(&mut *self.lock())
+            .into_values()
+            .map(|label| format!("{} (killed)", label))
+            .collect();
+        (report, None)
+    }
+}
+
+/// Signal every process in the group; one that has exited is ignored
+#[cfg(unix)]
+fn signal_group(pgid: u32, signal: libc::c_int) {
+    // SAFETY: killpg has no memory-safety preconditions
+    unsafe {
+        libc::killpg(pgid as libc::pid_t, signal);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_truncates_label() {
+        let groups = ProcessGroups::default();
+        groups.register(1, &"x".repeat(100));
+        let label = groups.lock().get(&1).cloned().unwrap();
+        assert_eq!(label.chars().count(), LABEL_CHARS + 1);
+
+        groups.unregister(1);
+        assert!(groups.is_empty());
+    }
+
+    // **Feature: Sabi-TUI, Property: Cancel Kills Process Tree**
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_kill_all_kills_children() {
+        use std::io::{BufRead, BufReader};
+        use std::os::unix::process::CommandExt;
+
+        // The shell's background child would survive killing just the shell
+        let mut child = std::process::Command::new("sh")
+            .arg("-c")
+            .arg("sleep 30 & echo $!; wait")
+            .stdout(std::process::Stdio::piped())
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        let mut line = String::new();
+        BufReader::new(child.stdout.take().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        let sleep_pid = line.trim().to_string();
+
+        let groups = ProcessGroups::default();
+        groups.register(child.id(), "sleep 30 & wait");
+        let (report, escalate) = groups.kill_all();
+        assert_eq!(report.len(), 1);
+        assert!(report[0].contains("sleep 30"));
+        assert!(groups.is_empty());
+        escalate.unwrap().await.unwrap();
+
+        // Gone, or a zombie waiting for an init that may not reap it
+        let ps = std::process::Command::new("ps")
+            .args(["-o", "stat=", "-p", &sleep_pid])
+            .output()
+            .unwrap();
+        let state = String::from_utf8_lossy(&ps.stdout);
+        assert!(state.trim().is_empty() || state.trim().starts_with('Z'));
+        let _ = child.wait();
+    }
+}
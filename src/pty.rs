@@ -0,0 +1,459 @@
+//! PTY execution backend
+//!
+//! Runs commands attached to a pseudo-terminal so programs that check
+//! isatty (colors, progress bars, installers) behave as they would in a
+//! real terminal, then cleans the captured output for display and the AI.
+
+use std::io::Read;
+use std::path::Path;
+
+use portable_pty::{ChildKiller, CommandBuilder, PtySize, native_pty_system};
+
+use crate::config::{EnvPolicy, IoPriority, ResourceLimits, ShellInvocation};
+use crate::encoding::{StreamDecoder, decode_output};
+use crate::executor::OutputSink;
+use crate::process_group::ProcessGroups;
+
+/// Terminal size reported to PTY commands
+const PTY_SIZE: PtySize = PtySize {
+    rows: 40,
+    cols: 120,
+    pixel_width: 0,
+    pixel_height: 0,
+};
+
+/// Raw outcome of a PTY run (stdout and stderr are merged by the terminal)
+#[derive(Debug)]
+pub struct PtyOutput {
+    /// Cleaned terminal output
+    pub output: String,
+    /// Lines cut from the middle of long output to keep memory bounded
+    pub omitted: usize,
+    /// Exit code of the shell
+    pub exit_code: i32,
+    /// Whether the shell exited successfully
+    pub success: bool,
+}
+
+/// Kills the PTY child when dropped, so aborting the task cancels the command
+struct KillOnDrop(Option<Box<dyn ChildKiller + Send + Sync>>);
+
+impl KillOnDrop {
+    fn disarm(&mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for KillOnDrop {
+    fn drop(&mut self) {
+        if let Some(ref mut killer) = self.0 {
+            let _ = killer.kill();
+        }
+    }
+}
+
+/// Run `command` through `shell` inside a fresh PTY
+///
+/// Complete lines are forwarded to `sink` as they arrive (the last one at
+/// the end). Only about `max_bytes` from each end of the output are kept;
+/// the middle is cut out as it grows, as `run_streaming` does.
+#[allow(clippy::too_many_arguments)]
+pub async fn run_in_pty(
+    shell: &ShellInvocation,
+    command: &str,
+    cwd: Option<&Path>,
+    env: &EnvPolicy,
+    limits: ResourceLimits,
+    max_bytes: usize,
+    sink: Option<OutputSink>,
+    groups: Option<ProcessGroups>,
+) -> Result<PtyOutput, String> {
+    let pair = native_pty_system()
+        .openpty(PTY_SIZE)
+        .map_err(|e| format!("Failed to open PTY: {}", e))?;
+
+    let mut cmd = shell_command(shell, command, limits);
+    if let Some(dir) = cwd {
+        cmd.cwd(dir);
+    }
+    if env.scrub {
+        cmd.env_clear();
+        for (key, value) in std::env::vars().filter(|(key, _)| env.allows(key)) {
+            cmd.env(key, value);
+        }
+    }
+    for (key, value) in &env.extra {
+        cmd.env(key, value);
+    }
+
+    let mut child = pair
+        .slave
+        .spawn_command(cmd)
+        .map_err(|e| format!("Failed to execute: {}", e))?;
+    // Only the child may hold the slave, or reads never see EOF
+    drop(pair.slave);
+
+    let mut reader = pair
+        .master
+        .try_clone_reader()
+        .map_err(|e| format!("Failed to read PTY: {}", e))?;
+    let mut guard = KillOnDrop(Some(child.clone_killer()));
+    // The PTY child leads a new session, so its pid is also its process group
+    let pgid = child.process_id();
+    if let (Some(groups), Some(pgid)) = (groups.as_ref(), pgid) {
+        groups.register(pgid, command);
+    }
+    let master = pair.master;
+
+    let handle = tokio::task::spawn_blocking(move || {
+        let mut raw = Vec::new();
+        let mut omitted = 0;
+        let mut decoder = StreamDecoder::default();
+        let mut buf = [0u8; 4096];
+        let forward = |lines: Vec<String>| {
+            if let Some(ref sink) = sink {
+                for line in lines {
+                    sink(
+                        clean_terminal_output(&line)
+                            .trim_end_matches('\n')
+                            .to_string(),
+                    );
+                }
+            }
+        };
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    raw.extend_from_slice(&buf[..n]);
+                    if raw.len() > 4 * max_bytes {
+                        omitted += squeeze(&mut raw, max_bytes);
+                    }
+                    if sink.is_some() {
+                        forward(decoder.push(&buf[..n]));
+                    }
+                }
+            }
+        }
+        forward(decoder.finish().0);
+        let status = child.wait();
+        drop(master);
+        (raw, omitted, status)
+    });
+
+    let (raw, omitted, status) = handle
+        .await
+        .map_err(|e| format!("PTY reader failed: {}", e))?;
+    guard.disarm();
+    if let (Some(groups), Some(pgid)) = (groups.as_ref(), pgid) {
+        groups.unregister(pgid);
+    }
+
+    let status = status.map_err(|e| format!("Failed to wait for command: {}", e))?;
+    Ok(PtyOutput {
+        output: clean_terminal_output(&decode_output(&raw)),
+        omitted,
+        exit_code: status.exit_code() as i32,
+        success: status.success(),
+    })
+}
+
+/// The command line running `command` through `shell`
+///
+/// Resource limits cannot be set with pre_exec here, so on Unix a `sh`
+/// sets them with its `ulimit` built-in and then execs the shell, which
+/// works whatever syntax the shell itself speaks.
+fn shell_command(shell: &ShellInvocation, command: &str, limits: ResourceLimits) -> CommandBuilder {
+    let mut cmd = match limits_script(limits) {
+        Some(script) if cfg!(unix) => {
+            let mut cmd = CommandBuilder::new("sh");
+            cmd.args(["-c", &script]);
+            cmd.arg(&shell.program);
+            cmd
+        }
+        _ => CommandBuilder::new(&shell.program),
+    };
+    cmd.arg(&shell.flag);
+    cmd.arg(command);
+    cmd
+}
+
+/// Cut the middle out of `raw`, keeping about `keep` bytes at each end
+///
+/// Cuts fall on line breaks where there are any, so escape sequences and
+/// characters stay whole. Returns the number of lines removed, at least 1.
+fn squeeze(raw: &mut Vec<u8>, keep: usize) -> usize {
+    let keep = keep.min(raw.len());
+    let head_end = raw[..keep]
+        .iter()
+        .rposition(|&b| b == b'\n')
+        .map_or(keep, |i| i + 1);
+    let start = raw.len() - keep;
+    let tail_start = raw[start..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or(start, |i| start + i + 1);
+    if tail_start <= head_end {
+        return 0;
+    }
+    let removed = raw[head_end..tail_start]
+        .iter()
+        .filter(|&&b| b == b'\n')
+        .count();
+    raw.drain(head_end..tail_start);
+    removed.max(1)
+}
+
+/// `sh -c` script applying `limits` before it execs its arguments, if any
+/// limit is set
+pub fn limits_script(limits: ResourceLimits) -> Option<String> {
+    let prefix = ulimit_prefix(limits);
+    (!prefix.is_empty()).then(|| format!("{}exec \"$0\" \"$@\"", prefix))
+}
+
+/// `ulimit`, `renice` and `ionice` commands equivalent to the configured limits
+///
+/// File size uses 512-byte blocks as POSIX specifies; shells that count in
+/// KiB end up with a looser limit rather than a stricter one. Priority
+/// changes are best effort and silent if the tools are missing.
+fn ulimit_prefix(limits: ResourceLimits) -> String {
+    let mut prefix = String::new();
+    if limits.nice > 0 {
+        prefix.push_str(&format!(
+            "renice -n {} -p $$ >/dev/null 2>&1; ",
+            limits.nice
+        ));
+    }
+    match limits.io_priority {
+        IoPriority::Normal => {}
+        IoPriority::Low => prefix.push_str("ionice -c 2 -n 7 -p $$ >/dev/null 2>&1; "),
+        IoPriority::Idle => prefix.push_str("ionice -c 3 -p $$ >/dev/null 2>&1; "),
+    }
+    if limits.cpu_seconds > 0 {
+        prefix.push_str(&format!("ulimit -t {}; ", limits.cpu_seconds));
+    }
+    if limits.memory_bytes > 0 {
+        prefix.push_str(&format!("ulimit -v {}; ", limits.memory_bytes / 1024));
+    }
+    if limits.file_size_bytes > 0 {
+        prefix.push_str(&format!("ulimit -f {}; ", limits.file_size_bytes / 512));
+    }
+    prefix
+}
+
+/// Strip ANSI escape sequences and resolve carriage returns and backspaces
+///
+/// Progress bars redraw a line with `\r`; only the final state of each line
+/// is kept. Line endings are normalized to `\n`.
+pub fn clean_terminal_output(raw: &str) -> String {
+    let stripped = strip_ansi(raw);
+    let mut out = String::with_capacity(stripped.len());
+
+    for (i, line) in stripped.split('\n').enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        let line = line.trim_end_matches('\r');
+        // Text after the last carriage return overwrites the start of the line
+        let mut visible: Vec<char> = Vec::new();
+        for segment in line.split('\r') {
+            let chars: Vec<char> = segment.chars().collect();
+            if chars.len() >= visible.len() {
+                visible = chars;
+            } else {
+                visible.splice(..chars.len(), chars);
+            }
+        }
+        let mut resolved = String::new();
+        for c in visible {
+            if c == '\u{8}' {
+                resolved.pop();
+            } else {
+                resolved.push(c);
+            }
+        }
+        out.push_str(&resolved);
+    }
+
+    out
+}
+
+/// Remove CSI, OSC, and other escape sequences
+pub fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\u{1b}' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            // CSI: ESC [ params... final byte in @..~
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC: ESC ] ... terminated by BEL or ESC \
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\u{7}' {
+                        break;
+                    }
+                    if c == '\u{1b}' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            // Charset designation takes one more character
+            Some('(' | ')' | '*' | '+') => {
+                chars.next();
+            }
+            _ => {}
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    // **Feature: Sabi-TUI, Property: Terminal Output Cleaning**
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(strip_ansi("\u{1b}[1;31mred\u{1b}[0m"), "red");
+        assert_eq!(strip_ansi("\u{1b}]0;title\u{7}text"), "text");
+        assert_eq!(strip_ansi("\u{1b}(Bplain"), "plain");
+    }
+
+    #[test]
+    fn test_clean_terminal_output_progress() {
+        assert_eq!(
+            clean_terminal_output("10%\r50%\r100%\r\ndone\r\n"),
+            "100%\ndone\n"
+        );
+        assert_eq!(clean_terminal_output("abcdef\rXY"), "XYcdef");
+        assert_eq!(clean_terminal_output("ab\u{8}c"), "ac");
+    }
+
+    #[test]
+    fn test_ulimit_prefix() {
+        assert_eq!(ulimit_prefix(ResourceLimits::default()), "");
+        let limits = ResourceLimits {
+            cpu_seconds: 5,
+            memory_bytes: 1024 * 1024,
+            file_size_bytes: 1024,
+            ..Default::default()
+        };
+        assert_eq!(
+            ulimit_prefix(limits),
+            "ulimit -t 5; ulimit -v 1024; ulimit -f 2; "
+        );
+        let limits = ResourceLimits {
+            nice: 5,
+            io_priority: IoPriority::Idle,
+            ..Default::default()
+        };
+        assert_eq!(
+            ulimit_prefix(limits),
+            "renice -n 5 -p $$ >/dev/null 2>&1; ionice -c 3 -p $$ >/dev/null 2>&1; "
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_in_pty_is_a_tty() {
+        let shell = ShellInvocation::default();
+        let result = run_in_pty(
+            &shell,
+            "test -t 1 && echo tty",
+            None,
+            &EnvPolicy::default(),
+            ResourceLimits::default(),
+            4096,
+            None,
+            None,
+        )
+        .await;
+
+        // Sandboxes without /dev/ptmx cannot open a PTY
+        if let Ok(out) = result {
+            assert!(out.success);
+            assert_eq!(out.output.trim(), "tty");
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_in_pty_keeps_output_bounded() {
+        let result = run_in_pty(
+            &ShellInvocation::default(),
+            "i=0; while [ $i -lt 5000 ]; do echo line $i; i=$((i+1)); done",
+            None,
+            &EnvPolicy::default(),
+            ResourceLimits::default(),
+            1024,
+            None,
+            None,
+        )
+        .await;
+
+        if let Ok(out) = result {
+            assert!(out.omitted > 0);
+            assert!(out.output.len() <= 5 * 1024, "{}", out.output.len());
+            assert!(out.output.starts_with("line 0\n"));
+            assert!(out.output.trim_end().ends_with("line 4999"));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_limits_set_before_any_shell() {
+        let pwsh = ShellInvocation::for_program("pwsh", false);
+        let argv = |limits| -> Vec<String> {
+            shell_command(&pwsh, "Get-Date", limits)
+                .get_argv()
+                .iter()
+                .map(|a| a.to_string_lossy().into_owned())
+                .collect()
+        };
+
+        assert_eq!(
+            argv(ResourceLimits::default()),
+            ["pwsh", "-Command", "Get-Date"]
+        );
+        let limits = ResourceLimits {
+            cpu_seconds: 5,
+            ..Default::default()
+        };
+        assert_eq!(
+            argv(limits),
+            [
+                "sh",
+                "-c",
+                "ulimit -t 5; exec \"$0\" \"$@\"",
+                "pwsh",
+                "-Command",
+                "Get-Date"
+            ]
+        );
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(100))]
+
+        // **Feature: Sabi-TUI, Property: Plain Text Unchanged**
+        // *For any* text without control characters, cleaning SHALL be the identity.
+        #[test]
+        fn prop_clean_plain_text_is_identity(text in "[a-zA-Z0-9 .,:/\n-]{0,200}") {
+            prop_assert_eq!(clean_terminal_output(&text), text);
+        }
+    }
+}
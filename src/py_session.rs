@@ -0,0 +1,375 @@
+//! Persistent Python session for run_python
+//!
+//! With `python_session` on, run_python code goes to one long-lived
+//! interpreter instead of a fresh `python -c` each time, so variables,
+//! imports and loaded data survive between tool calls. Requests and
+//! replies are JSON lines over the interpreter's stdin/stdout, replies
+//! behind a marker unique to the interpreter; like a notebook cell, the
+//! value of a trailing expression is printed.
+
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Deserialize;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command as TokioCommand};
+
+use crate::process_group::ProcessGroups;
+
+/// Driver run inside the interpreter
+///
+/// The real stdin/stdout carry the protocol; user code gets an empty stdin
+/// and captured stdout/stderr. Subprocesses still write to the real stdout,
+/// so replies start with the marker in `sys.argv[1]` and may follow their
+/// output on the same line.
+const DRIVER: &str = r#"
+import ast, contextlib, io, json, os, sys, traceback
+_proto_in, _proto_out, _mark = sys.stdin, sys.stdout, sys.argv[1]
+sys.stdin = io.StringIO()
+_ns = {"__name__": "__main__"}
+for _line in _proto_in:
+    _req = json.loads(_line)
+    _out, _err, _ok = io.StringIO(), io.StringIO(), True
+    with contextlib.redirect_stdout(_out), contextlib.redirect_stderr(_err):
+        try:
+            if _req.get("cwd"):
+                os.chdir(_req["cwd"])
+            _tree = ast.parse(_req["code"], "<run_python>", "exec")
+            _last = None
+            if _tree.body and isinstance(_tree.body[-1], ast.Expr):
+                _last = ast.Expression(_tree.body.pop().value)
+            exec(compile(_tree, "<run_python>", "exec"), _ns)
+            if _last is not None:
+                _value = eval(compile(_last, "<run_python>", "eval"), _ns)
+                if _value is not None:
+                    print(repr(_value))
+        except SystemExit as _e:
+            _ok = _e.code in (None, 0)
+        except BaseException:
+            _ok = False
+            traceback.print_exc()
+    _proto_out.write(_mark + json.dumps({"id": _req["id"], "stdout": _out.getvalue(), "stderr": _err.getvalue(), "ok": _ok}) + "\n")
+    _proto_out.flush()
+"#;
+
+/// How long a run may go without a reply before the interpreter is given up on
+const REPLY_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Result of running code in the session
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub success: bool,
+    /// A previous interpreter died or was reset, so earlier state is gone
+    pub restarted: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct Reply {
+    id: u64,
+    stdout: String,
+    stderr: String,
+    ok: bool,
+}
+
+/// A running interpreter
+struct Session {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+    interpreter: PathBuf,
+    next_id: u64,
+    /// Starts every reply line
+    marker: String,
+}
+
+/// Shared handle to the session, started on first use
+#[derive(Clone, Default)]
+pub struct PythonSession {
+    inner: Arc<tokio::sync::Mutex<Option<Session>>>,
+    /// Set after a reset so the next run reports lost state
+    was_reset: Arc<std::sync::atomic::AtomicBool>,
+}
+
+impl PythonSession {
+    /// Run `code`, starting the interpreter with `spawn` if needed
+    ///
+    /// `spawn` returns a command for `interpreter` with cwd, environment and
+    /// limits applied. While code runs, the interpreter's process group is
+    /// registered in `groups`, so cancelling kills it; the next run then
+    /// starts a fresh interpreter.
+    pub async fn run(
+        &self,
+        interpreter: &Path,
+        spawn: impl FnOnce() -> TokioCommand,
+        cwd: Option<&Path>,
+        code: &str,
+        groups: Option<&ProcessGroups>,
+    ) -> Result<SessionOutput, String> {
+        let mut guard = self.inner.lock().await;
+        let mut restarted = self
+            .was_reset
+            .swap(false, std::sync::atomic::Ordering::Relaxed);
+        if restarted {
+            // Reset while busy: the old interpreter is still here
+            guard.take();
+        }
+
+        let alive = match guard.as_mut() {
+            Some(session) => {
+                session.interpreter == interpreter && matches!(session.child.try_wait(), Ok(None))
+            }
+            None => false,
+        };
+        if !alive {
+            restarted |= guard.take().is_some();
+            *guard = Some(start(interpreter, spawn)?);
+        }
+        let session = guard.as_mut().expect("session started above");
+
+        let id = session.next_id;
+        session.next_id += 1;
+        let request = serde_json::json!({
+            "id": id,
+            "code": code,
+            "cwd": cwd.map(|c| c.to_string_lossy().into_owned()),
+        });
+
+        let pgid = session.child.id();
+        if let (Some(groups), Some(pgid)) = (groups, pgid) {
+            groups.register(pgid, "python session");
+        }
+        let reply = exchange(session, &request.to_string(), id, REPLY_TIMEOUT).await;
+        if let (Some(groups), Some(pgid)) = (groups, pgid) {
+            groups.unregister(pgid);
+        }
+
+        match reply {
+            Ok((reply, stray)) => Ok(SessionOutput {
+                stdout: stray + &reply.stdout,
+                stderr: reply.stderr,
+                success: reply.ok,
+                restarted,
+            }),
+            Err(e) => {
+                *guard = None;
+                Err(e)
+            }
+        }
+    }
+
+    /// Stop the interpreter, discarding its state
+    ///
+    /// Returns false if no session was running.
+    pub fn reset(&self) -> bool {
+        match self.inner.try_lock() {
+            Ok(mut guard) => {
+                let running = guard.take().is_some();
+                if running {
+                    self.was_reset
+                        .store(true, std::sync::atomic::Ordering::Relaxed);
+                }
+                running
+            }
+            // Busy running code: flag it so the next run starts over
+            Err(_) => {
+                self.was_reset
+                    .store(true, std::sync::atomic::Ordering::Relaxed);
+                true
+            }
+        }
+    }
+}
+
+fn start(interpreter: &Path, spawn: impl FnOnce() -> TokioCommand) -> Result<Session, String> {
+    let mut cmd = spawn();
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.as_std_mut().process_group(0);
+    }
+    // Not something user code prints by accident
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+    let marker = format!("\u{1e}sabi-reply-{:x}-{:x}:", std::process::id(), nanos);
+    let mut child = cmd
+        .arg("-u")
+        .arg("-c")
+        .arg(DRIVER)
+        .arg(&marker)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to start Python session: {}", e))?;
+
+    let stdin = child.stdin.take().ok_or("Python session has no stdin")?;
+    let stdout = child.stdout.take().ok_or("Python session has no stdout")?;
+    Ok(Session {
+        child,
+        stdin,
+        stdout: BufReader::new(stdout).lines(),
+        interpreter: interpreter.to_path_buf(),
+        next_id: 0,
+        marker,
+    })
+}
+
+/// Send a request and wait up to `timeout` for the reply with `id`
+///
+/// Replies to earlier, abandoned requests are skipped. Other output (e.g.
+/// from subprocesses writing straight to the inherited stdout) is returned
+/// as stray output.
+async fn exchange(
+    session: &mut Session,
+    request: &str,
+    id: u64,
+    timeout: Duration,
+) -> Result<(Reply, String), String> {
+    tokio::time::timeout(timeout, send_and_read(session, request, id))
+        .await
+        .unwrap_or_else(|_| {
+            Err(format!(
+                "Python session didn't answer within {}s and was stopped",
+                timeout.as_secs()
+            ))
+        })
+}
+
+async fn send_and_read(
+    session: &mut Session,
+    request: &str,
+    id: u64,
+) -> Result<(Reply, String), String> {
+    let lost = |e: std::io::Error| format!("Python session lost: {}", e);
+    session
+        .stdin
+        .write_all(format!("{}\n", request).as_bytes())
+        .await
+        .map_err(lost)?;
+    session.stdin.flush().await.map_err(lost)?;
+
+    let mut stray = String::new();
+    loop {
+        let line = session
+            .stdout
+            .next_line()
+            .await
+            .map_err(lost)?
+            .ok_or("Python session exited")?;
+        let Some((before, json)) = line.split_once(&session.marker) else {
+            stray.push_str(&line);
+            stray.push('\n');
+            continue;
+        };
+        // Output without a trailing newline ends up in front of the reply
+        if !before.is_empty() {
+            stray.push_str(before);
+            stray.push('\n');
+        }
+        match serde_json::from_str::<Reply>(json) {
+            Ok(reply) if reply.id == id => return Ok((reply, stray)),
+            Ok(_) => {}
+            Err(e) => return Err(format!("Python session sent a bad reply: {}", e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn python() -> Option<PathBuf> {
+        std::process::Command::new("python3")
+            .arg("--version")
+            .output()
+            .ok()
+            .filter(|o| o.status.success())
+            .map(|_| PathBuf::from("python3"))
+    }
+
+    async fn run(session: &PythonSession, python: &Path, code: &str) -> SessionOutput {
+        session
+            .run(python, || TokioCommand::new(python), None, code, None)
+            .await
+            .unwrap()
+    }
+
+    // **Feature: Sabi-TUI, Property: Python Session Keeps State**
+    #[tokio::test]
+    async fn test_state_persists_between_runs() {
+        let Some(python) = python() else {
+            return;
+        };
+        let session = PythonSession::default();
+
+        let first = run(&session, &python, "x = 21\nprint('set')").await;
+        assert_eq!(first.stdout, "set\n");
+        assert!(first.success);
+        assert!(!first.restarted);
+
+        // A trailing expression is echoed like a notebook cell
+        let second = run(&session, &python, "x * 2").await;
+        assert_eq!(second.stdout, "42\n");
+
+        let error = run(&session, &python, "1 / 0").await;
+        assert!(!error.success);
+        assert!(error.stderr.contains("ZeroDivisionError"));
+
+        assert!(session.reset());
+        let after = run(&session, &python, "print('x' in globals())").await;
+        assert_eq!(after.stdout, "False\n");
+        assert!(after.restarted);
+    }
+
+    #[tokio::test]
+    async fn test_input_does_not_eat_protocol() {
+        let Some(python) = python() else {
+            return;
+        };
+        let session = PythonSession::default();
+
+        let result = run(&session, &python, "input()").await;
+        assert!(!result.success);
+        assert!(result.stderr.contains("EOFError"));
+        assert_eq!(run(&session, &python, "1 + 1").await.stdout, "2\n");
+    }
+
+    #[tokio::test]
+    async fn test_output_without_newline_keeps_protocol() {
+        let Some(python) = python() else {
+            return;
+        };
+        let session = PythonSession::default();
+
+        // Straight to the real stdout, like a subprocess, without a newline
+        let code = "import os\nos.write(1, b'no newline')\nprint('{\"id\": 0}')";
+        let result = run(&session, &python, code).await;
+        assert!(result.success);
+        assert_eq!(result.stdout, "no newline\n{\"id\": 0}\n");
+        assert_eq!(run(&session, &python, "1 + 1").await.stdout, "2\n");
+    }
+
+    #[tokio::test]
+    async fn test_reply_timeout() {
+        let Some(python) = python() else {
+            return;
+        };
+        let mut session = start(&python, || TokioCommand::new(&python)).unwrap();
+        let request = serde_json::json!({ "id": 0, "code": "import time\ntime.sleep(30)" });
+        let error = exchange(
+            &mut session,
+            &request.to_string(),
+            0,
+            Duration::from_millis(200),
+        )
+        .await
+        .unwrap_err();
+        assert!(error.contains("didn't answer within"));
+    }
+}
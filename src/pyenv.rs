@@ -0,0 +1,250 @@
+//! Project Python environments
+//!
+//! run_python uses the project's virtualenv when there is one, so
+//! AI-generated code can import the project's dependencies. An activated
+//! `$VIRTUAL_ENV` wins, then a `.venv`/`venv` next to (or above) the
+//! working directory, then the environment poetry manages for the project.
+//! Directories above are searched up to the project root (`pyproject.toml`
+//! or `.git`) or the home directory; outside a project only the working
+//! directory is.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+
+/// Most package names listed in the system context
+const MAX_LISTED_PACKAGES: usize = 60;
+
+/// `poetry env info --path` per project directory, asked once per run
+static POETRY_ENVS: Mutex<BTreeMap<PathBuf, Option<PathBuf>>> = Mutex::new(BTreeMap::new());
+
+/// A Python environment to run code in
+#[derive(Debug, Clone, PartialEq)]
+pub struct PythonEnv {
+    /// Environment directory (the one containing `bin/` or `Scripts/`)
+    pub root: PathBuf,
+    /// Python executable inside it
+    pub interpreter: PathBuf,
+    /// How it was found: "active", "venv", "uv" or "poetry"
+    pub kind: &'static str,
+}
+
+impl PythonEnv {
+    /// Environment rooted at `root`, if it has an interpreter
+    fn at(root: PathBuf, kind: &'static str) -> Option<Self> {
+        let interpreter = interpreter_in(&root);
+        interpreter.exists().then_some(Self {
+            root,
+            interpreter,
+            kind,
+        })
+    }
+
+    /// Installed distributions as `name==version`, sorted by name
+    ///
+    /// Read from `*.dist-info` directories so environments without pip
+    /// (e.g. created by uv) are covered too.
+    pub fn packages(&self) -> Vec<String> {
+        let mut packages: Vec<String> = site_packages_dirs(&self.root)
+            .iter()
+            .filter_map(|dir| std::fs::read_dir(dir).ok())
+            .flatten()
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let stem = name.strip_suffix(".dist-info")?;
+                let (package, version) = stem.split_once('-')?;
+                Some(format!("{}=={}", package, version))
+            })
+            .collect();
+        packages.sort_by_key(|p| p.to_ascii_lowercase());
+        packages
+    }
+
+    /// System context lines for the AI
+    pub fn context_note(&self) -> String {
+        let packages = self.packages();
+        let listed = if packages.is_empty() {
+            "none".to_string()
+        } else if packages.len() > MAX_LISTED_PACKAGES {
+            format!(
+                "{} … and {} more",
+                packages[..MAX_LISTED_PACKAGES].join(", "),
+                packages.len() - MAX_LISTED_PACKAGES
+            )
+        } else {
+            packages.join(", ")
+        };
+        format!(
+            "- Python environment ({}): {} (run_python uses it)\n- Installed packages: {}",
+            self.kind,
+            self.root.display(),
+            listed
+        )
+    }
+}
+
+/// Find the Python environment for `cwd`
+pub fn detect(cwd: &Path) -> Option<PythonEnv> {
+    if let Some(active) = std::env::var_os("VIRTUAL_ENV").filter(|v| !v.is_empty())
+        && let Some(env) = PythonEnv::at(PathBuf::from(active), "active")
+    {
+        return Some(env);
+    }
+    detect_within(cwd, dirs::home_dir().as_deref())
+}
+
+fn detect_within(cwd: &Path, home: Option<&Path>) -> Option<PythonEnv> {
+    let is_boundary = |dir: &Path| {
+        dir.join("pyproject.toml").exists() || dir.join(".git").exists() || Some(dir) == home
+    };
+    let dirs: Vec<&Path> = match cwd.ancestors().position(is_boundary) {
+        Some(n) => cwd.ancestors().take(n + 1).collect(),
+        None => vec![cwd],
+    };
+
+    for dir in dirs {
+        let poetry = is_poetry_project(dir);
+        for name in [".venv", "venv"] {
+            let kind = if dir.join("uv.lock").exists() {
+                "uv"
+            } else if poetry {
+                "poetry"
+            } else {
+                "venv"
+            };
+            if let Some(env) = PythonEnv::at(dir.join(name), kind) {
+                return Some(env);
+            }
+        }
+        if poetry && let Some(root) = poetry_env_path(dir) {
+            return PythonEnv::at(root, "poetry");
+        }
+    }
+    None
+}
+
+fn interpreter_in(root: &Path) -> PathBuf {
+    if cfg!(target_os = "windows") {
+        root.join("Scripts").join("python.exe")
+    } else {
+        root.join("bin").join("python")
+    }
+}
+
+/// `lib/python3.X/site-packages` (or `Lib/site-packages` on Windows)
+fn site_packages_dirs(root: &Path) -> Vec<PathBuf> {
+    let windows = root.join("Lib").join("site-packages");
+    if windows.is_dir() {
+        return vec![windows];
+    }
+    std::fs::read_dir(root.join("lib"))
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.path().join("site-packages"))
+                .filter(|p| p.is_dir())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn is_poetry_project(dir: &Path) -> bool {
+    std::fs::read_to_string(dir.join("pyproject.toml"))
+        .map(|s| s.contains("[tool.poetry]"))
+        .unwrap_or(false)
+}
+
+/// Virtualenv poetry created for the project in `dir`, if any
+///
+/// poetry takes a while to start, and this runs on every `cd`, so the
+/// answer for each project is cached.
+fn poetry_env_path(dir: &Path) -> Option<PathBuf> {
+    let mut cache = POETRY_ENVS.lock().unwrap_or_else(|e| e.into_inner());
+    cache
+        .entry(dir.to_path_buf())
+        .or_insert_with(|| {
+            let output = Command::new("poetry")
+                .args(["env", "info", "--path"])
+                .current_dir(dir)
+                .stdin(Stdio::null())
+                .output()
+                .ok()?;
+            let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            (output.status.success() && !path.is_empty()).then(|| PathBuf::from(path))
+        })
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fake_env(root: &Path, packages: &[&str]) {
+        let bin = interpreter_in(root);
+        std::fs::create_dir_all(bin.parent().unwrap()).unwrap();
+        std::fs::write(&bin, "").unwrap();
+        let site = if cfg!(target_os = "windows") {
+            root.join("Lib").join("site-packages")
+        } else {
+            root.join("lib").join("python3.12").join("site-packages")
+        };
+        for package in packages {
+            std::fs::create_dir_all(site.join(format!("{}.dist-info", package))).unwrap();
+        }
+        std::fs::create_dir_all(site.join("requests")).unwrap();
+    }
+
+    // **Feature: Sabi-TUI, Property: Project Virtualenv Detected**
+    #[test]
+    fn test_detect_project_venv() {
+        if std::env::var_os("VIRTUAL_ENV").is_some() {
+            return; // an activated venv takes precedence
+        }
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let sub = dir.join("src").join("pkg");
+        std::fs::create_dir_all(&sub).unwrap();
+        std::fs::write(dir.join("pyproject.toml"), "[project]\nname = \"x\"\n").unwrap();
+        std::fs::write(dir.join("uv.lock"), "").unwrap();
+        fake_env(&dir.join(".venv"), &["requests-2.31.0", "Flask-3.0.0"]);
+
+        let env = detect(&sub).unwrap();
+        assert_eq!(env.kind, "uv");
+        assert_eq!(env.root, dir.join(".venv"));
+        assert_eq!(env.packages(), vec!["Flask==3.0.0", "requests==2.31.0"]);
+        assert!(
+            env.context_note()
+                .contains("Flask==3.0.0, requests==2.31.0")
+        );
+    }
+
+    #[test]
+    fn test_no_env_outside_project() {
+        if std::env::var_os("VIRTUAL_ENV").is_some() {
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("pyproject.toml"), "[project]\n").unwrap();
+
+        assert_eq!(detect(dir.path()), None);
+    }
+
+    #[test]
+    fn test_search_stops_at_home() {
+        let tmp = tempfile::tempdir().unwrap();
+        let home = tmp.path().join("home");
+        let work = home.join("work").join("scratch");
+        std::fs::create_dir_all(&work).unwrap();
+        fake_env(&tmp.path().join(".venv"), &[]);
+
+        // Above home, and outside any project, nothing is picked up
+        assert_eq!(detect_within(&work, Some(&home)), None);
+        assert_eq!(detect_within(&work, None), None);
+
+        fake_env(&home.join(".venv"), &[]);
+        let env = detect_within(&work, Some(&home)).unwrap();
+        assert_eq!(env.root, home.join(".venv"));
+    }
+}
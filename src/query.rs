@@ -0,0 +1,356 @@
+//! Structured file queries
+//!
+//! Loads JSON/YAML/TOML files and evaluates a small jq-like path
+//! expression so only the matched subtree is returned to the AI.
+//!
+//! Supported syntax:
+//! - `.` (whole document), `.key`, `.key.sub`, `."key with dots"`
+//! - `[N]` array index (negative counts from the end), `[]` all elements
+//! - a trailing `| keys` or `| length`
+
+use std::path::Path;
+
+use serde_json::Value;
+use thiserror::Error;
+
+/// Query errors
+#[derive(Debug, Error, PartialEq)]
+pub enum QueryError {
+    /// File could not be read
+    #[error("Failed to read file: {0}")]
+    Io(String),
+
+    /// File content could not be parsed
+    #[error("Failed to parse {format}: {message}")]
+    Parse {
+        format: &'static str,
+        message: String,
+    },
+
+    /// Path expression is malformed
+    #[error("Invalid query '{query}': {message}")]
+    InvalidQuery { query: String, message: String },
+}
+
+/// A single step in a path expression
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(i64),
+    Iterate,
+}
+
+/// Optional function applied after the path
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Keys,
+    Length,
+}
+
+/// Load a file as a JSON value, picking the format from its extension
+pub fn load_file(path: &str) -> Result<Value, QueryError> {
+    let content = std::fs::read_to_string(path).map_err(|e| QueryError::Io(e.to_string()))?;
+    let ext = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+
+    match ext.as_str() {
+        "yaml" | "yml" => parse_yaml(&content),
+        "toml" => parse_toml(&content),
+        "json" => parse_json(&content),
+        // Unknown extension: try each format in turn
+        _ => parse_json(&content)
+            .or_else(|_| parse_toml(&content))
+            .or_else(|_| parse_yaml(&content)),
+    }
+}
+
+fn parse_json(content: &str) -> Result<Value, QueryError> {
+    serde_json::from_str(content).map_err(|e| QueryError::Parse {
+        format: "JSON",
+        message: e.to_string(),
+    })
+}
+
+fn parse_yaml(content: &str) -> Result<Value, QueryError> {
+    serde_yaml::from_str(content).map_err(|e| QueryError::Parse {
+        format: "YAML",
+        message: e.to_string(),
+    })
+}
+
+fn parse_toml(content: &str) -> Result<Value, QueryError> {
+    let value: toml::Value = toml::from_str(content).map_err(|e| QueryError::Parse {
+        format: "TOML",
+        message: e.to_string(),
+    })?;
+    serde_json::to_value(value).map_err(|e| QueryError::Parse {
+        format: "TOML",
+        message: e.to_string(),
+    })
+}
+
+/// Evaluate a path expression against a value
+///
+/// Returns every match; paths without `[]` produce at most one.
+/// Missing keys and out-of-range indexes yield `null`, like jq.
+pub fn evaluate(value: &Value, query: &str) -> Result<Vec<Value>, QueryError> {
+    let (segments, function) = parse_query(query)?;
+
+    let mut current = vec![value.clone()];
+    for segment in &segments {
+        let mut next = Vec::new();
+        for v in current {
+            match segment {
+                Segment::Key(key) => match v {
+                    Value::Object(mut map) => next.push(map.remove(key).unwrap_or(Value::Null)),
+                    Value::Null => next.push(Value::Null),
+                    other => return Err(type_error(query, "index", &other)),
+                },
+                Segment::Index(idx) => match v {
+                    Value::Array(items) => {
+                        let len = items.len() as i64;
+                        let pos = if *idx < 0 { len + idx } else { *idx };
+                        next.push(
+                            usize::try_from(pos)
+                                .ok()
+                                .and_then(|p| items.get(p).cloned())
+                                .unwrap_or(Value::Null),
+                        );
+                    }
+                    Value::Null => next.push(Value::Null),
+                    other => return Err(type_error(query, "index", &other)),
+                },
+                Segment::Iterate => match v {
+                    Value::Array(items) => next.extend(items),
+                    Value::Object(map) => next.extend(map.into_iter().map(|(_, v)| v)),
+                    other => return Err(type_error(query, "iterate over", &other)),
+                },
+            }
+        }
+        current = next;
+    }
+
+    match function {
+        None => Ok(current),
+        Some(f) => current.iter().map(|v| apply(f, v, query)).collect(),
+    }
+}
+
+/// Load `path` and evaluate `query`, rendering matches as pretty JSON
+pub fn query_file(path: &str, query: &str) -> Result<String, QueryError> {
+    let value = load_file(path)?;
+    let results = evaluate(&value, query)?;
+    Ok(results
+        .iter()
+        .map(|v| serde_json::to_string_pretty(v).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+fn apply(function: Function, value: &Value, query: &str) -> Result<Value, QueryError> {
+    match (function, value) {
+        (Function::Keys, Value::Object(map)) => Ok(Value::Array(
+            map.keys().map(|k| Value::String(k.clone())).collect(),
+        )),
+        (Function::Keys, Value::Array(items)) => Ok(Value::Array(
+            (0..items.len()).map(|i| Value::from(i as u64)).collect(),
+        )),
+        (Function::Length, Value::Object(map)) => Ok(Value::from(map.len() as u64)),
+        (Function::Length, Value::Array(items)) => Ok(Value::from(items.len() as u64)),
+        (Function::Length, Value::String(s)) => Ok(Value::from(s.chars().count() as u64)),
+        (Function::Length, Value::Null) => Ok(Value::from(0u64)),
+        (Function::Keys, other) => Err(type_error(query, "get keys of", other)),
+        (Function::Length, other) => Err(type_error(query, "get length of", other)),
+    }
+}
+
+fn type_error(query: &str, action: &str, value: &Value) -> QueryError {
+    let kind = match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    };
+    QueryError::InvalidQuery {
+        query: query.to_string(),
+        message: format!("cannot {} {}", action, kind),
+    }
+}
+
+fn parse_query(query: &str) -> Result<(Vec<Segment>, Option<Function>), QueryError> {
+    let invalid = |message: &str| QueryError::InvalidQuery {
+        query: query.to_string(),
+        message: message.to_string(),
+    };
+
+    let (path, function) = match query.split_once('|') {
+        Some((path, func)) => {
+            let function = match func.trim() {
+                "keys" => Function::Keys,
+                "length" => Function::Length,
+                other => return Err(invalid(&format!("unknown function '{}'", other))),
+            };
+            (path.trim(), Some(function))
+        }
+        None => (query.trim(), None),
+    };
+
+    if path.is_empty() || path == "." {
+        return Ok((Vec::new(), function));
+    }
+    if !path.starts_with('.') && !path.starts_with('[') {
+        return Err(invalid("path must start with '.'"));
+    }
+
+    let chars: Vec<char> = path.chars().collect();
+    let mut segments = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '.' => {
+                i += 1;
+                if i < chars.len() && chars[i] == '"' {
+                    let (key, end) =
+                        read_quoted(&chars, i).ok_or_else(|| invalid("unterminated string"))?;
+                    segments.push(Segment::Key(key));
+                    i = end;
+                } else {
+                    let start = i;
+                    while i < chars.len() && chars[i] != '.' && chars[i] != '[' {
+                        i += 1;
+                    }
+                    if i > start {
+                        segments.push(Segment::Key(chars[start..i].iter().collect()));
+                    } else if i < chars.len() && chars[i] == '.' {
+                        return Err(invalid("empty key"));
+                    }
+                }
+            }
+            '[' => {
+                let close = chars[i..]
+                    .iter()
+                    .position(|&c| c == ']')
+                    .map(|p| p + i)
+                    .ok_or_else(|| invalid("missing ']'"))?;
+                let inner: String = chars[i + 1..close].iter().collect();
+                let inner = inner.trim();
+                if inner.is_empty() {
+                    segments.push(Segment::Iterate);
+                } else if let Some(key) = inner.strip_prefix('"').and_then(|s| s.strip_suffix('"'))
+                {
+                    segments.push(Segment::Key(key.to_string()));
+                } else {
+                    let idx = inner
+                        .parse::<i64>()
+                        .map_err(|_| invalid(&format!("bad index '{}'", inner)))?;
+                    segments.push(Segment::Index(idx));
+                }
+                i = close + 1;
+            }
+            c => return Err(invalid(&format!("unexpected '{}'", c))),
+        }
+    }
+
+    Ok((segments, function))
+}
+
+/// Read a double-quoted key starting at `start`; returns (key, index after closing quote)
+fn read_quoted(chars: &[char], start: usize) -> Option<(String, usize)> {
+    let end = chars[start + 1..].iter().position(|&c| c == '"')? + start + 1;
+    Some((chars[start + 1..end].iter().collect(), end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use serde_json::json;
+
+    fn sample() -> Value {
+        json!({
+            "name": "sabi",
+            "deps": {"serde": "1", "toml": "0.8"},
+            "items": [{"id": 1}, {"id": 2}, {"id": 3}],
+            "a.b": true
+        })
+    }
+
+    // **Feature: Sabi-TUI, Property: Query Paths**
+    #[test]
+    fn test_evaluate_paths() {
+        let v = sample();
+        assert_eq!(evaluate(&v, ".").unwrap(), vec![v.clone()]);
+        assert_eq!(evaluate(&v, ".name").unwrap(), vec![json!("sabi")]);
+        assert_eq!(evaluate(&v, ".deps.toml").unwrap(), vec![json!("0.8")]);
+        assert_eq!(evaluate(&v, ".items[1].id").unwrap(), vec![json!(2)]);
+        assert_eq!(evaluate(&v, ".items[-1].id").unwrap(), vec![json!(3)]);
+        assert_eq!(
+            evaluate(&v, ".items[].id").unwrap(),
+            vec![json!(1), json!(2), json!(3)]
+        );
+        assert_eq!(evaluate(&v, ".\"a.b\"").unwrap(), vec![json!(true)]);
+        assert_eq!(evaluate(&v, ".[\"a.b\"]").unwrap(), vec![json!(true)]);
+        assert_eq!(evaluate(&v, ".missing").unwrap(), vec![Value::Null]);
+    }
+
+    #[test]
+    fn test_evaluate_functions() {
+        let v = sample();
+        assert_eq!(
+            evaluate(&v, ".deps | keys").unwrap(),
+            vec![json!(["serde", "toml"])]
+        );
+        assert_eq!(evaluate(&v, ".items | length").unwrap(), vec![json!(3)]);
+        assert!(evaluate(&v, ".name | keys").is_err());
+        assert!(evaluate(&v, ". | sort").is_err());
+    }
+
+    #[test]
+    fn test_evaluate_invalid_queries() {
+        let v = sample();
+        assert!(evaluate(&v, "name").is_err());
+        assert!(evaluate(&v, ".items[x]").is_err());
+        assert!(evaluate(&v, ".items[0").is_err());
+        assert!(evaluate(&v, ".name.first").is_err());
+    }
+
+    #[test]
+    fn test_load_file_formats() {
+        let dir = tempfile::TempDir::new().unwrap();
+
+        let toml_path = dir.path().join("c.toml");
+        std::fs::write(&toml_path, "[package]\nname = \"x\"\n").unwrap();
+        let out = query_file(toml_path.to_str().unwrap(), ".package.name").unwrap();
+        assert_eq!(out, "\"x\"");
+
+        let yaml_path = dir.path().join("c.yaml");
+        std::fs::write(&yaml_path, "services:\n  web:\n    image: nginx\n").unwrap();
+        let out = query_file(yaml_path.to_str().unwrap(), ".services.web.image").unwrap();
+        assert_eq!(out, "\"nginx\"");
+
+        let json_path = dir.path().join("c.json");
+        std::fs::write(&json_path, "{not json").unwrap();
+        assert!(matches!(
+            query_file(json_path.to_str().unwrap(), "."),
+            Err(QueryError::Parse { format: "JSON", .. })
+        ));
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(50))]
+
+        // **Feature: Sabi-TUI, Property: Query Key Lookup**
+        // *For any* object key, `.key` SHALL return exactly the stored value.
+        #[test]
+        fn prop_key_lookup_returns_value(key in "[a-z_][a-z0-9_]{0,10}", n in any::<i64>()) {
+            let v = json!({ key.clone(): n });
+            let result = evaluate(&v, &format!(".{}", key)).unwrap();
+            prop_assert_eq!(result, vec![json!(n)]);
+        }
+    }
+}
@@ -0,0 +1,296 @@
+//! Interactive session list opened by `/sessions`
+//!
+//! Shows saved sessions newest first with their name, time, directory and
+//! first prompt. `Enter` switches, `d` then `y` deletes and `r` renames in
+//! place. The picker only tracks the selection and what is being typed;
+//! it reports what the user chose as a [`PickerAction`] for the app to
+//! carry out.
+//!
+//! `/resume` and `sabi --resume` open a compact version with the most
+//! recent sessions only, numbered so one key switches.
+
+use crossterm::event::{KeyCode, KeyEvent};
+
+use crate::app::Session;
+
+/// What the user chose in the picker
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PickerAction {
+    /// Nothing to do (moved, typing, ...)
+    None,
+    Close,
+    Switch(String),
+    Delete(String),
+    Rename {
+        id: String,
+        name: String,
+    },
+}
+
+/// Sessions in the quick resume list
+pub const RECENT: usize = 10;
+
+/// State of the session picker overlay
+#[derive(Debug, Clone)]
+pub struct SessionPicker {
+    pub sessions: Vec<Session>,
+    pub selected: usize,
+    /// Quick resume list: numbered, one line per session, no editing
+    pub quick: bool,
+    /// New name being typed after `r`
+    pub rename: Option<String>,
+    /// `d` was pressed; `y` deletes, anything else cancels
+    pub confirm_delete: bool,
+    /// Result of the last action, shown at the bottom
+    pub status: Option<String>,
+}
+
+impl SessionPicker {
+    pub fn new(sessions: Vec<Session>, current_id: &str) -> Self {
+        let selected = sessions.iter().position(|s| s.id == current_id);
+        Self {
+            sessions,
+            selected: selected.unwrap_or(0),
+            quick: false,
+            rename: None,
+            confirm_delete: false,
+            status: None,
+        }
+    }
+
+    /// Quick resume list of the `RECENT` newest of `sessions`
+    pub fn recent(mut sessions: Vec<Session>) -> Self {
+        sessions.truncate(RECENT);
+        Self {
+            quick: true,
+            ..Self::new(sessions, "")
+        }
+    }
+
+    /// Session under the cursor
+    pub fn current(&self) -> Option<&Session> {
+        self.sessions.get(self.selected)
+    }
+
+    /// Replace the list (after a delete or rename), keeping the cursor in range
+    pub fn refresh(&mut self, sessions: Vec<Session>) {
+        self.sessions = sessions;
+        self.selected = self.selected.min(self.sessions.len().saturating_sub(1));
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> PickerAction {
+        if let Some(ref mut name) = self.rename {
+            match key.code {
+                KeyCode::Enter => {
+                    let name = name.trim().to_string();
+                    self.rename = None;
+                    return match self.current() {
+                        Some(session) if !name.is_empty() => PickerAction::Rename {
+                            id: session.id.clone(),
+                            name,
+                        },
+                        _ => PickerAction::None,
+                    };
+                }
+                KeyCode::Esc => self.rename = None,
+                KeyCode::Backspace => {
+                    name.pop();
+                }
+                KeyCode::Char(c) => name.push(c),
+                _ => {}
+            }
+            return PickerAction::None;
+        }
+
+        if std::mem::take(&mut self.confirm_delete) {
+            if let KeyCode::Char('y') = key.code
+                && let Some(session) = self.current()
+            {
+                return PickerAction::Delete(session.id.clone());
+            }
+            self.status = None;
+            return PickerAction::None;
+        }
+
+        // 1-9 and 0 pick a session of the quick list
+        if self.quick
+            && let KeyCode::Char(c) = key.code
+            && let Some(digit) = c.to_digit(10)
+        {
+            let index = (digit as usize + 9) % 10;
+            return match self.sessions.get(index) {
+                Some(session) => PickerAction::Switch(session.id.clone()),
+                None => PickerAction::None,
+            };
+        }
+
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => return PickerAction::Close,
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1).min(self.sessions.len().saturating_sub(1));
+            }
+            KeyCode::Home | KeyCode::Char('g') => self.selected = 0,
+            KeyCode::End | KeyCode::Char('G') => {
+                self.selected = self.sessions.len().saturating_sub(1);
+            }
+            KeyCode::Enter => {
+                if let Some(session) = self.current() {
+                    return PickerAction::Switch(session.id.clone());
+                }
+            }
+            KeyCode::Char('d' | 'r') if self.quick => {}
+            KeyCode::Char('d') => {
+                if let Some(session) = self.current() {
+                    self.status = Some(format!("Delete '{}'? (y/n)", session.name));
+                    self.confirm_delete = true;
+                }
+            }
+            KeyCode::Char('r') => {
+                self.rename = self.current().map(|s| s.name.clone());
+            }
+            _ => {}
+        }
+        PickerAction::None
+    }
+}
+
+/// How long ago an RFC 3339 `timestamp` was, e.g. "5m ago" or "3d ago"
+pub fn age(timestamp: &str, now: chrono::DateTime<chrono::Local>) -> String {
+    let Ok(then) = chrono::DateTime::parse_from_rfc3339(timestamp) else {
+        return String::new();
+    };
+    let minutes = (now - then.with_timezone(&chrono::Local)).num_minutes();
+    match minutes {
+        ..1 => "just now".to_string(),
+        1..60 => format!("{}m ago", minutes),
+        60..1440 => format!("{}h ago", minutes / 60),
+        1440..10080 => format!("{}d ago", minutes / 1440),
+        _ => then.format("%Y-%m-%d").to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+
+    fn session(id: &str, name: &str) -> Session {
+        Session {
+            id: id.to_string(),
+            name: name.to_string(),
+            timestamp: String::new(),
+            cwd: String::new(),
+            messages: Vec::new(),
+            summary: None,
+            template: None,
+            workspace: None,
+        }
+    }
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn picker() -> SessionPicker {
+        let sessions = vec![session("b", "Newest"), session("a", "Oldest")];
+        SessionPicker::new(sessions, "a")
+    }
+
+    // **Feature: Sabi-TUI, Property: Session Picker**
+    #[test]
+    fn test_starts_on_current_and_switches() {
+        let mut picker = picker();
+        assert_eq!(picker.selected, 1);
+        picker.handle_key(key(KeyCode::Char('k')));
+        assert_eq!(
+            picker.handle_key(key(KeyCode::Enter)),
+            PickerAction::Switch("b".to_string())
+        );
+        assert_eq!(picker.handle_key(key(KeyCode::Esc)), PickerAction::Close);
+    }
+
+    #[test]
+    fn test_delete_needs_confirmation() {
+        let mut picker = picker();
+        picker.handle_key(key(KeyCode::Char('d')));
+        assert!(picker.status.as_deref().unwrap().contains("Oldest"));
+        assert_eq!(
+            picker.handle_key(key(KeyCode::Char('n'))),
+            PickerAction::None
+        );
+        assert!(!picker.confirm_delete);
+
+        picker.handle_key(key(KeyCode::Char('d')));
+        assert_eq!(
+            picker.handle_key(key(KeyCode::Char('y'))),
+            PickerAction::Delete("a".to_string())
+        );
+    }
+
+    #[test]
+    fn test_rename_edits_current_name() {
+        let mut picker = picker();
+        picker.handle_key(key(KeyCode::Char('r')));
+        assert_eq!(picker.rename.as_deref(), Some("Oldest"));
+        for _ in 0.."Oldest".len() {
+            picker.handle_key(key(KeyCode::Backspace));
+        }
+        for c in "deploy fix".chars() {
+            assert_eq!(picker.handle_key(key(KeyCode::Char(c))), PickerAction::None);
+        }
+        assert_eq!(
+            picker.handle_key(key(KeyCode::Enter)),
+            PickerAction::Rename {
+                id: "a".to_string(),
+                name: "deploy fix".to_string()
+            }
+        );
+        assert!(picker.rename.is_none());
+
+        // An empty name is ignored
+        picker.handle_key(key(KeyCode::Char('r')));
+        for _ in 0.."Oldest".len() {
+            picker.handle_key(key(KeyCode::Backspace));
+        }
+        assert_eq!(picker.handle_key(key(KeyCode::Enter)), PickerAction::None);
+    }
+
+    // **Feature: Sabi-TUI, Property: Quick Resume List**
+    #[test]
+    fn test_recent_switches_by_number() {
+        let sessions = (0..12).map(|i| session(&i.to_string(), "s")).collect();
+        let mut picker = SessionPicker::recent(sessions);
+        assert_eq!(picker.sessions.len(), RECENT);
+        assert_eq!(picker.selected, 0);
+        let switch = |id: &str| PickerAction::Switch(id.to_string());
+        assert_eq!(picker.handle_key(key(KeyCode::Char('1'))), switch("0"));
+        assert_eq!(picker.handle_key(key(KeyCode::Char('0'))), switch("9"));
+        // No editing in the quick list
+        assert_eq!(
+            picker.handle_key(key(KeyCode::Char('d'))),
+            PickerAction::None
+        );
+        assert!(!picker.confirm_delete);
+        picker.handle_key(key(KeyCode::Char('r')));
+        assert!(picker.rename.is_none());
+
+        let mut short = SessionPicker::recent(vec![session("a", "only")]);
+        assert_eq!(
+            short.handle_key(key(KeyCode::Char('2'))),
+            PickerAction::None
+        );
+    }
+
+    #[test]
+    fn test_age() {
+        let now = chrono::Local::now();
+        let ago = |minutes: i64| (now - chrono::Duration::minutes(minutes)).to_rfc3339();
+        assert_eq!(age(&ago(0), now), "just now");
+        assert_eq!(age(&ago(5), now), "5m ago");
+        assert_eq!(age(&ago(180), now), "3h ago");
+        assert_eq!(age(&ago(3 * 1440 + 5), now), "3d ago");
+        assert_eq!(age(&ago(30 * 1440), now).len(), "2026-01-01".len());
+        assert_eq!(age("not a time", now), "");
+    }
+}
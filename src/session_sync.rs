@@ -0,0 +1,228 @@
+//! Backing up saved sessions
+//!
+//! After a session is saved, `session_sync = "git"` commits the sessions
+//! directory to a git repository of its own (pushing it when a remote is
+//! set up), and `session_hook` runs any other command, e.g. `rclone sync`.
+//! Both run detached in the sessions directory so they never hold up the
+//! UI and finish even when sabi exits; their output goes to
+//! `~/.sabi/session_sync.log`. One backup of a directory runs at a time: a
+//! save while one runs queues another for when it's done, so git never
+//! finds the index locked by the previous one.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Config, ShellInvocation};
+
+/// Built-in backup of the sessions directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionSync {
+    /// No built-in backup
+    #[default]
+    Off,
+    /// Commit to a git repository in the sessions directory, pushing to its
+    /// first remote if it has one
+    Git,
+}
+
+/// Script run by `session_sync = "git"`
+const GIT_SYNC: &str = "[ -d .git ] || git init -q\n\
+    git add -A && git commit -qm \"sabi: save $SABI_SESSION_ID\" || exit 0\n\
+    remote=$(git remote | head -n 1)\n\
+    [ -z \"$remote\" ] || git push -q -u \"$remote\" HEAD";
+
+/// Directories with a backup running, each with the one queued after it
+static RUNNING: Mutex<BTreeMap<PathBuf, Option<Backup>>> = Mutex::new(BTreeMap::new());
+
+/// A backup of the sessions directory after one save
+struct Backup {
+    git: bool,
+    hook: Option<(ShellInvocation, String)>,
+    dir: PathBuf,
+    id: String,
+}
+
+impl Backup {
+    /// Start the processes, built-in sync first
+    fn start(&self) -> std::io::Result<Vec<Child>> {
+        let mut started = Vec::new();
+        if self.git {
+            started.push(spawn("sh", "-c", GIT_SYNC, &self.dir, &self.id)?);
+        }
+        if let Some((shell, hook)) = &self.hook {
+            started.push(spawn(
+                &shell.program,
+                &shell.flag,
+                hook,
+                &self.dir,
+                &self.id,
+            )?);
+        }
+        Ok(started)
+    }
+}
+
+/// Back up `dir` as configured after session `id` was saved
+///
+/// Runs on a thread of its own that waits for the processes; if a backup
+/// of `dir` is already running, this one follows it, replacing any other
+/// still waiting.
+pub fn after_save(config: &Config, dir: &Path, id: &str) {
+    let hook = config.session_hook.trim();
+    let backup = Backup {
+        git: config.session_sync == SessionSync::Git,
+        hook: (!hook.is_empty()).then(|| (config.shell_invocation(), hook.to_string())),
+        dir: dir.to_path_buf(),
+        id: id.to_string(),
+    };
+    if !backup.git && backup.hook.is_none() {
+        return;
+    }
+
+    let mut running = RUNNING.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(queued) = running.get_mut(dir) {
+        *queued = Some(backup);
+        return;
+    }
+    running.insert(dir.to_path_buf(), None);
+    std::thread::spawn(move || run_backups(backup));
+}
+
+/// Run `backup`, then whatever was queued for its directory meanwhile
+fn run_backups(mut backup: Backup) {
+    loop {
+        match backup.start() {
+            Ok(children) => {
+                for mut child in children {
+                    let _ = child.wait();
+                }
+            }
+            Err(e) => tracing::warn!(dir = %backup.dir.display(), "session backup failed: {}", e),
+        }
+        let mut running = RUNNING.lock().unwrap_or_else(|e| e.into_inner());
+        match running.get_mut(&backup.dir).and_then(Option::take) {
+            Some(next) => backup = next,
+            None => {
+                running.remove(&backup.dir);
+                return;
+            }
+        }
+    }
+}
+
+/// Run `script` with `program flag` in `dir`, output appended to the log
+fn spawn(program: &str, flag: &str, script: &str, dir: &Path, id: &str) -> std::io::Result<Child> {
+    let log = dir.parent().unwrap_or(dir).join("session_sync.log");
+    let log = File::options().create(true).append(true).open(log)?;
+    let file = dir.join(format!("{}.json", id));
+    Command::new(program)
+        .arg(flag)
+        .arg(script)
+        .current_dir(dir)
+        .env("SABI_SESSIONS_DIR", dir)
+        .env("SABI_SESSION_FILE", file)
+        .env("SABI_SESSION_ID", id)
+        .env("GIT_AUTHOR_NAME", "sabi")
+        .env("GIT_AUTHOR_EMAIL", "sabi@localhost")
+        .env("GIT_COMMITTER_NAME", "sabi")
+        .env("GIT_COMMITTER_EMAIL", "sabi@localhost")
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log)
+        .spawn()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wait_idle(dir: &Path) {
+        while RUNNING.lock().unwrap().contains_key(dir) {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+    }
+
+    // **Feature: Sabi-TUI, Property: Session Hook**
+    #[cfg(unix)]
+    #[test]
+    fn test_session_hook_runs_in_sessions_dir() {
+        let home = tempfile::tempdir().unwrap();
+        let dir = home.path().join("sessions");
+        std::fs::create_dir(&dir).unwrap();
+        let config = Config {
+            session_hook: "echo \"$SABI_SESSION_ID $SABI_SESSION_FILE\" > hook.txt".to_string(),
+            ..Config::default()
+        };
+
+        after_save(&config, &dir, "20260301_143022");
+        wait_idle(&dir);
+        let text = std::fs::read_to_string(dir.join("hook.txt")).unwrap();
+        let file = dir.join("20260301_143022.json");
+        assert_eq!(text.trim(), format!("20260301_143022 {}", file.display()));
+        assert!(home.path().join("session_sync.log").exists());
+        after_save(&Config::default(), &dir, "x");
+        assert!(!RUNNING.lock().unwrap().contains_key(&dir));
+    }
+
+    // **Feature: Sabi-TUI, Property: Git Session Sync**
+    #[cfg(unix)]
+    #[test]
+    fn test_git_sync_commits_sessions() {
+        let home = tempfile::tempdir().unwrap();
+        let dir = home.path().join("sessions");
+        std::fs::create_dir(&dir).unwrap();
+        let config = Config {
+            session_sync: SessionSync::Git,
+            ..Config::default()
+        };
+
+        std::fs::write(dir.join("a.json"), "{}").unwrap();
+        after_save(&config, &dir, "a");
+        wait_idle(&dir);
+        // Nothing new to commit is not a failure
+        after_save(&config, &dir, "a");
+        wait_idle(&dir);
+
+        let log = Command::new("git")
+            .args(["log", "--format=%s"])
+            .current_dir(&dir)
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&log.stdout).trim(), "sabi: save a");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_overlapping_saves_run_one_after_another() {
+        let home = tempfile::tempdir().unwrap();
+        let dir = home.path().join("sessions");
+        std::fs::create_dir(&dir).unwrap();
+        let config = Config {
+            session_sync: SessionSync::Git,
+            ..Config::default()
+        };
+
+        for id in ["a", "b", "c"] {
+            std::fs::write(dir.join(format!("{}.json", id)), "{}").unwrap();
+            after_save(&config, &dir, id);
+        }
+        wait_idle(&dir);
+
+        // Every save is backed up, and no sync found the index locked
+        let files = Command::new("git")
+            .args(["ls-files"])
+            .current_dir(&dir)
+            .output()
+            .unwrap();
+        let files = String::from_utf8_lossy(&files.stdout);
+        assert_eq!(files, "a.json\nb.json\nc.json\n");
+        let sync_log = std::fs::read_to_string(home.path().join("session_sync.log")).unwrap();
+        assert!(!sync_log.contains("index.lock"), "{}", sync_log);
+    }
+}
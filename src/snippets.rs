@@ -0,0 +1,253 @@
+//! Command snippets
+//!
+//! Named command templates from `~/.sabi/snippets.toml`, run with
+//! `/snippet <name>` without asking the AI. Placeholders are written
+//! `{{name}}` or `{{name:default}}`; each one is asked for in the input
+//! box, and the finished command goes to review like any other tool call.
+//! Each value is put in as a single shell word, quoted if it needs to be,
+//! so placeholders shouldn't be quoted in the template.
+//!
+//! ```toml
+//! [snippets.logs]
+//! description = "Recent logs of a systemd service"
+//! command = "journalctl -u {{service}} -n {{lines:100}} --no-pager"
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::executor::shell_quote;
+
+/// A named command template
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Snippet {
+    /// Shell command with `{{placeholder}}` markers
+    pub command: String,
+    /// Shown by `/snippet` without arguments
+    #[serde(default)]
+    pub description: String,
+}
+
+/// A value to ask for before the snippet can run
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placeholder {
+    pub name: String,
+    pub default: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SnippetFile {
+    #[serde(default)]
+    snippets: BTreeMap<String, Snippet>,
+}
+
+fn placeholder_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_-]*)\s*(?::([^}]*))?\}\}")
+            .expect("valid placeholder regex")
+    })
+}
+
+impl Snippet {
+    /// Placeholders in order of first appearance
+    pub fn placeholders(&self) -> Vec<Placeholder> {
+        let mut found: Vec<Placeholder> = Vec::new();
+        for caps in placeholder_regex().captures_iter(&self.command) {
+            let name = &caps[1];
+            let default = caps.get(2).map(|d| d.as_str().trim().to_string());
+            match found.iter_mut().find(|p| p.name == name) {
+                // A later `{{name:default}}` still provides the default
+                Some(existing) => {
+                    if existing.default.is_none() {
+                        existing.default = default;
+                    }
+                }
+                None => found.push(Placeholder {
+                    name: name.to_string(),
+                    default,
+                }),
+            }
+        }
+        found
+    }
+
+    /// The command with every placeholder replaced by its value, quoted
+    pub fn render(&self, values: &BTreeMap<String, String>) -> String {
+        placeholder_regex()
+            .replace_all(&self.command, |caps: &regex::Captures| {
+                values
+                    .get(&caps[1])
+                    .map(|v| quote_word(v))
+                    .unwrap_or_default()
+            })
+            .into_owned()
+    }
+}
+
+/// `value` as one shell word, left bare when nothing in it is special
+fn quote_word(value: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c);
+    if !value.is_empty() && value.chars().all(plain) {
+        value.to_string()
+    } else {
+        shell_quote(value)
+    }
+}
+
+/// Snippets file path (~/.sabi/snippets.toml)
+pub fn snippets_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".sabi").join("snippets.toml"))
+}
+
+/// Load snippets, by name; a missing file means no snippets
+pub fn load() -> Result<BTreeMap<String, Snippet>, String> {
+    let Some(path) = snippets_path().filter(|p| p.exists()) else {
+        return Ok(BTreeMap::new());
+    };
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    parse(&content).map_err(|e| format!("Invalid {}: {}", path.display(), e))
+}
+
+/// Snippets of a snippets.toml, by name
+pub fn parse(content: &str) -> Result<BTreeMap<String, Snippet>, toml::de::Error> {
+    toml::from_str::<SnippetFile>(content).map(|f| f.snippets)
+}
+
+/// A snippet whose placeholders are being filled in
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fill {
+    pub name: String,
+    snippet: Snippet,
+    placeholders: Vec<Placeholder>,
+    values: BTreeMap<String, String>,
+}
+
+impl Fill {
+    pub fn new(name: impl Into<String>, snippet: Snippet) -> Self {
+        Self {
+            name: name.into(),
+            placeholders: snippet.placeholders(),
+            snippet,
+            values: BTreeMap::new(),
+        }
+    }
+
+    /// The placeholder asked for next, if any are left
+    pub fn current(&self) -> Option<&Placeholder> {
+        self.placeholders.get(self.values.len())
+    }
+
+    /// Question for the current placeholder
+    pub fn prompt(&self) -> Option<String> {
+        let placeholder = self.current()?;
+        Some(match placeholder.default {
+            Some(ref default) => format!("{} (Enter for {:?})", placeholder.name, default),
+            None => placeholder.name.clone(),
+        })
+    }
+
+    /// Answer the current placeholder; empty input takes the default
+    pub fn answer(&mut self, value: &str) -> Result<(), String> {
+        let placeholder = self.current().ok_or("All values are filled in")?;
+        let value = match (value.trim(), &placeholder.default) {
+            ("", Some(default)) => default.clone(),
+            ("", None) => return Err(format!("{} needs a value", placeholder.name)),
+            (value, _) => value.to_string(),
+        };
+        self.values.insert(placeholder.name.clone(), value);
+        Ok(())
+    }
+
+    /// The finished command, once every placeholder has a value
+    pub fn command(&self) -> Option<String> {
+        self.current()
+            .is_none()
+            .then(|| self.snippet.render(&self.values))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // **Feature: Sabi-TUI, Property: Snippet Placeholders**
+    #[test]
+    fn test_fill_snippet() {
+        let snippets = parse(
+            r#"
+            [snippets.logs]
+            description = "Service logs"
+            command = "journalctl -u {{service}} -n {{ lines:100 }} | grep {{service}}"
+            "#,
+        )
+        .unwrap();
+        let snippet = snippets["logs"].clone();
+        assert_eq!(snippet.description, "Service logs");
+        assert_eq!(
+            snippet.placeholders(),
+            vec![
+                Placeholder {
+                    name: "service".to_string(),
+                    default: None,
+                },
+                Placeholder {
+                    name: "lines".to_string(),
+                    default: Some("100".to_string()),
+                },
+            ]
+        );
+
+        let mut fill = Fill::new("logs", snippet);
+        assert_eq!(fill.prompt().as_deref(), Some("service"));
+        assert!(fill.answer("  ").is_err());
+        fill.answer("nginx").unwrap();
+        assert_eq!(fill.prompt().as_deref(), Some("lines (Enter for \"100\")"));
+        assert_eq!(fill.command(), None);
+        fill.answer("").unwrap();
+        assert_eq!(
+            fill.command().as_deref(),
+            Some("journalctl -u nginx -n 100 | grep nginx")
+        );
+    }
+
+    #[test]
+    fn test_values_are_quoted() {
+        let snippet = Snippet {
+            command: "grep -rn {{pattern}} {{dir:.}}".to_string(),
+            description: String::new(),
+        };
+        let mut fill = Fill::new("find", snippet);
+        fill.answer("x; rm -rf ~").unwrap();
+        fill.answer("it's here").unwrap();
+        assert_eq!(
+            fill.command().as_deref(),
+            Some(r"grep -rn 'x; rm -rf ~' 'it'\''s here'")
+        );
+
+        let values = BTreeMap::from([("pattern".to_string(), "$(id)".to_string())]);
+        assert!(
+            fill.snippet
+                .render(&values)
+                .starts_with("grep -rn '$(id)' ")
+        );
+    }
+
+    #[test]
+    fn test_snippet_without_placeholders() {
+        let fill = Fill::new(
+            "df",
+            Snippet {
+                command: "df -h".to_string(),
+                description: String::new(),
+            },
+        );
+        assert_eq!(fill.prompt(), None);
+        assert_eq!(fill.command().as_deref(), Some("df -h"));
+    }
+}
@@ -99,6 +99,10 @@ pub enum StateEvent {
     AnalysisComplete,
     /// Continue from Done state
     Continue,
+    /// A tool call goes to review without the AI (re-run, snippet)
+    Rerun,
+    /// Tool call allowed by the approval policy, skipping review
+    AutoApprove,
 }
 
 /// Pure state transition function
@@ -113,6 +117,7 @@ pub fn transition(current: AppState, event: StateEvent) -> TransitionResult {
             TransitionResult::Success(AppState::Thinking)
         }
         (AppState::Input, StateEvent::Escape) => TransitionResult::Success(AppState::Done),
+        (AppState::Input, StateEvent::Rerun) => TransitionResult::Success(AppState::ReviewAction),
 
         // Thinking state transitions
         (AppState::Thinking, StateEvent::ToolCallReceived) => {
@@ -122,6 +127,9 @@ pub fn transition(current: AppState, event: StateEvent) -> TransitionResult {
             TransitionResult::Success(AppState::Input)
         }
         (AppState::Thinking, StateEvent::ApiError) => TransitionResult::Success(AppState::Input),
+        (AppState::Thinking, StateEvent::AutoApprove) => {
+            TransitionResult::Success(AppState::Executing)
+        }
 
         // ReviewAction state transitions
         (AppState::ReviewAction, StateEvent::ConfirmCommand) => {
@@ -148,6 +156,9 @@ pub fn transition(current: AppState, event: StateEvent) -> TransitionResult {
             TransitionResult::Success(AppState::Input)
         }
         (AppState::Finalizing, StateEvent::ApiError) => TransitionResult::Success(AppState::Input),
+        (AppState::Finalizing, StateEvent::AutoApprove) => {
+            TransitionResult::Success(AppState::Executing)
+        }
 
         // Done state transitions
         (AppState::Done, StateEvent::Continue) => TransitionResult::Success(AppState::Input),
@@ -166,11 +177,13 @@ pub fn is_valid_transition(from: AppState, to: AppState) -> bool {
         // From Input
         (AppState::Input, AppState::Thinking) => true,
         (AppState::Input, AppState::Done) => true,
-        (AppState::Input, AppState::Input) => true, // Stay in input (empty submit)
+        (AppState::Input, AppState::ReviewAction) => true, // Re-run last tool call
+        (AppState::Input, AppState::Input) => true,        // Stay in input (empty submit)
 
         // From Thinking
         (AppState::Thinking, AppState::ReviewAction) => true,
         (AppState::Thinking, AppState::Input) => true,
+        (AppState::Thinking, AppState::Executing) => true, // Auto-approved
 
         // From ReviewAction
         (AppState::ReviewAction, AppState::Executing) => true,
@@ -182,6 +195,7 @@ pub fn is_valid_transition(from: AppState, to: AppState) -> bool {
         // From Finalizing
         (AppState::Finalizing, AppState::ReviewAction) => true,
         (AppState::Finalizing, AppState::Input) => true,
+        (AppState::Finalizing, AppState::Executing) => true, // Auto-approved
 
         // From Done
         (AppState::Done, AppState::Input) => true,
@@ -234,6 +248,29 @@ mod tests {
         assert_eq!(result, TransitionResult::Success(AppState::Thinking));
     }
 
+    #[test]
+    fn test_auto_approve_skips_review() {
+        for state in [AppState::Thinking, AppState::Finalizing] {
+            let result = transition(state, StateEvent::AutoApprove);
+            assert_eq!(result, TransitionResult::Success(AppState::Executing));
+            assert!(is_valid_transition(state, AppState::Executing));
+        }
+        assert!(matches!(
+            transition(AppState::Input, StateEvent::AutoApprove),
+            TransitionResult::Error(_)
+        ));
+    }
+
+    #[test]
+    fn test_input_rerun_to_review() {
+        let result = transition(AppState::Input, StateEvent::Rerun);
+        assert_eq!(result, TransitionResult::Success(AppState::ReviewAction));
+        assert!(matches!(
+            transition(AppState::Thinking, StateEvent::Rerun),
+            TransitionResult::Error(_)
+        ));
+    }
+
     #[test]
     fn test_input_escape_to_done() {
         let result = transition(AppState::Input, StateEvent::Escape);
@@ -316,6 +353,8 @@ mod tests {
             Just(StateEvent::CommandComplete),
             Just(StateEvent::AnalysisComplete),
             Just(StateEvent::Continue),
+            Just(StateEvent::Rerun),
+            Just(StateEvent::AutoApprove),
         ]
     }
 
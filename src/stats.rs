@@ -0,0 +1,369 @@
+//! Opt-in local usage statistics
+//!
+//! With `stats = true`, every AI reply and tool run is appended as a line
+//! of JSON to `stats.jsonl` in the data directory. Nothing is sent
+//! anywhere; `sabi stats` summarizes the file: how many replies and tool
+//! runs there were, how often tools succeeded, latency percentiles and the
+//! programs run most.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{self, Config};
+use crate::message::{Message, MessageRole};
+
+/// What happened
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Event {
+    /// An AI reply and how long it took to arrive
+    Reply { ms: u64, tool_calls: usize },
+    /// A tool run; `program` is the first word of a run_cmd command
+    Tool {
+        tool: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        program: Option<String>,
+        ok: bool,
+        ms: u64,
+    },
+}
+
+/// A line of stats.jsonl
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    /// RFC 3339 timestamp
+    pub time: String,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+/// Stats file (stats.jsonl in the data directory)
+pub fn stats_path() -> Option<PathBuf> {
+    config::data_dir().map(|d| d.join("stats.jsonl"))
+}
+
+/// Records for a message added to the chat: replies and tool results
+pub fn records(message: &Message) -> Vec<Record> {
+    let Some(ms) = message.duration_ms else {
+        return Vec::new();
+    };
+    let time = message
+        .time
+        .clone()
+        .unwrap_or_else(|| chrono::Local::now().to_rfc3339());
+    let events = match message.role {
+        MessageRole::Model => vec![Event::Reply {
+            ms,
+            tool_calls: message.tool_calls.len(),
+        }],
+        MessageRole::Tool => message
+            .tool_runs
+            .iter()
+            .map(|run| Event::Tool {
+                tool: if run.call.is_mcp() {
+                    format!("mcp/{}/{}", run.call.server, run.call.name)
+                } else {
+                    run.call.tool.clone()
+                },
+                program: run
+                    .call
+                    .is_run_cmd()
+                    .then(|| run.call.command.split_whitespace().next())
+                    .flatten()
+                    .map(str::to_string),
+                ok: run.exit_code.unwrap_or(0) == 0 && run.signal.is_none(),
+                ms,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    events
+        .into_iter()
+        .map(|event| Record {
+            time: time.clone(),
+            event,
+        })
+        .collect()
+}
+
+/// Append `records` to the stats file at `path`
+pub fn append(path: &Path, records: &[Record]) -> std::io::Result<()> {
+    if records.is_empty() {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    for record in records {
+        writeln!(file, "{}", serde_json::to_string(record)?)?;
+    }
+    Ok(())
+}
+
+/// Record `message` in the stats file if `config` collects stats
+pub fn record(config: &Config, message: &Message) {
+    if !config.stats {
+        return;
+    }
+    if let Some(path) = stats_path() {
+        // Stats are best effort and never get in the way of the chat
+        let _ = append(&path, &records(message));
+    }
+}
+
+/// Records of the stats file at `path`; lines that don't parse are skipped
+pub fn load(path: &Path) -> Result<Vec<Record>, String> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Can't read {}: {}", path.display(), e)),
+    };
+    Ok(text
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// `p`th percentile (nearest rank) of sorted `values`
+fn percentile(sorted: &[u64], p: usize) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+/// "p50 1.2s · p90 4.0s · p99 9.1s"
+fn latency(mut ms: Vec<u64>) -> String {
+    ms.sort_unstable();
+    [50, 90, 99]
+        .iter()
+        .map(|&p| format!("p{} {:.1}s", p, percentile(&ms, p) as f64 / 1000.0))
+        .collect::<Vec<_>>()
+        .join(" · ")
+}
+
+/// Summary of `records`
+pub fn report(records: &[Record]) -> String {
+    let Some(first) = records.first() else {
+        return "No stats recorded yet.".to_string();
+    };
+    let since = first.time.get(..10).unwrap_or(&first.time);
+    let mut out = format!("Usage since {} ({} records)\n\n", since, records.len());
+
+    let replies: Vec<(u64, usize)> = records
+        .iter()
+        .filter_map(|r| match r.event {
+            Event::Reply { ms, tool_calls } => Some((ms, tool_calls)),
+            _ => None,
+        })
+        .collect();
+    let calling = replies.iter().filter(|(_, calls)| *calls > 0).count();
+    out.push_str(&format!(
+        "Replies:   {} ({} calling tools), {}\n",
+        replies.len(),
+        calling,
+        latency(replies.iter().map(|(ms, _)| *ms).collect())
+    ));
+
+    // Per tool: (runs that succeeded, latencies)
+    let mut tools: BTreeMap<&str, (usize, Vec<u64>)> = BTreeMap::new();
+    let mut programs: BTreeMap<&str, usize> = BTreeMap::new();
+    for record in records {
+        if let Event::Tool {
+            tool,
+            program,
+            ok,
+            ms,
+        } = &record.event
+        {
+            let entry = tools.entry(tool).or_default();
+            entry.0 += usize::from(*ok);
+            entry.1.push(*ms);
+            if let Some(program) = program {
+                *programs.entry(program).or_default() += 1;
+            }
+        }
+    }
+    let runs: usize = tools.values().map(|(_, ms)| ms.len()).sum();
+    let ok: usize = tools.values().map(|(ok, _)| ok).sum();
+    out.push_str(&format!(
+        "Tool runs: {} ({}% succeeded), {}\n",
+        runs,
+        (ok * 100).checked_div(runs).unwrap_or(0),
+        latency(
+            tools
+                .values()
+                .flat_map(|(_, ms)| ms.iter().copied())
+                .collect()
+        )
+    ));
+    let width = tools.keys().map(|t| t.chars().count()).max().unwrap_or(0);
+    for (tool, (ok, ms)) in &tools {
+        out.push_str(&format!(
+            "  {:<width$}  {:>5}  {:>3}%  {}\n",
+            tool,
+            ms.len(),
+            ok * 100 / ms.len(),
+            latency(ms.clone()),
+            width = width
+        ));
+    }
+
+    if !programs.is_empty() {
+        let mut programs: Vec<_> = programs.into_iter().collect();
+        programs.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        let top = programs
+            .iter()
+            .take(10)
+            .map(|(program, n)| format!("{} ({})", program, n))
+            .collect::<Vec<_>>()
+            .join(", ");
+        out.push_str(&format!("\nCommands run most: {}\n", top));
+    }
+    out
+}
+
+/// Handle `sabi stats [clear]`
+pub fn handle_stats_command(args: &[String]) -> Result<(), String> {
+    let path = stats_path().ok_or("Can't find the data directory")?;
+    match args.first().map(String::as_str) {
+        None => {
+            let records = load(&path)?;
+            print!("{}", report(&records));
+            if records.is_empty() {
+                println!("\nStats are opt-in: set stats = true in ~/.sabi/config.toml.");
+            }
+            Ok(())
+        }
+        Some("clear") => {
+            match std::fs::remove_file(&path) {
+                Ok(()) => eprintln!("✓ Cleared {}", path.display()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(format!("Can't remove {}: {}", path.display(), e)),
+            }
+            Ok(())
+        }
+        Some(_) => {
+            println!("Local usage statistics (stats = true in config.toml)\n");
+            println!("Usage:");
+            println!("  sabi stats          Summarize the recorded replies and tool runs");
+            println!("  sabi stats clear    Delete the recorded stats");
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::ToolRun;
+    use crate::tool_call::ToolCall;
+    use std::time::Duration;
+
+    fn run(command: &str, exit_code: i32) -> ToolRun {
+        ToolRun {
+            call: ToolCall::run_cmd(command),
+            exit_code: Some(exit_code),
+            signal: None,
+            truncated: false,
+            section: String::new(),
+        }
+    }
+
+    // **Feature: Sabi-TUI, Property: Usage Stats Records**
+    #[test]
+    fn test_records_of_messages() {
+        assert!(records(&Message::user("hi")).is_empty());
+        assert!(records(&Message::model("no timing")).is_empty());
+
+        let reply = Message::model("ok")
+            .took(Duration::from_millis(1500))
+            .with_tool_calls(vec![ToolCall::run_cmd("ls")]);
+        let [record] = records(&reply).try_into().unwrap();
+        assert_eq!(
+            record.event,
+            Event::Reply {
+                ms: 1500,
+                tool_calls: 1
+            }
+        );
+
+        let result = Message::tool("Tool: run_cmd")
+            .took(Duration::from_millis(200))
+            .with_tool_runs(vec![run("git status", 0), run("cargo test", 101)]);
+        let records = records(&result);
+        assert_eq!(
+            records[1].event,
+            Event::Tool {
+                tool: "run_cmd".to_string(),
+                program: Some("cargo".to_string()),
+                ok: false,
+                ms: 200
+            }
+        );
+
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("data/stats.jsonl");
+        append(&path, &records).unwrap();
+        append(&path, &records[..1]).unwrap();
+        std::fs::write(
+            temp.path().join("other.jsonl"),
+            "garbage\n{\"kind\":\"reply\"}\n",
+        )
+        .unwrap();
+        assert_eq!(load(&path).unwrap().len(), 3);
+        assert!(load(&temp.path().join("other.jsonl")).unwrap().is_empty());
+        assert!(load(&temp.path().join("missing")).unwrap().is_empty());
+    }
+
+    // **Feature: Sabi-TUI, Property: Usage Stats Report**
+    #[test]
+    fn test_report() {
+        assert_eq!(report(&[]), "No stats recorded yet.");
+        let record = |event| Record {
+            time: "2026-10-01T09:00:00+00:00".to_string(),
+            event,
+        };
+        let mut records: Vec<Record> = (1..=10)
+            .map(|s| {
+                record(Event::Reply {
+                    ms: s * 1000,
+                    tool_calls: usize::from(s % 2 == 0),
+                })
+            })
+            .collect();
+        for (program, ok) in [("git", true), ("git", true), ("cargo", false)] {
+            records.push(record(Event::Tool {
+                tool: "run_cmd".to_string(),
+                program: Some(program.to_string()),
+                ok,
+                ms: 400,
+            }));
+        }
+        records.push(record(Event::Tool {
+            tool: "read_file".to_string(),
+            program: None,
+            ok: true,
+            ms: 10,
+        }));
+
+        let report = report(&records);
+        assert!(report.starts_with("Usage since 2026-10-01 (14 records)"));
+        assert!(
+            report.contains("Replies:   10 (5 calling tools), p50 5.0s · p90 9.0s · p99 10.0s"),
+            "{}",
+            report
+        );
+        assert!(report.contains("Tool runs: 4 (75% succeeded)"));
+        assert!(report.contains("  run_cmd        3   66%  p50 0.4s"));
+        assert!(report.contains("Commands run most: git (2), cargo (1)"));
+    }
+}
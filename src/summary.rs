@@ -0,0 +1,153 @@
+//! Summaries of turns that fell out of the context window
+//!
+//! Providers only send the last `max_history_messages` messages. Once the
+//! history outgrows that, the AI is asked to summarize the older part, and
+//! requests send the summary in its place. The summary is saved with the
+//! session, so a resumed long session starts from it rather than from
+//! whatever happens to fit in the window.
+
+use serde::{Deserialize, Serialize};
+
+use crate::message::{Message, MessageRole};
+
+/// Summary of the start of a conversation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Summary {
+    /// The summary itself
+    pub text: String,
+    /// How many messages (system messages not counted) it covers
+    pub covers: usize,
+}
+
+/// Characters of a single message included in a summary request
+const MESSAGE_CHARS: usize = 2000;
+
+/// Messages (system messages not counted) to summarize up to, if the part
+/// of `history` not covered yet no longer fits in the window
+///
+/// Half the window is left unsummarized so a new summary isn't needed after
+/// every turn. A window of one message has no room for a summary.
+pub fn due(history: usize, covered: usize, max_history: usize) -> Option<usize> {
+    let covers = history.saturating_sub(max_history / 2);
+    (max_history > 1 && history.saturating_sub(covered) >= max_history && covers > covered)
+        .then_some(covers)
+}
+
+/// Messages to send in place of `messages` when `summary` covers their start
+///
+/// The system prompt is followed by the summary and as many of the messages
+/// after it as fit in `max_history`. Without a summary `messages` is sent
+/// as it is.
+pub fn context(
+    messages: &[Message],
+    summary: Option<&Summary>,
+    max_history: usize,
+) -> Vec<Message> {
+    let Some(summary) = summary else {
+        return messages.to_vec();
+    };
+    let history: Vec<&Message> = messages
+        .iter()
+        .filter(|m| m.role != MessageRole::System)
+        .collect();
+    let rest = &history[summary.covers.min(history.len())..];
+    let rest = &rest[rest.len().saturating_sub(max_history.saturating_sub(1))..];
+
+    let mut context: Vec<Message> = messages
+        .iter()
+        .filter(|m| m.is_system_prompt())
+        .cloned()
+        .collect();
+    context.push(Message::user(format!(
+        "Summary of our earlier conversation (those messages are not shown):\n{}",
+        summary.text.trim()
+    )));
+    context.extend(rest.iter().map(|m| (*m).clone()));
+    context
+}
+
+/// Request asking the AI to summarize `messages`, extending `previous`
+pub fn request(previous: Option<&Summary>, messages: &[Message]) -> Vec<Message> {
+    let mut transcript = String::new();
+    for msg in messages.iter().filter(|m| m.role != MessageRole::System) {
+        let role = match msg.role {
+            MessageRole::User if !msg.is_tool_result() => "User",
+            MessageRole::Model => "Assistant",
+            _ => "Tool result",
+        };
+        let mut content: String = msg.content.chars().take(MESSAGE_CHARS).collect();
+        if content.len() < msg.content.len() {
+            content.push_str(" […]");
+        }
+        transcript.push_str(&format!("{}:\n{}\n\n", role, content.trim_end()));
+    }
+    let previous = previous
+        .map(|s| format!("Summary so far:\n{}\n\n", s.text.trim()))
+        .unwrap_or_default();
+    vec![Message::user(format!(
+        "Summarize the conversation below so it can be continued without it. \
+         Keep the user's goals, decisions made, facts learned about the system \
+         (paths, versions, configuration), commands that worked or failed, and \
+         what is still left to do. Be concise. Reply in plain text only and do \
+         not call any tools.\n\n{}Conversation:\n\n{}",
+        previous,
+        transcript.trim_end()
+    ))]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // **Feature: Sabi-TUI, Property: Summary Due**
+    #[test]
+    fn test_due() {
+        assert_eq!(due(19, 0, 20), None);
+        assert_eq!(due(20, 0, 20), Some(10));
+        assert_eq!(due(25, 10, 20), None);
+        assert_eq!(due(30, 10, 20), Some(20));
+        assert_eq!(due(2, 0, 2), Some(1));
+        assert_eq!(due(3, 0, 1), None);
+        assert_eq!(due(5, 0, 0), None);
+    }
+
+    // **Feature: Sabi-TUI, Property: Summarized Context**
+    #[test]
+    fn test_context_replaces_summarized_messages() {
+        let mut messages = vec![Message::system("You MUST use tools"), Message::user("q1")];
+        messages.push(Message::model("a1"));
+        messages.push(Message::system("✓ Auto-approved"));
+        messages.push(Message::user("q2"));
+        messages.push(Message::model("a2"));
+        messages.push(Message::user("q3"));
+        assert_eq!(context(&messages, None, 3).len(), messages.len());
+
+        let summary = Summary {
+            text: "Asked q1.".to_string(),
+            covers: 2,
+        };
+        let sent = context(&messages, Some(&summary), 3);
+        let contents: Vec<&str> = sent.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents[0], "You MUST use tools");
+        assert!(contents[1].ends_with("not shown):\nAsked q1."));
+        assert_eq!(&contents[2..], ["a2", "q3"]);
+    }
+
+    #[test]
+    fn test_request_extends_previous_summary() {
+        let previous = Summary {
+            text: "Set up the repo.".to_string(),
+            covers: 4,
+        };
+        let messages = vec![
+            Message::user("run the tests"),
+            Message::tool(format!("Tool: run_cmd cargo test\n{}", "x".repeat(3000))),
+        ];
+        let request = request(Some(&previous), &messages);
+        assert_eq!(request.len(), 1);
+        let text = &request[0].content;
+        assert!(text.contains("Summary so far:\nSet up the repo.\n\n"));
+        assert!(text.contains("User:\nrun the tests\n\nTool result:\nTool: run_cmd"));
+        assert!(text.ends_with(" […]"));
+    }
+}
@@ -0,0 +1,99 @@
+//! Session templates
+//!
+//! `[templates.<name>]` in config.toml describes a kind of session, e.g. a
+//! code review or sysadmin work: context and instructions added to the
+//! system prompt for the whole session, a directory to start in and a
+//! model. `/new <name>` starts a session from it.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// A session to start from, from `[templates.<name>]`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SessionTemplate {
+    /// Background the AI gets up front (the project, the machine, the team)
+    #[serde(default)]
+    pub context: String,
+    /// Instructions the AI follows for the whole session
+    #[serde(default)]
+    pub instructions: String,
+    /// Directory to start in (`~/` is expanded; empty = current)
+    #[serde(default)]
+    pub cwd: String,
+    /// Model to switch to (empty = keep the current one)
+    #[serde(default)]
+    pub model: String,
+}
+
+impl SessionTemplate {
+    /// Text added to the system prompt of sessions started from template
+    /// `name`, empty when it has neither context nor instructions
+    pub fn prompt(&self, name: &str) -> String {
+        let context = self.context.trim();
+        let instructions = self.instructions.trim();
+        if context.is_empty() && instructions.is_empty() {
+            return String::new();
+        }
+        let mut text = format!("\n\n## Session: {}", name);
+        if !context.is_empty() {
+            text.push_str(&format!("\n\n{}", context));
+        }
+        if !instructions.is_empty() {
+            text.push_str(&format!(
+                "\n\nFollow these instructions for the whole session:\n{}",
+                instructions
+            ));
+        }
+        text
+    }
+
+    /// Directory to start in, resolved against `cwd`, if set and existing
+    pub fn start_dir(&self, cwd: &Path) -> Option<PathBuf> {
+        let dir = self.cwd.trim();
+        if dir.is_empty() {
+            return None;
+        }
+        let path = match dir.strip_prefix("~/").zip(dirs::home_dir()) {
+            Some((rest, home)) => home.join(rest),
+            None => cwd.join(dir),
+        };
+        path.is_dir().then_some(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // **Feature: Sabi-TUI, Property: Session Template Prompt**
+    #[test]
+    fn test_prompt() {
+        assert_eq!(SessionTemplate::default().prompt("empty"), "");
+        let template = SessionTemplate {
+            context: "Rust service, deployed with systemd.\n".to_string(),
+            instructions: "Only suggest changes; don't write files.".to_string(),
+            ..SessionTemplate::default()
+        };
+        assert_eq!(
+            template.prompt("code-review"),
+            "\n\n## Session: code-review\n\nRust service, deployed with systemd.\n\n\
+             Follow these instructions for the whole session:\n\
+             Only suggest changes; don't write files."
+        );
+    }
+
+    #[test]
+    fn test_start_dir() {
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::create_dir(temp.path().join("repo")).unwrap();
+        let template = |cwd: &str| SessionTemplate {
+            cwd: cwd.to_string(),
+            ..SessionTemplate::default()
+        };
+        assert_eq!(template("").start_dir(temp.path()), None);
+        assert_eq!(template("missing").start_dir(temp.path()), None);
+        let dir = template("repo").start_dir(temp.path());
+        assert_eq!(dir, Some(temp.path().join("repo")));
+    }
+}
@@ -0,0 +1,614 @@
+//! Color palettes for light and dark terminals
+//!
+//! The UI draws with a palette of named roles instead of fixed colors, so
+//! it stays readable on light backgrounds too. With `theme = "auto"` the
+//! terminal is asked for its background color (OSC 11), falling back to
+//! `$COLORFGBG`, then to the dark palette; a non-empty `$NO_COLOR` picks
+//! the monochrome palette instead.
+//!
+//! The colorblind and monochrome palettes don't rely on red versus green:
+//! they use blue/orange or no color at all, and mark states with symbols.
+//!
+//! Terminals with fewer colors get the palette mapped to the nearest
+//! colors they have, and terminals that can't draw Braille or emoji get an
+//! ASCII spinner and status bar (`colors` and `glyphs` override detection).
+
+use std::time::Duration;
+
+use ratatui::style::{Color, Modifier, Style};
+use serde::{Deserialize, Serialize};
+
+/// How long to wait for the terminal to answer the background query
+///
+/// The wait normally ends at the reply to the device attributes query sent
+/// after it, which every terminal answers; this only bounds silent ones.
+const QUERY_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// `theme` config setting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeMode {
+    /// Detect the terminal background
+    #[default]
+    Auto,
+    Dark,
+    Light,
+    /// Blue/orange instead of green/red, with symbols for states
+    Colorblind,
+    /// No colors (also picked by `$NO_COLOR`)
+    Mono,
+}
+
+/// `colors` config setting: how many colors the terminal shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorSupport {
+    /// Detect from `$COLORTERM` and `$TERM`
+    #[default]
+    Auto,
+    /// 24-bit colors
+    Truecolor,
+    /// The xterm 256-color palette
+    #[serde(rename = "256")]
+    Ansi256,
+    /// The 16 basic ANSI colors
+    #[serde(rename = "16")]
+    Ansi16,
+}
+
+impl ColorSupport {
+    /// The setting, detecting the terminal for `Auto`
+    pub fn resolve(self) -> Self {
+        match self {
+            Self::Auto => detect_colors(
+                std::env::var("TERM").ok().as_deref(),
+                std::env::var("COLORTERM").ok().as_deref(),
+            ),
+            other => other,
+        }
+    }
+}
+
+/// `glyphs` config setting: Braille and emoji, or ASCII only
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum GlyphSupport {
+    /// Detect from `$TERM` and the locale
+    #[default]
+    Auto,
+    Unicode,
+    Ascii,
+}
+
+impl GlyphSupport {
+    /// Whether to draw the spinner and status bar with ASCII only
+    pub fn ascii_only(self) -> bool {
+        match self {
+            Self::Auto => {
+                let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+                    .iter()
+                    .filter_map(|name| std::env::var(name).ok())
+                    .find(|value| !value.is_empty());
+                !detect_unicode(std::env::var("TERM").ok().as_deref(), locale.as_deref())
+            }
+            Self::Unicode => false,
+            Self::Ascii => true,
+        }
+    }
+}
+
+/// Terminals known to show only the 16 basic colors
+const BASIC_TERMS: &[&str] = &["linux", "vt100", "vt102", "vt220", "ansi", "cons25", "dumb"];
+
+/// Color support from `$TERM` and `$COLORTERM`; unknown terminals are
+/// assumed to have 256 colors
+fn detect_colors(term: Option<&str>, colorterm: Option<&str>) -> ColorSupport {
+    let term = term.unwrap_or("");
+    if matches!(colorterm, Some("truecolor" | "24bit")) || term.ends_with("-direct") {
+        ColorSupport::Truecolor
+    } else if BASIC_TERMS.contains(&term) {
+        ColorSupport::Ansi16
+    } else {
+        ColorSupport::Ansi256
+    }
+}
+
+/// Whether Braille and emoji can be drawn: not on the Linux console and
+/// other basic terminals, nor with a non-UTF-8 locale
+fn detect_unicode(term: Option<&str>, locale: Option<&str>) -> bool {
+    if term.is_some_and(|t| BASIC_TERMS.contains(&t)) {
+        return false;
+    }
+    match locale {
+        Some(locale) => {
+            let locale = locale.to_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        }
+        None => true,
+    }
+}
+
+/// RGB of the basic ANSI colors (xterm defaults), in color number order
+const BASIC_RGB: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// The basic ANSI colors, in color number order
+const BASIC_COLORS: [Color; 16] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::Gray,
+    Color::DarkGray,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::LightCyan,
+    Color::White,
+];
+
+/// RGB of a color of the xterm 256-color palette
+fn indexed_rgb(index: u8) -> (u8, u8, u8) {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    match index {
+        0..=15 => BASIC_RGB[index as usize],
+        16..=231 => {
+            let i = index - 16;
+            (
+                LEVELS[(i / 36) as usize],
+                LEVELS[(i / 6 % 6) as usize],
+                LEVELS[(i % 6) as usize],
+            )
+        }
+        _ => {
+            let level = 8 + 10 * (index - 232);
+            (level, level, level)
+        }
+    }
+}
+
+/// Index of the color in `candidates` closest to `rgb`
+fn nearest(rgb: (u8, u8, u8), candidates: impl Iterator<Item = (u8, u8, u8)>) -> usize {
+    let distance = |(r, g, b): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(r, rgb.0) + d(g, rgb.1) + d(b, rgb.2)
+    };
+    candidates
+        .enumerate()
+        .min_by_key(|&(_, c)| distance(c))
+        .map_or(0, |(i, _)| i)
+}
+
+/// `color` as the closest one the terminal can show
+pub fn fit_color(color: Color, colors: ColorSupport) -> Color {
+    match (color, colors) {
+        (Color::Rgb(r, g, b), ColorSupport::Ansi256) => {
+            Color::Indexed(16 + nearest((r, g, b), (16..=255).map(indexed_rgb)) as u8)
+        }
+        (Color::Rgb(r, g, b), ColorSupport::Ansi16) => {
+            BASIC_COLORS[nearest((r, g, b), BASIC_RGB.into_iter())]
+        }
+        (Color::Indexed(index), ColorSupport::Ansi16) => {
+            BASIC_COLORS[nearest(indexed_rgb(index), BASIC_RGB.into_iter())]
+        }
+        (color, _) => color,
+    }
+}
+
+/// Terminal background brightness
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Background {
+    Dark,
+    Light,
+}
+
+/// Colors the UI draws with, by role
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    /// "You:" messages
+    pub user: Color,
+    /// "AI:" messages
+    pub model: Color,
+    /// "System:" messages
+    pub system: Color,
+    /// Borders, bullets and headings
+    pub accent: Color,
+    /// Bold text, focus and output borders
+    pub highlight: Color,
+    /// Success, code, the input state
+    pub success: Color,
+    /// Errors and dangerous commands
+    pub danger: Color,
+    /// Hints, stderr and secondary text
+    pub muted: Color,
+    /// Paths and links
+    pub info: Color,
+    /// Pager, container target, executing state
+    pub special: Color,
+    /// Input box border
+    pub border: Color,
+    /// Mark safe and dangerous states with symbols, not just colors
+    pub symbols: bool,
+}
+
+impl Palette {
+    /// For dark backgrounds (the original colors)
+    pub const fn dark() -> Self {
+        Self {
+            user: Color::Green,
+            model: Color::Blue,
+            system: Color::Yellow,
+            accent: Color::Cyan,
+            highlight: Color::Yellow,
+            success: Color::Green,
+            danger: Color::Red,
+            muted: Color::DarkGray,
+            info: Color::Blue,
+            special: Color::Magenta,
+            border: Color::White,
+            symbols: false,
+        }
+    }
+
+    /// For light backgrounds: darker shades that keep their contrast on white
+    pub const fn light() -> Self {
+        Self {
+            user: Color::Indexed(28),
+            model: Color::Indexed(25),
+            system: Color::Indexed(130),
+            accent: Color::Indexed(30),
+            highlight: Color::Indexed(130),
+            success: Color::Indexed(28),
+            danger: Color::Indexed(160),
+            muted: Color::Indexed(243),
+            info: Color::Indexed(25),
+            special: Color::Indexed(90),
+            border: Color::Indexed(238),
+            symbols: false,
+        }
+    }
+
+    /// Okabe-Ito style colors that stay apart with any color vision:
+    /// sky blue for safe, orange for danger
+    pub const fn colorblind() -> Self {
+        Self {
+            user: Color::Indexed(74),
+            model: Color::Indexed(175),
+            system: Color::Indexed(227),
+            accent: Color::Indexed(74),
+            highlight: Color::Indexed(227),
+            success: Color::Indexed(74),
+            danger: Color::Indexed(208),
+            muted: Color::Indexed(246),
+            info: Color::Indexed(110),
+            special: Color::Indexed(175),
+            border: Color::White,
+            symbols: true,
+        }
+    }
+
+    /// The terminal's own colors only
+    pub const fn mono() -> Self {
+        Self {
+            user: Color::Reset,
+            model: Color::Reset,
+            system: Color::Reset,
+            accent: Color::Reset,
+            highlight: Color::Reset,
+            success: Color::Reset,
+            danger: Color::Reset,
+            muted: Color::Reset,
+            info: Color::Reset,
+            special: Color::Reset,
+            border: Color::Reset,
+            symbols: true,
+        }
+    }
+
+    /// The palette with every color fit to what the terminal shows
+    pub fn fit(self, colors: ColorSupport) -> Self {
+        let fit = |color| fit_color(color, colors);
+        Self {
+            user: fit(self.user),
+            model: fit(self.model),
+            system: fit(self.system),
+            accent: fit(self.accent),
+            highlight: fit(self.highlight),
+            success: fit(self.success),
+            danger: fit(self.danger),
+            muted: fit(self.muted),
+            info: fit(self.info),
+            special: fit(self.special),
+            border: fit(self.border),
+            symbols: self.symbols,
+        }
+    }
+
+    /// Black text on `color` for badges and highlights; reversed video
+    /// when there are no colors
+    pub fn badge(&self, color: Color) -> Style {
+        if color == Color::Reset {
+            Style::new().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::new().fg(Color::Black).bg(color)
+        }
+    }
+
+    pub fn for_background(background: Background) -> Self {
+        match background {
+            Background::Dark => Self::dark(),
+            Background::Light => Self::light(),
+        }
+    }
+
+    /// Palette for `mode`, querying the terminal for `Auto`
+    ///
+    /// Call with raw mode on and before anything else reads terminal input.
+    pub fn resolve(mode: ThemeMode) -> Self {
+        match mode {
+            ThemeMode::Dark => Self::dark(),
+            ThemeMode::Light => Self::light(),
+            ThemeMode::Colorblind => Self::colorblind(),
+            ThemeMode::Mono => Self::mono(),
+            ThemeMode::Auto if no_color(std::env::var("NO_COLOR").ok().as_deref()) => Self::mono(),
+            ThemeMode::Auto => {
+                Self::for_background(detect_background().unwrap_or(Background::Dark))
+            }
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+/// `$NO_COLOR` asks for no colors when it is set to anything but ""
+fn no_color(value: Option<&str>) -> bool {
+    value.is_some_and(|v| !v.is_empty())
+}
+
+/// Background of the terminal, if it can be told
+pub fn detect_background() -> Option<Background> {
+    query_background_color().map(background_of).or_else(|| {
+        std::env::var("COLORFGBG")
+            .ok()
+            .and_then(|v| parse_colorfgbg(&v))
+    })
+}
+
+/// Light or dark by relative luminance
+fn background_of((r, g, b): (f32, f32, f32)) -> Background {
+    if 0.2126 * r + 0.7152 * g + 0.0722 * b > 0.5 {
+        Background::Light
+    } else {
+        Background::Dark
+    }
+}
+
+/// `$COLORFGBG` is "fg;bg" (or "fg;default;bg") with ANSI color numbers
+fn parse_colorfgbg(value: &str) -> Option<Background> {
+    let bg: u8 = value.rsplit(';').next()?.trim().parse().ok()?;
+    Some(match bg {
+        7 | 15 => Background::Light,
+        _ => Background::Dark,
+    })
+}
+
+/// Parse an OSC 11 reply like `ESC ] 11 ; rgb:ffff/ffff/ffff BEL`
+///
+/// Components have 1-4 hex digits; returns them scaled to 0.0-1.0.
+fn parse_osc11(reply: &str) -> Option<(f32, f32, f32)> {
+    let rgb = reply.split("rgb:").nth(1)?;
+    let rgb = rgb.split(['\x07', '\x1b']).next()?;
+    let parts: Vec<f32> = rgb
+        .split('/')
+        .map(|hex| {
+            let hex = hex.trim();
+            let value = u32::from_str_radix(hex, 16).ok()?;
+            let max = (1u32 << (4 * hex.len().clamp(1, 4))) - 1;
+            Some(value as f32 / max as f32)
+        })
+        .collect::<Option<_>>()?;
+    match parts[..] {
+        [r, g, b] => Some((r, g, b)),
+        _ => None,
+    }
+}
+
+/// Whether `reply` ends with the answer to a device attributes query
+/// (`ESC [ ? ... c`)
+fn has_device_attributes(reply: &[u8]) -> bool {
+    reply
+        .windows(3)
+        .rposition(|w| w == b"\x1b[?")
+        .is_some_and(|start| reply[start..].contains(&b'c'))
+}
+
+/// Ask the terminal for its background color (OSC 11)
+///
+/// A device attributes query follows it; terminals answer in order, so its
+/// reply marks the end of any OSC 11 reply. Input still pending when the
+/// wait ends is discarded so a late reply can't reach the input box.
+#[cfg(unix)]
+fn query_background_color() -> Option<(f32, f32, f32)> {
+    use std::io::{IsTerminal, Read, Write};
+    use std::os::fd::AsRawFd;
+    use std::time::Instant;
+
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+    let mut tty = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .ok()?;
+    tty.write_all(b"\x1b]11;?\x07\x1b[c").ok()?;
+    tty.flush().ok()?;
+
+    let deadline = Instant::now() + QUERY_TIMEOUT;
+    let mut reply = Vec::new();
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let mut fds = libc::pollfd {
+            fd: tty.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: one valid pollfd for the duration of the call
+        let ready = unsafe { libc::poll(&mut fds, 1, remaining.as_millis() as libc::c_int) };
+        if ready <= 0 {
+            // SAFETY: tcflush only discards the terminal's queued input
+            unsafe { libc::tcflush(tty.as_raw_fd(), libc::TCIFLUSH) };
+            break;
+        }
+        let mut chunk = [0u8; 64];
+        match tty.read(&mut chunk) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => reply.extend_from_slice(&chunk[..n]),
+        }
+        if has_device_attributes(&reply) {
+            break;
+        }
+    }
+    parse_osc11(&String::from_utf8_lossy(&reply))
+}
+
+#[cfg(not(unix))]
+fn query_background_color() -> Option<(f32, f32, f32)> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // **Feature: Sabi-TUI, Property: Terminal Background Detection**
+    #[test]
+    fn test_parse_osc11_reply() {
+        let white = parse_osc11("\x1b]11;rgb:ffff/ffff/ffff\x07").unwrap();
+        assert_eq!(background_of(white), Background::Light);
+
+        let dark = parse_osc11("\x1b]11;rgb:1e/1e/2e\x1b\\").unwrap();
+        assert_eq!(background_of(dark), Background::Dark);
+
+        assert_eq!(parse_osc11("\x1b]11;?\x07"), None);
+
+        // The device attributes reply ends the wait, with or without OSC 11
+        assert!(has_device_attributes(b"\x1b]11;rgb:0/0/0\x07\x1b[?62;22c"));
+        assert!(has_device_attributes(b"\x1b[?1;2c"));
+        assert!(!has_device_attributes(b"\x1b]11;rgb:0/0/0\x07\x1b[?62;"));
+        assert_eq!(parse_osc11("rgb:zz/00/00"), None);
+    }
+
+    #[test]
+    fn test_parse_colorfgbg() {
+        assert_eq!(parse_colorfgbg("15;0"), Some(Background::Dark));
+        assert_eq!(parse_colorfgbg("0;15"), Some(Background::Light));
+        assert_eq!(parse_colorfgbg("0;default;7"), Some(Background::Light));
+        assert_eq!(parse_colorfgbg("garbage"), None);
+    }
+
+    #[test]
+    fn test_explicit_mode_skips_detection() {
+        assert_eq!(Palette::resolve(ThemeMode::Light), Palette::light());
+        assert_eq!(Palette::resolve(ThemeMode::Dark), Palette::dark());
+        assert_eq!(Palette::resolve(ThemeMode::Mono), Palette::mono());
+    }
+
+    // **Feature: Sabi-TUI, Property: Color Capability Detection**
+    #[test]
+    fn test_detect_capabilities() {
+        assert_eq!(
+            detect_colors(Some("xterm-256color"), Some("truecolor")),
+            ColorSupport::Truecolor
+        );
+        assert_eq!(
+            detect_colors(Some("xterm-256color"), None),
+            ColorSupport::Ansi256
+        );
+        assert_eq!(detect_colors(Some("linux"), None), ColorSupport::Ansi16);
+
+        assert!(detect_unicode(Some("xterm-256color"), Some("en_US.UTF-8")));
+        assert!(detect_unicode(Some("xterm"), None));
+        assert!(!detect_unicode(Some("xterm"), Some("C")));
+        assert!(!detect_unicode(Some("linux"), Some("en_US.UTF-8")));
+    }
+
+    // **Feature: Sabi-TUI, Property: Color Degradation**
+    #[test]
+    fn test_fit_colors() {
+        // Basic colors are left alone
+        assert_eq!(fit_color(Color::Red, ColorSupport::Ansi16), Color::Red);
+        assert_eq!(
+            fit_color(Color::Indexed(208), ColorSupport::Ansi256),
+            Color::Indexed(208)
+        );
+        assert_eq!(
+            fit_color(Color::Rgb(255, 135, 0), ColorSupport::Ansi256),
+            Color::Indexed(208)
+        );
+        assert_eq!(
+            fit_color(Color::Indexed(160), ColorSupport::Ansi16),
+            Color::Red
+        );
+        assert_eq!(
+            fit_color(Color::Indexed(243), ColorSupport::Ansi16),
+            Color::DarkGray
+        );
+
+        let basic = Palette::light().fit(ColorSupport::Ansi16);
+        assert!(
+            [basic.user, basic.danger, basic.muted, basic.border]
+                .iter()
+                .all(|c| BASIC_COLORS.contains(c))
+        );
+    }
+
+    // **Feature: Sabi-TUI, Property: Accessible Palettes**
+    #[test]
+    fn test_accessible_palettes() {
+        assert!(no_color(Some("1")));
+        assert!(!no_color(Some("")));
+        assert!(!no_color(None));
+
+        // Safe and dangerous never rely on red versus green alone
+        for palette in [Palette::colorblind(), Palette::mono()] {
+            assert!(palette.symbols);
+            assert_ne!(palette.danger, Color::Red);
+            assert_ne!(palette.success, Color::Green);
+        }
+        assert_ne!(Palette::colorblind().danger, Palette::colorblind().success);
+
+        let mono = Palette::mono();
+        assert_eq!(
+            mono.badge(mono.highlight),
+            Style::new().add_modifier(Modifier::REVERSED)
+        );
+        let dark = Palette::dark();
+        assert_eq!(dark.badge(dark.accent).bg, Some(Color::Cyan));
+    }
+}
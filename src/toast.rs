@@ -0,0 +1,127 @@
+//! Short-lived notifications shown in a corner of the screen
+//!
+//! Status reports like "Switched to model X" or "Copied 120 characters" are
+//! not part of the conversation. They show up as toasts for a few seconds
+//! instead of as system messages, so they never reach the AI's context.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How long a toast stays on screen
+pub const TOAST_TIME: Duration = Duration::from_secs(4);
+
+/// At most this many toasts are shown; older ones are dropped
+pub const MAX_TOASTS: usize = 3;
+
+/// What a toast reports, for its color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToastKind {
+    Info,
+    Success,
+    Error,
+}
+
+impl ToastKind {
+    /// Guess the kind from the leading ✓ / ✗ / ⚠ of a status text
+    pub fn of(text: &str) -> Self {
+        if text.starts_with('✓') {
+            Self::Success
+        } else if text.starts_with('✗') || text.starts_with('⚠') {
+            Self::Error
+        } else {
+            Self::Info
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Toast {
+    pub text: String,
+    pub kind: ToastKind,
+    pub shown_at: Instant,
+}
+
+/// Toasts on screen, oldest first
+#[derive(Debug, Default)]
+pub struct Toasts {
+    items: VecDeque<Toast>,
+}
+
+impl Toasts {
+    pub fn push(&mut self, text: impl Into<String>, now: Instant) {
+        let text = text.into();
+        let kind = ToastKind::of(&text);
+        // The same report twice in a row just restarts its timer
+        self.items.retain(|t| t.text != text);
+        self.items.push_back(Toast {
+            text,
+            kind,
+            shown_at: now,
+        });
+        while self.items.len() > MAX_TOASTS {
+            self.items.pop_front();
+        }
+    }
+
+    /// Drop toasts that were shown long enough; true if any went away
+    pub fn expire(&mut self, now: Instant) -> bool {
+        let before = self.items.len();
+        self.items
+            .retain(|t| now.saturating_duration_since(t.shown_at) < TOAST_TIME);
+        self.items.len() != before
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Toast> {
+        self.items.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // **Feature: Sabi-TUI, Property: Toasts Expire**
+    #[test]
+    fn test_toasts_expire() {
+        let start = Instant::now();
+        let mut toasts = Toasts::default();
+        toasts.push("✓ Switched to: model-a", start);
+        toasts.push("Compact layout on.", start + Duration::from_secs(2));
+
+        assert!(!toasts.expire(start + Duration::from_secs(3)));
+        assert!(toasts.expire(start + TOAST_TIME));
+        let left: Vec<_> = toasts.iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(left, ["Compact layout on."]);
+        toasts.expire(start + Duration::from_secs(10));
+        assert!(toasts.is_empty());
+    }
+
+    // **Feature: Sabi-TUI, Property: Toast Limit**
+    #[test]
+    fn test_toasts_keep_latest() {
+        let now = Instant::now();
+        let mut toasts = Toasts::default();
+        for i in 0..5 {
+            toasts.push(format!("toast {}", i), now);
+        }
+        toasts.push("toast 3", now);
+        let texts: Vec<_> = toasts.iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(texts, ["toast 2", "toast 4", "toast 3"]);
+    }
+
+    #[test]
+    fn test_toast_kind() {
+        assert_eq!(ToastKind::of("✓ Exported"), ToastKind::Success);
+        assert_eq!(ToastKind::of("✗ Export failed"), ToastKind::Error);
+        assert_eq!(ToastKind::of("⚠ Can't copy"), ToastKind::Error);
+        assert_eq!(ToastKind::of("Compact layout on."), ToastKind::Info);
+    }
+}
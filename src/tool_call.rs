@@ -3,10 +3,32 @@
 //! Handles parsing of tool call JSON from AI responses, supporting both
 //! raw JSON and markdown code blocks.
 
+use std::ops::Range;
+
 use serde::{Deserialize, Serialize};
 
 /// Allowed tools
-const ALLOWED_TOOLS: &[&str] = &["run_cmd", "read_file", "write_file", "search", "run_python", "mcp"];
+pub const ALLOWED_TOOLS: &[&str] = &[
+    "run_cmd",
+    "read_file",
+    "write_file",
+    "search",
+    "run_python",
+    "mcp",
+    "open_editor",
+    "man_page",
+    "check_command",
+    "query_json",
+];
+
+/// Tools that only read state and may run concurrently
+const READ_ONLY_TOOLS: &[&str] = &[
+    "read_file",
+    "search",
+    "man_page",
+    "check_command",
+    "query_json",
+];
 
 /// Dangerous path patterns (home dirs, system dirs)
 const DANGEROUS_PATHS: &[&str] = &[
@@ -29,33 +51,45 @@ const DANGEROUS_PATHS: &[&str] = &[
 pub struct ToolCall {
     /// The tool to invoke
     pub tool: String,
-    /// For run_cmd: the command to execute
-    #[serde(default)]
+    /// For run_cmd: the command to execute; for check_command: space-separated binary names
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub command: String,
-    /// For read_file/write_file: the file path
-    #[serde(default)]
+    /// For read_file/write_file/open_editor/query_json: the file path
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub path: String,
     /// For write_file: the content to write
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub content: String,
     /// For search: the pattern to search
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub pattern: String,
     /// For search: the directory to search in
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub directory: String,
     /// For run_python: the Python code to execute
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub code: String,
     /// For mcp: the MCP server name
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub server: String,
     /// For mcp: the tool name on the MCP server
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub name: String,
     /// For mcp: the arguments to pass to the tool
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
     pub arguments: serde_json::Value,
+    /// For man_page: the command or function to look up (e.g. "tar", "printf(3)")
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub topic: String,
+    /// For man_page: optional section heading to return (e.g. "OPTIONS")
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub section: String,
+    /// For query_json: the path expression (e.g. ".dependencies | keys")
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub query: String,
+    /// For check_command: also run each binary with `--version`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub versions: bool,
 }
 
 impl ToolCall {
@@ -72,6 +106,10 @@ impl ToolCall {
             server: String::new(),
             name: String::new(),
             arguments: serde_json::Value::Null,
+            topic: String::new(),
+            section: String::new(),
+            query: String::new(),
+            versions: false,
         }
     }
 
@@ -100,11 +138,39 @@ impl ToolCall {
         self.tool == "write_file"
     }
 
+    /// Check if this is an open_editor tool call
+    pub fn is_open_editor(&self) -> bool {
+        self.tool == "open_editor"
+    }
+
+    /// Check if this is a man_page tool call
+    pub fn is_man_page(&self) -> bool {
+        self.tool == "man_page"
+    }
+
+    /// Check if this is a check_command tool call
+    pub fn is_check_command(&self) -> bool {
+        self.tool == "check_command"
+    }
+
+    /// Check if this is a query_json tool call
+    pub fn is_query_json(&self) -> bool {
+        self.tool == "query_json"
+    }
+
     /// Check if this is a search tool call
     pub fn is_search(&self) -> bool {
         self.tool == "search"
     }
 
+    /// Check if this tool only reads state (safe to run in parallel)
+    ///
+    /// check_command with `versions` runs the binaries it finds, so it is
+    /// reviewed like any other command.
+    pub fn is_read_only(&self) -> bool {
+        READ_ONLY_TOOLS.contains(&self.tool.as_str()) && !self.versions
+    }
+
     /// Check if this tool is allowed
     pub fn is_allowed_tool(&self) -> bool {
         ALLOWED_TOOLS.contains(&self.tool.as_str())
@@ -176,6 +242,30 @@ impl ToolCall {
         None
     }
 
+    /// Parse every tool call in an AI response
+    ///
+    /// Accepts a JSON array of tool calls (raw or in a code block) or several
+    /// JSON objects in sequence. Falls back to `parse` for a single call.
+    pub fn parse_all(response: &str) -> Vec<Self> {
+        let trimmed = response.trim();
+
+        // A JSON array, possibly wrapped in a markdown block
+        if let (Some(start), Some(end)) = (trimmed.find('['), trimmed.rfind(']'))
+            && start < end
+            && let Ok(calls) = serde_json::from_str::<Vec<Self>>(&trimmed[start..=end])
+            && !calls.is_empty()
+        {
+            return calls;
+        }
+
+        let calls = Self::find_json_objects(trimmed);
+        if calls.len() > 1 {
+            return calls;
+        }
+
+        Self::parse(trimmed).into_iter().collect()
+    }
+
     /// Try to parse the entire string as JSON
     fn try_parse_json(s: &str) -> Option<Self> {
         serde_json::from_str(s).ok()
@@ -209,18 +299,7 @@ impl ToolCall {
                 if let Some(end_idx) = s[content_start..].find("```") {
                     let command = s[content_start..content_start + end_idx].trim();
                     if !command.is_empty() {
-                        return Some(Self {
-                            tool: "run_cmd".to_string(),
-                            command: command.to_string(),
-                            path: String::new(),
-                            content: String::new(),
-                            pattern: String::new(),
-                            directory: String::new(),
-                            code: String::new(),
-                            server: String::new(),
-                            name: String::new(),
-                            arguments: serde_json::Value::Null,
-                        });
+                        return Some(Self::run_cmd(command));
                     }
                 }
             }
@@ -233,6 +312,20 @@ impl ToolCall {
     ///
     /// Looks for `{...}` patterns and attempts to parse them
     fn try_find_json_object(s: &str) -> Option<Self> {
+        Self::find_json_objects(s).into_iter().next()
+    }
+
+    /// Find every top-level `{...}` in the response that parses as a tool call
+    fn find_json_objects(s: &str) -> Vec<Self> {
+        Self::json_objects(s)
+            .into_iter()
+            .map(|(_, call)| call)
+            .collect()
+    }
+
+    /// Top-level `{...}` tool calls with their byte ranges in `s`
+    fn json_objects(s: &str) -> Vec<(Range<usize>, Self)> {
+        let mut found = Vec::new();
         let mut depth = 0;
         let mut start: Option<usize> = None;
 
@@ -250,7 +343,7 @@ impl ToolCall {
                         if let Some(start_idx) = start {
                             let json_str = &s[start_idx..=i];
                             if let Some(tool_call) = Self::try_parse_json(json_str) {
-                                return Some(tool_call);
+                                found.push((start_idx..i + 1, tool_call));
                             }
                         }
                         start = None;
@@ -260,15 +353,78 @@ impl ToolCall {
             }
         }
 
-        None
+        found
+    }
+
+    /// The prose of an AI response around its tool calls, e.g. why it wants
+    /// to run a command; `None` if the response is only the call
+    pub fn reasoning(response: &str) -> Option<String> {
+        // Drop fenced blocks holding the calls, keep other code
+        let mut prose = String::new();
+        let mut rest = response;
+        while let Some(start) = rest.find("```") {
+            let body_start = start + 3;
+            let Some(len) = rest[body_start..].find("```") else {
+                break;
+            };
+            let block = &rest[body_start..body_start + len];
+            let (lang, body) = block.split_once('\n').unwrap_or(("", block));
+            let body = body.trim();
+            let is_call = matches!(lang.trim(), "bash" | "sh" | "shell" | "zsh")
+                || Self::try_parse_json(body).is_some()
+                || serde_json::from_str::<Vec<Self>>(body).is_ok();
+            if is_call {
+                prose.push_str(&rest[..start]);
+            } else {
+                prose.push_str(&rest[..body_start + len + 3]);
+            }
+            rest = &rest[body_start + len + 3..];
+        }
+        prose.push_str(rest);
+
+        // Drop raw JSON calls, and the brackets of a raw array of them
+        let mut text = String::new();
+        let mut end = 0;
+        for (range, _) in Self::json_objects(&prose) {
+            text.push_str(&prose[end..range.start]);
+            end = range.end;
+        }
+        text.push_str(&prose[end..]);
+
+        let mut lines: Vec<&str> = Vec::new();
+        for line in text.lines().map(str::trim_end) {
+            if line
+                .chars()
+                .all(|c| matches!(c, '[' | ']' | ',') || c.is_whitespace())
+                && !line.is_empty()
+            {
+                continue;
+            }
+            // One blank line between paragraphs
+            if line.is_empty() && lines.last().is_none_or(|l| l.is_empty()) {
+                continue;
+            }
+            lines.push(line);
+        }
+        let reasoning = lines.join("\n").trim().to_string();
+        (!reasoning.is_empty()).then_some(reasoning)
     }
 }
 
+/// Why none of the calls in a batch mixing in other tools were run
+pub const MIXED_BATCH_REASON: &str = "only read-only tools run together, and this call came \
+     with others that aren't; send those one at a time";
+
 /// Result of parsing an AI response
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ParsedResponse {
     /// AI wants to execute a command
     ToolCall(Box<ToolCall>),
+    /// AI issued several independent read-only tool calls
+    ToolCalls(Vec<ToolCall>),
+    /// AI issued several tool calls, not all of them read-only; none are
+    /// run, and the AI is told so (see `MIXED_BATCH_REASON`)
+    MixedToolCalls(Vec<ToolCall>),
     /// AI provided a text response (no tool call)
     TextResponse(String),
 }
@@ -276,15 +432,41 @@ pub enum ParsedResponse {
 impl ParsedResponse {
     /// Parse an AI response into either a tool call or text response
     pub fn parse(response: &str) -> Self {
+        let calls = ToolCall::parse_all(response);
+        if calls.len() > 1 {
+            let read_only = calls
+                .iter()
+                .all(|c| c.is_allowed_tool() && c.is_read_only());
+            return if read_only {
+                ParsedResponse::ToolCalls(calls)
+            } else {
+                ParsedResponse::MixedToolCalls(calls)
+            };
+        }
+
         match ToolCall::parse(response) {
             Some(tool_call) => ParsedResponse::ToolCall(Box::new(tool_call)),
             None => ParsedResponse::TextResponse(response.to_string()),
         }
     }
 
+    /// The tool calls to run, none for a text response or a refused batch
+    pub fn tool_calls(&self) -> Vec<ToolCall> {
+        match self {
+            ParsedResponse::ToolCall(call) => vec![(**call).clone()],
+            ParsedResponse::ToolCalls(calls) => calls.clone(),
+            ParsedResponse::MixedToolCalls(_) | ParsedResponse::TextResponse(_) => Vec::new(),
+        }
+    }
+
     /// Check if this is a tool call
     pub fn is_tool_call(&self) -> bool {
-        matches!(self, ParsedResponse::ToolCall(_))
+        matches!(
+            self,
+            ParsedResponse::ToolCall(_)
+                | ParsedResponse::ToolCalls(_)
+                | ParsedResponse::MixedToolCalls(_)
+        )
     }
 
     /// Check if this is a text response
@@ -594,4 +776,119 @@ mod tests {
         let other = ToolCall::new("other_tool", "test");
         assert!(!other.is_run_cmd());
     }
+
+    #[test]
+    fn test_is_open_editor() {
+        let json = r#"{"tool": "open_editor", "path": "notes.txt"}"#;
+        let tool_call = ToolCall::parse(json).unwrap();
+        assert!(tool_call.is_open_editor());
+        assert!(tool_call.is_allowed_tool());
+        assert_eq!(tool_call.path, "notes.txt");
+    }
+
+    #[test]
+    fn test_is_check_command() {
+        let json = r#"{"tool": "check_command", "command": "docker jq rg"}"#;
+        let tool_call = ToolCall::parse(json).unwrap();
+        assert!(tool_call.is_check_command());
+        assert!(tool_call.is_allowed_tool());
+        assert!(!tool_call.is_run_cmd());
+    }
+
+    #[test]
+    fn test_is_query_json() {
+        let json = r#"{"tool": "query_json", "path": "Cargo.toml", "query": ".dependencies"}"#;
+        let tool_call = ToolCall::parse(json).unwrap();
+        assert!(tool_call.is_query_json());
+        assert!(tool_call.is_allowed_tool());
+        assert_eq!(tool_call.query, ".dependencies");
+    }
+
+    // **Feature: Sabi-TUI, Property: Parallel Read-Only Tool Calls**
+    #[test]
+    fn test_parse_multiple_read_only_calls() {
+        let array =
+            r#"[{"tool": "read_file", "path": "a.txt"}, {"tool": "read_file", "path": "b.txt"}]"#;
+        match ParsedResponse::parse(array) {
+            ParsedResponse::ToolCalls(calls) => {
+                assert_eq!(calls.len(), 2);
+                assert_eq!(calls[1].path, "b.txt");
+            }
+            other => panic!("expected ToolCalls, got {:?}", other),
+        }
+
+        let sequence = "{\"tool\": \"search\", \"pattern\": \"*.rs\"}\n{\"tool\": \"check_command\", \"command\": \"jq\"}";
+        assert!(matches!(
+            ParsedResponse::parse(sequence),
+            ParsedResponse::ToolCalls(ref calls) if calls.len() == 2
+        ));
+    }
+
+    #[test]
+    fn test_parse_multiple_with_write_is_refused() {
+        let mixed = r#"[{"tool": "read_file", "path": "a.txt"}, {"tool": "run_cmd", "command": "rm a.txt"}]"#;
+        let parsed = ParsedResponse::parse(mixed);
+        match parsed {
+            ParsedResponse::MixedToolCalls(ref calls) => {
+                assert_eq!(calls.len(), 2);
+                assert_eq!(calls[1].command, "rm a.txt");
+            }
+            ref other => panic!("expected MixedToolCalls, got {:?}", other),
+        }
+        assert!(parsed.is_tool_call());
+        assert!(parsed.tool_calls().is_empty());
+    }
+
+    #[test]
+    fn test_check_command_versions_not_read_only() {
+        let plain = ToolCall::parse(r#"{"tool": "check_command", "command": "jq"}"#).unwrap();
+        assert!(plain.is_read_only());
+        let json = r#"{"tool": "check_command", "command": "jq", "versions": true}"#;
+        let versions = ToolCall::parse(json).unwrap();
+        assert!(versions.versions);
+        assert!(!versions.is_read_only());
+        assert!(!serde_json::to_string(&plain).unwrap().contains("versions"));
+    }
+
+    #[test]
+    fn test_is_man_page() {
+        let json = r#"{"tool": "man_page", "topic": "tar", "section": "OPTIONS"}"#;
+        let tool_call = ToolCall::parse(json).unwrap();
+        assert!(tool_call.is_man_page());
+        assert!(tool_call.is_allowed_tool());
+        assert_eq!(tool_call.topic, "tar");
+        assert_eq!(tool_call.section, "OPTIONS");
+    }
+
+    // **Feature: Sabi-TUI, Property: Reasoning Around Tool Calls**
+    #[test]
+    fn test_reasoning() {
+        let response = "The disk is probably full, let me check.\n\n```json\n{\"tool\": \"run_cmd\", \"command\": \"df -h\"}\n```\n\nThen I'll look at the logs.";
+        assert_eq!(
+            ToolCall::reasoning(response).as_deref(),
+            Some("The disk is probably full, let me check.\n\nThen I'll look at the logs.")
+        );
+
+        let raw = "Listing first: {\"tool\": \"run_cmd\", \"command\": \"ls\"}";
+        assert_eq!(ToolCall::reasoning(raw).as_deref(), Some("Listing first:"));
+
+        let array = "Reading both.\n[\n{\"tool\": \"read_file\", \"path\": \"a\"},\n{\"tool\": \"read_file\", \"path\": \"b\"}\n]";
+        assert_eq!(ToolCall::reasoning(array).as_deref(), Some("Reading both."));
+
+        let shell = "Restart it:\n```bash\nsystemctl restart nginx\n```";
+        assert_eq!(ToolCall::reasoning(shell).as_deref(), Some("Restart it:"));
+
+        // Other code is part of the explanation
+        let example =
+            "The config has\n```toml\nport = 80\n```\n{\"tool\": \"read_file\", \"path\": \"c\"}";
+        assert_eq!(
+            ToolCall::reasoning(example).as_deref(),
+            Some("The config has\n```toml\nport = 80\n```")
+        );
+
+        assert_eq!(
+            ToolCall::reasoning(r#"{"tool": "run_cmd", "command": "ls"}"#),
+            None
+        );
+    }
 }
@@ -3,112 +3,36 @@
 //! Handles terminal UI layout and rendering with ratatui.
 //! Layout: top pane (chat history), middle pane (command/output), bottom pane (status)
 
+use std::collections::HashMap;
+use std::sync::Arc;
+
 use ratatui::{
     Frame,
-    layout::{Constraint, Direction, Layout, Rect},
+    layout::{Constraint, Direction, Layout, Margin, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span, Text},
-    widgets::{Block, Borders, Paragraph, Wrap},
+    widgets::{
+        Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph, Scrollbar,
+        ScrollbarOrientation, ScrollbarState, Wrap,
+    },
 };
-
-use crate::app::App;
-use crate::message::MessageRole;
+use unicode_width::UnicodeWidthStr;
+
+use crate::app::{App, Pager, Pane, Session, SudoPrompt};
+use crate::executor::{SIGNAL_PREFIX, STDERR_TAG};
+use crate::help;
+use crate::images::{self, Placement};
+use crate::markdown;
+use crate::message::{Message, MessageRole};
+use crate::preview::{self, FilePreview};
+use crate::session_picker::{self, SessionPicker};
+use crate::setup::{self, SetupWizard, Step};
 use crate::state::AppState;
+use crate::theme::Palette;
+use crate::toast::ToastKind;
 
-/// Spinner frames for loading animation
-const SPINNER_FRAMES: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
-
-/// Parse a line with basic markdown and return styled spans
-fn parse_markdown_line(line: &str, base_style: Style) -> Line<'static> {
-    let mut spans: Vec<Span<'static>> = Vec::new();
-    let line = line.to_string();
-
-    // Handle bullet points
-    let (prefix, content) =
-        if line.trim_start().starts_with("* ") || line.trim_start().starts_with("- ") {
-            let indent = line.len() - line.trim_start().len();
-            let bullet = format!("{}• ", " ".repeat(indent));
-            (
-                Some(Span::styled(bullet, base_style.fg(Color::Cyan))),
-                line.trim_start()[2..].to_string(),
-            )
-        } else {
-            (None, line)
-        };
-
-    if let Some(p) = prefix {
-        spans.push(p);
-    }
-
-    // Parse **bold** and *italic*
-    let mut chars = content.chars().peekable();
-    let mut current = String::new();
-
-    while let Some(ch) = chars.next() {
-        if ch == '*' {
-            if chars.peek() == Some(&'*') {
-                // **bold**
-                chars.next();
-                if !current.is_empty() {
-                    spans.push(Span::styled(current.clone(), base_style));
-                    current.clear();
-                }
-                let mut bold_text = String::new();
-                while let Some(c) = chars.next() {
-                    if c == '*' && chars.peek() == Some(&'*') {
-                        chars.next();
-                        break;
-                    }
-                    bold_text.push(c);
-                }
-                spans.push(Span::styled(
-                    bold_text,
-                    base_style.fg(Color::Yellow).add_modifier(Modifier::BOLD),
-                ));
-            } else {
-                // *italic* - just show as cyan
-                if !current.is_empty() {
-                    spans.push(Span::styled(current.clone(), base_style));
-                    current.clear();
-                }
-                let mut italic_text = String::new();
-                for c in chars.by_ref() {
-                    if c == '*' {
-                        break;
-                    }
-                    italic_text.push(c);
-                }
-                spans.push(Span::styled(italic_text, base_style.fg(Color::Cyan)));
-            }
-        } else if ch == '`' {
-            // `code`
-            if !current.is_empty() {
-                spans.push(Span::styled(current.clone(), base_style));
-                current.clear();
-            }
-            let mut code_text = String::new();
-            for c in chars.by_ref() {
-                if c == '`' {
-                    break;
-                }
-                code_text.push(c);
-            }
-            spans.push(Span::styled(code_text, Style::default().fg(Color::Green)));
-        } else {
-            current.push(ch);
-        }
-    }
-
-    if !current.is_empty() {
-        spans.push(Span::styled(current, base_style));
-    }
-
-    if spans.is_empty() {
-        spans.push(Span::styled("", base_style));
-    }
-
-    Line::from(spans)
-}
+/// Most rows the AI's reasoning takes above a command under review
+const REASONING_ROWS: usize = 4;
 
 /// Minimum terminal dimensions for proper rendering
 pub const MIN_WIDTH: u16 = 40;
@@ -120,7 +44,12 @@ pub fn render(frame: &mut Frame, app: &App) {
 
     // Check minimum dimensions
     if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
-        render_size_warning(frame, area);
+        render_size_warning(frame, &app.palette, area);
+        return;
+    }
+
+    if let Some(ref pager) = app.pager {
+        render_pager(frame, pager, app, area);
         return;
     }
 
@@ -128,192 +57,861 @@ pub fn render(frame: &mut Frame, app: &App) {
     let chunks = create_main_layout(area, app);
 
     // Render each pane
-    render_chat_history(frame, app, chunks[0]);
+    let (chat, preview) = split_preview(chunks[0], app);
+    render_chat_history(frame, app, chat);
+    if let Some(area) = preview
+        && let Some(preview) = app
+            .current_tool
+            .as_ref()
+            .and_then(|t| preview::build(t, &app.cwd))
+    {
+        render_file_preview(frame, &preview, &app.palette, area);
+    }
     render_middle_pane(frame, app, chunks[1]);
     render_status_bar(frame, app, chunks[2]);
+
+    if let Some(ref picker) = app.session_picker {
+        render_session_picker(frame, picker, app, area);
+    }
+    if let Some(ref path) = app.export_path {
+        render_export_prompt(frame, path, &app.palette, area);
+    }
+    if let Some(ref wizard) = app.setup {
+        render_setup(frame, wizard, &app.palette, area);
+    }
+    if let Some(ref session) = app.resume_offer {
+        render_resume_offer(frame, session, &app.palette, area);
+    }
+    if let Some(scroll) = app.help_scroll {
+        render_help(frame, app, scroll, area);
+    }
+    render_toasts(frame, app, area);
 }
 
-/// Create the main three-pane layout
-fn create_main_layout(area: Rect, app: &App) -> Vec<Rect> {
+/// Create the main three-pane layout: chat, middle pane, status bar
+pub fn create_main_layout(area: Rect, app: &App) -> Vec<Rect> {
     // Adjust middle pane size based on state
     let has_suggestions = !app.get_suggestions().is_empty();
+    let border = border_rows(app);
 
     let middle_height = match app.state {
         AppState::ReviewAction => {
             // Calculate height based on command content + border
             let lines = app.get_action_text().lines().count().max(1);
-            Constraint::Length((lines as u16 + 2).min(12)) // +2 for border, max 12
+            let reasoning = reasoning_lines(app, pane_inner_width(app, area.width)).len();
+            Constraint::Length((lines as u16 + border).min(12) + reasoning as u16) // max 12
         }
         AppState::Executing => {
             // Spinner + output preview
             let output_lines = app.execution_output.lines().count();
-            Constraint::Length((output_lines as u16 + 3).clamp(3, 15))
+            Constraint::Length((output_lines as u16 + 1 + border).clamp(3, 15))
         }
         AppState::Thinking | AppState::Finalizing => {
             // Show spinner area
-            Constraint::Length(3)
+            Constraint::Length(1 + border)
         }
         AppState::Input if has_suggestions => {
             // Show suggestions
-            Constraint::Length(3 + app.get_suggestions().len() as u16 + 2)
+            Constraint::Length(1 + border + app.get_suggestions().len() as u16 + border)
+        }
+        AppState::Input => {
+            // Grow with a multi-line draft
+            let lines = app.input_textarea.lines().len() as u16;
+            Constraint::Length((lines + border).clamp(1 + border, 8 + border))
         }
         _ => {
             // Minimal middle pane in other states
-            Constraint::Length(3)
+            Constraint::Length(1 + border)
         }
     };
+    // Busy states keep an input below for typing ahead
+    let middle_height = match middle_height {
+        Constraint::Length(rows) if app.is_busy() => Constraint::Length(rows + 1 + border),
+        other => other,
+    };
+    // The compact status bar is a single line
+    let status_height = if app.compact { 1 } else { 3 };
 
     Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Min(5),    // Chat history (flexible)
-            middle_height,         // Middle pane (state-dependent)
-            Constraint::Length(3), // Status bar (fixed)
+            Constraint::Min(5),                // Chat history (flexible)
+            middle_height,                     // Middle pane (state-dependent)
+            Constraint::Length(status_height), // Status bar
         ])
         .split(area)
         .to_vec()
 }
 
+/// Border rows of a pane: a box, or just a top rule in compact mode
+pub fn border_rows(app: &App) -> u16 {
+    if app.compact { 1 } else { 2 }
+}
+
+/// Text columns inside a pane `width` wide
+fn pane_inner_width(app: &App, width: u16) -> u16 {
+    if app.compact {
+        width
+    } else {
+        width.saturating_sub(2)
+    }
+}
+
+/// Block around a pane: a box, or a top rule carrying the title in compact mode
+fn pane_block(app: &App) -> Block<'static> {
+    let borders = if app.compact {
+        Borders::TOP
+    } else {
+        Borders::ALL
+    };
+    Block::default().borders(borders)
+}
+
+/// Where chat text is drawn inside the chat pane `chat`
+///
+/// Inside the box normally; compact mode has no box but keeps the last
+/// column for the scrollbar.
+pub fn chat_text_area(app: &App, chat: Rect) -> Rect {
+    if app.compact {
+        Rect {
+            width: chat.width.saturating_sub(1),
+            ..chat
+        }
+    } else {
+        chat.inner(Margin::new(1, 1))
+    }
+}
+
+/// Split the chat area for the file preview when it is shown
+///
+/// The preview is on during review of read_file / write_file with F2
+/// toggled on and at least 80 columns to share.
+pub fn split_preview(chat: Rect, app: &App) -> (Rect, Option<Rect>) {
+    let wanted = app.show_preview
+        && app.state == AppState::ReviewAction
+        && app.current_tool.as_ref().is_some_and(preview::applies_to)
+        && chat.width >= 80;
+    if !wanted {
+        return (chat, None);
+    }
+    let halves = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+        .split(chat);
+    (halves[0], Some(halves[1]))
+}
+
+/// Render the file preview, centered on its focus line
+fn render_file_preview(frame: &mut Frame, preview: &FilePreview, palette: &Palette, area: Rect) {
+    let visible = area.height.saturating_sub(2) as usize;
+    let max_scroll = preview.lines.len().saturating_sub(visible);
+    let scroll = preview.focus.saturating_sub(visible / 2).min(max_scroll);
+    let gutter = preview.lines.len().max(1).to_string().len();
+
+    let lines: Vec<Line> = preview
+        .lines
+        .iter()
+        .skip(scroll)
+        .take(visible)
+        .map(|line| {
+            let (marker, style) = if line.changed {
+                ("▌", Style::default().fg(palette.success))
+            } else {
+                (" ", Style::default())
+            };
+            Line::from(vec![
+                Span::styled(
+                    format!("{:>gutter$} ", line.number, gutter = gutter),
+                    Style::default().fg(palette.muted),
+                ),
+                Span::styled(marker, Style::default().fg(palette.success)),
+                Span::styled(line.text.clone(), style),
+            ])
+        })
+        .collect();
+
+    let widget = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" {} ", preview.title))
+            .title_bottom(" F2 to hide ")
+            .border_style(Style::default().fg(palette.info)),
+    );
+    frame.render_widget(widget, area);
+}
+
 /// Render size warning when terminal is too small
-fn render_size_warning(frame: &mut Frame, area: Rect) {
+fn render_size_warning(frame: &mut Frame, palette: &Palette, area: Rect) {
     let warning = Paragraph::new(format!(
         "Terminal too small\nMin: {}x{}\nCurrent: {}x{}",
         MIN_WIDTH, MIN_HEIGHT, area.width, area.height
     ))
-    .style(Style::default().fg(Color::Red))
+    .style(Style::default().fg(palette.danger))
     .block(Block::default().borders(Borders::ALL).title("Warning"));
 
     frame.render_widget(warning, area);
 }
 
-/// Maximum lines to render in chat history to prevent crashes
-const MAX_RENDER_LINES: usize = 500;
+/// Messages with more rendered lines than this are collapsed
+pub const COLLAPSE_AFTER: usize = 12;
+
+/// Lines of a collapsed message still shown
+const COLLAPSED_LINES: usize = 6;
+
+/// One message as rendered: prefix, body, marker, image rows, separator
+#[derive(Debug)]
+pub struct MessageLines {
+    lines: Vec<Line<'static>>,
+    /// Line of the "▸ N more lines" marker
+    marker: Option<usize>,
+    /// First of the blank lines an inline image is drawn over
+    image: Option<usize>,
+    /// The last line is the blank line after the message
+    separated: bool,
+}
 
-/// Render the chat history pane (top)
-fn render_chat_history(frame: &mut Frame, app: &App, area: Rect) {
-    let mut lines: Vec<Line> = Vec::new();
-    let content_width = area.width.saturating_sub(4) as usize; // borders + padding
+/// What a message's rendering depends on besides its content
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RenderKey {
+    width: usize,
+    /// Content length, in case a message was replaced in place
+    len: usize,
+    selected: bool,
+    expanded: bool,
+    revealed: Option<usize>,
+    image: bool,
+    compact: bool,
+    timestamps: bool,
+    ascii: bool,
+    palette: Palette,
+}
+
+/// Rendered messages kept between frames, by index into `app.messages`
+///
+/// An entry is rebuilt when its key changes: a resize (width), selection,
+/// expansion, the reveal moving on, or a display setting like timestamps
+/// or the palette. Search matches are highlighted on top when drawing.
+/// Cleared when the history is replaced.
+#[derive(Debug, Default)]
+pub struct ChatCache {
+    messages: HashMap<usize, (RenderKey, Arc<MessageLines>)>,
+}
 
-    for message in &app.messages {
+impl ChatCache {
+    pub fn clear(&mut self) {
+        self.messages.clear();
+    }
+}
+
+/// The chat history as rendered for an area
+///
+/// Holds the cached lines of every message; only the visible window is
+/// copied out for drawing.
+pub struct ChatView {
+    /// Rendered messages in order, with their index into `app.messages`
+    blocks: Vec<(usize, Arc<MessageLines>)>,
+    /// First line of each block
+    starts: Vec<usize>,
+    total: usize,
+    /// First visible line
+    pub scroll: usize,
+    /// Lines holding a "▸ N more lines" marker
+    pub markers: Vec<usize>,
+    /// (first line, message) of the blank blocks inline images are drawn over
+    pub images: Vec<(usize, usize)>,
+}
+
+impl ChatView {
+    /// Number of rendered lines
+    pub fn line_count(&self) -> usize {
+        self.total
+    }
+
+    /// (block, line within it) of rendered line `line`
+    fn locate(&self, line: usize) -> Option<(usize, usize)> {
+        if line >= self.total {
+            return None;
+        }
+        let block = self.starts.partition_point(|&start| start <= line) - 1;
+        Some((block, line - self.starts[block]))
+    }
+
+    pub fn line(&self, line: usize) -> Option<&Line<'static>> {
+        let (block, offset) = self.locate(line)?;
+        self.blocks[block].1.lines.get(offset)
+    }
+
+    /// Index into `app.messages` of the message line `line` belongs to
+    ///
+    /// None for the blank lines between messages.
+    pub fn owner(&self, line: usize) -> Option<usize> {
+        let (block, offset) = self.locate(line)?;
+        let (index, ref rendered) = self.blocks[block];
+        let separator = rendered.separated && offset + 1 == rendered.lines.len();
+        (!separator).then_some(index)
+    }
+
+    /// Every rendered line, top to bottom
+    pub fn lines(&self) -> impl Iterator<Item = &Line<'static>> {
+        self.blocks.iter().flat_map(|(_, rendered)| &rendered.lines)
+    }
+
+    /// Copies of `count` lines starting at `from`
+    pub fn window(&self, from: usize, count: usize) -> Vec<Line<'static>> {
+        let Some((first, offset)) = self.locate(from) else {
+            return Vec::new();
+        };
+        self.blocks[first..]
+            .iter()
+            .flat_map(|(_, rendered)| &rendered.lines)
+            .skip(offset)
+            .take(count)
+            .cloned()
+            .collect()
+    }
+
+    /// Indexes into `app.messages` of the messages shown, in order
+    pub fn messages(&self) -> impl Iterator<Item = usize> + '_ {
+        self.blocks.iter().map(|&(index, _)| index)
+    }
+
+    /// First rendered line of message `index`
+    pub fn message_start(&self, index: usize) -> Option<usize> {
+        let block = self.blocks.iter().position(|&(i, _)| i == index)?;
+        Some(self.starts[block])
+    }
+}
+
+/// Whether a message can be collapsed: tool results and AI replies
+pub fn is_collapsible(message: &Message) -> bool {
+    match message.role {
+        MessageRole::Model | MessageRole::Tool => true,
+        MessageRole::User => message.is_tool_result(),
+        MessageRole::System => false,
+    }
+}
+
+/// Rendered chat history lines and the first line visible in `area`
+///
+/// Mouse selection maps screen positions through this, so it must match
+/// what `render_chat_history` draws. Messages come from `app.chat_cache`
+/// when nothing they depend on changed.
+pub fn chat_view(app: &App, area: Rect) -> ChatView {
+    let text_area = chat_text_area(app, area);
+    let content_width = text_area.width.saturating_sub(2) as usize; // padding
+    let mut cache = app.chat_cache.borrow_mut();
+
+    let mut blocks = Vec::new();
+    let mut starts = Vec::new();
+    let mut markers = Vec::new();
+    let mut images = Vec::new();
+    let mut total = 0;
+    for (index, message) in app.messages.iter().enumerate() {
         // Skip system prompt (first system message with tools definition)
-        if message.role == MessageRole::System && message.content.contains("MUST use tools") {
+        if message.is_system_prompt() {
             continue;
         }
 
-        let (prefix, style) = get_message_style(&message.role);
+        let key = RenderKey {
+            width: content_width,
+            len: message.content.len(),
+            selected: app.selected_message == Some(index),
+            expanded: app.expanded_messages.contains(&index),
+            revealed: app.reveal.filter(|r| r.message == index).map(|r| r.shown),
+            image: message
+                .image
+                .as_ref()
+                .is_some_and(|i| app.image_protocol.can_show(i)),
+            compact: app.compact,
+            timestamps: app.config.show_timestamps,
+            ascii: app.ascii,
+            palette: app.palette,
+        };
+        let rendered = match cache.messages.get(&index) {
+            Some((cached, rendered)) if *cached == key => rendered.clone(),
+            _ => {
+                let rendered = Arc::new(render_message(app, message, key));
+                cache.messages.insert(index, (key, rendered.clone()));
+                rendered
+            }
+        };
 
-        // Add prefix line
-        lines.push(Line::from(Span::styled(prefix, style)));
+        starts.push(total);
+        markers.extend(rendered.marker.map(|m| total + m));
+        images.extend(rendered.image.map(|i| (total + i, index)));
+        total += rendered.lines.len();
+        blocks.push((index, rendered));
+    }
 
-        // Add content lines with indentation and markdown parsing for AI messages
-        let base_style = style.remove_modifier(Modifier::BOLD);
+    let visible_height = text_area.height as usize;
 
-        // Limit content lines per message to prevent huge outputs
-        let max_lines_per_msg = 100;
-        let mut line_count = 0;
+    // Simple scroll: when offset is 0, show the last visible_height lines
+    let scroll = total
+        .saturating_sub(visible_height)
+        .saturating_sub(app.scroll_offset as usize);
+    ChatView {
+        blocks,
+        starts,
+        total,
+        scroll,
+        markers,
+        images,
+    }
+}
 
-        for content_line in message.content.lines() {
+/// Lines of one message for `key`
+fn render_message(app: &App, message: &Message, key: RenderKey) -> MessageLines {
+    let palette = &key.palette;
+    // Older sessions stored tool results as user messages
+    let role = if message.is_tool_result() {
+        &MessageRole::Tool
+    } else {
+        &message.role
+    };
+    let (prefix, style) = message_style(role, palette);
+    let mut lines: Vec<Line<'static>> = Vec::new();
+    let mut marker = None;
+    let mut image = None;
+
+    // Add prefix line; the selected message's is highlighted
+    let prefix_style = if key.selected {
+        style.add_modifier(Modifier::REVERSED)
+    } else {
+        style
+    };
+    let mut header = vec![Span::styled(prefix, prefix_style)];
+    if key.timestamps
+        && let Some(timing) = message.timing()
+    {
+        header.push(Span::styled(
+            format!("  {}", timing),
+            Style::default().fg(palette.muted),
+        ));
+    }
+    lines.push(Line::from(header));
+
+    let body = match key.revealed {
+        // Reply being typed out: what is shown so far, then a cursor
+        Some(shown) => {
+            let mut partial = message.clone();
+            partial.content = message.content.chars().take(shown).collect();
+            partial.content.push('▌');
+            message_body(&partial, key.width, style, palette)
+        }
+        None if message.is_tool_result() => tool_result_body(app, message, key.width),
+        None => message_body(message, key.width, style, palette),
+    };
+    let hidden = body.len().saturating_sub(COLLAPSED_LINES);
+    let collapsed = is_collapsible(message) && body.len() > COLLAPSE_AFTER && !key.expanded;
+    if collapsed {
+        lines.extend(body.into_iter().take(COLLAPSED_LINES));
+        marker = Some(lines.len());
+        lines.push(Line::from(Span::styled(
+            format!("  ▸ {} more lines", hidden),
+            Style::default().fg(palette.muted),
+        )));
+    } else {
+        lines.extend(body);
+    }
+    if let Some(ref attached) = message.image {
+        lines.push(Line::from(Span::styled(
+            format!("  {}", images::placeholder(attached)),
+            Style::default().fg(palette.muted),
+        )));
+        if key.image {
+            image = Some(lines.len());
+            lines.extend(std::iter::repeat_n(Line::from(""), images::IMAGE_ROWS));
+        }
+    }
+
+    // Add empty line between messages
+    if !key.compact {
+        lines.push(Line::from(""));
+    }
+    MessageLines {
+        lines,
+        marker,
+        image,
+        separated: !key.compact,
+    }
+}
+
+/// Where the inline images fully inside the chat pane `chat` are drawn
+///
+/// Nothing is drawn under the pager and overlays.
+pub fn image_placements(app: &App, chat: Rect) -> Vec<Placement> {
+    if app.pager.is_some()
+        || app.session_picker.is_some()
+        || app.export_path.is_some()
+        || app.setup.is_some()
+        || app.resume_offer.is_some()
+        || app.help_scroll.is_some()
+    {
+        return Vec::new();
+    }
+    let view = chat_view(app, chat);
+    let text = chat_text_area(app, chat);
+    let visible = text.height as usize;
+    let width = text.width.saturating_sub(4).min(images::IMAGE_COLUMNS);
+    view.images
+        .into_iter()
+        .filter(|&(line, _)| {
+            line >= view.scroll && line + images::IMAGE_ROWS <= view.scroll + visible
+        })
+        .map(|(line, message)| Placement {
+            message,
+            area: Rect::new(
+                text.x + 2,
+                text.y + (line - view.scroll) as u16,
+                width,
+                images::IMAGE_ROWS as u16,
+            ),
+        })
+        .collect()
+}
+
+/// Content lines of one message, indented and wrapped to `content_width`
+fn message_body(
+    message: &Message,
+    content_width: usize,
+    style: Style,
+    palette: &Palette,
+) -> Vec<Line<'static>> {
+    let mut lines: Vec<Line<'static>> = Vec::new();
+
+    // Add content lines with indentation and markdown parsing for AI messages
+    let base_style = style.remove_modifier(Modifier::BOLD);
+
+    // Limit content lines per message to prevent huge outputs
+    let max_lines_per_msg = 100;
+    let mut line_count = 0;
+
+    // AI messages are markdown, wrapped by the renderer
+    if message.role == MessageRole::Model {
+        let width = content_width.saturating_sub(2).max(10);
+        for line in markdown::render(&message.content, width, base_style, palette) {
             if line_count >= max_lines_per_msg {
                 lines.push(Line::from(Span::styled(
                     "  ... [truncated for display]".to_string(),
-                    Style::default().fg(Color::DarkGray),
+                    Style::default().fg(palette.muted),
                 )));
                 break;
             }
+            let mut spans = vec![Span::raw("  ")];
+            spans.extend(line.spans);
+            lines.push(Line::from(spans));
+            line_count += 1;
+        }
+        return lines;
+    }
 
-            let indented = format!("  {}", content_line);
-
-            // Manually wrap long lines (char-aware for UTF-8)
-            let char_count: usize = indented.chars().count();
-            if char_count > content_width && content_width > 10 {
-                let chars: Vec<char> = indented.chars().collect();
-                for chunk in chars.chunks(content_width) {
-                    let chunk_str: String = chunk.iter().collect();
-                    if message.role == MessageRole::Model {
-                        lines.push(parse_markdown_line(&chunk_str, base_style));
-                    } else {
-                        lines.push(Line::from(Span::styled(chunk_str, base_style)));
-                    }
-                    line_count += 1;
-                }
-            } else {
-                if message.role == MessageRole::Model {
-                    lines.push(parse_markdown_line(&indented, base_style));
+    for content_line in message.content.lines() {
+        if line_count >= max_lines_per_msg {
+            lines.push(Line::from(Span::styled(
+                "  ... [truncated for display]".to_string(),
+                Style::default().fg(palette.muted),
+            )));
+            break;
+        }
+        line_count += push_wrapped(&mut lines, content_line, content_width, base_style);
+    }
+    lines
+}
+
+/// Push `text` indented, wrapped by display width; returns the lines added
+fn push_wrapped(
+    lines: &mut Vec<Line<'static>>,
+    text: &str,
+    content_width: usize,
+    style: Style,
+) -> usize {
+    let indented = format!("  {}", text);
+    if indented.width() > content_width && content_width > 10 {
+        let pieces = markdown::split_width(&indented, content_width);
+        let count = pieces.len();
+        lines.extend(
+            pieces
+                .into_iter()
+                .map(|piece| Line::from(Span::styled(piece, style))),
+        );
+        count
+    } else {
+        lines.push(Line::from(Span::styled(indented, style)));
+        1
+    }
+}
+
+/// Lines of a tool result: per tool, a name badge and exit code chip over
+/// its output
+fn tool_result_body(app: &App, message: &Message, content_width: usize) -> Vec<Line<'static>> {
+    let palette = &app.palette;
+    let icon = if app.ascii { "" } else { "🔧 " };
+    let max_lines_per_msg = 100;
+    let mut lines: Vec<Line<'static>> = Vec::new();
+    let mut line_count = 0;
+
+    'outputs: for output in message.tool_outputs() {
+        if !output.tool.is_empty() {
+            // Long commands are cut to leave room for the chip
+            let room = content_width.saturating_sub(16).max(10);
+            let mut name: String = output.tool.chars().take(room).collect();
+            if name.len() < output.tool.len() {
+                name.push('…');
+            }
+            let mut header = vec![
+                Span::raw("  "),
+                Span::styled(
+                    format!(" {}{} ", icon, name),
+                    palette.badge(palette.special),
+                ),
+            ];
+            if let Some(code) = output.exit_code {
+                let color = if code == 0 {
+                    palette.success
                 } else {
-                    lines.push(Line::from(Span::styled(indented, base_style)));
-                }
-                line_count += 1;
+                    palette.danger
+                };
+                header.push(Span::raw(" "));
+                header.push(Span::styled(
+                    format!(" exit {} ", code),
+                    palette.badge(color).add_modifier(Modifier::BOLD),
+                ));
             }
+            lines.push(Line::from(header));
         }
 
-        // Add empty line between messages
-        lines.push(Line::from(""));
+        for line in output.lines {
+            if line_count >= max_lines_per_msg {
+                lines.push(Line::from(Span::styled(
+                    "  ... [truncated for display]".to_string(),
+                    Style::default().fg(palette.muted),
+                )));
+                break 'outputs;
+            }
+            let style = output_line_style(line, Style::default(), palette);
+            line_count += push_wrapped(&mut lines, line, content_width, style);
+        }
     }
 
-    // Limit total lines to prevent rendering issues
-    if lines.len() > MAX_RENDER_LINES {
-        let skip = lines.len() - MAX_RENDER_LINES;
-        lines = lines.into_iter().skip(skip).collect();
+    // Where the complete text is kept
+    let icon = if app.ascii { "" } else { "📦 " };
+    for id in &message.artifacts {
+        lines.push(Line::from(vec![
+            Span::raw("  "),
+            Span::styled(
+                format!(" {}artifact {} ", icon, id),
+                palette.badge(palette.muted),
+            ),
+        ]));
     }
+    lines
+}
 
-    let total_lines = lines.len();
-    let text = Text::from(lines);
-    let visible_height = area.height.saturating_sub(2) as usize;
+/// Render the chat history pane (top)
+fn render_chat_history(frame: &mut Frame, app: &App, area: Rect) {
+    let view = chat_view(app, area);
+    let scroll = view.scroll;
+    let text_area = chat_text_area(app, area);
+    let visible = text_area.height as usize;
+    let mut lines = view.window(scroll, visible);
+
+    let mut title = " Chat History ".to_string();
+    if let Some(ref search) = app.search {
+        let matches = find_matches(view.lines(), &search.query);
+        let current = search.current.min(matches.len().saturating_sub(1));
+        for (index, &(line, from, to)) in matches.iter().enumerate() {
+            let Some(line) = line.checked_sub(scroll).filter(|&l| l < lines.len()) else {
+                continue;
+            };
+            let style = if index == current {
+                app.palette
+                    .badge(app.palette.accent)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                app.palette.badge(app.palette.highlight)
+            };
+            lines[line] = highlight_columns(std::mem::take(&mut lines[line]), from, to, style);
+        }
+        if let Some(status) = search_status(app, matches.len()) {
+            title = format!(" Chat History · {} ", status);
+        }
+    }
 
-    // Simple scroll: when offset is 0, show the last visible_height lines
-    let scroll = if app.scroll_offset == 0 {
-        total_lines.saturating_sub(visible_height) as u16
+    if let Some(ref selection) = app.chat_selection {
+        let reversed = Style::new().add_modifier(Modifier::REVERSED);
+        for (index, line) in lines.iter_mut().enumerate() {
+            let len = line_text(line).chars().count();
+            if let Some((from, to)) = selection.columns(scroll + index, len) {
+                *line = highlight_columns(std::mem::take(line), from, to, reversed);
+            }
+        }
+    }
+
+    let border = if app.focus == Pane::Chat {
+        app.palette.highlight
     } else {
-        total_lines
-            .saturating_sub(visible_height)
-            .saturating_sub(app.scroll_offset as usize) as u16
+        app.palette.accent
     };
+    let total = view.line_count();
+    let chat = Paragraph::new(Text::from(lines));
+    if app.compact {
+        // No box: the search and position go to the status bar
+        frame.render_widget(chat, text_area);
+    } else {
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .title(title)
+            .border_style(Style::default().fg(border));
+        if let Some(position) = scroll_position(scroll, visible, total) {
+            block = block.title(Line::from(format!(" {} ", position)).right_aligned());
+        }
+        frame.render_widget(chat.block(block), area);
+    }
 
-    let chat = Paragraph::new(text)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title(" Chat History ")
-                .border_style(Style::default().fg(Color::Cyan)),
-        )
-        .scroll((scroll, 0));
+    // Scrollbar on the right border, between the corners (or the last column)
+    let scrollbar_area = if app.compact {
+        area
+    } else {
+        area.inner(Margin::new(0, 1))
+    };
+    if total > visible {
+        let mut state = ScrollbarState::new(total.saturating_sub(visible))
+            .position(scroll)
+            .viewport_content_length(visible);
+        frame.render_stateful_widget(
+            Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                .begin_symbol(None)
+                .end_symbol(None)
+                .thumb_style(Style::default().fg(border))
+                .track_style(Style::default().fg(app.palette.muted)),
+            scrollbar_area,
+            &mut state,
+        );
+    }
+}
 
-    frame.render_widget(chat, area);
+/// "'query' 2/5 (n/N)" for the current search, if it has matches
+fn search_status(app: &App, matches: usize) -> Option<String> {
+    let search = app.search.as_ref().filter(|_| matches > 0)?;
+    Some(format!(
+        "'{}' {}/{} (n/N)",
+        search.query,
+        search.current.min(matches - 1) + 1,
+        matches
+    ))
 }
 
-/// Get styling for a message based on its role
-pub fn get_message_style(role: &MessageRole) -> (&'static str, Style) {
-    match role {
-        MessageRole::User => (
-            "You:",
-            Style::default()
-                .fg(Color::Green)
-                .add_modifier(Modifier::BOLD),
-        ),
-        MessageRole::Model => (
-            "AI:",
-            Style::default()
-                .fg(Color::Blue)
-                .add_modifier(Modifier::BOLD),
-        ),
-        MessageRole::System => (
-            "System:",
-            Style::default()
-                .fg(Color::Yellow)
-                .add_modifier(Modifier::BOLD),
-        ),
+/// "123/800": last visible line out of the total, when the chat scrolls
+fn scroll_position(scroll: usize, visible: usize, total: usize) -> Option<String> {
+    (total > visible).then(|| format!("{}/{}", (scroll + visible).min(total), total))
+}
+
+/// Plain text of a rendered line
+pub fn line_text(line: &Line) -> String {
+    line.spans
+        .iter()
+        .map(|span| span.content.as_ref())
+        .collect()
+}
+
+/// Case-insensitive occurrences of `query` in `lines`
+///
+/// Returns (line, from, to) with char columns, end exclusive.
+pub fn find_matches<'a, 'b: 'a>(
+    lines: impl IntoIterator<Item = &'a Line<'b>>,
+    query: &str,
+) -> Vec<(usize, usize, usize)> {
+    // Lowercase char by char so columns stay aligned with the rendered text
+    let lower = |text: &str| -> Vec<char> {
+        text.chars()
+            .map(|c| c.to_lowercase().next().unwrap_or(c))
+            .collect()
+    };
+    let needle = lower(query);
+    if needle.is_empty() {
+        return Vec::new();
     }
+    let mut matches = Vec::new();
+    for (index, line) in lines.into_iter().enumerate() {
+        let haystack = lower(&line_text(line));
+        let mut col = 0;
+        while col + needle.len() <= haystack.len() {
+            if haystack[col..col + needle.len()] == needle[..] {
+                matches.push((index, col, col + needle.len()));
+                col += needle.len();
+            } else {
+                col += 1;
+            }
+        }
+    }
+    matches
+}
+
+/// Patch `style` onto chars `from..to` of `line`, splitting spans as needed
+fn highlight_columns(line: Line<'static>, from: usize, to: usize, style: Style) -> Line<'static> {
+    let mut spans = Vec::new();
+    let mut col = 0;
+    for span in line.spans {
+        let chars: Vec<char> = span.content.chars().collect();
+        let start = from.clamp(col, col + chars.len()) - col;
+        let end = to.clamp(col, col + chars.len()) - col;
+        col += chars.len();
+        if start == end {
+            spans.push(span);
+            continue;
+        }
+        let part = |range: std::ops::Range<usize>| chars[range].iter().collect::<String>();
+        if start > 0 {
+            spans.push(Span::styled(part(0..start), span.style));
+        }
+        spans.push(Span::styled(part(start..end), span.style.patch(style)));
+        if end < chars.len() {
+            spans.push(Span::styled(part(end..chars.len()), span.style));
+        }
+    }
+    Line::from(spans).style(line.style)
+}
+
+/// Get styling for a message based on its role (dark palette)
+pub fn get_message_style(role: &MessageRole) -> (&'static str, Style) {
+    message_style(role, &Palette::dark())
+}
+
+/// Prefix and style of a message by role
+pub fn message_style(role: &MessageRole, palette: &Palette) -> (&'static str, Style) {
+    let (prefix, color) = match role {
+        MessageRole::User => ("You:", palette.user),
+        MessageRole::Model => ("AI:", palette.model),
+        MessageRole::System => ("System:", palette.system),
+        MessageRole::Tool => ("Tool:", palette.special),
+    };
+    (
+        prefix,
+        Style::default().fg(color).add_modifier(Modifier::BOLD),
+    )
 }
 
 /// Render the middle pane based on current state
 fn render_middle_pane(frame: &mut Frame, app: &App, area: Rect) {
+    let area = if app.is_busy() {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1 + border_rows(app))])
+            .split(area);
+        render_queue_input(frame, app, chunks[1]);
+        chunks[0]
+    } else {
+        area
+    };
+
     match app.state {
         AppState::ReviewAction => {
             render_command_box(frame, app, area);
         }
-        AppState::Executing => {
-            render_execution_output(frame, app, area);
-        }
+        AppState::Executing => match app.sudo_prompt {
+            Some(ref prompt) => render_sudo_prompt(frame, prompt, &app.palette, area),
+            None => render_execution_output(frame, app, area),
+        },
         AppState::Thinking | AppState::Finalizing => {
             render_spinner(frame, app, area);
         }
@@ -321,7 +919,7 @@ fn render_middle_pane(frame: &mut Frame, app: &App, area: Rect) {
             render_input_box(frame, app, area);
         }
         AppState::Done => {
-            render_done_message(frame, area);
+            render_done_message(frame, app, area);
         }
     }
 }
@@ -329,15 +927,44 @@ fn render_middle_pane(frame: &mut Frame, app: &App, area: Rect) {
 /// Render the command review box with danger indicator
 fn render_command_box(frame: &mut Frame, app: &App, area: Rect) {
     let border_color = if app.dangerous_command_detected {
-        Color::Red
+        app.palette.danger
     } else {
-        Color::Green
+        app.palette.success
     };
 
-    let title = if app.dangerous_command_detected {
-        " ⚠ DANGEROUS COMMAND - Review Carefully! "
+    // Shell commands show which shell will run them
+    let shell = app
+        .current_tool
+        .as_ref()
+        .filter(|t| t.is_run_cmd())
+        .map(|_| format!("[{}] ", app.config.shell_invocation().label()))
+        .unwrap_or_default();
+
+    // Palettes with symbols don't leave safe versus dangerous to color
+    let mark = match (app.palette.symbols, app.dangerous_command_detected) {
+        (false, _) => "",
+        (true, false) => "✓ ",
+        (true, true) => "⚠ ",
+    };
+
+    let title = if app.explaining {
+        format!(" {}Command {}- Explaining... ", mark, shell)
+    } else if !app.danger_reasons.is_empty() {
+        format!(
+            " ⚠ matched: {} {}(Ctrl+X to explain) ",
+            app.danger_reasons.join("; "),
+            shell
+        )
+    } else if app.dangerous_command_detected {
+        format!(
+            " ⚠ DANGEROUS COMMAND {}- Review Carefully! (Ctrl+X to explain) ",
+            shell
+        )
     } else {
-        " Command (Enter to execute, Esc to cancel) "
+        format!(
+            " {}Command {}(Enter to execute, Ctrl+X to explain, Esc to cancel) ",
+            mark, shell
+        )
     };
 
     let mut border_style = Style::default().fg(border_color);
@@ -351,43 +978,562 @@ fn render_command_box(frame: &mut Frame, app: &App, area: Rect) {
         }
     }
 
-    let block = Block::default()
-        .borders(Borders::ALL)
-        .title(title)
-        .border_style(border_style);
+    let mut block = pane_block(app).title(title).border_style(border_style);
+    if app.palette.symbols && app.dangerous_command_detected {
+        block = block.border_type(BorderType::Thick);
+    }
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    // The AI's reasoning goes above the editable command
+    let reasoning: Vec<Line> = reasoning_lines(app, inner.width)
+        .into_iter()
+        .map(|line| {
+            Line::from(Span::styled(
+                line,
+                Style::default()
+                    .fg(app.palette.model)
+                    .add_modifier(Modifier::ITALIC),
+            ))
+        })
+        .collect();
+    let [reasoning_area, command_area] = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(reasoning.len() as u16),
+            Constraint::Min(1),
+        ])
+        .areas(inner);
+    frame.render_widget(Paragraph::new(reasoning), reasoning_area);
+    frame.render_widget(&app.action_textarea, command_area);
+}
 
-    // Render the textarea widget
-    let mut textarea = app.action_textarea.clone();
-    textarea.set_block(block);
+/// The AI's reasoning for the call under review, wrapped to `width` and
+/// cut to `REASONING_ROWS`
+fn reasoning_lines(app: &App, width: u16) -> Vec<String> {
+    let Some(ref text) = app.reasoning else {
+        return Vec::new();
+    };
+    let mut lines: Vec<String> = text
+        .lines()
+        .flat_map(|line| markdown::wrap(vec![Span::raw(line.to_string())], width as usize))
+        .map(|spans| spans.iter().map(|s| s.content.as_ref()).collect())
+        .collect();
+    if lines.len() > REASONING_ROWS {
+        lines.truncate(REASONING_ROWS);
+        let last = &mut lines[REASONING_ROWS - 1];
+        *last =
+            markdown::split_width(last, (width as usize).saturating_sub(1)).swap_remove(0) + "…";
+    }
+    lines
+}
 
-    frame.render_widget(&textarea, area);
+/// Render the full-screen output pager
+fn render_pager(frame: &mut Frame, pager: &Pager, app: &App, area: Rect) {
+    let visible = area.height.saturating_sub(2) as usize;
+    let total = pager.lines.len();
+    let query = pager.search.as_ref().map_or("", |s| s.query.as_str());
+    let highlight = app.palette.badge(app.palette.highlight);
+    let lines: Vec<Line> = pager.lines[pager.scroll.min(total)..]
+        .iter()
+        .enumerate()
+        .take(visible)
+        .map(|(index, line)| {
+            let skip = if app.output_wrap { 0 } else { pager.hscroll };
+            let mut line = output_line(app, pager.scroll + index + 1, total, line, skip);
+            for (_, from, to) in find_matches([&line], query) {
+                line = highlight_columns(line, from, to, highlight);
+            }
+            line
+        })
+        .collect();
+
+    // Wrapped lines can take several rows each
+    let end = if app.output_wrap {
+        let width = area.width.saturating_sub(2).max(1) as usize;
+        let mut rows = 0;
+        let shown = lines
+            .iter()
+            .take_while(|line| {
+                rows += line.width().div_ceil(width).max(1);
+                rows <= visible
+            })
+            .count();
+        pager.scroll + shown.max(1)
+    } else {
+        pager.scroll + lines.len()
+    }
+    .min(total);
+
+    let side = if app.output_wrap { "" } else { " ←→" };
+    let title = format!(
+        " {} [{}-{}/{}] (↑↓{} PgUp PgDn g G, w wrap, # numbers, q to close) ",
+        pager.title,
+        pager.scroll + 1,
+        end,
+        total,
+        side
+    );
+    // The search prompt, or where the current match is
+    let footer = match (&pager.query, &pager.search) {
+        (Some(query), _) => format!(" /{}▌ ", query),
+        (None, Some(search)) => {
+            let count = pager.matches(&search.query).len();
+            let current = if count == 0 { 0 } else { search.current + 1 };
+            format!(" \"{}\" {}/{} (n / N) ", search.query, current, count)
+        }
+        (None, None) => " / to search ".to_string(),
+    };
+    let mut widget = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(title)
+            .title_bottom(footer)
+            .border_style(Style::default().fg(app.palette.accent)),
+    );
+    if app.output_wrap {
+        widget = widget.wrap(Wrap { trim: false });
+    }
+
+    frame.render_widget(widget, area);
 }
 
-/// Render command execution output
-fn render_execution_output(frame: &mut Frame, app: &App, area: Rect) {
-    let spinner_char = SPINNER_FRAMES[app.spinner_frame % SPINNER_FRAMES.len()];
+/// Area of `percent_x` by `percent_y` percent centered in `area`
+fn centered(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
+    let width = area.width * percent_x / 100;
+    let height = area.height * percent_y / 100;
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
 
-    let output = if app.execution_output.is_empty() {
-        format!("{} Executing command...", spinner_char)
+/// Render toasts stacked in the top right corner, newest at the bottom
+fn render_toasts(frame: &mut Frame, app: &App, area: Rect) {
+    // Compact toasts are reversed lines padded by a space instead of boxes
+    let border = if app.compact { 0 } else { 2 };
+    let max_width = (area.width / 2).max(20).min(area.width);
+    let mut y = area.y;
+    for toast in app.toasts.iter() {
+        let lines = markdown::split_width(&toast.text, max_width.saturating_sub(2) as usize);
+        let width = lines.iter().map(|l| l.width()).max().unwrap_or(0) as u16 + 2;
+        let height = (lines.len() as u16 + border).min(area.bottom().saturating_sub(y));
+        if height == 0 {
+            break;
+        }
+        let color = match toast.kind {
+            ToastKind::Info => app.palette.accent,
+            ToastKind::Success => app.palette.success,
+            ToastKind::Error => app.palette.danger,
+        };
+        let rect = Rect::new(area.right().saturating_sub(width), y, width, height);
+        let paragraph = if app.compact {
+            let text: Vec<Line> = lines
+                .iter()
+                .map(|l| Line::from(format!(" {} ", l)))
+                .collect();
+            Paragraph::new(text).style(Style::default().fg(color).add_modifier(Modifier::REVERSED))
+        } else {
+            let text: Vec<Line> = lines.into_iter().map(Line::from).collect();
+            Paragraph::new(text).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(color)),
+            )
+        };
+        frame.render_widget(Clear, rect);
+        frame.render_widget(paragraph, rect);
+        y += height;
+    }
+}
+
+/// Render the export prompt: one line for the path, centered
+fn render_export_prompt(frame: &mut Frame, path: &str, palette: &Palette, area: Rect) {
+    let width = area.width.min(70);
+    let rect = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + area.height.saturating_sub(3) / 2,
+        width,
+        3.min(area.height),
+    );
+    // Keep the end of a long path in view
+    let inner = width.saturating_sub(3) as usize;
+    let shown: String = {
+        let chars: Vec<char> = path.chars().collect();
+        chars[chars.len().saturating_sub(inner)..].iter().collect()
+    };
+    let prompt = Paragraph::new(format!("{}█", shown)).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" Export chat, .html for HTML (Enter to save, Esc to cancel) ")
+            .border_style(Style::default().fg(palette.accent)),
+    );
+    frame.render_widget(Clear, rect);
+    frame.render_widget(prompt, rect);
+}
+
+/// Render the `/setup` overlay: the provider list or the current question
+fn render_setup(frame: &mut Frame, wizard: &SetupWizard, palette: &Palette, area: Rect) {
+    let mut lines: Vec<Line> = Vec::new();
+    if wizard.step == Step::Provider {
+        for (i, (name, description, _)) in setup::PROVIDERS.iter().enumerate() {
+            let style = if i == wizard.selected {
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("{} {}", i + 1, name), style),
+                Span::styled(
+                    format!("  {}", description),
+                    Style::default().fg(palette.muted),
+                ),
+            ]));
+        }
     } else {
-        app.execution_output.clone()
+        let provider = setup::PROVIDERS[wizard.selected].0;
+        lines.push(Line::from(Span::styled(
+            format!("{}: {}", provider, wizard.prompt()),
+            Style::default().fg(palette.muted),
+        )));
+        lines.push(Line::from(format!("{}█", wizard.field())));
+    }
+    if let Some(ref error) = wizard.error {
+        lines.push(Line::from(Span::styled(
+            format!("✗ {}", error),
+            Style::default().fg(palette.danger),
+        )));
+    }
+
+    let width = area.width.min(70);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let rect = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + area.height.saturating_sub(height) / 2,
+        width,
+        height,
+    );
+    let footer = match wizard.step {
+        Step::Provider => " ↑↓ or 1-3 choose · Enter next · Esc close ",
+        _ => " Enter next · Esc back ",
+    };
+    let widget = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" Setup {} ", wizard.progress()))
+            .title_bottom(footer)
+            .border_style(Style::default().fg(palette.accent)),
+    );
+    frame.render_widget(Clear, rect);
+    frame.render_widget(widget, rect);
+}
+
+/// Render the startup "Resume last session?" prompt
+fn render_resume_offer(frame: &mut Frame, session: &Session, palette: &Palette, area: Rect) {
+    let width = area.width.min(70);
+    let rect = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + area.height.saturating_sub(4) / 2,
+        width,
+        4.min(area.height),
+    );
+    let count = session.messages.len();
+    let text = vec![
+        Line::from(Span::styled(
+            session.name.clone(),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(Span::styled(
+            format!(
+                "{} message{} · {}",
+                count,
+                if count == 1 { "" } else { "s" },
+                session.preview()
+            ),
+            Style::default().fg(palette.muted),
+        )),
+    ];
+    let prompt = Paragraph::new(text).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" Resume last session? (y/n) ")
+            .border_style(Style::default().fg(palette.accent)),
+    );
+    frame.render_widget(Clear, rect);
+    frame.render_widget(prompt, rect);
+}
+
+/// Render the `/sessions` overlay: two lines per session, newest first
+fn render_session_picker(frame: &mut Frame, picker: &SessionPicker, app: &App, area: Rect) {
+    if picker.quick {
+        render_recent_sessions(frame, picker, app, area);
+        return;
+    }
+    let area = centered(area, 85, 80);
+    let palette = &app.palette;
+    let home = dirs::home_dir().map(|h| h.to_string_lossy().into_owned());
+
+    let items: Vec<ListItem> = picker
+        .sessions
+        .iter()
+        .map(|session| {
+            let current = session.id == app.current_session_id;
+            let when = chrono::DateTime::parse_from_rfc3339(&session.timestamp)
+                .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_else(|_| session.id.clone());
+            let cwd = match home {
+                Some(ref home) if session.cwd.starts_with(home.as_str()) => {
+                    format!("~{}", &session.cwd[home.len()..])
+                }
+                _ => session.cwd.clone(),
+            };
+            let name = if current {
+                format!("{} (current)", session.name)
+            } else {
+                session.name.clone()
+            };
+            ListItem::new(vec![
+                Line::from(vec![
+                    Span::styled(name, Style::default().add_modifier(Modifier::BOLD)),
+                    Span::styled(
+                        format!("  {}  {}  {} messages", when, cwd, session.messages.len()),
+                        Style::default().fg(palette.muted),
+                    ),
+                ]),
+                Line::from(Span::styled(
+                    format!("  {}", session.preview()),
+                    Style::default().fg(palette.info),
+                )),
+            ])
+        })
+        .collect();
+
+    let footer = match (&picker.rename, &picker.status) {
+        (Some(name), _) => format!(" New name: {}█ (Enter to save, Esc to cancel) ", name),
+        (None, Some(status)) => format!(" {} ", status),
+        (None, None) => " Enter switch · d delete · r rename · Esc close ".to_string(),
     };
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(match app.workspace {
+                    Some(ref workspace) => {
+                        format!(" Sessions · {} ({}) ", workspace, picker.sessions.len())
+                    }
+                    None => format!(" Sessions ({}) ", picker.sessions.len()),
+                })
+                .title_bottom(footer)
+                .border_style(Style::default().fg(palette.accent)),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol("▶ ");
+    let mut state = ListState::default().with_selected(Some(picker.selected));
+
+    frame.render_widget(Clear, area);
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+/// Render the quick resume list: one numbered line per session
+fn render_recent_sessions(frame: &mut Frame, picker: &SessionPicker, app: &App, area: Rect) {
+    let height = (picker.sessions.len() as u16 + 2).min(area.height);
+    let width = (area.width * 85 / 100).max(40).min(area.width);
+    let area = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 3,
+        width,
+        height,
+    );
+    let palette = &app.palette;
+    let now = chrono::Local::now();
+
+    let items: Vec<ListItem> = picker
+        .sessions
+        .iter()
+        .enumerate()
+        .map(|(i, session)| {
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{} ", (i + 1) % 10),
+                    Style::default().fg(palette.highlight),
+                ),
+                Span::styled(
+                    session.name.clone(),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(
+                    format!("  {}  ", session_picker::age(&session.timestamp, now)),
+                    Style::default().fg(palette.muted),
+                ),
+                Span::styled(session.preview(), Style::default().fg(palette.info)),
+            ]))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Resume session ")
+                .title_bottom(" 1-9 0 or Enter resume · Esc close ")
+                .border_style(Style::default().fg(palette.accent)),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default().with_selected(Some(picker.selected));
+
+    frame.render_widget(Clear, area);
+    frame.render_stateful_widget(list, area, &mut state);
+}
 
-    let output_widget = Paragraph::new(output)
+/// Render the help overlay
+fn render_help(frame: &mut Frame, app: &App, scroll: u16, area: Rect) {
+    let area = centered(area, 80, 85);
+    let mut lines = Vec::new();
+    for section in help::sections(app) {
+        lines.push(Line::from(Span::styled(
+            section.title,
+            Style::default()
+                .fg(app.palette.accent)
+                .add_modifier(Modifier::BOLD),
+        )));
+        let width = section
+            .rows
+            .iter()
+            .map(|(key, _)| key.chars().count())
+            .max()
+            .unwrap_or(0);
+        for (key, what) in section.rows {
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("  {:<width$}  ", key, width = width),
+                    Style::default().fg(app.palette.highlight),
+                ),
+                Span::raw(what),
+            ]));
+        }
+        lines.push(Line::default());
+    }
+
+    let max_scroll = lines
+        .len()
+        .saturating_sub(area.height.saturating_sub(2) as usize);
+    let help = Paragraph::new(lines)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title(" Output ")
-                .border_style(Style::default().fg(Color::Yellow)),
+                .title(" Help ")
+                .title_bottom(" ↑↓ PgUp PgDn scroll · Esc close ")
+                .border_style(Style::default().fg(app.palette.accent)),
+        )
+        .scroll((scroll.min(max_scroll as u16), 0));
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(help, area);
+}
+
+/// Render the masked sudo password input
+fn render_sudo_prompt(frame: &mut Frame, prompt: &SudoPrompt, palette: &Palette, area: Rect) {
+    let text = vec![
+        Line::from(Span::styled(
+            format!("$ {}", prompt.tool.command),
+            Style::default().fg(palette.muted),
+        )),
+        Line::from(format!("Password: {}", prompt.masked())),
+    ];
+
+    let widget = Paragraph::new(text)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" 🔑 sudo password (Enter to run, Esc to cancel) ")
+                .border_style(Style::default().fg(palette.special)),
         )
         .wrap(Wrap { trim: false });
 
+    frame.render_widget(widget, area);
+}
+
+/// Render command execution output
+fn render_execution_output(frame: &mut Frame, app: &App, area: Rect) {
+    let spinner_char = app.spinner_char();
+
+    let output = if app.execution_output.is_empty() {
+        Text::from(format!("{} Executing command...", spinner_char))
+    } else {
+        // Keep the newest lines in view while output streams in, unless the
+        // pane was scrolled up
+        let visible = area.height.saturating_sub(border_rows(app)) as usize;
+        let lines: Vec<&str> = app.execution_output.lines().collect();
+        let end = lines
+            .len()
+            .saturating_sub(app.output_scroll)
+            .max(visible.min(lines.len()));
+        let first = end.saturating_sub(visible);
+        Text::from(
+            lines[first..end]
+                .iter()
+                .enumerate()
+                .map(|(index, line)| output_line(app, first + index + 1, lines.len(), line, 0))
+                .collect::<Vec<_>>(),
+        )
+    };
+
+    let (title, border) = if app.focus == Pane::Output {
+        (
+            " Output (↑↓ scroll, w: wrap, #: line numbers) ",
+            app.palette.highlight,
+        )
+    } else {
+        (" Output (Tab to scroll) ", app.palette.accent)
+    };
+    let mut output_widget = Paragraph::new(output).block(
+        pane_block(app)
+            .title(title)
+            .border_style(Style::default().fg(border)),
+    );
+    if app.output_wrap {
+        output_widget = output_widget.wrap(Wrap { trim: false });
+    }
+
     frame.render_widget(output_widget, area);
 }
 
+/// Line `number` of `total` lines of command output
+///
+/// Prefixed with a line number gutter when line numbers are on, and with
+/// the first `skip` characters cut off (the pager's horizontal scroll).
+fn output_line(app: &App, number: usize, total: usize, text: &str, skip: usize) -> Line<'static> {
+    let style = output_line_style(text, Style::default(), &app.palette);
+    let text: String = text.chars().skip(skip).collect();
+    if !app.output_line_numbers {
+        return Line::from(Span::styled(text, style));
+    }
+    let digits = total.max(1).to_string().len();
+    Line::from(vec![
+        Span::styled(
+            format!("{:>digits$} │ ", number),
+            Style::default().fg(app.palette.muted),
+        ),
+        Span::styled(text, style),
+    ])
+}
+
+/// Highlight signal reports and dim stderr lines of command output
+fn output_line_style(line: &str, base: Style, palette: &Palette) -> Style {
+    if line.starts_with(SIGNAL_PREFIX) {
+        base.fg(palette.danger).add_modifier(Modifier::BOLD)
+    } else if line.contains(STDERR_TAG) {
+        base.fg(palette.muted).add_modifier(Modifier::DIM)
+    } else {
+        base
+    }
+}
+
 /// Render spinner for async operations
 fn render_spinner(frame: &mut Frame, app: &App, area: Rect) {
-    let spinner_char = SPINNER_FRAMES[app.spinner_frame % SPINNER_FRAMES.len()];
+    let spinner_char = app.spinner_char();
     let message = match app.state {
         AppState::Thinking => "Thinking...",
         AppState::Finalizing => "Analyzing output...",
@@ -397,12 +1543,8 @@ fn render_spinner(frame: &mut Frame, app: &App, area: Rect) {
     let spinner_text = format!("{} {}", spinner_char, message);
 
     let spinner = Paragraph::new(spinner_text)
-        .style(Style::default().fg(Color::Cyan))
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Cyan)),
-        );
+        .style(Style::default().fg(app.palette.accent))
+        .block(pane_block(app).border_style(Style::default().fg(app.palette.accent)));
 
     frame.render_widget(spinner, area);
 }
@@ -413,10 +1555,25 @@ fn render_input_box(frame: &mut Frame, app: &App, area: Rect) {
 
     if suggestions.is_empty() {
         // Normal input box
-        let block = Block::default()
-            .borders(Borders::ALL)
-            .title(" Enter your query (Esc to quit) ")
-            .border_style(Style::default().fg(Color::White));
+        let send = if app.multiline {
+            "Ctrl+S to send, "
+        } else {
+            ""
+        };
+        let title = match (app.config.vi_mode, app.focus) {
+            (true, Pane::Chat) => " -- NORMAL -- (i to type, Ctrl+C to quit) ".to_string(),
+            (true, _) => format!(" Enter your query ({}Esc for normal mode) ", send),
+            (false, _) => format!(" Enter your query ({}Esc to quit) ", send),
+        };
+        // Dimmed while another pane has the focus
+        let border = if app.focus == Pane::Input {
+            app.palette.border
+        } else {
+            app.palette.muted
+        };
+        let block = pane_block(app)
+            .title(title)
+            .border_style(Style::default().fg(border));
 
         let mut textarea = app.input_textarea.clone();
         textarea.set_block(block);
@@ -425,14 +1582,13 @@ fn render_input_box(frame: &mut Frame, app: &App, area: Rect) {
         // Split area for input and suggestions
         let chunks = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Length(3), Constraint::Min(1)])
+            .constraints([Constraint::Length(1 + border_rows(app)), Constraint::Min(1)])
             .split(area);
 
         // Input box
-        let block = Block::default()
-            .borders(Borders::ALL)
+        let block = pane_block(app)
             .title(" Command ")
-            .border_style(Style::default().fg(Color::Cyan));
+            .border_style(Style::default().fg(app.palette.accent));
 
         let mut textarea = app.input_textarea.clone();
         textarea.set_block(block);
@@ -446,34 +1602,56 @@ fn render_input_box(frame: &mut Frame, app: &App, area: Rect) {
                     Span::styled(
                         *cmd,
                         Style::default()
-                            .fg(Color::Yellow)
+                            .fg(app.palette.highlight)
                             .add_modifier(Modifier::BOLD),
                     ),
                     Span::raw(" - "),
-                    Span::styled(*desc, Style::default().fg(Color::DarkGray)),
+                    Span::styled(*desc, Style::default().fg(app.palette.muted)),
                 ])
             })
             .collect();
 
         let suggestions_widget = Paragraph::new(suggestion_lines).block(
-            Block::default()
-                .borders(Borders::ALL)
+            pane_block(app)
                 .title(" Suggestions ")
-                .border_style(Style::default().fg(Color::DarkGray)),
+                .border_style(Style::default().fg(app.palette.muted)),
         );
         frame.render_widget(suggestions_widget, chunks[1]);
     }
 }
 
+/// Render the input for typing ahead while the agent is busy
+fn render_queue_input(frame: &mut Frame, app: &App, area: Rect) {
+    let border = if app.focus == Pane::Input {
+        app.palette.border
+    } else {
+        app.palette.muted
+    };
+    let mut block = pane_block(app)
+        .title(" Type ahead (Enter to queue) ")
+        .border_style(Style::default().fg(border));
+    if !app.queued.is_empty() {
+        let queued = format!(" {} queued ", app.queued.len());
+        block = block.title(
+            Line::from(Span::styled(queued, app.palette.badge(app.palette.info))).right_aligned(),
+        );
+    }
+
+    let mut textarea = app.input_textarea.clone();
+    textarea.set_placeholder_text("");
+    textarea.set_block(block);
+    frame.render_widget(&textarea, area);
+}
+
 /// Render done state message
-fn render_done_message(frame: &mut Frame, area: Rect) {
+fn render_done_message(frame: &mut Frame, app: &App, area: Rect) {
+    let palette = &app.palette;
     let message = Paragraph::new("Press Enter to continue or Esc to quit")
-        .style(Style::default().fg(Color::Green))
+        .style(Style::default().fg(palette.success))
         .block(
-            Block::default()
-                .borders(Borders::ALL)
+            pane_block(app)
                 .title(" Done ")
-                .border_style(Style::default().fg(Color::Green)),
+                .border_style(Style::default().fg(palette.success)),
         );
 
     frame.render_widget(message, area);
@@ -485,33 +1663,32 @@ fn render_status_bar(frame: &mut Frame, app: &App, area: Rect) {
 
     // Build keybindings help based on state
     let keybindings = match app.state {
-        AppState::Input => "Enter: Submit | Esc: Quit | ↑↓: Scroll",
-        AppState::Thinking => "Esc: Cancel",
-        AppState::ReviewAction => "Enter: Execute | Esc: Cancel | Edit command",
-        AppState::Executing => "Esc: Cancel",
-        AppState::Finalizing => "Esc: Cancel",
-        AppState::Done => "Enter: Continue | Esc/q: Quit",
+        AppState::Input => "Enter: Submit | Esc: Quit | F1: Help",
+        AppState::Thinking => "Esc: Cancel | F1: Help",
+        AppState::ReviewAction => "Enter: Execute | Esc: Cancel | F1: Help",
+        AppState::Executing => "Esc: Cancel | F1: Help",
+        AppState::Finalizing => "Esc: Cancel | F1: Help",
+        AppState::Done => "Enter: Continue | Esc/q: Quit | F1: Help",
     };
 
     // Build status line
     let mut spans = vec![
         Span::styled(
             format!(" {} ", state_name),
-            Style::default()
-                .fg(Color::Black)
-                .bg(get_state_color(&app.state))
+            app.palette
+                .badge(state_color(&app.state, &app.palette))
                 .add_modifier(Modifier::BOLD),
         ),
         Span::raw(" "),
     ];
+    spans.extend(context_gauge(app));
 
     // Add safe mode indicator
     if app.config.safe_mode {
         spans.push(Span::styled(
-            " 🔒 SAFE ",
-            Style::default()
-                .fg(Color::Black)
-                .bg(Color::Yellow)
+            if app.ascii { " SAFE " } else { " 🔒 SAFE " },
+            app.palette
+                .badge(app.palette.highlight)
                 .add_modifier(Modifier::BOLD),
         ));
         spans.push(Span::raw(" "));
@@ -519,43 +1696,129 @@ fn render_status_bar(frame: &mut Frame, app: &App, area: Rect) {
 
     // Add Python indicator
     if app.python_available {
-        spans.push(Span::styled(" 🐍 ", Style::default().fg(Color::Green)));
+        let icon = if app.ascii { "py" } else { "🐍" };
+        let label = match app.python_env {
+            Some(ref env) => format!(" {} {} ", icon, env.kind),
+            None => format!(" {} ", icon),
+        };
+        spans.push(Span::styled(
+            label,
+            Style::default().fg(app.palette.success),
+        ));
     }
 
+    // Add container target indicator
+    if let Some(ref target) = app.target {
+        spans.push(Span::styled(
+            format!(" {} {} ", if app.ascii { "@" } else { "📦" }, target.name),
+            Style::default().fg(app.palette.special),
+        ));
+    }
+
+    // Add working directory
+    spans.push(Span::styled(
+        format!(
+            "{} {} ",
+            if app.ascii { "dir" } else { "📁" },
+            app.display_cwd()
+        ),
+        Style::default().fg(app.palette.info),
+    ));
+
     // Add error message if present
     if let Some(ref error) = app.error_message {
         spans.push(Span::styled(
             format!("Error: {} ", error),
-            Style::default().fg(Color::Red),
+            Style::default().fg(app.palette.danger),
         ));
     }
 
     // Add keybindings
     spans.push(Span::styled(
         keybindings,
-        Style::default().fg(Color::DarkGray),
+        Style::default().fg(app.palette.muted),
     ));
 
+    // The compact chat has no title, so its search and position go here
+    if app.compact {
+        let chat = app.chat_area();
+        let view = chat_view(app, chat);
+        let visible = chat_text_area(app, chat).height as usize;
+        let mut extra = Vec::new();
+        if let Some(ref search) = app.search {
+            extra.extend(search_status(
+                app,
+                find_matches(view.lines(), &search.query).len(),
+            ));
+        }
+        extra.extend(scroll_position(view.scroll, visible, view.line_count()));
+        if !extra.is_empty() {
+            spans.push(Span::styled(
+                format!(" | {}", extra.join(" ")),
+                Style::default().fg(app.palette.accent),
+            ));
+        }
+    }
+
     let status_line = Line::from(spans);
 
-    let status = Paragraph::new(status_line).block(
-        Block::default()
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::DarkGray)),
-    );
+    let status = if app.compact {
+        Paragraph::new(status_line)
+    } else {
+        Paragraph::new(status_line).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.palette.muted)),
+        )
+    };
 
     frame.render_widget(status, area);
 }
 
-/// Get color for state indicator
+/// Cells of the context gauge bar
+const GAUGE_CELLS: usize = 5;
+
+/// Context window use as a small bar: "ctx ▰▰▱▱▱ 42%", yellow from 70%
+/// and red from 90%
+fn context_gauge(app: &App) -> Vec<Span<'static>> {
+    let (tokens, window) = app.context_usage();
+    let percent = (tokens * 100 / window).min(100);
+    let color = match percent {
+        90.. => app.palette.danger,
+        70.. => app.palette.highlight,
+        _ => app.palette.success,
+    };
+    let filled = (percent * GAUGE_CELLS).div_ceil(100).min(GAUGE_CELLS);
+    let (full, empty) = if app.ascii {
+        ("#", "-")
+    } else {
+        ("▰", "▱")
+    };
+    vec![
+        Span::styled("ctx ", Style::default().fg(app.palette.muted)),
+        Span::styled(full.repeat(filled), Style::default().fg(color)),
+        Span::styled(
+            empty.repeat(GAUGE_CELLS - filled),
+            Style::default().fg(app.palette.muted),
+        ),
+        Span::styled(format!(" {}% ", percent), Style::default().fg(color)),
+    ]
+}
+
+/// Get color for state indicator (dark palette)
 fn get_state_color(state: &AppState) -> Color {
+    state_color(state, &Palette::dark())
+}
+
+/// Color of the state badge
+fn state_color(state: &AppState, palette: &Palette) -> Color {
     match state {
-        AppState::Input => Color::Green,
-        AppState::Thinking => Color::Yellow,
-        AppState::ReviewAction => Color::Cyan,
-        AppState::Executing => Color::Magenta,
-        AppState::Finalizing => Color::Yellow,
-        AppState::Done => Color::Green,
+        AppState::Input => palette.success,
+        AppState::Thinking => palette.highlight,
+        AppState::ReviewAction => palette.accent,
+        AppState::Executing => palette.special,
+        AppState::Finalizing => palette.highlight,
+        AppState::Done => palette.success,
     }
 }
 
@@ -692,7 +1955,7 @@ mod tests {
 
     #[test]
     fn test_middle_pane_shows_spinner_in_thinking() {
-        let app = test_app();
+        let _app = test_app();
         // Thinking state should show spinner
         assert!(AppState::Thinking.shows_spinner());
     }
@@ -796,9 +2059,38 @@ mod tests {
         }
     }
 
+    // **Feature: Sabi-TUI, Property: Context Gauge**
+    #[test]
+    fn test_context_gauge() {
+        let mut app = test_app();
+        app.ascii = true;
+        app.config.context_window = Some(1000);
+        let gauge = |app: &App| {
+            let spans = context_gauge(app);
+            let text: String = spans.iter().map(|s| s.content.as_ref()).collect();
+            (text, spans[1].style.fg)
+        };
+        assert_eq!(
+            gauge(&app),
+            ("ctx ----- 0% ".to_string(), Some(app.palette.success))
+        );
+
+        app.add_message(Message::user("x".repeat(3000)));
+        assert_eq!(
+            gauge(&app),
+            ("ctx ####- 75% ".to_string(), Some(app.palette.highlight))
+        );
+        app.add_message(Message::model("x".repeat(800)));
+        assert_eq!(gauge(&app).1, Some(app.palette.danger));
+
+        // Only what's sent counts
+        app.config.max_history_messages = 1;
+        assert_eq!(gauge(&app).0, "ctx #---- 20% ");
+    }
+
     #[test]
     fn test_state_colors_are_distinct() {
-        let colors: Vec<Color> = AppState::all_states().iter().map(get_state_color).collect();
+        let _colors: Vec<Color> = AppState::all_states().iter().map(get_state_color).collect();
 
         // At minimum, Input and ReviewAction should have different colors
         let input_color = get_state_color(&AppState::Input);
@@ -825,4 +2117,219 @@ mod tests {
         // We verify the flag affects the rendering logic
         assert!(app.dangerous_command_detected);
     }
+
+    // **Feature: Sabi-TUI, Property: Chat Search**
+    #[test]
+    fn test_find_matches_case_insensitive() {
+        let lines = vec![
+            Line::from(vec![Span::raw("  Error: "), Span::raw("build error")]),
+            Line::from("  nothing here"),
+            Line::from("  ÉRROR érror"),
+        ];
+        assert_eq!(find_matches(&lines, "error"), vec![(0, 2, 7), (0, 15, 20)]);
+        assert_eq!(find_matches(&lines, "érror"), vec![(2, 2, 7), (2, 8, 13)]);
+        assert!(find_matches(&lines, "").is_empty());
+
+        let highlighted = highlight_columns(lines[0].clone(), 5, 11, Style::new().bg(Color::Red));
+        assert_eq!(line_text(&highlighted), line_text(&lines[0]));
+        assert_eq!(highlighted.spans.len(), 4);
+    }
+
+    // **Feature: Sabi-TUI, Property: Chat Scroll Position**
+    #[test]
+    fn test_scroll_position_indicator() {
+        assert_eq!(scroll_position(0, 20, 15), None);
+        assert_eq!(scroll_position(0, 20, 800), Some("20/800".to_string()));
+        assert_eq!(scroll_position(103, 20, 800), Some("123/800".to_string()));
+        assert_eq!(scroll_position(780, 20, 800), Some("800/800".to_string()));
+    }
+
+    // **Feature: Sabi-TUI, Property: Output Line Numbers**
+    #[test]
+    fn test_output_line_gutter() {
+        let mut app = test_app();
+        assert_eq!(line_text(&output_line(&app, 7, 120, "error", 0)), "error");
+        app.output_line_numbers = true;
+        assert_eq!(
+            line_text(&output_line(&app, 7, 120, "error here", 6)),
+            "  7 │ here"
+        );
+    }
+
+    // **Feature: Sabi-TUI, Property: Cached, Virtualized Chat**
+    #[test]
+    fn test_chat_view_reuses_cached_messages() {
+        let mut app = test_app();
+        for i in 0..400 {
+            app.add_message(Message::user(format!("message {i}")));
+        }
+        let area = Rect::new(0, 0, 80, 20);
+        let view = chat_view(&app, area);
+
+        // Nothing is cut off: the oldest message is still there
+        assert_eq!(view.line_count(), 400 * 3);
+        assert_eq!(line_text(view.line(1).unwrap()), "  message 0");
+        assert_eq!(view.window(view.scroll, 18).len(), 18);
+        assert_eq!(view.owner(2), None);
+        assert_eq!(view.owner(3), Some(1));
+
+        // Unchanged messages are not rendered again
+        let again = chat_view(&app, area);
+        assert!(Arc::ptr_eq(&view.blocks[5].1, &again.blocks[5].1));
+
+        // Selecting re-renders only that message; a resize renders all
+        app.selected_message = Some(5);
+        let selected = chat_view(&app, area);
+        assert!(!Arc::ptr_eq(&view.blocks[5].1, &selected.blocks[5].1));
+        assert!(Arc::ptr_eq(&view.blocks[6].1, &selected.blocks[6].1));
+        let resized = chat_view(&app, Rect::new(0, 0, 60, 20));
+        assert!(!Arc::ptr_eq(&selected.blocks[6].1, &resized.blocks[6].1));
+    }
+
+    #[test]
+    fn test_display_settings_rerender_cached_messages() {
+        let mut app = test_app();
+        let mut message = Message::user("hello");
+        message.time = Some("2026-03-01T14:30:22+00:00".to_string());
+        app.add_message(message);
+        let area = Rect::new(0, 0, 80, 20);
+        let header = |app: &App| line_text(chat_view(app, area).line(0).unwrap());
+        assert!(!header(&app).contains("14:30:22"));
+
+        // `/config set show_timestamps` applies to messages already rendered
+        app.config.show_timestamps = true;
+        assert!(header(&app).contains("14:30:22"));
+        app.config.show_timestamps = false;
+        assert!(!header(&app).contains("14:30:22"));
+
+        let before = chat_view(&app, area);
+        app.palette = Palette::mono();
+        let after = chat_view(&app, area);
+        assert!(!Arc::ptr_eq(&before.blocks[0].1, &after.blocks[0].1));
+    }
+
+    // **Feature: Sabi-TUI, Property: Tool Result Blocks**
+    #[test]
+    fn test_tool_result_block() {
+        let mut app = test_app();
+        app.ascii = true;
+        app.add_message(Message::tool(
+            "Tool: run_cmd ls missing\nExit code: 2\nOutput:\nls: missing: No such file",
+        ));
+        let view = chat_view(&app, Rect::new(0, 0, 80, 20));
+        let lines: Vec<String> = view.lines().map(line_text).collect();
+        assert_eq!(
+            lines,
+            [
+                "Tool:",
+                "   run_cmd ls missing   exit 2 ",
+                "  ls: missing: No such file",
+                ""
+            ]
+        );
+        let chip = &view.line(1).unwrap().spans[3];
+        assert_eq!(chip.style.bg, Some(app.palette.danger));
+    }
+
+    // **Feature: Sabi-TUI, Property: Compact Layout**
+    #[test]
+    fn test_compact_layout_saves_rows() {
+        let mut app = test_app();
+        app.add_message(Message::user("one"));
+        app.add_message(Message::model("two"));
+        let area = Rect::new(0, 0, 80, 24);
+
+        let boxed = create_main_layout(area, &app);
+        let boxed_lines = chat_view(&app, boxed[0]).line_count();
+        app.compact = true;
+        let compact = create_main_layout(area, &app);
+        assert_eq!(compact[2].height, 1);
+        assert_eq!(compact[1].height, 2);
+        assert_eq!(compact[0].height, boxed[0].height + 3);
+
+        // Text fills the pane, minus the scrollbar column
+        let text = chat_text_area(&app, compact[0]);
+        assert_eq!((text.y, text.height), (compact[0].y, compact[0].height));
+        assert_eq!(text.width, 79);
+
+        // No blank line between messages
+        assert_eq!(chat_view(&app, compact[0]).line_count(), boxed_lines - 2);
+    }
+
+    // **Feature: Sabi-TUI, Property: Reasoning In Review**
+    #[test]
+    fn test_reasoning_above_command() {
+        let mut app = test_app();
+        app.state = AppState::ReviewAction;
+        app.set_action_text("df -h");
+        let area = Rect::new(0, 0, 40, 24);
+        assert_eq!(create_main_layout(area, &app)[1].height, 3);
+
+        app.reasoning = Some("The disk is probably full.".to_string());
+        assert_eq!(create_main_layout(area, &app)[1].height, 4);
+
+        // Long reasoning is cut to a few rows
+        app.reasoning = Some("word ".repeat(100));
+        let lines = reasoning_lines(&app, 38);
+        assert_eq!(lines.len(), REASONING_ROWS);
+        assert!(lines[REASONING_ROWS - 1].ends_with('…'));
+        assert!(lines.iter().all(|l| l.width() <= 38));
+    }
+
+    // **Feature: Sabi-TUI, Property: Accessible Palettes**
+    #[test]
+    fn test_review_marks_safety_with_symbols() {
+        let mut app = test_app();
+        app.state = AppState::ReviewAction;
+        app.set_action_text("ls");
+        let title = |app: &App| {
+            let backend = ratatui::backend::TestBackend::new(60, 3);
+            let mut terminal = ratatui::Terminal::new(backend).unwrap();
+            terminal
+                .draw(|frame| render_command_box(frame, app, frame.area()))
+                .unwrap();
+            let buffer = terminal.backend().buffer();
+            (0..60)
+                .map(|x| buffer[(x, 0)].symbol().to_string())
+                .collect::<String>()
+        };
+        assert!(!title(&app).contains('✓'));
+
+        app.palette = Palette::mono();
+        assert!(title(&app).contains("✓ Command"));
+        app.dangerous_command_detected = true;
+        let dangerous = title(&app);
+        assert!(dangerous.contains('⚠'));
+        assert!(dangerous.starts_with('┏'));
+    }
+
+    // **Feature: Sabi-TUI, Property: Quick Resume List**
+    #[test]
+    fn test_recent_sessions_are_numbered() {
+        let app = test_app();
+        let sessions = (1..=10)
+            .map(|i| {
+                let mut session = crate::app::Session::new();
+                session.id = i.to_string();
+                session.name = format!("Task {}", i);
+                let question = Message::user(format!("question {}", i));
+                session.messages.push(question);
+                session
+            })
+            .collect();
+        let picker = SessionPicker::recent(sessions);
+        let backend = ratatui::backend::TestBackend::new(80, 14);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        terminal
+            .draw(|frame| render_session_picker(frame, &picker, &app, frame.area()))
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        let rows: Vec<String> = (0..14)
+            .map(|y| (0..80).map(|x| buffer[(x, y)].symbol()).collect())
+            .collect();
+        assert!(rows[0].contains("Resume session"));
+        assert!(rows[1].contains("1 Task 1  just now  question 1"));
+        assert!(rows[10].contains("0 Task 10"));
+        assert!(rows[11].contains("Esc close"));
+    }
 }
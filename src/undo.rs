@@ -0,0 +1,181 @@
+//! Undo for agent file modifications
+//!
+//! Files are snapshotted before write tools change them, so `/undo` can
+//! put back what the agent overwrote (or remove files it created).
+//! Snapshots live in memory for the current session only.
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+
+/// Most snapshots kept per session; the oldest are dropped first
+const MAX_SNAPSHOTS: usize = 50;
+
+/// Files larger than this are not snapshotted
+const MAX_SNAPSHOT_BYTES: u64 = 10 * 1024 * 1024;
+
+/// State of a file before the agent modified it
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileSnapshot {
+    /// Absolute path of the modified file
+    pub path: PathBuf,
+    /// Previous contents, or None if the file did not exist
+    pub previous: Option<Vec<u8>>,
+    /// When the snapshot was taken
+    pub taken_at: DateTime<Local>,
+}
+
+impl FileSnapshot {
+    /// One-line description of what restoring this snapshot does
+    pub fn describe(&self) -> String {
+        let action = match self.previous {
+            Some(ref bytes) => format!("restore {} bytes", bytes.len()),
+            None => "delete (created by agent)".to_string(),
+        };
+        format!(
+            "{} {} - {}",
+            self.taken_at.format("%H:%M:%S"),
+            self.path.display(),
+            action
+        )
+    }
+
+    /// Put the file back the way it was
+    pub fn restore(&self) -> std::io::Result<()> {
+        match self.previous {
+            Some(ref bytes) => std::fs::write(&self.path, bytes),
+            None if self.path.exists() => std::fs::remove_file(&self.path),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Stack of file snapshots, most recent last
+#[derive(Debug, Default)]
+pub struct UndoStack {
+    entries: Vec<FileSnapshot>,
+}
+
+impl UndoStack {
+    /// Snapshot `path` before it is modified
+    ///
+    /// Directories and files too large to keep in memory are skipped with
+    /// an error so the caller can tell the user the change can't be undone.
+    pub fn record(&mut self, path: &Path) -> Result<(), String> {
+        let previous = match std::fs::metadata(path) {
+            Ok(meta) if meta.is_dir() => {
+                return Err(format!("{} is a directory", path.display()));
+            }
+            Ok(meta) if meta.len() > MAX_SNAPSHOT_BYTES => {
+                return Err(format!(
+                    "{} is too large to snapshot ({} bytes)",
+                    path.display(),
+                    meta.len()
+                ));
+            }
+            Ok(_) => Some(std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?),
+            Err(_) => None,
+        };
+
+        self.entries.push(FileSnapshot {
+            path: path.to_path_buf(),
+            previous,
+            taken_at: Local::now(),
+        });
+        if self.entries.len() > MAX_SNAPSHOTS {
+            self.entries.remove(0);
+        }
+        Ok(())
+    }
+
+    /// Snapshots available to undo, most recent first
+    pub fn list(&self) -> impl Iterator<Item = &FileSnapshot> {
+        self.entries.iter().rev()
+    }
+
+    /// Number of snapshots
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether there is nothing to undo
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Revert the last `count` changes, most recent first
+    ///
+    /// Returns a report line per snapshot. A snapshot that fails to restore
+    /// is reported and dropped, so one bad file doesn't block the rest.
+    pub fn undo(&mut self, count: usize) -> Vec<String> {
+        let start = self.entries.len().saturating_sub(count);
+        self.entries
+            .drain(start..)
+            .rev()
+            .map(|snapshot| match snapshot.restore() {
+                Ok(()) => format!("✓ {}", snapshot.describe()),
+                Err(e) => format!("✗ {}: {}", snapshot.path.display(), e),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    // **Feature: Sabi-TUI, Property: Undo Restores Overwritten Files**
+    #[test]
+    fn test_undo_restores_and_deletes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let existing = dir.join("existing.txt");
+        let created = dir.join("created.txt");
+        std::fs::write(&existing, "original").unwrap();
+
+        let mut stack = UndoStack::default();
+        stack.record(&existing).unwrap();
+        std::fs::write(&existing, "changed").unwrap();
+        stack.record(&created).unwrap();
+        std::fs::write(&created, "new").unwrap();
+
+        let listed: Vec<&Path> = stack.list().map(|s| s.path.as_path()).collect();
+        assert_eq!(listed, vec![created.as_path(), existing.as_path()]);
+
+        let report = stack.undo(1);
+        assert_eq!(report.len(), 1);
+        assert!(!created.exists());
+        assert_eq!(std::fs::read_to_string(&existing).unwrap(), "changed");
+
+        stack.undo(5);
+        assert_eq!(std::fs::read_to_string(&existing).unwrap(), "original");
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn test_record_rejects_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut stack = UndoStack::default();
+
+        assert!(stack.record(dir.path()).is_err());
+        assert!(stack.is_empty());
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(20))]
+
+        // **Feature: Sabi-TUI, Property: Undo Stack Is Bounded**
+        // *For any* number of recorded changes, at most MAX_SNAPSHOTS SHALL be kept.
+        #[test]
+        fn prop_undo_stack_bounded(count in 0usize..120) {
+            let dir = tempfile::tempdir().unwrap();
+            let mut stack = UndoStack::default();
+            let path = dir.path().join("never-created");
+            for _ in 0..count {
+                stack.record(&path).unwrap();
+            }
+            prop_assert_eq!(stack.len(), count.min(MAX_SNAPSHOTS));
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! Workspaces: named project roots that group sessions
+//!
+//! `[workspaces.<name>]` in config.toml names a project directory. Starting
+//! sabi inside it, or `/workspace switch <name>`, makes it the current
+//! workspace: new sessions belong to it, `/sessions` and `/resume` list
+//! only its sessions, and file tools reaching outside its root need the
+//! 2-step confirmation.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Severity;
+use crate::executor::DangerAssessment;
+use crate::path_guard;
+use crate::tool_call::ToolCall;
+
+/// A project directory, from `[workspaces.<name>]`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Workspace {
+    /// Project root (`~/` is expanded)
+    pub root: String,
+    /// File tools outside the root need confirmation
+    #[serde(default = "default_scope_tools")]
+    pub scope_tools: bool,
+}
+
+fn default_scope_tools() -> bool {
+    true
+}
+
+impl Workspace {
+    /// The root as an absolute path
+    pub fn root_path(&self) -> PathBuf {
+        path_guard::resolve(Path::new("/"), self.root.trim())
+    }
+}
+
+/// Name of the workspace containing `cwd`, the innermost if several do
+pub fn detect(workspaces: &BTreeMap<String, Workspace>, cwd: &Path) -> Option<String> {
+    workspaces
+        .iter()
+        .map(|(name, workspace)| (name, workspace.root_path()))
+        .filter(|(_, root)| cwd.starts_with(root))
+        .max_by_key(|(_, root)| root.components().count())
+        .map(|(name, _)| name.clone())
+}
+
+/// File paths `tool` reads or writes
+fn tool_paths(tool: &ToolCall) -> Vec<&str> {
+    match tool.tool.as_str() {
+        "read_file" | "write_file" | "open_editor" | "query_json" => vec![&tool.path],
+        "search" if tool.directory.is_empty() => vec!["."],
+        "search" => vec![&tool.directory],
+        _ => Vec::new(),
+    }
+}
+
+/// Why `tool` reaches outside workspace `name` at `root`, if it does
+pub fn check_scope(
+    name: &str,
+    root: &Path,
+    cwd: &Path,
+    tool: &ToolCall,
+) -> Option<DangerAssessment> {
+    let reasons: Vec<String> = tool_paths(tool)
+        .into_iter()
+        .filter(|path| !path_guard::resolve(cwd, path).starts_with(root))
+        .map(|path| format!("{} is outside workspace '{}'", path, name))
+        .collect();
+    (!reasons.is_empty()).then_some(DangerAssessment {
+        severity: Severity::Warn,
+        reasons,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace(root: &str) -> Workspace {
+        Workspace {
+            root: root.to_string(),
+            scope_tools: true,
+        }
+    }
+
+    // **Feature: Sabi-TUI, Property: Workspace Detection**
+    #[test]
+    fn test_detect_innermost() {
+        let mut workspaces = BTreeMap::new();
+        workspaces.insert("src".to_string(), workspace("/home/me/src"));
+        workspaces.insert("api".to_string(), workspace("/home/me/src/api/"));
+        let detect = |cwd: &str| detect(&workspaces, Path::new(cwd));
+        assert_eq!(detect("/home/me/src/api/handlers").as_deref(), Some("api"));
+        assert_eq!(detect("/home/me/src/web").as_deref(), Some("src"));
+        assert_eq!(detect("/home/me/src-old"), None);
+        assert_eq!(detect("/tmp"), None);
+    }
+
+    // **Feature: Sabi-TUI, Property: Workspace Tool Scope**
+    #[test]
+    fn test_check_scope() {
+        let root = Path::new("/home/me/api");
+        let tool = |json: &str| -> ToolCall { serde_json::from_str(json).unwrap() };
+        let check = |cwd: &str, json: &str| check_scope("api", root, Path::new(cwd), &tool(json));
+
+        assert!(
+            check(
+                "/home/me/api",
+                r#"{"tool": "read_file", "path": "src/main.rs"}"#
+            )
+            .is_none()
+        );
+        assert!(check("/home/me/api/src", r#"{"tool": "search", "pattern": "fn"}"#).is_none());
+        assert!(
+            check(
+                "/home/me/api",
+                r#"{"tool": "run_cmd", "command": "cat /etc/hosts"}"#
+            )
+            .is_none()
+        );
+
+        let danger = check(
+            "/home/me/api",
+            r#"{"tool": "write_file", "path": "../web/x"}"#,
+        )
+        .unwrap();
+        assert_eq!(danger.severity, Severity::Warn);
+        assert_eq!(danger.reasons, ["../web/x is outside workspace 'api'"]);
+        assert!(check("/tmp", r#"{"tool": "search", "pattern": "fn"}"#).is_some());
+    }
+}
@@ -0,0 +1,139 @@
+//! Write conflict protection
+//!
+//! Files are stamped (mtime, size, content hash) when read_file or
+//! write_file touches them. Before another write_file overwrites a stamped
+//! file, the stamp is compared with what is on disk: if a background
+//! process, the user or an editor changed it in between, the write is
+//! treated as dangerous and needs explicit confirmation, and the AI is
+//! told about the mismatch.
+
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::{DateTime, Local};
+
+/// What a file looked like when sabi last saw it
+#[derive(Debug, Clone, PartialEq)]
+struct Stamp {
+    modified: Option<SystemTime>,
+    len: u64,
+    hash: u64,
+}
+
+impl Stamp {
+    /// Current state of `path`, or None if it doesn't exist
+    fn of(path: &Path) -> Option<Self> {
+        let meta = std::fs::metadata(path).ok()?;
+        let content = std::fs::read(path).ok()?;
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        Some(Self {
+            modified: meta.modified().ok(),
+            len: meta.len(),
+            hash: hasher.finish(),
+        })
+    }
+}
+
+/// Stamps of files the agent has read or written this session
+#[derive(Debug, Default)]
+pub struct WriteGuard {
+    seen: HashMap<PathBuf, Option<Stamp>>,
+}
+
+impl WriteGuard {
+    /// Remember `path` as it is now (after a read_file or write_file)
+    pub fn record(&mut self, path: &Path) {
+        self.seen.insert(path.to_path_buf(), Stamp::of(path));
+    }
+
+    /// Describe how `path` changed since it was recorded
+    ///
+    /// Returns None for unchanged files and files never recorded.
+    pub fn check(&self, path: &Path) -> Option<String> {
+        let seen = self.seen.get(path)?;
+        let now = Stamp::of(path);
+        match (seen, now) {
+            (Some(seen), Some(now)) => {
+                // Touched but identical content is not a conflict
+                if seen.hash == now.hash && seen.len == now.len {
+                    return None;
+                }
+                let when = now
+                    .modified
+                    .map(|t| {
+                        format!(
+                            ", modified {}",
+                            DateTime::<Local>::from(t).format("%H:%M:%S")
+                        )
+                    })
+                    .unwrap_or_default();
+                Some(format!(
+                    "{} changed on disk since it was last read ({} → {} bytes{})",
+                    path.display(),
+                    seen.len,
+                    now.len,
+                    when
+                ))
+            }
+            (Some(_), None) => Some(format!(
+                "{} was deleted since it was last read",
+                path.display()
+            )),
+            (None, Some(_)) => Some(format!(
+                "{} was created by something else since it was last checked",
+                path.display()
+            )),
+            (None, None) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // **Feature: Sabi-TUI, Property: Write Conflict Detection**
+    #[test]
+    fn test_detects_changes_since_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        std::fs::write(&path, "one").unwrap();
+
+        let mut guard = WriteGuard::default();
+        assert_eq!(guard.check(&path), None);
+
+        guard.record(&path);
+        assert_eq!(guard.check(&path), None);
+
+        // Same content rewritten is fine
+        std::fs::write(&path, "one").unwrap();
+        assert_eq!(guard.check(&path), None);
+
+        std::fs::write(&path, "one two").unwrap();
+        let conflict = guard.check(&path).unwrap();
+        assert!(conflict.contains("changed on disk"));
+        assert!(conflict.contains("3 → 7 bytes"));
+
+        guard.record(&path);
+        assert_eq!(guard.check(&path), None);
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(guard.check(&path).unwrap().contains("deleted"));
+    }
+
+    #[test]
+    fn test_detects_file_created_after_check() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("new.txt");
+
+        let mut guard = WriteGuard::default();
+        guard.record(&path);
+        assert_eq!(guard.check(&path), None);
+
+        std::fs::write(&path, "from a background job").unwrap();
+        assert!(guard.check(&path).unwrap().contains("created"));
+    }
+}
@@ -0,0 +1,183 @@
+//! Windows Subsystem for Linux support
+//!
+//! Under WSL the Windows drives are mounted at `/mnt/<letter>`, and Windows
+//! programs print `C:\...` paths that Linux tools can't open. The AI is
+//! told it runs under WSL, Windows paths in tool arguments are translated
+//! before use, and Windows paths in tool output get their WSL equivalent.
+
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::tool_call::ToolCall;
+
+/// Most path translations appended to one tool result
+const MAX_NOTED_PATHS: usize = 5;
+
+/// WSL distribution name, or None when not running under WSL
+pub fn distro() -> Option<&'static str> {
+    static DISTRO: OnceLock<Option<String>> = OnceLock::new();
+    DISTRO
+        .get_or_init(|| {
+            if let Ok(name) = std::env::var("WSL_DISTRO_NAME")
+                && !name.is_empty()
+            {
+                return Some(name);
+            }
+            let release = std::fs::read_to_string("/proc/sys/kernel/osrelease").ok()?;
+            release
+                .to_ascii_lowercase()
+                .contains("microsoft")
+                .then(|| "WSL".to_string())
+        })
+        .as_deref()
+}
+
+/// Whether sabi is running under WSL
+pub fn is_wsl() -> bool {
+    distro().is_some()
+}
+
+/// `/mnt/c/Users/me` → `C:\Users\me`
+pub fn to_windows_path(path: &str) -> Option<String> {
+    let rest = path.strip_prefix("/mnt/")?;
+    let mut chars = rest.chars();
+    let drive = chars.next().filter(|c| c.is_ascii_alphabetic())?;
+    let tail = chars.as_str();
+    if !(tail.is_empty() || tail.starts_with('/')) {
+        return None;
+    }
+    let tail = tail.trim_start_matches('/').replace('/', "\\");
+    Some(format!("{}:\\{}", drive.to_ascii_uppercase(), tail))
+}
+
+/// `C:\Users\me` or `C:/Users/me` → `/mnt/c/Users/me`
+pub fn to_wsl_path(path: &str) -> Option<String> {
+    let mut chars = path.chars();
+    let drive = chars.next().filter(|c| c.is_ascii_alphabetic())?;
+    let tail = chars.as_str().strip_prefix(':')?;
+    if !(tail.is_empty() || tail.starts_with(['\\', '/'])) {
+        return None;
+    }
+    let tail = tail.trim_start_matches(['\\', '/']).replace('\\', "/");
+    let mut out = format!("/mnt/{}", drive.to_ascii_lowercase());
+    if !tail.is_empty() {
+        out.push('/');
+        out.push_str(tail.trim_end_matches('/'));
+    }
+    Some(out)
+}
+
+/// Rewrite Windows paths in a tool's path arguments to WSL paths
+///
+/// Returns whether anything changed.
+pub fn translate_tool_paths(tool: &mut ToolCall) -> bool {
+    let mut changed = false;
+    for field in [&mut tool.path, &mut tool.directory] {
+        if let Some(translated) = to_wsl_path(field) {
+            *field = translated;
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Note mapping Windows paths found in `output` to WSL paths
+pub fn path_note(output: &str) -> Option<String> {
+    static WINDOWS_PATH: OnceLock<Regex> = OnceLock::new();
+    let re = WINDOWS_PATH.get_or_init(|| {
+        Regex::new(r#"\b[A-Za-z]:\\[^\s"'<>|*?]*"#).expect("valid Windows path regex")
+    });
+
+    let mut seen: Vec<&str> = Vec::new();
+    for m in re.find_iter(output) {
+        if !seen.contains(&m.as_str()) {
+            seen.push(m.as_str());
+        }
+    }
+    let lines: Vec<String> = seen
+        .into_iter()
+        .filter_map(|p| Some(format!("{} = {}", p, to_wsl_path(p)?)))
+        .take(MAX_NOTED_PATHS)
+        .collect();
+    (!lines.is_empty()).then(|| format!("[WSL paths: {}]", lines.join(", ")))
+}
+
+/// System context lines describing the WSL environment
+pub fn context_note(cwd: &str) -> Option<String> {
+    let distro = distro()?;
+    let mut note = format!(
+        "- WSL: {} (Windows drives are under /mnt/<drive>; Windows programs run as cmd.exe, \
+         powershell.exe, notepad.exe and need Windows paths, e.g. from `wslpath -w`)",
+        distro
+    );
+    if let Some(windows) = to_windows_path(cwd) {
+        note.push_str(&format!("\n- Working directory in Windows: {}", windows));
+    }
+    Some(note)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    // **Feature: Sabi-TUI, Property: WSL Path Translation**
+    #[test]
+    fn test_path_translation() {
+        assert_eq!(
+            to_windows_path("/mnt/c/Users/me/a.txt").as_deref(),
+            Some(r"C:\Users\me\a.txt")
+        );
+        assert_eq!(to_windows_path("/mnt/d").as_deref(), Some(r"D:\"));
+        assert_eq!(to_windows_path("/mnt/data/x"), None);
+        assert_eq!(to_windows_path("/home/me"), None);
+
+        assert_eq!(
+            to_wsl_path(r"C:\Users\me\a.txt").as_deref(),
+            Some("/mnt/c/Users/me/a.txt")
+        );
+        assert_eq!(to_wsl_path("D:/src/").as_deref(), Some("/mnt/d/src"));
+        assert_eq!(to_wsl_path(r"C:\").as_deref(), Some("/mnt/c"));
+        assert_eq!(to_wsl_path("src/main.rs"), None);
+        assert_eq!(to_wsl_path("C:relative"), None);
+    }
+
+    #[test]
+    fn test_translate_tool_paths() {
+        let mut tool = ToolCall::new("read_file", "");
+        tool.path = r"C:\Users\me\notes.txt".to_string();
+        assert!(translate_tool_paths(&mut tool));
+        assert_eq!(tool.path, "/mnt/c/Users/me/notes.txt");
+        assert!(!translate_tool_paths(&mut tool));
+    }
+
+    #[test]
+    fn test_path_note() {
+        let output = "Directory: C:\\Users\\me\nC:\\Users\\me\\a.txt\nC:\\Users\\me";
+        assert_eq!(
+            path_note(output).as_deref(),
+            Some(
+                r"[WSL paths: C:\Users\me = /mnt/c/Users/me, C:\Users\me\a.txt = /mnt/c/Users/me/a.txt]"
+            )
+        );
+        assert_eq!(path_note("no paths here: /mnt/c/x"), None);
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(100))]
+
+        // **Feature: Sabi-TUI, Property: WSL Path Round Trip**
+        // *For any* path under a drive mount, translating to Windows and back SHALL be lossless.
+        #[test]
+        fn prop_path_round_trip(drive in "[a-z]", parts in prop::collection::vec("[A-Za-z0-9_.-]{1,10}", 0..5)) {
+            let mut path = format!("/mnt/{}", drive);
+            for part in &parts {
+                path.push('/');
+                path.push_str(part);
+            }
+            let windows = to_windows_path(&path).unwrap();
+            prop_assert_eq!(to_wsl_path(&windows), Some(path));
+        }
+    }
+}